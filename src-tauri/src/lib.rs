@@ -1,7 +1,5 @@
 use log::{error, info};
 use image::{DynamicImage, ImageReader, GenericImageView, ImageFormat};
-use image::codecs::jpeg::JpegEncoder;
-use image::ExtendedColorType;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -12,6 +10,11 @@ use tokio::process::Command;
 use tokio::sync::Mutex;
 use tauri::{Emitter, Manager};
 
+#[cfg(test)]
+mod test_support;
+#[cfg(test)]
+mod tests;
+
 
 // ============================================================================
 // Data Structures
@@ -21,18 +24,198 @@ fn new_command(program: &str) -> Command {
     let mut cmd = Command::new(program);
     #[cfg(target_os = "windows")]
     cmd.creation_flags(0x08000000);
+    // Own process group, so killing it also reaches helpers (yt-dlp runs ffmpeg)
+    #[cfg(unix)]
+    cmd.process_group(0);
     cmd
 }
 
+// Kills a child and everything it started
+async fn kill_process_tree(pid: u32) {
+    #[cfg(windows)]
+    {
+        let _ = new_command("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .output()
+            .await;
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = Command::new("kill")
+            .args(["-TERM", "--", &format!("-{}", pid)])
+            .output()
+            .await;
+        let _ = Command::new("kill").arg(pid.to_string()).output().await;
+    }
+}
+
+// Same as kill_process_tree, for the exit hook where there is no runtime to await on
+fn kill_process_tree_blocking(pid: u32) {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .creation_flags(0x08000000)
+            .output();
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = std::process::Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", pid)])
+            .output();
+        let _ = std::process::Command::new("kill").args(["-KILL", &pid.to_string()]).output();
+    }
+}
+
+// Every running ffmpeg/ffprobe/yt-dlp child with the partial output it is writing, so
+// they can be killed and cleaned up when the app exits. Process-wide so helpers without
+// an AppHandle can register too, and a std Mutex so the exit hook can use it outside
+// the async runtime.
+static CHILDREN: std::sync::LazyLock<std::sync::Mutex<HashMap<u32, Option<String>>>> =
+    std::sync::LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+fn register_child(pid: Option<u32>, output_path: Option<&str>) {
+    if let (Some(pid), Ok(mut children)) = (pid, CHILDREN.lock()) {
+        children.insert(pid, output_path.map(String::from));
+    }
+}
+
+// For children that only find out what they write as they go (yt-dlp moving on to the
+// next file). A child that has already been unregistered stays that way.
+fn set_child_output(pid: Option<u32>, output_path: &str) {
+    if let (Some(pid), Ok(mut children)) = (pid, CHILDREN.lock()) {
+        if let Some(output) = children.get_mut(&pid) {
+            *output = Some(output_path.to_string());
+        }
+    }
+}
+
+fn unregister_child(pid: Option<u32>) {
+    if let (Some(pid), Ok(mut children)) = (pid, CHILDREN.lock()) {
+        children.remove(&pid);
+    }
+}
+
+// An output file along with the pieces yt-dlp writes next to it while downloading:
+// "<name>.part", "<name>.part-Frag<n>" and "<name>.ytdl"
+fn remove_partial_output(path: &std::path::Path) {
+    if path.is_file() {
+        let _ = std::fs::remove_file(path);
+    }
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().map(|n| n.to_string_lossy().to_string())) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let entry_name = entry.file_name().to_string_lossy().to_string();
+        if let Some(rest) = entry_name.strip_prefix(&name) {
+            if rest.starts_with(".part") || rest == ".ytdl" {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+// Kills every registered child and removes the partial files they were writing
+fn shutdown_children() {
+    let children: Vec<(u32, Option<String>)> = match CHILDREN.lock() {
+        Ok(mut children) => children.drain().collect(),
+        Err(_) => return,
+    };
+    for (pid, output_path) in children {
+        info!("Stopping child process {} on exit", pid);
+        kill_process_tree_blocking(pid);
+        if let Some(path) = output_path {
+            remove_partial_output(std::path::Path::new(&path));
+        }
+    }
+}
+
+// Keeps a child registered until dropped, which also covers a wait that is abandoned
+// (a timed-out probe)
+struct ChildRegistration(Option<u32>);
+
+impl ChildRegistration {
+    fn new(pid: Option<u32>, output_path: Option<&str>) -> Self {
+        register_child(pid, output_path);
+        Self(pid)
+    }
+}
+
+impl Drop for ChildRegistration {
+    fn drop(&mut self) {
+        unregister_child(self.0);
+    }
+}
+
+// Command::output for helpers that run to completion (probes, previews, analysis passes),
+// registered for the length of the run so quitting the app stops them too
+async fn child_output(cmd: &mut Command, output_path: Option<&str>) -> std::io::Result<std::process::Output> {
+    let child = cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let _registration = ChildRegistration::new(child.id(), output_path);
+    child.wait_with_output().await
+}
+
+// Encoder options for animated WebP. libwebp_anim picks keyframes and frame blending
+// itself, which is most of what makes it smaller than a GIF.
+fn webp_anim_args(quality: Option<u32>, lossless: Option<bool>, plays: u32) -> Result<Vec<String>, String> {
+    let quality = quality.unwrap_or(75);
+    if quality > 100 {
+        return Err("WebP quality must be between 0 and 100".to_string());
+    }
+    Ok(vec![
+        "-c:v".to_string(),
+        "libwebp_anim".to_string(),
+        "-lossless".to_string(),
+        if lossless.unwrap_or(false) { "1" } else { "0" }.to_string(),
+        "-q:v".to_string(),
+        quality.to_string(),
+        "-loop".to_string(),
+        plays.to_string(),
+    ])
+}
+
+// APNG keeps full colour and alpha, for UI recordings and stickers. It's written with
+// a .png extension, which is what browsers and sticker uploads expect.
+fn apng_args(plays: u32) -> Vec<String> {
+    let plays = plays.to_string();
+    ["-c:v", "apng", "-pix_fmt", "rgba", "-plays", &plays, "-f", "apng"].iter().map(|a| a.to_string()).collect()
+}
+
+// File extension and encoder options for an animation format. loop_count follows the
+// GIF muxer: 0 loops forever, -1 plays once and N repeats N more times; WebP and
+// APNG count plays instead, with 0 still meaning forever.
+fn animation_output_args(format: Option<&str>, quality: Option<u32>, lossless: Option<bool>, loop_count: Option<i32>) -> Result<(&'static str, Vec<String>), String> {
+    let loop_count = loop_count.unwrap_or(0);
+    if !(-1..=65535).contains(&loop_count) {
+        return Err("loop_count must be -1 (play once), 0 (forever) or a number of repeats".to_string());
+    }
+    let plays = match loop_count {
+        0 => 0,
+        -1 => 1,
+        repeats => repeats as u32 + 1,
+    };
+    match format {
+        None | Some("") | Some("gif") => Ok(("gif", vec!["-loop".to_string(), loop_count.to_string()])),
+        Some("webp") => Ok(("webp", webp_anim_args(quality, lossless, plays)?)),
+        Some("apng") => Ok(("png", apng_args(plays))),
+        Some(other) => Err(format!("Unsupported animation format: {}", other)),
+    }
+}
+
 #[tauri::command]
-async fn image_to_gif(options: ImageToGifOptions) -> Result<String, String> {
+async fn image_to_gif(app: tauri::AppHandle, options: ImageToGifOptions) -> Result<String, String> {
     if options.image_paths.is_empty() {
-        return Err("No images provided".to_string());
+        return Err(msg("error.no_images"));
     }
+    check_scope(&app, options.image_paths.iter().chain(&options.output_folder).chain(&options.output_path))?;
 
-    let ffmpeg_path = get_ffmpeg_path();
     let fps = options.fps.unwrap_or(12).clamp(1, 60);
     let width = options.width.unwrap_or(480).clamp(64, 4096);
+    let (ext, mut encoder_args) = animation_output_args(options.format.as_deref(), options.webp_quality, options.webp_lossless, options.loop_count)?;
 
     let first_path = PathBuf::from(&options.image_paths[0]);
     let first_stem = first_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "animated".to_string());
@@ -45,7 +228,7 @@ async fn image_to_gif(options: ImageToGifOptions) -> Result<String, String> {
             .filter(|v| !v.is_empty())
             .map(PathBuf::from)
             .unwrap_or_else(|| first_path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from(".")));
-        output_base.join(format!("{}_animated.gif", first_stem))
+        output_base.join(format!("{}_animated.{}", first_stem, ext))
     };
 
     let mut concat_file = std::env::temp_dir();
@@ -60,6 +243,7 @@ async fn image_to_gif(options: ImageToGifOptions) -> Result<String, String> {
             .as_ref()
             .and_then(|arr| arr.get(index).copied())
             .unwrap_or((1000.0 / fps as f64).round().max(10.0) as u32);
+        let duration_ms = if index + 1 == options.image_paths.len() { duration_ms + options.hold_last_frame_ms.unwrap_or(0) } else { duration_ms };
         concat_lines.push_str(&format!("duration {:.3}\n", duration_ms as f64 / 1000.0));
     }
     if let Some(last) = options.image_paths.last() {
@@ -70,22 +254,62 @@ async fn image_to_gif(options: ImageToGifOptions) -> Result<String, String> {
 
     let output_path_str = output_path.to_string_lossy().to_string();
     let concat_str = concat_file.to_string_lossy().to_string();
-    let vf = format!("fps={},scale={}:-1:flags=lanczos,split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse", fps, width);
 
-    let output = new_command(&ffmpeg_path)
-        .args(&[
-            "-y",
-            "-f",
-            "concat",
-            "-safe",
-            "0",
-            "-i",
-            &concat_str,
-            "-vf",
-            &vf,
-            &output_path_str,
-        ])
-        .output()
+    // Frames stay RGBA throughout, so transparent PNGs (or a keyed-out background) come
+    // out transparent rather than filled with black. GIF has one transparent palette
+    // entry, so partly transparent pixels are rounded to fully on or off.
+    let mut alpha_filter = "format=rgba".to_string();
+    if let Some(color) = options.transparent_color.as_deref().filter(|c| !c.trim().is_empty()) {
+        let similarity = options.transparency_similarity.unwrap_or(0.1);
+        if !(0.01..=1.0).contains(&similarity) {
+            return Err("transparency_similarity must be between 0.01 and 1".to_string());
+        }
+        alpha_filter.push_str(&format!(",colorkey={}:{}:0", caption_color(Some(color), "black")?, similarity));
+    }
+    // Images of different sizes are scaled onto one canvas. The filter graph is kept
+    // across size changes (-reinit_filter 0), or rebuilding it on each change would
+    // restart the palette and break the GIF.
+    let dimensions: Vec<(u32, u32)> = options.image_paths.iter().filter_map(|p| image::image_dimensions(p).ok()).collect();
+    let mixed_sizes = dimensions.windows(2).any(|pair| pair[0] != pair[1]);
+    let size_filter = match (mixed_sizes, options.fit_mode.as_deref()) {
+        (false, None) => format!("scale={}:-1:flags=lanczos", width),
+        (_, mode) => {
+            let (first_w, first_h) = dimensions.first().copied().filter(|(w, h)| *w > 0 && *h > 0).unwrap_or((width, width));
+            let height = ((width as f64 * first_h as f64 / first_w as f64).round() as u32 / 2 * 2).max(2);
+            match mode.unwrap_or("fit") {
+                "fit" => {
+                    let background = match options.background_color.as_deref().filter(|c| !c.trim().is_empty()) {
+                        Some(color) => caption_color(Some(color), "black")?,
+                        None => "black@0".to_string(),
+                    };
+                    format!(
+                        "scale={w}:{h}:force_original_aspect_ratio=decrease:flags=lanczos,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2:color={bg},setsar=1",
+                        w = width, h = height, bg = background
+                    )
+                }
+                "fill" => format!("scale={w}:{h}:force_original_aspect_ratio=increase:flags=lanczos,crop={w}:{h},setsar=1", w = width, h = height),
+                other => return Err(format!("Unknown fit mode: {}", other)),
+            }
+        }
+    };
+    let vf = if ext != "gif" {
+        format!("{},fps={},{}", alpha_filter, fps, size_filter)
+    } else {
+        format!(
+            "{},fps={},{},split[s0][s1];[s0]palettegen=reserve_transparent=1[p];[s1][p]paletteuse=alpha_threshold=128",
+            alpha_filter, fps, size_filter
+        )
+    };
+    match ext {
+        // Differencing against the previous frame would let it show through the transparent parts
+        "gif" => encoder_args.extend(["-gifflags".to_string(), "-transdiff".to_string()]),
+        "webp" => encoder_args.extend(["-pix_fmt".to_string(), "yuva420p".to_string()]),
+        _ => {}
+    }
+
+    let output = child_output(ffmpeg_command(["-y", "-reinit_filter", "0", "-f", "concat", "-safe", "0", "-i", &concat_str, "-vf", &vf])
+        .args(&encoder_args)
+        .arg(&output_path_str), Some(&output_path_str))
         .await
         .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
 
@@ -100,7 +324,13 @@ async fn image_to_gif(options: ImageToGifOptions) -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn pdf_to_images(pdf_path: String, output_dir: String, format: Option<String>) -> Result<String, String> {
+async fn pdf_to_images(app: tauri::AppHandle, window: tauri::WebviewWindow, pdf_path: String, output_dir: String, format: Option<String>, chroma_subsampling: Option<String>, progressive: Option<bool>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    // Pages are written by ffmpeg's MJPEG encoder, which only does baseline JPEG
+    if progressive.unwrap_or(false) {
+        return Err("Progressive JPEG is not available when exporting PDF pages".to_string());
+    }
+    check_scope(&app, [&pdf_path, &output_dir])?;
     let validated_pdf = validate_path(&pdf_path).ok_or("Invalid PDF path")?;
     let pdf_stem = validated_pdf
         .file_stem()
@@ -118,27 +348,42 @@ async fn pdf_to_images(pdf_path: String, output_dir: String, format: Option<Stri
     let export_folder = output_root.join(format!("{}_pages", pdf_stem));
     std::fs::create_dir_all(&export_folder).map_err(|e| format!("Failed to create export folder: {}", e))?;
 
+    // Page count drives per-page progress; ffmpeg's own timestamps mean nothing for a PDF
+    let page_count = printpdf::lopdf::Document::load(&validated_pdf)
+        .map(|doc| doc.get_pages().len())
+        .ok();
+
     let output_pattern = export_folder.join(format!("page_%04d.{}", ext)).to_string_lossy().to_string();
-    let ffmpeg_path = get_ffmpeg_path();
-    let output = new_command(&ffmpeg_path)
-        .args(&[
-            "-y",
-            "-i",
-            &pdf_path,
-            "-vsync",
-            "0",
-            &output_pattern,
-        ])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run ffmpeg for PDF export: {}", e))?;
+    let mut args: Vec<String> = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        pdf_path.clone(),
+        "-vsync".to_string(),
+        "0".to_string(),
+    ];
+    if ext == "jpg" {
+        if let Some(pix_fmt) = jpeg_pix_fmt(chroma_subsampling.as_deref()) {
+            args.extend(["-pix_fmt".to_string(), pix_fmt.to_string()]);
+        }
+    }
+    args.push(output_pattern);
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to convert PDF pages: {}", stderr));
+    let export_path = export_folder.to_string_lossy().to_string();
+    let run = run_ffmpeg_job_with_frames(&app, "pdf_to_images", None, &args, &export_path, None, page_count).await?;
+
+    if run.cancelled {
+        let _ = std::fs::remove_dir_all(&export_folder);
+        emit_job_cancelled(&app, "pdf_to_images");
+        return Err("PDF export cancelled".to_string());
+    }
+    if !run.success {
+        let message = format!("Failed to convert PDF pages: {}", run.stderr_tail);
+        emit_job_error(&app, "pdf_to_images", message.clone());
+        return Err(message);
     }
 
-    Ok(export_folder.to_string_lossy().to_string())
+    emit_job_complete(&app, "pdf_to_images", &export_path);
+    Ok(export_path)
 }
 
 fn uuid_like_seed(paths: &[String]) -> u64 {
@@ -160,7 +405,9 @@ pub struct Filter {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncodeOptions {
+    #[serde(default)]
     pub input: String,
+    #[serde(default)]
     pub format: String,
     pub codec: Option<String>,
     pub preset: Option<String>,
@@ -180,6 +427,28 @@ pub struct EncodeOptions {
     pub resolution: Option<String>,
     pub work_priority: Option<String>,
     pub threads: Option<u32>,
+    pub tune: Option<String>,
+    pub profile: Option<String>,
+    pub level: Option<String>,
+    pub gop_size: Option<u32>,
+    pub b_frames: Option<u32>,
+    pub codec_params: Option<HashMap<String, String>>,
+    pub preserve_metadata: Option<bool>,
+    pub subtitle_policy: Option<String>,
+    pub faststart: Option<bool>,
+    pub fragmented: Option<bool>,
+    pub field_order: Option<String>,
+    pub telecine: Option<String>,
+    pub decimate: Option<bool>,
+    pub loudness_preset: Option<String>,
+    pub input_fps: Option<String>,
+    pub color_space: Option<String>,
+    pub source_color_space: Option<String>,
+    pub color_mode: Option<String>,
+    pub color_range: Option<String>,
+    pub audio_offset_ms: Option<i64>,
+    pub inverse_telecine: Option<bool>,
+    pub speed: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -193,17 +462,48 @@ pub struct SubtitleTrack {
     pub path: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExtractAudioOptions {
     pub input: String,
+    #[serde(default)]
     pub format: String,
     pub bitrate: Option<String>,
     pub sample_rate: Option<String>,
     pub mp3_mode: Option<String>,
     pub mp3_quality: Option<String>,
+    // "cbr" (bitrate) or "vbr" (aac_quality, 0.1-2 for ffmpeg's AAC encoder)
+    pub aac_mode: Option<String>,
+    pub aac_quality: Option<String>,
+    // libopus rate control: "vbr", "cvbr" (constrained) or "cbr", around the bitrate;
+    // compression level 0-10 trades encoding speed for quality
+    pub opus_mode: Option<String>,
+    pub opus_compression_level: Option<String>,
     pub flac_level: Option<String>,
+    // WAV/FLAC sample format: "16", "24" or "32f" (float, WAV only)
+    pub bit_depth: Option<String>,
     pub output_folder: Option<String>,
     pub work_priority: Option<String>,
+    pub loudness_preset: Option<String>,
+    // Which audio stream to take: position among the audio streams, or a language tag
+    pub stream_index: Option<usize>,
+    pub language: Option<String>,
+    // Only extract this part of the input, e.g. a single song or quote
+    pub start_seconds: Option<f64>,
+    pub end_seconds: Option<f64>,
+    // Fades at the edges of the extracted clip (after any range trim)
+    pub fade_in_seconds: Option<f64>,
+    pub fade_out_seconds: Option<f64>,
+    // "stereo" (downmix), "mono", "channel" (one channel, see channel) or "split_mono"
+    // (a stereo source into separate left and right files)
+    pub channel_mode: Option<String>,
+    // Channel name such as "FC" or "LFE", or its position, for the "channel" mode
+    pub channel: Option<String>,
+    // Cover art for MP3/M4A/FLAC: an image path or URL, "first_frame" for the video's
+    // first frame, or "thumbnail" for the image yt-dlp saved next to a download
+    pub album_art: Option<String>,
+    // "instrumental" (karaoke: cancels what is mixed to the centre) or "vocals" (keeps
+    // only the centre); stereo sources only
+    pub vocal_mode: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -215,6 +515,32 @@ pub struct ImageToGifOptions {
     pub output_folder: Option<String>,
     pub output_path: Option<String>,
     pub work_priority: Option<String>,
+    // "gif" (default), "webp" or "apng", with the same quality settings as video_to_gif
+    pub format: Option<String>,
+    pub webp_quality: Option<u32>,
+    pub webp_lossless: Option<bool>,
+    // 0 loops forever (default), -1 plays once, N repeats N more times
+    pub loop_count: Option<i32>,
+    // Extra time on the last frame before the animation ends or starts over
+    pub hold_last_frame_ms: Option<u32>,
+    // Background colour to make transparent (chroma key), with how close a colour has
+    // to be to count, from 0.01 (exact) to 1 (default 0.1)
+    pub transparent_color: Option<String>,
+    pub transparency_similarity: Option<f64>,
+    // How frames of different sizes are fitted to the canvas (the first image's shape
+    // at the output width): "fit" (default) letterboxes, "fill" crops the overflow
+    pub fit_mode: Option<String>,
+    // Letterbox colour for "fit"; transparent when not set
+    pub background_color: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PdfExportOptions {
+    pub deskew: Option<bool>,
+    pub auto_orient: Option<bool>,
+    pub chroma_subsampling: Option<String>,
+    pub progressive: Option<bool>,
+    pub compression: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -229,6 +555,7 @@ pub struct TrimVideoOptions {
     pub input: String,
     pub start_seconds: f64,
     pub end_seconds: f64,
+    pub format: Option<String>,
     pub output_folder: Option<String>,
     pub work_priority: Option<String>,
 }
@@ -249,6 +576,56 @@ pub struct DownloadOptions {
     pub format_id: Option<String>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NormalizeAudioOptions {
+    pub loudness_range: Option<f64>,
+    pub audio_bitrate: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioSpeedOptions {
+    pub pitch_mode: Option<String>,
+    pub pitch_semitones: Option<f64>,
+    pub format: Option<String>,
+    pub bitrate: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConvertAudioOptions {
+    // mp3, flac, wav, opus or aac (written as .m4a)
+    pub format: String,
+    pub bitrate: Option<String>,
+    pub sample_rate: Option<String>,
+    // Transpose by this many semitones (-12 to 12) without changing the tempo
+    pub pitch_semitones: Option<f64>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayGainOptions {
+    // Write the tags, or only report the values (default true)
+    pub write_tags: Option<bool>,
+    // Treat the files as one album, on by default when there is more than one
+    pub album: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PipedConvertOptions {
+    // mp3, m4a, opus, flac, wav (audio) or mp4, mkv, webm (video)
+    pub output_format: Option<String>,
+    // Maximum video height, ignored for audio formats
+    pub quality: Option<String>,
+    // Raw yt-dlp -f selector, overrides the one picked from output_format and quality
+    pub format_selector: Option<String>,
+    pub video_codec: Option<String>,
+    pub crf: Option<u32>,
+    pub audio_bitrate: Option<String>,
+    pub file_name: Option<String>,
+    pub output_path: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VideoToGifOptions {
     pub input: String,
@@ -258,1663 +635,12550 @@ pub struct VideoToGifOptions {
     pub start_seconds: Option<f64>,
     pub end_seconds: Option<f64>,
     pub crop: Option<serde_json::Value>,
+    pub format: Option<String>,
     pub output_folder: Option<String>,
     pub work_priority: Option<String>,
+    pub input_fps: Option<String>,
+    // GIF palette: 2-256 colours, built from every frame ("full"), only what moves
+    // ("diff", default) or per frame ("single")
+    pub max_colors: Option<u32>,
+    pub stats_mode: Option<String>,
+    // "sierra2_4a" (default), "floyd_steinberg", "bayer" (with bayer_scale 0-5, higher
+    // is less visible but larger) or "none"
+    pub dither: Option<String>,
+    pub bayer_scale: Option<u32>,
+    // Only redraw the changed part of each frame, then run gifsicle -O3 if it's installed
+    pub optimize: Option<bool>,
+    // Animated WebP: quality 0-100 (default 75), or lossless
+    pub webp_quality: Option<u32>,
+    pub webp_lossless: Option<bool>,
+    // 0 loops forever (default), -1 plays once, N repeats N more times
+    pub loop_count: Option<i32>,
+    // Extra time on the last frame before the animation ends or starts over
+    pub hold_last_frame_ms: Option<u32>,
+    // "forward" (default), "reverse", or "pingpong" (forward then back, a boomerang)
+    pub direction: Option<String>,
+    pub caption: Option<GifCaption>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GifOptimizeOptions {
+    // Narrower frames and fewer of them, both only ever reduced
+    pub width: Option<u32>,
+    pub fps: Option<u32>,
+    // Palette settings as in VideoToGifOptions
+    pub max_colors: Option<u32>,
+    pub dither: Option<String>,
+    pub bayer_scale: Option<u32>,
+    // gifsicle --lossy level (1-200, around 30-80 is typical); needs gifsicle installed
+    pub lossy: Option<u32>,
+    pub output_folder: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct VideoMetadata {
-    pub resolution: Option<String>,
-    pub width: Option<u32>,
-    pub height: Option<u32>,
-    pub duration: Option<String>,
-    pub duration_seconds: Option<f64>,
-    pub bitrate: Option<String>,
-    pub fps: Option<f64>,
+pub struct GifOptimizeReport {
+    pub output_path: String,
+    pub original_bytes: u64,
+    pub optimized_bytes: u64,
+    // Share of the original size saved, negative if the result came out bigger
+    pub saved_percent: f64,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StickerOptions {
+    pub start_seconds: Option<f64>,
+    pub end_seconds: Option<f64>,
+    // Same shape as VideoToGifOptions::crop: {x, y, w, h} in source pixels
+    pub crop: Option<serde_json::Value>,
+    pub output_folder: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ImageInfo {
-    pub width: u32,
-    pub height: u32,
-    pub codec: String,
-    pub pixel_format: String,
-    pub color_space: String,
-    pub bit_depth: String,
-    pub format: String,
-    pub size_bytes: u64,
-    pub mtime_ms: u64,
+pub struct StickerExportReport {
+    pub output_path: String,
+    pub preset: String,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub within_limit: bool,
+    // Settings of the attempt that was kept
+    pub fps: u32,
+    pub quality: String,
+    pub attempts: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GifCaption {
+    pub text: String,
+    // A font file, or a family name looked up in the system font folders (default Impact)
+    pub font: Option<String>,
+    // Pixels on the output frame; defaults to a tenth of its height
+    pub size: Option<u32>,
+    // Colour names or hex, e.g. "white" or "#ffcc00"
+    pub color: Option<String>,
+    pub outline_color: Option<String>,
+    pub outline_width: Option<u32>,
+    // "top" (default), "bottom" or "center"
+    pub position: Option<String>,
+    // When the caption shows, in seconds of the GIF (after trimming and speed)
+    pub start_seconds: Option<f64>,
+    pub end_seconds: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProgressEvent {
-    pub percent: f64,
-    pub time: String,
-    pub speed: String,
+pub struct ScreenshotBurstOptions {
+    pub input: String,
+    pub timestamp: f64,
+    pub count: Option<u32>,
+    pub window_seconds: Option<f64>,
+    pub format: Option<String>,
+    pub output_folder: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DownloadProgress {
-    pub percent: Option<f64>,
-    pub size: Option<String>,
-    pub speed: Option<String>,
-    pub eta: Option<String>,
-    pub status: Option<String>,
+pub struct BestThumbnailOptions {
+    pub input: String,
+    pub candidates: Option<u32>,
+    pub top_k: Option<u32>,
+    pub format: Option<String>,
+    pub output_folder: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EncoderInfo {
-    pub nvenc: bool,
-    pub amf: bool,
-    pub qsv: bool,
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailCandidate {
+    pub path: String,
+    pub time_seconds: f64,
+    pub score: f64,
+    pub sharpness: f64,
+    pub brightness: f64,
+    pub contrast: f64,
+    pub edge_density: f64,
+    pub skin_ratio: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairOptions {
+    pub input: String,
+    pub output_folder: Option<String>,
+    pub strategy: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct VideoInfoResult {
-    pub is_playlist: bool,
-    pub title: Option<String>,
-    pub thumbnail: Option<String>,
-    pub duration: Option<String>,
-    pub channel: Option<String>,
-    pub is_video: Option<bool>,
-    pub formats: Option<Vec<serde_json::Value>>,
-    pub url: Option<String>,
-    pub count: Option<u32>,
-    pub entries: Option<Vec<serde_json::Value>>,
-    pub error: Option<String>,
+pub struct Mp4Atom {
+    pub kind: String,
+    pub offset: u64,
+    pub size: u64,
+    pub truncated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ThumbnailResult {
-    pub data: String,
-    pub count: u32,
-    pub cols: u32,
-    pub rows: u32,
-    pub interval: f64,
+pub struct RepairAttempt {
+    pub strategy: String,
+    pub success: bool,
+    pub message: Option<String>,
 }
 
-// ============================================================================
-// Global State for Process Management
-// ============================================================================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    pub output_path: Option<String>,
+    pub strategy: Option<String>,
+    pub attempts: Vec<RepairAttempt>,
+    pub input_atoms: Vec<Mp4Atom>,
+    pub output_atoms: Vec<Mp4Atom>,
+    pub moov_recovered: bool,
+    pub duration_seconds: Option<f64>,
+    pub has_video: bool,
+    pub has_audio: bool,
+}
 
-struct AppState {
-    current_pid: Mutex<Option<u32>>,
-    current_output_path: Mutex<Option<String>>,
-    is_cancelling: Mutex<bool>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoverRecordingOptions {
+    pub input: String,
+    pub reference: Option<String>,
+    pub output_folder: Option<String>,
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        Self {
-            current_pid: Mutex::new(None),
-            current_output_path: Mutex::new(None),
-            is_cancelling: Mutex::new(false),
-        }
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryResult {
+    pub output_path: String,
+    pub method: String,
+    pub duration_seconds: Option<f64>,
+    pub message: Option<String>,
 }
 
-// ============================================================================
-// Utility Functions
-// ============================================================================
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConcatAudioOptions {
+    // mp3, m4a, m4b (audiobook), flac, wav or opus
+    pub format: String,
+    // Silence between the files, or an overlap that fades one into the next (not both)
+    pub gap_ms: Option<u32>,
+    pub crossfade_ms: Option<u32>,
+    pub bitrate: Option<String>,
+    // A chapter per source file, named after it (default on)
+    pub chapters: Option<bool>,
+    pub output_folder: Option<String>,
+}
 
-fn get_ffmpeg_path() -> String {
-    // Try to find ffmpeg in PATH or in bin folder
-    // First check if bundled in resources
-    if let Ok(exe_path) = std::env::current_exe() {
-        let bin_path = exe_path.parent().map(|p| p.join("bin").join("ffmpeg.exe"));
-        if let Some(path) = bin_path {
-            if path.exists() {
-                return path.to_string_lossy().to_string();
-            }
-        }
-    }
-    // Fallback to system PATH
-    "ffmpeg".to_string()
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioVisualizerOptions {
+    // "waves" (showwaves), "spectrum" (showspectrum) or "cqt" (showcqt, musical notes)
+    pub style: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<u32>,
+    // Hex colours such as "#63f1af"; the waveform colour is ignored by the other styles
+    pub color: Option<String>,
+    pub background_color: Option<String>,
+    // Still image behind the visualization, cropped to fill the frame
+    pub background_image: Option<String>,
+    pub output_folder: Option<String>,
 }
 
-fn get_ffprobe_path() -> String {
-    if let Ok(exe_path) = std::env::current_exe() {
-        let bin_path = exe_path.parent().map(|p| p.join("bin").join("ffprobe.exe"));
-        if let Some(path) = bin_path {
-            if path.exists() {
-                return path.to_string_lossy().to_string();
-            }
-        }
-    }
-    "ffprobe".to_string()
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConcatOptions {
+    pub mode: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<String>,
+    pub crf: Option<u32>,
+    pub format: Option<String>,
+    pub output_path: Option<String>,
+    pub output_folder: Option<String>,
+    pub transition: Option<String>,
+    pub transition_seconds: Option<f64>,
 }
 
- async fn has_audio_stream(file_path: &str) -> Result<bool, String> {
-     let ffprobe_path = get_ffprobe_path();
-     let output = new_command(&ffprobe_path)
-         .args(&[
-             "-v", "error",
-             "-select_streams", "a:0",
-             "-show_entries", "stream=index",
-             "-of", "csv=p=0",
-             file_path,
-         ])
-         .output()
-         .await
-         .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterInfo {
+    pub index: usize,
+    pub title: Option<String>,
+    pub start: f64,
+    pub end: f64,
+}
 
-     if !output.status.success() {
-         let stderr = String::from_utf8_lossy(&output.stderr);
-         return Err(format!("ffprobe failed: {}", stderr));
-     }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitVideoOptions {
+    pub input: String,
+    pub mode: String,
+    pub parts: Option<u32>,
+    pub segment_seconds: Option<f64>,
+    pub max_size_mb: Option<f64>,
+    pub output_folder: Option<String>,
+}
 
-     let stdout = String::from_utf8_lossy(&output.stdout);
-     Ok(!stdout.trim().is_empty())
- }
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SilenceSplitOptions {
+    // Level below which audio counts as silence, in dB (default -40)
+    pub noise_db: Option<f64>,
+    // How long a gap has to be to split there (default 2 s)
+    pub min_silence_seconds: Option<f64>,
+    // Gaps that would leave a shorter track are ignored (default 20 s)
+    pub min_track_seconds: Option<f64>,
+    // Silence kept at each end of a track (default 0.3 s)
+    pub keep_silence_seconds: Option<f64>,
+    // mp3, m4a, flac, wav or opus; unset copies the audio as is
+    pub format: Option<String>,
+    pub name_prefix: Option<String>,
+    pub start_number: Option<u32>,
+    pub output_folder: Option<String>,
+}
 
-fn get_ytdlp_path() -> String {
-    if let Ok(exe_path) = std::env::current_exe() {
-        let bin_path = exe_path.parent().map(|p| p.join("bin").join("yt-dlp.exe"));
-        if let Some(path) = bin_path {
-            if path.exists() {
-                return path.to_string_lossy().to_string();
-            }
-        }
-    }
-    "yt-dlp".to_string()
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandPreview {
+    pub program: String,
+    pub args: Vec<String>,
+    pub output_path: String,
+    pub command_line: String,
+    pub warnings: Vec<String>,
 }
 
-fn validate_path(input_path: &str) -> Option<PathBuf> {
-    if input_path.is_empty() {
-        return None;
-    }
-    let path = PathBuf::from(input_path);
-    if path.exists() {
-        Some(path)
-    } else {
-        None
-    }
+// One processed file as the queue saw it; sizes and durations are filled in by the backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobReportEntry {
+    pub input: String,
+    pub output_path: Option<String>,
+    pub operation: Option<String>,
+    pub settings: Option<serde_json::Value>,
+    pub status: Option<String>,
+    pub error: Option<String>,
+    pub elapsed_seconds: Option<f64>,
 }
 
-fn validate_url(url: &str) -> bool {
-    if let Ok(url_obj) = url::Url::parse(url) {
-        url_obj.scheme() == "http" || url_obj.scheme() == "https"
-    } else {
-        false
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobReportOptions {
+    pub format: Option<String>,
+    pub output_path: Option<String>,
+    pub title: Option<String>,
 }
 
-// ============================================================================
-// Dialog Commands
-// ============================================================================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobReportRow {
+    pub input: String,
+    pub output_path: Option<String>,
+    pub operation: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub settings: Option<serde_json::Value>,
+    pub elapsed_seconds: Option<f64>,
+    pub input_bytes: Option<u64>,
+    pub output_bytes: Option<u64>,
+    pub input_duration: Option<f64>,
+    pub output_duration: Option<f64>,
+    pub size_ratio: Option<f64>,
+}
 
-#[tauri::command]
-async fn select_file(app: tauri::AppHandle, filters: Option<Vec<Filter>>, _allow_all: Option<bool>) -> Result<Option<String>, String> {
-    info!("select_file called with filters: {:?}", filters);
-    
-    use tauri_plugin_dialog::DialogExt;
-    
-    let mut builder = app.dialog().file();
-    
-    if let Some(f) = filters {
-        for filter in f {
-            let extensions: Vec<&str> = filter.extensions.iter().map(|s| s.as_str()).collect();
-            builder = builder.add_filter(filter.name, &extensions);
-        }
-    }
-    
-    let result = builder.blocking_pick_file();
-    
-    Ok(result.map(|p| p.to_string()))
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityMetric {
+    pub name: String,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub harmonic_mean: Option<f64>,
+    pub frames: Vec<f64>,
 }
 
-#[tauri::command]
-async fn select_files(app: tauri::AppHandle, filters: Option<Vec<Filter>>, _allow_all: Option<bool>) -> Result<Vec<String>, String> {
-    info!("select_files called with filters: {:?}", filters);
-    
-    use tauri_plugin_dialog::DialogExt;
-    
-    let mut builder = app.dialog().file();
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityReport {
+    pub reference: String,
+    pub encoded: String,
+    pub frame_count: usize,
+    pub metrics: Vec<QualityMetric>,
+}
 
-    if let Some(f) = filters {
-        for filter in f {
-            let extensions: Vec<&str> = filter.extensions.iter().map(|s| s.as_str()).collect();
-            builder = builder.add_filter(filter.name, &extensions);
-        }
-    }
-    
-    let result = builder.blocking_pick_files();
-    
-    Ok(result.map(|paths| paths.into_iter().map(|p| p.to_string()).collect()).unwrap_or_default())
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonOptions {
+    pub mode: Option<String>,
+    pub label_a: Option<String>,
+    pub label_b: Option<String>,
+    pub height: Option<u32>,
+    pub start_seconds: Option<f64>,
+    pub duration_seconds: Option<f64>,
+    pub crf: Option<u32>,
+    pub output_path: Option<String>,
+    pub output_folder: Option<String>,
 }
 
-#[tauri::command]
-async fn save_file(app: tauri::AppHandle, _filters: Option<Vec<Filter>>, default_path: Option<String>, _title: Option<String>) -> Result<Option<String>, String> {
-    info!("save_file called with default_path: {:?}", default_path);
-    
-    use tauri_plugin_dialog::DialogExt;
-    
-    let result = app.dialog()
-        .file()
-        .set_file_name(default_path.unwrap_or_else(|| "output.pdf".to_string()))
-        .blocking_save_file();
-    
-    Ok(result.map(|p| p.to_string()))
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SceneDetectOptions {
+    pub min_scene_seconds: Option<f64>,
+    pub export: Option<String>,
+    pub thumbnail_format: Option<String>,
+    pub output_folder: Option<String>,
 }
 
-#[tauri::command]
-async fn select_folder(app: tauri::AppHandle) -> Result<Option<String>, String> {
-    info!("select_folder called");
-    
-    use tauri_plugin_dialog::DialogExt;
-    
-    let result = app.dialog()
-        .file()
-        .blocking_pick_folder();
-    
-    Ok(result.map(|p| p.to_string()))
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneInfo {
+    pub index: usize,
+    pub start: f64,
+    pub end: f64,
+    pub duration: f64,
+    pub score: Option<f64>,
+    pub thumbnail_path: Option<String>,
 }
 
-// ============================================================================
-// Utility Commands
-// ============================================================================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvSyncReport {
+    // "in_sync", "offset", "drift" or "inconclusive"
+    pub verdict: String,
+    // Audio start minus video start as stored in the container
+    pub container_offset_ms: i64,
+    // Median delay of sound onsets behind scene cuts, positive when the audio is late
+    pub estimated_offset_ms: Option<i64>,
+    // Change in that delay between the first and last part of the file
+    pub drift_ms: Option<i64>,
+    pub matched_events: usize,
+    pub confidence: f64,
+    // Value to pass to fix_av_sync, only set for a steady offset
+    pub suggested_offset_ms: Option<i64>,
+    pub segments: Vec<AvSyncSegment>,
+}
 
-#[tauri::command]
-async fn list_files(directory: String, extensions: Option<Vec<String>>) -> Result<Vec<String>, String> {
-    info!("list_files called for directory: {}", directory);
-    
-    let path = PathBuf::from(&directory);
-    if !path.exists() || !path.is_dir() {
-        return Err("Invalid directory path".to_string());
-    }
-    
-    let video_extensions = extensions.unwrap_or_else(|| {
-        vec!["mp4".to_string(), "mkv".to_string(), "avi".to_string(), "mov".to_string(), "webm".to_string(), "flv".to_string(), "wmv".to_string()]
-    });
-    
-    let mut files = Vec::new();
-    
-    if let Ok(entries) = std::fs::read_dir(&path) {
-        for entry in entries.flatten() {
-            if let Ok(file_type) = entry.file_type() {
-                if file_type.is_file() {
-                    if let Some(ext) = entry.path().extension() {
-                        let ext_str = ext.to_string_lossy().to_lowercase();
-                        if video_extensions.iter().any(|e| e.to_lowercase() == ext_str) {
-                            files.push(entry.path().to_string_lossy().to_string());
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    Ok(files)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvSyncSegment {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub offset_ms: i64,
+    pub matches: usize,
 }
 
-#[tauri::command]
-async fn get_app_version(app: tauri::AppHandle) -> Result<String, String> {
-    info!("get_app_version called");
-    Ok(app.package_info().version.to_string())
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelecineReport {
+    // "telecined", "interlaced" or "progressive"
+    pub verdict: String,
+    pub frame_rate: Option<f64>,
+    pub progressive_frames: u64,
+    pub interlaced_frames: u64,
+    pub undetermined_frames: u64,
 }
 
-// ============================================================================
-// FFmpeg Commands
-// ============================================================================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGainTrack {
+    pub path: String,
+    pub integrated_lufs: f64,
+    pub track_gain_db: f64,
+    // Linear true peak, 1.0 is full scale
+    pub track_peak: f64,
+    pub tagged: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
 
-#[tauri::command]
-async fn get_encoders() -> Result<EncoderInfo, String> {
-    info!("get_encoders called");
-    
-    let ffmpeg_path = get_ffmpeg_path();
-    
-    let output = new_command(&ffmpeg_path)
-        .args(&["-encoders"])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
-    
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let stderr_str = String::from_utf8_lossy(&output.stderr);
-    let combined = format!("{}{}", output_str, stderr_str);
-    
-    let encoders = EncoderInfo {
-        nvenc: combined.contains("h264_nvenc") || combined.contains("hevc_nvenc"),
-        amf: combined.contains("h264_amf") || combined.contains("hevc_amf"),
-        qsv: combined.contains("h264_qsv") || combined.contains("hevc_qsv"),
-    };
-    
-    Ok(encoders)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGainReport {
+    pub album_gain_db: Option<f64>,
+    pub album_peak: Option<f64>,
+    pub tracks: Vec<ReplayGainTrack>,
 }
 
-#[tauri::command]
-async fn get_metadata(file_path: String) -> Result<VideoMetadata, String> {
-    info!("get_metadata called for: {}", file_path);
-    
-    let validated = validate_path(&file_path).ok_or("Invalid file path")?;
-    let path_str = validated.to_string_lossy().to_string();
-    
-    // Use ffprobe to get basic metadata
-    let ffprobe_path = get_ffprobe_path();
-    let output = new_command(&ffprobe_path)
-        .args(&[
-            "-v", "error",
-            "-select_streams", "v:0",
-            "-show_entries", "stream=width,height,r_frame_rate",
-            "-of", "csv=p=0",
-            &path_str
-        ])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneReport {
+    pub input: String,
+    pub threshold: f64,
+    pub scenes: Vec<SceneInfo>,
+    pub output_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessPreset {
+    pub id: String,
+    pub name: String,
+    pub integrated_lufs: f64,
+    pub true_peak_db: f64,
+    pub loudness_range: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscTitle {
+    pub id: String,
+    pub name: String,
+    pub duration_seconds: f64,
+    pub chapters: Vec<f64>,
+    pub size_bytes: u64,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscInfo {
+    pub kind: String,
+    pub root: String,
+    pub titles: Vec<DiscTitle>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GifToVideoOptions {
+    pub loops: Option<u32>,
+    pub min_duration_seconds: Option<f64>,
+    pub width: Option<u32>,
+    pub crf: Option<u32>,
+    pub background: Option<String>,
+    pub keep_transparency: Option<bool>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimelapseOptions {
+    pub every_nth_frame: Option<u32>,
+    pub speed_factor: Option<f64>,
+    pub fps: Option<f64>,
+    pub blend: Option<String>,
+    pub crf: Option<u32>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageSequence {
+    pub pattern: String,
+    pub first_frame: u64,
+    pub last_frame: u64,
+    pub frame_count: usize,
+    pub missing_frames: usize,
+    pub fps_guess: f64,
+    pub duration_seconds: f64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StackVideosOptions {
+    pub cell_height: Option<u32>,
+    pub audio: Option<String>,
+    pub shortest: Option<bool>,
+    pub crf: Option<u32>,
+    pub output_path: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntroOutroOptions {
+    pub intro: Option<String>,
+    pub outro: Option<String>,
+    pub transition: Option<String>,
+    pub transition_seconds: Option<f64>,
+    pub crf: Option<u32>,
+    pub output_path: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WipeExportOptions {
+    pub kind: Option<String>,
+    pub format: Option<String>,
+    pub timestamp: Option<f64>,
+    pub position: Option<f64>,
+    pub duration_seconds: Option<f64>,
+    pub width: Option<u32>,
+    pub label_a: Option<String>,
+    pub label_b: Option<String>,
+    pub output_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AvSyncOptions {
+    pub output_path: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MusicMixOptions {
+    // Music level under the original audio, 0-1 (default 0.25)
+    pub music_volume: Option<f64>,
+    // Turn the music down further while the original audio is loud, e.g. speech (default on)
+    pub ducking: Option<bool>,
+    // Repeat a music track that is shorter than the video (default on)
+    pub loop_music: Option<bool>,
+    pub fade_in_seconds: Option<f64>,
+    // Fade the music out before the video ends (default 3)
+    pub fade_out_seconds: Option<f64>,
+    pub audio_bitrate: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplaceAudioOptions {
+    pub fit: Option<String>,
+    pub audio_codec: Option<String>,
+    pub audio_bitrate: Option<String>,
+    pub output_path: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccessibilityMuxOptions {
+    pub audio_description: Option<String>,
+    pub audio_description_language: Option<String>,
+    pub main_audio_language: Option<String>,
+    pub captions: Option<Vec<CaptionTrack>>,
+    pub output_path: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rendition {
+    // Used in the file name, defaults to "720p" style or "audio"
+    pub name: Option<String>,
+    // "video" (default) or "audio" for an audio-only output
+    pub kind: Option<String>,
+    pub height: Option<u32>,
+    // "h264" (default), "h265" or "vp9"; for audio-only outputs "aac", "mp3" or "opus"
+    pub codec: Option<String>,
+    pub crf: Option<u32>,
+    // Target video bitrate in kbps, used instead of crf when set
+    pub bitrate: Option<u32>,
+    pub preset: Option<String>,
+    pub audio_codec: Option<String>,
+    pub audio_bitrate: Option<u32>,
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenditionOptions {
+    pub output_folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptionTrack {
+    pub path: String,
+    pub language: Option<String>,
+    // "subtitles", "sdh" (for the deaf and hard of hearing) or "forced"
+    pub kind: Option<String>,
+    pub default: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoMetadata {
+    pub resolution: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub bitrate: Option<String>,
+    pub fps: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    pub codec: String,
+    pub pixel_format: String,
+    pub color_space: String,
+    pub bit_depth: String,
+    pub format: String,
+    pub size_bytes: u64,
+    pub mtime_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    pub percent: f64,
+    pub time: String,
+    pub speed: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub schema_version: u32,
+    pub job_type: String,
+    pub percent: Option<f64>,
+    pub size: Option<String>,
+    pub speed: Option<String>,
+    pub eta: Option<String>,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncoderInfo {
+    pub nvenc: bool,
+    pub amf: bool,
+    pub qsv: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoInfoResult {
+    pub is_playlist: bool,
+    pub title: Option<String>,
+    pub thumbnail: Option<String>,
+    pub duration: Option<String>,
+    pub channel: Option<String>,
+    pub is_video: Option<bool>,
+    pub formats: Option<Vec<serde_json::Value>>,
+    pub url: Option<String>,
+    pub count: Option<u32>,
+    pub entries: Option<Vec<serde_json::Value>>,
+    pub error: Option<String>,
+    // Set when the failure is a known dead end such as DRM or a login wall
+    pub error_kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailResult {
+    pub data: String,
+    pub count: u32,
+    pub cols: u32,
+    pub rows: u32,
+    pub interval: f64,
+}
+
+// ============================================================================
+// Global State for Process Management
+// ============================================================================
+
+struct AppState {
+    current_pid: Mutex<Option<u32>>,
+    current_output_path: Mutex<Option<String>>,
+    is_cancelling: Mutex<bool>,
+    // Microphone/system audio capture, which runs alongside the other jobs
+    recording: std::sync::Mutex<Option<ActiveRecording>>,
+    // Kept open for the app's lifetime: on X11 the copied data is served by whoever
+    // owns the clipboard, so it would vanish if the handle were dropped after copying
+    clipboard: std::sync::Mutex<Option<arboard::Clipboard>>,
+    // Rate limiting state for progress events, keyed by window label and job type
+    progress_throttle: std::sync::Mutex<HashMap<String, ProgressSlot>>,
+    // (window label, job type) pairs whose jobs are sent to every window rather than only
+    // the window that started them
+    job_broadcast: std::sync::Mutex<std::collections::HashSet<(String, String)>>,
+    // Folders the user has opened through a dialog or by dropping files on the window;
+    // file commands refuse paths outside them (see scoped_path)
+    path_scope: std::sync::Mutex<Vec<PathBuf>>,
+}
+
+// Last progress event sent for a job and the newest one held back since
+#[derive(Default)]
+struct ProgressSlot {
+    last_emit: Option<std::time::Instant>,
+    pending: Option<(String, serde_json::Value)>,
+    flush_scheduled: bool,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            current_pid: Mutex::new(None),
+            current_output_path: Mutex::new(None),
+            is_cancelling: Mutex::new(false),
+            recording: std::sync::Mutex::new(None),
+            clipboard: std::sync::Mutex::new(None),
+            progress_throttle: std::sync::Mutex::new(HashMap::new()),
+            job_broadcast: std::sync::Mutex::new(std::collections::HashSet::new()),
+            path_scope: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+}
+
+// ============================================================================
+// Utility Functions
+// ============================================================================
+
+// VIDEO_TOOLBOX_FFMPEG, VIDEO_TOOLBOX_FFPROBE and VIDEO_TOOLBOX_YT_DLP let the tests
+// substitute scripted fakes for the tools. Only test builds read them, so the environment
+// can't point a shipped app at some other binary.
+#[cfg(test)]
+fn tool_override(tool: &str) -> Option<String> {
+    let name = format!("VIDEO_TOOLBOX_{}", tool.to_uppercase().replace('-', "_"));
+    std::env::var(name).ok().filter(|path| !path.is_empty())
+}
+
+#[cfg(not(test))]
+fn tool_override(_tool: &str) -> Option<String> {
+    None
+}
+
+fn get_ffmpeg_path() -> String {
+    if let Some(path) = tool_override("ffmpeg") {
+        return path;
+    }
+    // Try to find ffmpeg in PATH or in bin folder
+    // First check if bundled in resources
+    if let Ok(exe_path) = std::env::current_exe() {
+        let bin_path = exe_path.parent().map(|p| p.join("bin").join("ffmpeg.exe"));
+        if let Some(path) = bin_path {
+            if path.exists() {
+                return path.to_string_lossy().to_string();
+            }
+        }
+    }
+    // Fallback to system PATH
+    "ffmpeg".to_string()
+}
+
+fn get_ffprobe_path() -> String {
+    if let Some(path) = tool_override("ffprobe") {
+        return path;
+    }
+    if let Ok(exe_path) = std::env::current_exe() {
+        let bin_path = exe_path.parent().map(|p| p.join("bin").join("ffprobe.exe"));
+        if let Some(path) = bin_path {
+            if path.exists() {
+                return path.to_string_lossy().to_string();
+            }
+        }
+    }
+    "ffprobe".to_string()
+}
+
+// Safe probe mode: set from settings at startup and by set_safe_probe_mode
+static SAFE_PROBE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+const PROBE_TIMEOUT_SECS: u64 = 30;
+const SAFE_PROBE_TIMEOUT_SECS: u64 = 10;
+
+// Local file containers we expect to meet; anything else is refused in safe mode
+const SAFE_PROBE_FORMATS: &str = "mov,mp4,m4a,3gp,3g2,mj2,matroska,webm,avi,flv,mpegts,mpeg,asf,ogg,wav,mp3,flac,aac,gif,image2,png_pipe,jpeg_pipe,webp_pipe,srt,ass,webvtt,ape,wv,aiff,caf,ac3,eac3,dts,truehd,w64,tta,mxf,dv,h264,hevc";
+
+fn ffprobe_command() -> Command {
+    let mut cmd = new_command(&get_ffprobe_path());
+    cmd.kill_on_drop(true);
+    if SAFE_PROBE.load(std::sync::atomic::Ordering::Relaxed) {
+        // Small probe window, no network or playlist protocols, known demuxers only
+        cmd.args([
+            "-probesize", "5000000",
+            "-analyzeduration", "5000000",
+            "-protocol_whitelist", "file",
+            "-format_whitelist", SAFE_PROBE_FORMATS,
+            "-max_streams", "64",
+        ]);
+    }
+    cmd
+}
+
+// Protocols ffmpeg jobs may open in safe mode: local files, the pipes between tools,
+// and the concat: protocol that disc folder titles are read through
+const SAFE_FFMPEG_PROTOCOLS: &str = "file,pipe,concat";
+
+// In safe mode every ffmpeg input gets the probe limits ffprobe_command uses. There is
+// no demuxer whitelist here, as jobs legitimately read concat lists and lavfi sources.
+fn safe_input_args(args: Vec<String>) -> Vec<String> {
+    if !SAFE_PROBE.load(std::sync::atomic::Ordering::Relaxed) {
+        return args;
+    }
+    let mut limited = Vec::with_capacity(args.len());
+    for arg in args {
+        if arg == "-i" {
+            limited.extend(
+                ["-probesize", "5000000", "-analyzeduration", "5000000", "-protocol_whitelist", SAFE_FFMPEG_PROTOCOLS].map(String::from),
+            );
+        }
+        limited.push(arg);
+    }
+    limited
+}
+
+fn ffmpeg_command<I, S>(args: I) -> Command
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    let args = args.into_iter().map(|a| a.as_ref().to_string_lossy().to_string()).collect();
+    let mut cmd = new_command(&get_ffmpeg_path());
+    cmd.args(safe_input_args(args));
+    cmd
+}
+
+// Runs ffprobe with a time limit so a malformed file cannot hang the app
+async fn probe_output(cmd: &mut Command) -> std::io::Result<std::process::Output> {
+    let limit = if SAFE_PROBE.load(std::sync::atomic::Ordering::Relaxed) { SAFE_PROBE_TIMEOUT_SECS } else { PROBE_TIMEOUT_SECS };
+    match tokio::time::timeout(std::time::Duration::from_secs(limit), child_output(cmd, None)).await {
+        Ok(result) => result,
+        Err(_) => Err(std::io::Error::new(std::io::ErrorKind::TimedOut, format!("ffprobe did not finish within {}s", limit))),
+    }
+}
+
+ async fn has_audio_stream(file_path: &str) -> Result<bool, String> {
+     let output = probe_output(ffprobe_command()
+         .args(&[
+             "-v", "error",
+             "-select_streams", "a:0",
+             "-show_entries", "stream=index",
+             "-of", "csv=p=0",
+             file_path,
+         ]))
+         .await
+         .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+     if !output.status.success() {
+         let stderr = String::from_utf8_lossy(&output.stderr);
+         return Err(format!("ffprobe failed: {}", stderr));
+     }
+
+     let stdout = String::from_utf8_lossy(&output.stdout);
+     Ok(!stdout.trim().is_empty())
+ }
+
+// Codec names of the streams of one type ("v", "a", "s") in a file
+async fn stream_codecs(file_path: &str, stream_type: &str) -> Result<Vec<String>, String> {
+    let output = probe_output(ffprobe_command()
+        .args([
+            "-v", "error",
+            "-select_streams", stream_type,
+            "-show_entries", "stream=codec_name",
+            "-of", "csv=p=0",
+            file_path,
+        ]))
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffprobe failed: {}", stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+}
+
+// Bitmap subtitle formats that MP4/MOV cannot carry (mov_text is text only)
+fn is_image_subtitle(codec: &str) -> bool {
+    matches!(codec, "hdmv_pgs_subtitle" | "dvd_subtitle" | "dvb_subtitle" | "xsub")
+}
+
+fn get_ytdlp_path() -> String {
+    if let Some(path) = tool_override("yt-dlp") {
+        return path;
+    }
+    if let Ok(exe_path) = std::env::current_exe() {
+        let bin_path = exe_path.parent().map(|p| p.join("bin").join("yt-dlp.exe"));
+        if let Some(path) = bin_path {
+            if path.exists() {
+                return path.to_string_lossy().to_string();
+            }
+        }
+    }
+    "yt-dlp".to_string()
+}
+
+// untrunc is not bundled; recovery falls back to it only when it is found in the
+// app's bin folder or on PATH
+fn find_untrunc() -> Option<String> {
+    let name = if cfg!(windows) { "untrunc.exe" } else { "untrunc" };
+    let bundled = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|p| p.join("bin")));
+    let path_dirs = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    bundled
+        .into_iter()
+        .chain(path_dirs)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+fn validate_path(input_path: &str) -> Option<PathBuf> {
+    if input_path.is_empty() {
+        return None;
+    }
+    let path = PathBuf::from(input_path);
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn validate_url(url: &str) -> bool {
+    if let Ok(url_obj) = url::Url::parse(url) {
+        url_obj.scheme() == "http" || url_obj.scheme() == "https"
+    } else {
+        false
+    }
+}
+
+// Output folder if one was chosen, otherwise the folder the input lives in
+fn resolve_output_dir(input_path: &std::path::Path, output_folder: Option<&String>) -> PathBuf {
+    match output_folder {
+        Some(folder) if !folder.is_empty() => PathBuf::from(folder),
+        _ => input_path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from(".")),
+    }
+}
+
+fn job_type_folder(job_type: &str) -> String {
+    match job_type {
+        "encode" => "Encoded".to_string(),
+        "extract_audio" | "convert_audio" => "Audio".to_string(),
+        "trim" => "Trimmed".to_string(),
+        "gif" => "GIFs".to_string(),
+        "concat" | "intro_outro" | "concat_audio" => "Merged".to_string(),
+        "split" | "chapter_split" | "scenes" | "silence_split" | "cue_split" => "Split".to_string(),
+        "screenshots" | "thumbnails" | "frames" => "Images".to_string(),
+        other => {
+            let mut name = other.replace('_', " ");
+            if let Some(first) = name.get_mut(0..1) {
+                first.make_ascii_uppercase();
+            }
+            name
+        }
+    }
+}
+
+// resolve_output_dir plus the dated/per-operation subfolders from the output organization
+// setting. Only applies under a chosen output folder, next-to-input output stays as is.
+fn job_output_dir(input_path: &std::path::Path, output_folder: Option<&String>, job_type: &str) -> PathBuf {
+    let dir = planned_output_dir(input_path, output_folder, job_type);
+    if dir != resolve_output_dir(input_path, output_folder) {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            error!("Failed to create output folder {}: {}", dir.display(), e);
+        }
+    }
+    dir
+}
+
+// Where job_output_dir puts the output, without creating anything. For the builders a
+// command preview shares with the job; the job makes the folder once it actually runs.
+fn planned_output_dir(input_path: &std::path::Path, output_folder: Option<&String>, job_type: &str) -> PathBuf {
+    let mut dir = resolve_output_dir(input_path, output_folder);
+    if output_folder.map(|f| f.is_empty()).unwrap_or(true) {
+        return dir;
+    }
+    let mode = OUTPUT_ORGANIZATION.read().map(|m| m.clone()).unwrap_or_default();
+    if mode.starts_with("date") {
+        dir.push(chrono::Local::now().format("%Y-%m-%d").to_string());
+    }
+    if mode.ends_with("type") {
+        dir.push(job_type_folder(job_type));
+    }
+    dir
+}
+
+// Makes the folder of a JobPlan's output before the job writes to it
+fn create_planned_output_dir(output_path: &str) {
+    let Some(dir) = std::path::Path::new(output_path).parent().filter(|dir| !dir.as_os_str().is_empty()) else {
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        error!("Failed to create output folder {}: {}", dir.display(), e);
+    }
+}
+
+// Strip characters Windows refuses in file names and collapse whitespace
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') || c.is_control() { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    cleaned.trim_matches(|c| c == '.' || c == ' ').chars().take(120).collect()
+}
+
+// Chroma subsampling for JPEG output: "444" keeps full colour resolution (text and UI
+// screenshots), "420" is smallest. Unset keeps the previous encoder's 4:4:4.
+fn jpeg_sampling_factor(value: Option<&str>) -> jpeg_encoder::SamplingFactor {
+    match value.map(|v| v.replace(':', "")).as_deref() {
+        Some("422") => jpeg_encoder::SamplingFactor::R_4_2_2,
+        Some("420") => jpeg_encoder::SamplingFactor::R_4_2_0,
+        _ => jpeg_encoder::SamplingFactor::R_4_4_4,
+    }
+}
+
+// The same choice for ffmpeg's MJPEG encoder, which picks it from the pixel format
+fn jpeg_pix_fmt(value: Option<&str>) -> Option<&'static str> {
+    match value.map(|v| v.replace(':', "")).as_deref() {
+        Some("444") => Some("yuvj444p"),
+        Some("422") => Some("yuvj422p"),
+        Some("420") => Some("yuvj420p"),
+        _ => None,
+    }
+}
+
+// Grab a single full-resolution frame. Input-side -ss is frame accurate when decoding.
+// Quality is 1-100 for JPEG/WebP (best when unset) and ignored for lossless PNG.
+async fn extract_frame_at(input: &str, seconds: f64, output_path: &std::path::Path, format: &str, quality: Option<u32>, chroma_subsampling: Option<&str>) -> Result<(), String> {
+    let mut args = vec![
+        "-y".to_string(),
+        "-ss".to_string(),
+        format!("{:.3}", seconds.max(0.0)),
+        "-i".to_string(),
+        input.to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+    ];
+    match format {
+        "jpg" | "jpeg" => {
+            // ffmpeg's MJPEG scale runs 2 (best) to 31 (worst)
+            let q = quality.map(|q| 2 + (100 - q.clamp(1, 100)) * 29 / 99).unwrap_or(2);
+            args.extend(["-q:v".to_string(), q.to_string()]);
+            if let Some(pix_fmt) = jpeg_pix_fmt(chroma_subsampling) {
+                args.extend(["-pix_fmt".to_string(), pix_fmt.to_string()]);
+            }
+        }
+        "webp" => args.extend([
+            "-c:v".to_string(),
+            "libwebp".to_string(),
+            "-quality".to_string(),
+            quality.unwrap_or(100).clamp(1, 100).to_string(),
+        ]),
+        _ => {}
+    }
+    args.push(output_path.to_string_lossy().to_string());
+
+    let output = child_output(&mut ffmpeg_command(&args), None)
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !output.status.success() || !output_path.exists() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to extract frame at {:.3}s: {}", seconds, stderr));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Message Catalog
+// ============================================================================
+
+// Locale used for status and error text sent to the UI. Empty means English.
+static CURRENT_LOCALE: std::sync::RwLock<String> = std::sync::RwLock::new(String::new());
+
+const SUPPORTED_LOCALES: &[&str] = &["en", "es", "fr", "de", "pt", "ja", "zh"];
+
+// key -> [(locale, text)]. Placeholders are written as {name}.
+const MESSAGE_CATALOG: &[(&str, &[(&str, &str)])] = &[
+    ("status.creating_output", &[
+        ("en", "Creating output file..."),
+        ("es", "Creando archivo de salida..."),
+        ("fr", "Création du fichier de sortie..."),
+        ("de", "Ausgabedatei wird erstellt..."),
+        ("pt", "Criando arquivo de saída..."),
+        ("ja", "出力ファイルを作成しています..."),
+        ("zh", "正在创建输出文件..."),
+    ]),
+    ("status.creating_file", &[
+        ("en", "Creating file..."),
+        ("es", "Creando archivo..."),
+        ("fr", "Création du fichier..."),
+        ("de", "Datei wird erstellt..."),
+        ("pt", "Criando arquivo..."),
+        ("ja", "ファイルを作成しています..."),
+        ("zh", "正在创建文件..."),
+    ]),
+    ("status.merging", &[
+        ("en", "Merging audio and video..."),
+        ("es", "Combinando audio y video..."),
+        ("fr", "Fusion de l'audio et de la vidéo..."),
+        ("de", "Audio und Video werden zusammengeführt..."),
+        ("pt", "Mesclando áudio e vídeo..."),
+        ("ja", "音声と映像を結合しています..."),
+        ("zh", "正在合并音频和视频..."),
+    ]),
+    ("status.extracting_audio", &[
+        ("en", "Extracting audio..."),
+        ("es", "Extrayendo audio..."),
+        ("fr", "Extraction de l'audio..."),
+        ("de", "Audio wird extrahiert..."),
+        ("pt", "Extraindo áudio..."),
+        ("ja", "音声を抽出しています..."),
+        ("zh", "正在提取音频..."),
+    ]),
+    ("status.fetching_metadata", &[
+        ("en", "Fetching metadata..."),
+        ("es", "Obteniendo metadatos..."),
+        ("fr", "Récupération des métadonnées..."),
+        ("de", "Metadaten werden abgerufen..."),
+        ("pt", "Obtendo metadados..."),
+        ("ja", "メタデータを取得しています..."),
+        ("zh", "正在获取元数据..."),
+    ]),
+    ("status.preparing_stream", &[
+        ("en", "Preparing stream..."),
+        ("es", "Preparando transmisión..."),
+        ("fr", "Préparation du flux..."),
+        ("de", "Stream wird vorbereitet..."),
+        ("pt", "Preparando transmissão..."),
+        ("ja", "ストリームを準備しています..."),
+        ("zh", "正在准备流..."),
+    ]),
+    ("status.extracting_metadata", &[
+        ("en", "Extracting metadata..."),
+        ("es", "Extrayendo metadatos..."),
+        ("fr", "Extraction des métadonnées..."),
+        ("de", "Metadaten werden extrahiert..."),
+        ("pt", "Extraindo metadados..."),
+        ("ja", "メタデータを抽出しています..."),
+        ("zh", "正在提取元数据..."),
+    ]),
+    ("status.starting_download", &[
+        ("en", "Starting download..."),
+        ("es", "Iniciando descarga..."),
+        ("fr", "Démarrage du téléchargement..."),
+        ("de", "Download wird gestartet..."),
+        ("pt", "Iniciando download..."),
+        ("ja", "ダウンロードを開始しています..."),
+        ("zh", "正在开始下载..."),
+    ]),
+    ("status.finalizing_download", &[
+        ("en", "Finalizing download..."),
+        ("es", "Finalizando descarga..."),
+        ("fr", "Finalisation du téléchargement..."),
+        ("de", "Download wird abgeschlossen..."),
+        ("pt", "Finalizando download..."),
+        ("ja", "ダウンロードを完了しています..."),
+        ("zh", "正在完成下载..."),
+    ]),
+    ("status.downloading", &[
+        ("en", "Downloading..."),
+        ("es", "Descargando..."),
+        ("fr", "Téléchargement..."),
+        ("de", "Wird heruntergeladen..."),
+        ("pt", "Baixando..."),
+        ("ja", "ダウンロード中..."),
+        ("zh", "正在下载..."),
+    ]),
+    ("status.cleaning_up", &[
+        ("en", "Cleaning up temporary files..."),
+        ("es", "Limpiando archivos temporales..."),
+        ("fr", "Nettoyage des fichiers temporaires..."),
+        ("de", "Temporäre Dateien werden bereinigt..."),
+        ("pt", "Limpando arquivos temporários..."),
+        ("ja", "一時ファイルを削除しています..."),
+        ("zh", "正在清理临时文件..."),
+    ]),
+    ("status.fixing_timestamps", &[
+        ("en", "Finalizing media timestamps..."),
+        ("es", "Finalizando marcas de tiempo..."),
+        ("fr", "Finalisation des horodatages..."),
+        ("de", "Zeitstempel werden finalisiert..."),
+        ("pt", "Finalizando carimbos de tempo..."),
+        ("ja", "タイムスタンプを確定しています..."),
+        ("zh", "正在完成媒体时间戳..."),
+    ]),
+    ("status.error", &[
+        ("en", "Error: {message}"),
+        ("es", "Error: {message}"),
+        ("fr", "Erreur : {message}"),
+        ("de", "Fehler: {message}"),
+        ("pt", "Erro: {message}"),
+        ("ja", "エラー: {message}"),
+        ("zh", "错误：{message}"),
+    ]),
+    ("error.download_failed", &[
+        ("en", "Download failed with code {code}"),
+        ("es", "La descarga falló con el código {code}"),
+        ("fr", "Le téléchargement a échoué avec le code {code}"),
+        ("de", "Download mit Code {code} fehlgeschlagen"),
+        ("pt", "O download falhou com o código {code}"),
+        ("ja", "ダウンロードに失敗しました (コード {code})"),
+        ("zh", "下载失败，代码 {code}"),
+    ]),
+    ("error.download_failed_details", &[
+        ("en", "Download failed with code {code}: {details}"),
+        ("es", "La descarga falló con el código {code}: {details}"),
+        ("fr", "Le téléchargement a échoué avec le code {code} : {details}"),
+        ("de", "Download mit Code {code} fehlgeschlagen: {details}"),
+        ("pt", "O download falhou com o código {code}: {details}"),
+        ("ja", "ダウンロードに失敗しました (コード {code}): {details}"),
+        ("zh", "下载失败，代码 {code}：{details}"),
+    ]),
+    ("error.ytdlp_drm", &[
+        ("en", "This video is DRM protected and can't be downloaded"),
+        ("es", "Este vídeo está protegido con DRM y no se puede descargar"),
+        ("fr", "Cette vidéo est protégée par DRM et ne peut pas être téléchargée"),
+        ("de", "Dieses Video ist DRM-geschützt und kann nicht heruntergeladen werden"),
+        ("pt", "Este vídeo é protegido por DRM e não pode ser baixado"),
+        ("ja", "この動画は DRM で保護されているためダウンロードできません"),
+        ("zh", "此视频受 DRM 保护，无法下载"),
+    ]),
+    ("error.ytdlp_private", &[
+        ("en", "This video is private"),
+        ("es", "Este vídeo es privado"),
+        ("fr", "Cette vidéo est privée"),
+        ("de", "Dieses Video ist privat"),
+        ("pt", "Este vídeo é privado"),
+        ("ja", "この動画は非公開です"),
+        ("zh", "此视频为私密视频"),
+    ]),
+    ("error.ytdlp_age_restricted", &[
+        ("en", "This video is age restricted and needs a signed-in account"),
+        ("es", "Este vídeo tiene restricción de edad y requiere iniciar sesión"),
+        ("fr", "Cette vidéo est soumise à une limite d'âge et nécessite un compte connecté"),
+        ("de", "Dieses Video ist altersbeschränkt und erfordert ein angemeldetes Konto"),
+        ("pt", "Este vídeo tem restrição de idade e exige uma conta conectada"),
+        ("ja", "この動画は年齢制限があり、ログインが必要です"),
+        ("zh", "此视频有年龄限制，需要登录账号"),
+    ]),
+    ("error.ytdlp_login_required", &[
+        ("en", "This video requires signing in"),
+        ("es", "Este vídeo requiere iniciar sesión"),
+        ("fr", "Cette vidéo nécessite une connexion"),
+        ("de", "Für dieses Video ist eine Anmeldung erforderlich"),
+        ("pt", "Este vídeo exige login"),
+        ("ja", "この動画にはログインが必要です"),
+        ("zh", "此视频需要登录"),
+    ]),
+    ("error.ytdlp_geo_blocked", &[
+        ("en", "This video isn't available in your region"),
+        ("es", "Este vídeo no está disponible en tu región"),
+        ("fr", "Cette vidéo n'est pas disponible dans votre région"),
+        ("de", "Dieses Video ist in deiner Region nicht verfügbar"),
+        ("pt", "Este vídeo não está disponível na sua região"),
+        ("ja", "この動画はお住まいの地域では視聴できません"),
+        ("zh", "此视频在你所在的地区不可用"),
+    ]),
+    ("error.ytdlp_unavailable", &[
+        ("en", "This video is unavailable or has been removed"),
+        ("es", "Este vídeo no está disponible o se ha eliminado"),
+        ("fr", "Cette vidéo est indisponible ou a été supprimée"),
+        ("de", "Dieses Video ist nicht verfügbar oder wurde entfernt"),
+        ("pt", "Este vídeo está indisponível ou foi removido"),
+        ("ja", "この動画は利用できないか、削除されています"),
+        ("zh", "此视频不可用或已被删除"),
+    ]),
+    ("error.ytdlp_rate_limited", &[
+        ("en", "The site is rate limiting requests, try again later"),
+        ("es", "El sitio está limitando las solicitudes, inténtalo más tarde"),
+        ("fr", "Le site limite les requêtes, réessayez plus tard"),
+        ("de", "Die Seite begrenzt Anfragen, versuche es später erneut"),
+        ("pt", "O site está limitando as solicitações, tente novamente mais tarde"),
+        ("ja", "サイトがリクエストを制限しています。しばらくしてから再試行してください"),
+        ("zh", "该网站正在限制请求，请稍后再试"),
+    ]),
+    ("error.ytdlp_unsupported_url", &[
+        ("en", "This site or link isn't supported"),
+        ("es", "Este sitio o enlace no es compatible"),
+        ("fr", "Ce site ou ce lien n'est pas pris en charge"),
+        ("de", "Diese Seite oder dieser Link wird nicht unterstützt"),
+        ("pt", "Este site ou link não é compatível"),
+        ("ja", "このサイトまたはリンクには対応していません"),
+        ("zh", "不支持此网站或链接"),
+    ]),
+    ("error.process_exited", &[
+        ("en", "FFmpeg exited with code {code}"),
+        ("es", "FFmpeg terminó con el código {code}"),
+        ("fr", "FFmpeg s'est arrêté avec le code {code}"),
+        ("de", "FFmpeg wurde mit Code {code} beendet"),
+        ("pt", "O FFmpeg terminou com o código {code}"),
+        ("ja", "FFmpeg がコード {code} で終了しました"),
+        ("zh", "FFmpeg 已退出，代码 {code}"),
+    ]),
+    ("error.invalid_url", &[
+        ("en", "Invalid URL format"),
+        ("es", "Formato de URL no válido"),
+        ("fr", "Format d'URL invalide"),
+        ("de", "Ungültiges URL-Format"),
+        ("pt", "Formato de URL inválido"),
+        ("ja", "URL の形式が無効です"),
+        ("zh", "URL 格式无效"),
+    ]),
+    ("error.invalid_path", &[
+        ("en", "Invalid file path"),
+        ("es", "Ruta de archivo no válida"),
+        ("fr", "Chemin de fichier invalide"),
+        ("de", "Ungültiger Dateipfad"),
+        ("pt", "Caminho de arquivo inválido"),
+        ("ja", "ファイルパスが無効です"),
+        ("zh", "文件路径无效"),
+    ]),
+    ("error.no_images", &[
+        ("en", "No images provided"),
+        ("es", "No se proporcionaron imágenes"),
+        ("fr", "Aucune image fournie"),
+        ("de", "Keine Bilder angegeben"),
+        ("pt", "Nenhuma imagem fornecida"),
+        ("ja", "画像が指定されていません"),
+        ("zh", "未提供图像"),
+    ]),
+];
+
+// "pt-BR", "zh_CN.UTF-8" -> "pt", "zh"; unsupported locales fall back to English
+fn normalize_locale(locale: &str) -> &'static str {
+    let lang = locale
+        .split(['-', '_', '.'])
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    SUPPORTED_LOCALES.iter().find(|l| **l == lang).copied().unwrap_or("en")
+}
+
+fn msg(key: &str) -> String {
+    let locale = CURRENT_LOCALE.read().map(|l| l.clone()).unwrap_or_default();
+    let entries = MESSAGE_CATALOG.iter().find(|(k, _)| *k == key).map(|(_, e)| *e);
+    let Some(entries) = entries else {
+        return key.to_string();
+    };
+    entries
+        .iter()
+        .find(|(l, _)| *l == locale)
+        .or_else(|| entries.iter().find(|(l, _)| *l == "en"))
+        .map(|(_, text)| text.to_string())
+        .unwrap_or_else(|| key.to_string())
+}
+
+fn msg_with(key: &str, values: &[(&str, &str)]) -> String {
+    let mut text = msg(key);
+    for (name, value) in values {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+#[tauri::command]
+async fn set_locale(locale: String) -> Result<String, String> {
+    info!("set_locale called with: {}", locale);
+    let normalized = normalize_locale(&locale);
+    let mut current = CURRENT_LOCALE.write().map_err(|e| format!("Failed to set locale: {}", e))?;
+    *current = normalized.to_string();
+    Ok(normalized.to_string())
+}
+
+#[tauri::command]
+async fn get_locale() -> Result<String, String> {
+    let current = CURRENT_LOCALE.read().map(|l| l.clone()).unwrap_or_default();
+    Ok(if current.is_empty() { "en".to_string() } else { current })
+}
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+// Default container/format per operation, used when a request leaves its format empty
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputFormatDefaults {
+    pub encode: Option<String>,
+    pub trim: Option<String>,
+    pub extract_audio: Option<String>,
+    pub gif: Option<String>,
+    pub concat: Option<String>,
+    pub download_video: Option<String>,
+    pub download_audio: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub output_formats: OutputFormatDefaults,
+    pub webhooks: Vec<WebhookConfig>,
+    // Restricts ffprobe for files from untrusted sources
+    pub safe_probe: bool,
+    // "" (off), "date", "type" or "date_type": subfolders created under the chosen output folder
+    pub output_organization: String,
+    // Progress events sent per second and job, 0 for no limit. Unset means the default.
+    pub progress_events_per_second: Option<u32>,
+}
+
+// HTTP POST fired when a job finishes. Without a template the job event itself is sent as JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub enabled: bool,
+    // "complete", "error" and/or "cancelled"; empty means complete and error
+    pub events: Vec<String>,
+    pub payload_template: Option<String>,
+    pub content_type: Option<String>,
+}
+
+const OUTPUT_FORMAT_CHOICES: &[(&str, &[&str])] = &[
+    ("encode", &["mp4", "mkv", "mov", "webm", "avi", "flv", "wmv", "m4v"]),
+    // Trimming stream-copies, so only containers that take any codec the source may have
+    ("trim", &["mp4", "mkv", "mov"]),
+    ("extract_audio", &["mp3", "aac", "flac", "wav", "ogg", "opus"]),
+    ("gif", &["gif", "webp", "apng"]),
+    ("concat", &["mp4", "mkv", "mov", "webm"]),
+    ("download_video", &["mp4", "mkv", "mov", "webm"]),
+    ("download_audio", &["mp3", "m4a", "aac", "flac", "wav", "opus", "vorbis"]),
+];
+
+fn app_data_file<R: tauri::Runtime>(app: &tauri::AppHandle<R>, file_name: &str) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| format!("Failed to resolve app data folder: {}", e))?;
+    Ok(dir.join(file_name))
+}
+
+fn settings_file_path<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    app_data_file(app, "settings.json")
+}
+
+fn write_app_data_json<T: Serialize>(app: &tauri::AppHandle, file_name: &str, value: &T) -> Result<(), String> {
+    let path = app_data_file(app, file_name)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create app data folder: {}", e))?;
+    }
+    let text = serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize {}: {}", file_name, e))?;
+    std::fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", file_name, e))
+}
+
+fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// A missing or unreadable settings file just means defaults
+fn load_settings<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> AppSettings {
+    settings_file_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write_settings(app: &tauri::AppHandle, settings: &AppSettings) -> Result<(), String> {
+    write_app_data_json(app, "settings.json", settings)
+}
+
+fn is_valid_output_format(operation: &str, format: &str) -> bool {
+    OUTPUT_FORMAT_CHOICES
+        .iter()
+        .find(|(op, _)| *op == operation)
+        .map(|(_, formats)| formats.contains(&format))
+        .unwrap_or(false)
+}
+
+fn output_format_slot<'a>(defaults: &'a mut OutputFormatDefaults, operation: &str) -> Option<&'a mut Option<String>> {
+    match operation {
+        "encode" => Some(&mut defaults.encode),
+        "trim" => Some(&mut defaults.trim),
+        "extract_audio" => Some(&mut defaults.extract_audio),
+        "gif" => Some(&mut defaults.gif),
+        "concat" => Some(&mut defaults.concat),
+        "download_video" => Some(&mut defaults.download_video),
+        "download_audio" => Some(&mut defaults.download_audio),
+        _ => None,
+    }
+}
+
+// Configured default for an operation, if any
+fn default_output_format<R: tauri::Runtime>(app: &tauri::AppHandle<R>, operation: &str) -> Option<String> {
+    let mut settings = load_settings(app);
+    output_format_slot(&mut settings.output_formats, operation)
+        .and_then(|slot| slot.take())
+        .filter(|f| is_valid_output_format(operation, f))
+}
+
+fn check_output_format(operation: &str, format: &str) -> Result<(), String> {
+    if is_valid_output_format(operation, format) {
+        Ok(())
+    } else {
+        Err(format!("{} is not a supported output format for {}", format, operation))
+    }
+}
+
+// Fills an omitted format from the configured default; explicit choices are checked
+// against the operation's list
+fn apply_output_format_default(app: &tauri::AppHandle, operation: &str, format: &mut Option<String>) -> Result<(), String> {
+    match format.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        Some(explicit) => check_output_format(operation, explicit),
+        None => {
+            *format = default_output_format(app, operation);
+            Ok(())
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_settings(app: tauri::AppHandle) -> Result<AppSettings, String> {
+    Ok(load_settings(&app))
+}
+
+#[tauri::command]
+async fn set_safe_probe_mode(app: tauri::AppHandle, enabled: bool) -> Result<AppSettings, String> {
+    info!("set_safe_probe_mode called: {}", enabled);
+    let mut settings = load_settings(&app);
+    settings.safe_probe = enabled;
+    write_settings(&app, &settings)?;
+    SAFE_PROBE.store(enabled, std::sync::atomic::Ordering::Relaxed);
+    Ok(settings)
+}
+
+// Mirrors AppSettings::output_organization so path building doesn't re-read settings.json
+static OUTPUT_ORGANIZATION: std::sync::RwLock<String> = std::sync::RwLock::new(String::new());
+
+const DEFAULT_PROGRESS_EVENTS_PER_SECOND: u32 = 10;
+
+// Mirrors AppSettings::progress_events_per_second, read on every progress line
+static PROGRESS_EVENT_RATE: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(DEFAULT_PROGRESS_EVENTS_PER_SECOND);
+
+#[tauri::command]
+async fn set_progress_event_rate(app: tauri::AppHandle, per_second: u32) -> Result<AppSettings, String> {
+    info!("set_progress_event_rate called: {}", per_second);
+    if per_second > 60 {
+        return Err("Choose at most 60 progress events per second (0 for no limit)".to_string());
+    }
+    let mut settings = load_settings(&app);
+    settings.progress_events_per_second = Some(per_second);
+    write_settings(&app, &settings)?;
+    PROGRESS_EVENT_RATE.store(per_second, std::sync::atomic::Ordering::Relaxed);
+    Ok(settings)
+}
+
+#[tauri::command]
+async fn set_output_organization(app: tauri::AppHandle, mode: String) -> Result<AppSettings, String> {
+    info!("set_output_organization called: {}", mode);
+    let mode = match mode.as_str() {
+        "" | "none" => String::new(),
+        "date" | "type" | "date_type" => mode,
+        other => return Err(format!("Unknown output organization: {}", other)),
+    };
+    let mut settings = load_settings(&app);
+    settings.output_organization = mode.clone();
+    write_settings(&app, &settings)?;
+    if let Ok(mut current) = OUTPUT_ORGANIZATION.write() {
+        *current = mode;
+    }
+    Ok(settings)
+}
+
+#[tauri::command]
+async fn set_default_output_format(app: tauri::AppHandle, operation: String, format: Option<String>) -> Result<AppSettings, String> {
+    info!("set_default_output_format called: {} -> {:?}", operation, format);
+
+    let format = format.map(|f| f.trim().to_lowercase()).filter(|f| !f.is_empty());
+    if let Some(ref f) = format {
+        if !is_valid_output_format(&operation, f) {
+            return Err(format!("Unsupported format '{}' for {}", f, operation));
+        }
+    }
+
+    let mut settings = load_settings(&app);
+    let slot = output_format_slot(&mut settings.output_formats, &operation)
+        .ok_or_else(|| format!("Unknown operation: {}", operation))?;
+    *slot = format;
+    write_settings(&app, &settings)?;
+    Ok(settings)
+}
+
+// ============================================================================
+// Encoding Presets
+// ============================================================================
+
+const PRESET_FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodingPreset {
+    pub name: String,
+    pub options: EncodeOptions,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+// Shape of presets.json and of exported preset files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PresetFile {
+    version: u32,
+    presets: Vec<EncodingPreset>,
+}
+
+fn load_presets(app: &tauri::AppHandle) -> Vec<EncodingPreset> {
+    app_data_file(app, "presets.json")
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str::<PresetFile>(&text).ok())
+        .map(|file| file.presets)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|mut preset| {
+            // Presets saved by older versions may still carry file paths
+            clear_file_fields(&mut preset.options);
+            preset
+        })
+        .collect()
+}
+
+fn write_presets(app: &tauri::AppHandle, presets: &[EncodingPreset]) -> Result<(), String> {
+    write_app_data_json(app, "presets.json", &PresetFile { version: PRESET_FILE_VERSION, presets: presets.to_vec() })
+}
+
+// Presets describe settings only, never a particular file: the input, where it goes,
+// and the extra tracks and chapters picked for it are all left out
+fn clear_file_fields(options: &mut EncodeOptions) {
+    options.input = String::new();
+    options.output_folder = None;
+    options.audio_tracks = None;
+    options.subtitle_tracks = None;
+    options.chapters_file = None;
+}
+
+fn preset_from(name: &str, options: &EncodeOptions) -> Result<EncodingPreset, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Preset name cannot be empty".to_string());
+    }
+    let mut options = options.clone();
+    clear_file_fields(&mut options);
+    Ok(EncodingPreset { name: name.to_string(), options, updated_at: unix_timestamp() })
+}
+
+// Replaces a preset with the same name (case-insensitive) or appends a new one
+fn upsert_preset(presets: &mut Vec<EncodingPreset>, preset: EncodingPreset) {
+    match presets.iter_mut().find(|p| p.name.eq_ignore_ascii_case(&preset.name)) {
+        Some(existing) => *existing = preset,
+        None => presets.push(preset),
+    }
+}
+
+#[tauri::command]
+async fn save_preset(app: tauri::AppHandle, name: String, options: EncodeOptions) -> Result<Vec<EncodingPreset>, String> {
+    info!("save_preset called: {}", name);
+    let mut presets = load_presets(&app);
+    upsert_preset(&mut presets, preset_from(&name, &options)?);
+    write_presets(&app, &presets)?;
+    Ok(presets)
+}
+
+#[tauri::command]
+async fn list_presets(app: tauri::AppHandle) -> Result<Vec<EncodingPreset>, String> {
+    Ok(load_presets(&app))
+}
+
+#[tauri::command]
+async fn load_preset(app: tauri::AppHandle, name: String) -> Result<EncodeOptions, String> {
+    load_presets(&app)
+        .into_iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+        .map(|p| p.options)
+        .ok_or_else(|| format!("Preset not found: {}", name))
+}
+
+#[tauri::command]
+async fn delete_preset(app: tauri::AppHandle, name: String) -> Result<Vec<EncodingPreset>, String> {
+    info!("delete_preset called: {}", name);
+    let mut presets = load_presets(&app);
+    let before = presets.len();
+    presets.retain(|p| !p.name.eq_ignore_ascii_case(name.trim()));
+    if presets.len() == before {
+        return Err(format!("Preset not found: {}", name));
+    }
+    write_presets(&app, &presets)?;
+    Ok(presets)
+}
+
+#[tauri::command]
+async fn export_presets(app: tauri::AppHandle, path: String, names: Option<Vec<String>>) -> Result<usize, String> {
+    info!("export_presets called: {}", path);
+    scoped_path(&app, &path)?;
+    let presets: Vec<EncodingPreset> = load_presets(&app)
+        .into_iter()
+        .filter(|p| names.as_ref().map(|n| n.iter().any(|name| name.eq_ignore_ascii_case(&p.name))).unwrap_or(true))
+        .collect();
+    if presets.is_empty() {
+        return Err("No presets to export".to_string());
+    }
+    let count = presets.len();
+    let text = serde_json::to_string_pretty(&PresetFile { version: PRESET_FILE_VERSION, presets })
+        .map_err(|e| format!("Failed to serialize presets: {}", e))?;
+    std::fs::write(&path, text).map_err(|e| format!("Failed to write preset file: {}", e))?;
+    Ok(count)
+}
+
+#[tauri::command]
+async fn import_presets(app: tauri::AppHandle, path: String) -> Result<Vec<EncodingPreset>, String> {
+    info!("import_presets called: {}", path);
+    scoped_path(&app, &path)?;
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read preset file: {}", e))?;
+
+    // Accept a full export, a bare list, or a single shared preset
+    let imported = if let Ok(file) = serde_json::from_str::<PresetFile>(&text) {
+        if file.version > PRESET_FILE_VERSION {
+            return Err(format!("Preset file version {} is newer than this app supports", file.version));
+        }
+        file.presets
+    } else if let Ok(list) = serde_json::from_str::<Vec<EncodingPreset>>(&text) {
+        list
+    } else {
+        let single: EncodingPreset = serde_json::from_str(&text).map_err(|e| format!("Invalid preset file: {}", e))?;
+        vec![single]
+    };
+
+    let mut presets = load_presets(&app);
+    for preset in &imported {
+        upsert_preset(&mut presets, preset_from(&preset.name, &preset.options)?);
+    }
+    write_presets(&app, &presets)?;
+    Ok(presets)
+}
+
+// ============================================================================
+// Command Templates
+// ============================================================================
+
+const TEMPLATE_PLACEHOLDERS: &[&str] = &["input", "output", "fps"];
+
+// A named ffmpeg argument line with {input}, {output} and {fps} placeholders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandTemplate {
+    pub name: String,
+    pub args: String,
+    pub description: Option<String>,
+    // Output extension, the input's own when unset
+    pub output_extension: Option<String>,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateRunOptions {
+    pub fps: Option<String>,
+    pub output_path: Option<String>,
+    pub output_folder: Option<String>,
+}
+
+fn load_command_templates(app: &tauri::AppHandle) -> Vec<CommandTemplate> {
+    app_data_file(app, "command_templates.json")
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write_command_templates(app: &tauri::AppHandle, templates: &[CommandTemplate]) -> Result<(), String> {
+    write_app_data_json(app, "command_templates.json", &templates)
+}
+
+// Splits an argument line like a shell would for quoting, without running one:
+// "..." and '...' group words, nothing is expanded
+fn split_template_args(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quote: Option<char> = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_token = true;
+            }
+            None if c.is_whitespace() => {
+                if in_token {
+                    args.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err("Unclosed quote in template".to_string());
+    }
+    if in_token {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+// Templates are checked against allow lists rather than for known bad options: ffmpeg
+// has too many options, muxers and filters that open files of their own to list them all.
+
+// Options that take no value (boolean ones also come as "-no<name>")
+const TEMPLATE_FLAG_OPTIONS: &[&str] = &[
+    "an", "vn", "sn", "dn", "shortest", "copyts", "start_at_zero", "accurate_seek", "autorotate", "autoscale",
+    "hide_banner", "nostats", "stats", "bitexact",
+];
+
+// Options that take a value, optionally with a stream specifier ("-c:v", "-b:a:0")
+const TEMPLATE_VALUE_OPTIONS: &[&str] = &[
+    "c", "codec", "vcodec", "acodec", "scodec", "b", "crf", "cq", "qp", "q", "qscale", "preset", "tune", "profile",
+    "level", "pix_fmt", "r", "s", "aspect", "g", "bf", "refs", "keyint_min", "sc_threshold", "maxrate", "minrate",
+    "bufsize", "rc", "ar", "ac", "sample_fmt", "channel_layout", "compression_level", "vbr", "application",
+    "cpu-used", "deadline", "row-mt", "tile-columns", "crf_max", "threads", "vf", "af", "filter", "filter_complex",
+    "lavfi", "map", "map_metadata", "map_chapters", "metadata", "disposition", "movflags", "ss", "t", "to",
+    "sseof", "frames", "vframes", "aframes", "fps_mode", "vsync", "colorspace", "color_primaries", "color_trc",
+    "color_range", "tag", "strict", "max_muxing_queue_size", "itsoffset", "stream_loop", "loglevel", "v",
+];
+
+// Filters a template may use. None of them reads or writes files, apart from the few
+// options caught by TEMPLATE_FILE_FILTER_KEYS.
+const TEMPLATE_FILTERS: &[&str] = &[
+    "scale", "crop", "pad", "fps", "format", "setsar", "setdar", "transpose", "hflip", "vflip", "rotate",
+    "yadif", "bwdif", "hqdn3d", "nlmeans", "unsharp", "smartblur", "gblur", "boxblur", "deband", "deblock", "eq",
+    "hue", "curves", "colorbalance", "colorchannelmixer", "lutrgb", "lutyuv", "zscale", "tonemap", "colorspace",
+    "chromakey", "colorkey", "vignette", "noise", "drawbox", "drawtext", "overlay", "hstack", "vstack", "xstack",
+    "split", "asplit", "concat", "trim", "atrim", "setpts", "asetpts", "reverse", "areverse", "select", "aselect",
+    "tpad", "apad", "fade", "afade", "minterpolate", "framerate", "tblend", "thumbnail", "tile", "palettegen",
+    "paletteuse", "null", "anull", "copy", "volume", "loudnorm", "dynaudnorm", "acompressor", "alimiter",
+    "aresample", "aformat", "pan", "amix", "amerge", "atempo", "highpass", "lowpass", "equalizer", "bass",
+    "treble", "silenceremove", "afftdn", "adelay", "aecho", "asetrate",
+];
+
+// Filter options that name a file to read or write
+const TEMPLATE_FILE_FILTER_KEYS: &[&str] = &[
+    "file", "filename", "f", "textfile", "fontfile", "psfile", "stats_file", "model", "model_path", "log_path",
+];
+
+fn is_template_flag(name: &str) -> bool {
+    TEMPLATE_FLAG_OPTIONS.contains(&name) || name.strip_prefix("no").map(|n| TEMPLATE_FLAG_OPTIONS.contains(&n)).unwrap_or(false)
+}
+
+// Splits filtergraph text at the given separators, leaving '...' quotes and
+// backslash-escaped characters alone
+fn split_filter_text(text: &str, separators: &[char]) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '\'' {
+            quoted = !quoted;
+        } else if !quoted && separators.contains(&c) {
+            parts.push(String::new());
+            continue;
+        }
+        if let Some(part) = parts.last_mut() {
+            part.push(c);
+        }
+    }
+    parts
+}
+
+// Every filter in the graph has to be on TEMPLATE_FILTERS and leave out the options
+// that take a file name
+fn check_template_filtergraph(graph: &str) -> Result<(), String> {
+    let label_re = regex::Regex::new(r"^(\s*\[[^\]]*\])*\s*|(\s*\[[^\]]*\])*\s*$").map_err(|e| e.to_string())?;
+    for filter in split_filter_text(graph, &[';', ',']) {
+        let filter = label_re.replace_all(&filter, "").to_string();
+        if filter.is_empty() {
+            continue;
+        }
+        let (name, args) = filter.split_once('=').unwrap_or((&filter, ""));
+        let name = name.split('@').next().unwrap_or(name).trim();
+        if !TEMPLATE_FILTERS.contains(&name) {
+            return Err(format!("The {} filter is not allowed in templates", name));
+        }
+        for arg in split_filter_text(args, &[':']) {
+            if let Some((key, _)) = arg.split_once('=') {
+                if TEMPLATE_FILE_FILTER_KEYS.contains(&key.trim()) {
+                    return Err(format!("The {} option of the {} filter is not allowed in templates", key.trim(), name));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Template rules: the input is read only through "-i {input}", the output is exactly one
+// standalone {output} argument, and only known placeholders appear. The app adds -y itself.
+// Nothing else may open files: only allow-listed options and filters are accepted, and
+// no extra output arguments.
+fn parse_command_template(line: &str) -> Result<Vec<String>, String> {
+    let args = split_template_args(line)?;
+    if args.is_empty() {
+        return Err("Template is empty".to_string());
+    }
+
+    let placeholder_re = regex::Regex::new(r"\{([a-zA-Z_]+)\}").map_err(|e| e.to_string())?;
+    for arg in &args {
+        for cap in placeholder_re.captures_iter(arg) {
+            if !TEMPLATE_PLACEHOLDERS.contains(&&cap[1]) {
+                return Err(format!("Unknown placeholder {{{}}}, use {{input}}, {{output}} or {{fps}}", &cap[1]));
+            }
+        }
+    }
+
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "-i" && args.get(i + 1).map(|a| a.as_str()) != Some("{input}") {
+            return Err("Templates can only read {input}, other -i sources are not allowed".to_string());
+        }
+        if arg.contains("{input}") && (arg != "{input}" || i == 0 || args[i - 1] != "-i") {
+            return Err("{input} must appear as \"-i {input}\"".to_string());
+        }
+        if arg.contains("{output}") && arg != "{output}" {
+            return Err("{output} must be an argument on its own".to_string());
+        }
+    }
+    if !args.iter().any(|a| a == "{input}") {
+        return Err("Template must read the input with -i {input}".to_string());
+    }
+    match args.iter().filter(|a| *a == "{output}").count() {
+        0 => return Err("Template must write to {output}".to_string()),
+        1 => {}
+        _ => return Err("Template can only write one {output}".to_string()),
+    }
+    if args.iter().any(|a| a == "-y" || a == "-n") {
+        return Err("Leave out -y/-n, overwriting is handled by the app".to_string());
+    }
+
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        if arg == "-i" {
+            args_iter.next();
+            continue;
+        }
+        let Some(option) = arg.strip_prefix('-').filter(|o| !o.is_empty()) else {
+            if arg != "{output}" {
+                return Err(format!("Unexpected argument \"{}\", templates can only write to {{output}}", arg));
+            }
+            continue;
+        };
+        let name = option.split(':').next().unwrap_or(option);
+        if is_template_flag(name) {
+            continue;
+        }
+        if !TEMPLATE_VALUE_OPTIONS.contains(&name) {
+            return Err(format!("{} is not allowed in templates", arg));
+        }
+        let value = args_iter.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        if matches!(name, "vf" | "af" | "filter" | "filter_complex" | "lavfi") {
+            check_template_filtergraph(value)?;
+        }
+    }
+    Ok(args)
+}
+
+#[tauri::command]
+async fn validate_command_template(args: String) -> Result<Vec<String>, String> {
+    parse_command_template(&args)
+}
+
+#[tauri::command]
+async fn save_command_template(app: tauri::AppHandle, template: CommandTemplate) -> Result<Vec<CommandTemplate>, String> {
+    info!("save_command_template called: {}", template.name);
+    let name = template.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Template name cannot be empty".to_string());
+    }
+    parse_command_template(&template.args)?;
+    let output_extension = template
+        .output_extension
+        .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+        .filter(|e| !e.is_empty());
+    if let Some(ext) = &output_extension {
+        if !ext.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("Invalid output extension: {}", ext));
+        }
+    }
+
+    let template = CommandTemplate { name, output_extension, updated_at: unix_timestamp(), ..template };
+    let mut templates = load_command_templates(&app);
+    match templates.iter_mut().find(|t| t.name.eq_ignore_ascii_case(&template.name)) {
+        Some(existing) => *existing = template,
+        None => templates.push(template),
+    }
+    write_command_templates(&app, &templates)?;
+    Ok(templates)
+}
+
+#[tauri::command]
+async fn list_command_templates(app: tauri::AppHandle) -> Result<Vec<CommandTemplate>, String> {
+    Ok(load_command_templates(&app))
+}
+
+#[tauri::command]
+async fn delete_command_template(app: tauri::AppHandle, name: String) -> Result<Vec<CommandTemplate>, String> {
+    info!("delete_command_template called: {}", name);
+    let mut templates = load_command_templates(&app);
+    let before = templates.len();
+    templates.retain(|t| !t.name.eq_ignore_ascii_case(name.trim()));
+    if templates.len() == before {
+        return Err(format!("Template not found: {}", name));
+    }
+    write_command_templates(&app, &templates)?;
+    Ok(templates)
+}
+
+// Runs a saved template as a regular job, with progress, cancel and completion events
+#[tauri::command]
+async fn run_command_template(app: tauri::AppHandle, window: tauri::WebviewWindow, name: String, input: String, options: Option<TemplateRunOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("run_command_template called: {} on {}", name, input);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_path).chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let template = load_command_templates(&app)
+        .into_iter()
+        .find(|t| t.name.eq_ignore_ascii_case(name.trim()))
+        .ok_or_else(|| format!("Template not found: {}", name))?;
+    // Re-checked in case the file was edited by hand
+    let template_args = parse_command_template(&template.args)?;
+
+    let summary = probe_media_summary(&input).await.ok();
+    let fps = match options.fps.as_deref().filter(|f| !f.is_empty() && *f != "source") {
+        Some(fps) => {
+            parse_frame_rate(fps).ok_or_else(|| format!("Invalid frame rate: {}", fps))?;
+            Some(fps.to_string())
+        }
+        None => summary.as_ref().and_then(|s| s.frame_rate.clone()),
+    };
+    if template_args.iter().any(|a| a.contains("{fps}")) && fps.is_none() {
+        return Err("This template uses {fps} but the input's frame rate is unknown, set one explicitly".to_string());
+    }
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = template
+        .output_extension
+        .clone()
+        .or_else(|| input_path.extension().map(|e| e.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "mp4".to_string());
+    let output_path_str = match options.output_path.as_ref().filter(|p| !p.is_empty()) {
+        Some(path) => path.clone(),
+        None => job_output_dir(&input_path, options.output_folder.as_ref(), "template")
+            .join(format!("{}_{}.{}", stem, sanitize_file_name(&template.name).replace(' ', "_"), ext))
+            .to_string_lossy()
+            .to_string(),
+    };
+    if std::path::Path::new(&output_path_str) == input_path.as_path() {
+        return Err("The output would overwrite the input".to_string());
+    }
+
+    let mut args = vec!["-y".to_string()];
+    args.extend(template_args.iter().map(|arg| {
+        arg.replace("{input}", &input)
+            .replace("{output}", &output_path_str)
+            .replace("{fps}", fps.as_deref().unwrap_or(""))
+    }));
+
+    let duration = summary.and_then(|s| s.duration);
+    let run = run_ffmpeg_job(&app, "template", None, &args, &output_path_str, duration).await?;
+    finish_ffmpeg_job(&app, "template", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Template run cancelled".to_string())
+    } else {
+        Err(format!("Template '{}' failed: {}", template.name, run.stderr_tail))
+    }
+}
+
+// ============================================================================
+// Session Restore
+// ============================================================================
+
+// What the app was doing when it was last closed, kept in session.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionState {
+    #[serde(default)]
+    pub active_tool: Option<String>,
+    #[serde(default)]
+    pub tools: HashMap<String, ToolSnapshot>,
+    #[serde(default)]
+    pub pending_jobs: Vec<PendingJob>,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+// The frontend's state for one tool (selected files, open panels) and the options it
+// last ran with. Both are opaque to the backend.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolSnapshot {
+    #[serde(default)]
+    pub state: serde_json::Value,
+    pub last_options: Option<serde_json::Value>,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingJob {
+    pub id: String,
+    pub job_type: String,
+    pub input: Option<String>,
+    #[serde(default)]
+    pub options: serde_json::Value,
+    #[serde(default)]
+    pub added_at: u64,
+    // Set on load when the input file has been moved or deleted since
+    #[serde(default)]
+    pub missing_input: bool,
+}
+
+// Keeps session.json small enough to load at startup without a noticeable delay
+const MAX_TOOL_SNAPSHOT_BYTES: usize = 256 * 1024;
+
+fn load_session_state(app: &tauri::AppHandle) -> SessionState {
+    app_data_file(app, "session.json")
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write_session_state(app: &tauri::AppHandle, session: &mut SessionState) -> Result<(), String> {
+    session.updated_at = unix_timestamp();
+    write_app_data_json(app, "session.json", session)
+}
+
+// The saved session, with pending jobs whose input is gone flagged rather than dropped
+// so the frontend can tell the user what couldn't be restored
+#[tauri::command]
+async fn load_session(app: tauri::AppHandle) -> Result<SessionState, String> {
+    let mut session = load_session_state(&app);
+    for job in session.pending_jobs.iter_mut() {
+        job.missing_input = job.input.as_deref().map(|p| !std::path::Path::new(p).exists()).unwrap_or(false);
+    }
+    Ok(session)
+}
+
+#[tauri::command]
+async fn set_active_tool(app: tauri::AppHandle, tool: Option<String>) -> Result<(), String> {
+    let mut session = load_session_state(&app);
+    session.active_tool = tool.filter(|t| !t.trim().is_empty());
+    write_session_state(&app, &mut session)
+}
+
+// Stores a snapshot of one tool's state. last_options is only replaced when given, so
+// saving UI state often doesn't lose the options of the last run.
+#[tauri::command]
+async fn save_tool_state(app: tauri::AppHandle, tool: String, state: serde_json::Value, last_options: Option<serde_json::Value>) -> Result<ToolSnapshot, String> {
+    let tool = tool.trim().to_string();
+    if tool.is_empty() {
+        return Err("Tool name can't be empty".to_string());
+    }
+    let size = serde_json::to_string(&state).map(|t| t.len()).unwrap_or(0)
+        + last_options.as_ref().and_then(|o| serde_json::to_string(o).ok()).map(|t| t.len()).unwrap_or(0);
+    if size > MAX_TOOL_SNAPSHOT_BYTES {
+        return Err(format!("Tool state is too large to save ({} KB, limit {} KB)", size / 1024, MAX_TOOL_SNAPSHOT_BYTES / 1024));
+    }
+
+    let mut session = load_session_state(&app);
+    let previous = session.tools.remove(&tool);
+    let snapshot = ToolSnapshot {
+        state,
+        last_options: last_options.or_else(|| previous.and_then(|p| p.last_options)),
+        updated_at: unix_timestamp(),
+    };
+    session.tools.insert(tool, snapshot.clone());
+    write_session_state(&app, &mut session)?;
+    Ok(snapshot)
+}
+
+#[tauri::command]
+async fn load_tool_state(app: tauri::AppHandle, tool: String) -> Result<Option<ToolSnapshot>, String> {
+    Ok(load_session_state(&app).tools.remove(tool.trim()))
+}
+
+// Replaces the saved queue with the jobs that haven't run yet
+#[tauri::command]
+async fn save_pending_jobs(app: tauri::AppHandle, jobs: Vec<PendingJob>) -> Result<usize, String> {
+    let mut session = load_session_state(&app);
+    let now = unix_timestamp();
+    session.pending_jobs = jobs
+        .into_iter()
+        .map(|mut job| {
+            if job.added_at == 0 {
+                job.added_at = now;
+            }
+            job.missing_input = false;
+            job
+        })
+        .collect();
+    write_session_state(&app, &mut session)?;
+    Ok(session.pending_jobs.len())
+}
+
+#[tauri::command]
+async fn clear_session(app: tauri::AppHandle) -> Result<(), String> {
+    info!("clear_session called");
+    let path = app_data_file(&app, "session.json")?;
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to remove session.json: {}", e))?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// File Notes and Tags
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileAnnotation {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSummary {
+    pub file_count: usize,
+    pub total_bytes: u64,
+    pub annotated_count: usize,
+    pub tag_counts: HashMap<String, usize>,
+    pub annotations: HashMap<String, FileAnnotation>,
+}
+
+// Same file, same key, however the path was spelled
+fn annotation_key(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+fn load_annotations(app: &tauri::AppHandle) -> HashMap<String, FileAnnotation> {
+    app_data_file(app, "file_notes.json")
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !normalized.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+// Replaces the tags and note for a file; clearing both removes the entry
+#[tauri::command]
+async fn set_file_annotation(app: tauri::AppHandle, path: String, tags: Vec<String>, note: Option<String>) -> Result<FileAnnotation, String> {
+    info!("set_file_annotation called for: {}", path);
+    scoped_path(&app, &path)?;
+    let mut annotations = load_annotations(&app);
+    let key = annotation_key(&path);
+    let annotation = FileAnnotation {
+        tags: normalize_tags(tags),
+        note: note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()),
+        updated_at: unix_timestamp(),
+    };
+
+    if annotation.tags.is_empty() && annotation.note.is_none() {
+        annotations.remove(&key);
+    } else {
+        annotations.insert(key, annotation.clone());
+    }
+    write_app_data_json(&app, "file_notes.json", &annotations)?;
+    Ok(annotation)
+}
+
+// Annotations for the given files, keyed by the path as passed in
+#[tauri::command]
+async fn get_file_annotations(app: tauri::AppHandle, paths: Vec<String>) -> Result<HashMap<String, FileAnnotation>, String> {
+    let annotations = load_annotations(&app);
+    Ok(paths
+        .into_iter()
+        .filter_map(|path| annotations.get(&annotation_key(&path)).cloned().map(|a| (path, a)))
+        .collect())
+}
+
+#[tauri::command]
+async fn find_files_by_tag(app: tauri::AppHandle, tag: String) -> Result<Vec<String>, String> {
+    let tag = tag.trim();
+    let mut files: Vec<String> = load_annotations(&app)
+        .into_iter()
+        .filter(|(_, a)| a.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        .map(|(path, _)| path)
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[tauri::command]
+async fn list_file_tags(app: tauri::AppHandle) -> Result<HashMap<String, usize>, String> {
+    let mut counts = HashMap::new();
+    for annotation in load_annotations(&app).values() {
+        for tag in &annotation.tags {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+    }
+    Ok(counts)
+}
+
+#[tauri::command]
+async fn summarize_folder(app: tauri::AppHandle, directory: String, extensions: Option<Vec<String>>) -> Result<FolderSummary, String> {
+    info!("summarize_folder called for: {}", directory);
+    let files = list_files(app.clone(), directory, extensions).await?;
+    let annotations = load_annotations(&app);
+
+    let mut summary = FolderSummary {
+        file_count: files.len(),
+        total_bytes: 0,
+        annotated_count: 0,
+        tag_counts: HashMap::new(),
+        annotations: HashMap::new(),
+    };
+    for file in files {
+        summary.total_bytes += std::fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+        if let Some(annotation) = annotations.get(&annotation_key(&file)) {
+            summary.annotated_count += 1;
+            for tag in &annotation.tags {
+                *summary.tag_counts.entry(tag.clone()).or_insert(0) += 1;
+            }
+            summary.annotations.insert(file, annotation.clone());
+        }
+    }
+    Ok(summary)
+}
+
+// ============================================================================
+// Job Reports
+// ============================================================================
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_bytes(bytes: Option<u64>) -> String {
+    match bytes {
+        Some(b) if b >= 1024 * 1024 * 1024 => format!("{:.2} GB", b as f64 / 1024.0 / 1024.0 / 1024.0),
+        Some(b) if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / 1024.0 / 1024.0),
+        Some(b) => format!("{:.0} KB", b as f64 / 1024.0),
+        None => String::new(),
+    }
+}
+
+fn opt_number(value: Option<f64>, precision: usize) -> String {
+    value.map(|v| format!("{:.*}", precision, v)).unwrap_or_default()
+}
+
+async fn build_report_row(entry: JobReportEntry) -> JobReportRow {
+    let file_size = |path: &str| std::fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| m.len());
+    let input_bytes = file_size(&entry.input);
+    let output_bytes = entry.output_path.as_deref().and_then(file_size);
+    let input_duration = match input_bytes {
+        Some(_) => probe_media_summary(&entry.input).await.ok().and_then(|s| s.duration),
+        None => None,
+    };
+    let output_duration = match (&entry.output_path, output_bytes) {
+        (Some(path), Some(_)) => probe_media_summary(path).await.ok().and_then(|s| s.duration),
+        _ => None,
+    };
+    let status = entry.status.clone().unwrap_or_else(|| {
+        if entry.error.is_some() { "error".to_string() } else if output_bytes.is_some() { "done".to_string() } else { "unknown".to_string() }
+    });
+
+    JobReportRow {
+        size_ratio: match (input_bytes, output_bytes) {
+            (Some(i), Some(o)) if i > 0 => Some(o as f64 / i as f64),
+            _ => None,
+        },
+        input: entry.input,
+        output_path: entry.output_path,
+        operation: entry.operation,
+        status,
+        error: entry.error,
+        settings: entry.settings,
+        elapsed_seconds: entry.elapsed_seconds,
+        input_bytes,
+        output_bytes,
+        input_duration,
+        output_duration,
+    }
+}
+
+fn render_report_csv(rows: &[JobReportRow]) -> String {
+    let mut out = String::from("input,output,operation,status,error,elapsed_seconds,input_bytes,output_bytes,input_duration,output_duration,size_ratio,settings\n");
+    for row in rows {
+        let fields = [
+            row.input.clone(),
+            row.output_path.clone().unwrap_or_default(),
+            row.operation.clone().unwrap_or_default(),
+            row.status.clone(),
+            row.error.clone().unwrap_or_default(),
+            opt_number(row.elapsed_seconds, 1),
+            row.input_bytes.map(|b| b.to_string()).unwrap_or_default(),
+            row.output_bytes.map(|b| b.to_string()).unwrap_or_default(),
+            opt_number(row.input_duration, 3),
+            opt_number(row.output_duration, 3),
+            opt_number(row.size_ratio, 3),
+            row.settings.as_ref().map(|s| s.to_string()).unwrap_or_default(),
+        ];
+        out.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn render_report_html(title: &str, rows: &[JobReportRow]) -> String {
+    let failed = rows.iter().filter(|r| r.status == "error").count();
+    let before: u64 = rows.iter().filter_map(|r| r.input_bytes).sum();
+    let after: u64 = rows.iter().filter_map(|r| r.output_bytes).sum();
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{t}</title>\n<style>body{{font-family:sans-serif;margin:24px}}table{{border-collapse:collapse;width:100%}}th,td{{border:1px solid #ccc;padding:4px 8px;font-size:13px;text-align:left;vertical-align:top}}th{{background:#f0f0f0}}.error{{color:#b00020}}</style>\n</head><body>\n<h1>{t}</h1>\n<p>{n} file(s), {f} failed. Total size {b} &rarr; {a}.</p>\n<table>\n<tr><th>Input</th><th>Output</th><th>Operation</th><th>Status</th><th>Size before</th><th>Size after</th><th>Duration</th><th>Time taken</th><th>Settings</th></tr>\n",
+        t = html_escape(title),
+        n = rows.len(),
+        f = failed,
+        b = format_bytes(Some(before)),
+        a = format_bytes(Some(after)),
+    );
+    for row in rows {
+        let status = match &row.error {
+            Some(err) => format!("<span class=\"error\">{}: {}</span>", html_escape(&row.status), html_escape(err)),
+            None => html_escape(&row.status),
+        };
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>\n",
+            html_escape(&row.input),
+            html_escape(row.output_path.as_deref().unwrap_or("")),
+            html_escape(row.operation.as_deref().unwrap_or("")),
+            status,
+            format_bytes(row.input_bytes),
+            format_bytes(row.output_bytes),
+            opt_number(row.output_duration.or(row.input_duration), 1),
+            opt_number(row.elapsed_seconds, 1),
+            html_escape(&row.settings.as_ref().map(|s| s.to_string()).unwrap_or_default()),
+        ));
+    }
+    html.push_str("</table>\n</body></html>\n");
+    html
+}
+
+// Writes a JSON/CSV/HTML summary of a finished batch. Without an explicit path the report
+// goes next to the first output (or input) file.
+#[tauri::command]
+async fn export_job_report(app: tauri::AppHandle, entries: Vec<JobReportEntry>, options: JobReportOptions) -> Result<String, String> {
+    info!("export_job_report called with {} entries", entries.len());
+
+    if entries.is_empty() {
+        return Err("No jobs to report".to_string());
+    }
+    let format = options.format.clone().map(|f| f.to_lowercase()).unwrap_or_else(|| "html".to_string());
+    if !matches!(format.as_str(), "json" | "csv" | "html") {
+        return Err(format!("Unsupported report format: {}", format));
+    }
+
+    let report_path = match options.output_path.as_ref().filter(|p| !p.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let anchor = entries
+                .iter()
+                .find_map(|e| e.output_path.clone())
+                .unwrap_or_else(|| entries[0].input.clone());
+            resolve_output_dir(std::path::Path::new(&anchor), None).join(format!("video_toolbox_report_{}.{}", unix_timestamp(), format))
+        }
+    };
+    check_scope(&app, entries.iter().flat_map(|e| std::iter::once(&e.input).chain(&e.output_path)))?;
+    scoped_path(&app, &report_path.to_string_lossy())?;
+
+    let mut rows = Vec::with_capacity(entries.len());
+    for entry in entries {
+        rows.push(build_report_row(entry).await);
+    }
+
+    let title = options.title.clone().unwrap_or_else(|| "Video Toolbox job report".to_string());
+    let content = match format.as_str() {
+        "json" => serde_json::to_string_pretty(&serde_json::json!({
+            "title": title,
+            "generatedAt": unix_timestamp(),
+            "jobs": rows,
+        }))
+        .map_err(|e| format!("Failed to serialize report: {}", e))?,
+        "csv" => render_report_csv(&rows),
+        _ => render_report_html(&title, &rows),
+    };
+
+    std::fs::write(&report_path, content).map_err(|e| format!("Failed to write report: {}", e))?;
+    Ok(report_path.to_string_lossy().to_string())
+}
+
+// ============================================================================
+// Webhooks
+// ============================================================================
+
+// Escapes a value for use inside a JSON string in a payload template
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string());
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+// Fills {event}, {jobType}, {outputPath}, {fileName}, {message} and {warnings} in a template
+fn render_webhook_payload(template: &str, kind: &str, event: &serde_json::Value) -> String {
+    let field = |key: &str| event.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let output_path = field("outputPath");
+    let file_name = std::path::Path::new(&output_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let warnings = event
+        .get("warnings")
+        .and_then(|w| w.as_array())
+        .map(|w| w.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>().join("; "))
+        .unwrap_or_default();
+
+    [
+        ("event", kind.to_string()),
+        ("jobType", field("jobType")),
+        ("outputPath", output_path.clone()),
+        ("fileName", file_name),
+        ("message", field("message")),
+        ("warnings", warnings),
+    ]
+    .iter()
+    .fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), &json_escape(value)))
+}
+
+fn webhook_wants(webhook: &WebhookConfig, kind: &str) -> bool {
+    if webhook.events.is_empty() {
+        kind == "complete" || kind == "error"
+    } else {
+        webhook.events.iter().any(|e| e == kind)
+    }
+}
+
+async fn send_webhook(webhook: &WebhookConfig, kind: &str, event: &serde_json::Value) -> Result<u16, String> {
+    let (body, content_type) = match webhook.payload_template.as_ref().filter(|t| !t.trim().is_empty()) {
+        Some(template) => (
+            render_webhook_payload(template, kind, event),
+            webhook.content_type.clone().unwrap_or_else(|| "application/json".to_string()),
+        ),
+        None => {
+            let mut payload = event.clone();
+            if let Some(obj) = payload.as_object_mut() {
+                obj.insert("event".to_string(), serde_json::Value::String(kind.to_string()));
+            }
+            (payload.to_string(), "application/json".to_string())
+        }
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .post(&webhook.url)
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Webhook request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Webhook returned HTTP {}", status.as_u16()));
+    }
+    Ok(status.as_u16())
+}
+
+// Fire-and-forget: a slow or broken endpoint must never hold up the job
+fn notify_webhooks<T: Serialize>(app: &tauri::AppHandle, kind: &str, event: &T) {
+    let webhooks: Vec<WebhookConfig> = load_settings(app)
+        .webhooks
+        .into_iter()
+        .filter(|w| w.enabled && validate_url(&w.url) && webhook_wants(w, kind))
+        .collect();
+    if webhooks.is_empty() {
+        return;
+    }
+    let Ok(event) = serde_json::to_value(event) else {
+        return;
+    };
+    let kind = kind.to_string();
+    tauri::async_runtime::spawn(async move {
+        for webhook in webhooks {
+            if let Err(e) = send_webhook(&webhook, &kind, &event).await {
+                error!("Webhook {} failed: {}", webhook.url, e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+async fn set_webhooks(app: tauri::AppHandle, webhooks: Vec<WebhookConfig>) -> Result<AppSettings, String> {
+    info!("set_webhooks called with {} webhook(s)", webhooks.len());
+    if let Some(bad) = webhooks.iter().find(|w| !validate_url(&w.url)) {
+        return Err(format!("Invalid webhook URL: {}", bad.url));
+    }
+    let mut settings = load_settings(&app);
+    settings.webhooks = webhooks;
+    write_settings(&app, &settings)?;
+    Ok(settings)
+}
+
+// Sends a sample completion event so users can check their endpoint and template
+#[tauri::command]
+async fn test_webhook(webhook: WebhookConfig) -> Result<u16, String> {
+    if !validate_url(&webhook.url) {
+        return Err(format!("Invalid webhook URL: {}", webhook.url));
+    }
+    let sample = serde_json::to_value(JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: "encode".to_string(),
+        output_path: "C:/Videos/example_encoded.mp4".to_string(),
+        warnings: Vec::new(),
+        frame_stats: None,
+        output_paths: Vec::new(),
+    })
+    .map_err(|e| format!("Failed to build sample event: {}", e))?;
+    send_webhook(&webhook, "complete", &sample).await
+}
+
+// ============================================================================
+// Path Scope
+// ============================================================================
+
+const PATH_SCOPE_FILE: &str = "path_scope.json";
+
+// Canonical form of a path for scope checks. Paths that don't exist yet (export
+// targets) resolve through their parent folder.
+fn canonical_scope_path(path: &std::path::Path) -> Option<PathBuf> {
+    if let Ok(canonical) = path.canonicalize() {
+        return Some(canonical);
+    }
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty())?.canonicalize().ok()?;
+    Some(parent.join(path.file_name()?))
+}
+
+// Folders that are always in scope: the default download and recording locations
+fn builtin_scope_roots() -> Vec<PathBuf> {
+    [dirs::download_dir(), dirs::audio_dir()]
+        .into_iter()
+        .flatten()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .collect()
+}
+
+fn load_path_scope(app: &tauri::AppHandle) -> Vec<PathBuf> {
+    app_data_file(app, PATH_SCOPE_FILE)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str::<Vec<String>>(&text).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+// Records the folder of a path the user picked (the path itself for folders),
+// replacing approved folders it contains
+fn approve_path(app: &tauri::AppHandle, path: &std::path::Path) {
+    let Some(canonical) = canonical_scope_path(path) else {
+        return;
+    };
+    let root = if canonical.is_dir() {
+        canonical
+    } else {
+        match canonical.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => return,
+        }
+    };
+    let state = app.state::<Arc<AppState>>();
+    let Ok(mut scope) = state.path_scope.lock() else {
+        return;
+    };
+    if scope.iter().any(|r| root.starts_with(r)) {
+        return;
+    }
+    scope.retain(|r| !r.starts_with(&root));
+    scope.push(root);
+    let saved: Vec<String> = scope.iter().map(|r| r.to_string_lossy().to_string()).collect();
+    drop(scope);
+    if let Err(e) = write_app_data_json(app, PATH_SCOPE_FILE, &saved) {
+        error!("Failed to save approved folders: {}", e);
+    }
+}
+
+// Resolves a path from the webview and rejects it unless it lies inside an approved
+// folder, so a compromised frontend can't reach the rest of the disk. Every command that
+// reads or writes a path it was handed (inputs, output files and output folders) checks
+// it with this or check_scope before touching the disk.
+fn scoped_path(app: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    if path.is_empty() {
+        return Err(msg("error.invalid_path"));
+    }
+    let canonical = canonical_scope_path(std::path::Path::new(path)).ok_or_else(|| msg("error.invalid_path"))?;
+    let state = app.state::<Arc<AppState>>();
+    let allowed = state.path_scope.lock().map(|scope| scope.iter().any(|r| canonical.starts_with(r))).unwrap_or(false)
+        || builtin_scope_roots().iter().any(|r| canonical.starts_with(r));
+    // The app's own data is never in scope, even inside an approved folder, so no job can
+    // write over the settings or the list of approved folders
+    let app_data = app.path().app_data_dir().ok().and_then(|dir| dir.canonicalize().ok());
+    let allowed = allowed && !app_data.is_some_and(|dir| canonical.starts_with(dir));
+    if !allowed {
+        log::warn!("Blocked access outside the approved folders: {}", canonical.display());
+        return Err(format!("{} is outside the folders opened in Video Toolbox. Choose it with a file dialog first.", path));
+    }
+    Ok(canonical)
+}
+
+// scoped_path for each path a command was handed. Empty ones stand for "not set" (an
+// output folder left blank means next to the input) and are skipped.
+fn check_scope<'a, I: IntoIterator<Item = &'a String>>(app: &tauri::AppHandle, paths: I) -> Result<(), String> {
+    for path in paths.into_iter().filter(|p| !p.is_empty()) {
+        scoped_path(app, path)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn list_approved_folders(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    let state = app.state::<Arc<AppState>>();
+    let scope = state.path_scope.lock().map_err(|_| "Approved folders are unavailable".to_string())?;
+    Ok(scope.iter().map(|r| r.to_string_lossy().to_string()).collect())
+}
+
+// Withdraws access to a folder approved earlier
+#[tauri::command]
+async fn revoke_approved_folder(app: tauri::AppHandle, folder: String) -> Result<(), String> {
+    info!("revoke_approved_folder called for: {}", folder);
+    let target = canonical_scope_path(std::path::Path::new(&folder)).unwrap_or_else(|| PathBuf::from(&folder));
+    let state = app.state::<Arc<AppState>>();
+    let saved: Vec<String> = {
+        let mut scope = state.path_scope.lock().map_err(|_| "Approved folders are unavailable".to_string())?;
+        scope.retain(|r| *r != target && !r.starts_with(&target));
+        scope.iter().map(|r| r.to_string_lossy().to_string()).collect()
+    };
+    write_app_data_json(&app, PATH_SCOPE_FILE, &saved)
+}
+
+// ============================================================================
+// Dialog Commands
+// ============================================================================
+
+#[tauri::command]
+async fn select_file(app: tauri::AppHandle, filters: Option<Vec<Filter>>, _allow_all: Option<bool>) -> Result<Option<String>, String> {
+    info!("select_file called with filters: {:?}", filters);
+    
+    use tauri_plugin_dialog::DialogExt;
+    
+    let mut builder = app.dialog().file();
+    
+    if let Some(f) = filters {
+        for filter in f {
+            let extensions: Vec<&str> = filter.extensions.iter().map(|s| s.as_str()).collect();
+            builder = builder.add_filter(filter.name, &extensions);
+        }
+    }
+    
+    let result = builder.blocking_pick_file();
+    if let Some(path) = result.as_ref().and_then(|p| p.as_path()) {
+        approve_path(&app, path);
+    }
+    
+    Ok(result.map(|p| p.to_string()))
+}
+
+#[tauri::command]
+async fn select_files(app: tauri::AppHandle, filters: Option<Vec<Filter>>, _allow_all: Option<bool>) -> Result<Vec<String>, String> {
+    info!("select_files called with filters: {:?}", filters);
+    
+    use tauri_plugin_dialog::DialogExt;
+    
+    let mut builder = app.dialog().file();
+
+    if let Some(f) = filters {
+        for filter in f {
+            let extensions: Vec<&str> = filter.extensions.iter().map(|s| s.as_str()).collect();
+            builder = builder.add_filter(filter.name, &extensions);
+        }
+    }
+    
+    let result = builder.blocking_pick_files();
+    for path in result.iter().flatten().filter_map(|p| p.as_path()) {
+        approve_path(&app, path);
+    }
+    
+    Ok(result.map(|paths| paths.into_iter().map(|p| p.to_string()).collect()).unwrap_or_default())
+}
+
+#[tauri::command]
+async fn save_file(app: tauri::AppHandle, _filters: Option<Vec<Filter>>, default_path: Option<String>, _title: Option<String>) -> Result<Option<String>, String> {
+    info!("save_file called with default_path: {:?}", default_path);
+    
+    use tauri_plugin_dialog::DialogExt;
+    
+    let result = app.dialog()
+        .file()
+        .set_file_name(default_path.unwrap_or_else(|| "output.pdf".to_string()))
+        .blocking_save_file();
+    if let Some(path) = result.as_ref().and_then(|p| p.as_path()) {
+        approve_path(&app, path);
+    }
+    
+    Ok(result.map(|p| p.to_string()))
+}
+
+#[tauri::command]
+async fn select_folder(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    info!("select_folder called");
+    
+    use tauri_plugin_dialog::DialogExt;
+    
+    let result = app.dialog()
+        .file()
+        .blocking_pick_folder();
+    if let Some(path) = result.as_ref().and_then(|p| p.as_path()) {
+        approve_path(&app, path);
+    }
+    
+    Ok(result.map(|p| p.to_string()))
+}
+
+// ============================================================================
+// Utility Commands
+// ============================================================================
+
+#[tauri::command]
+async fn list_files(app: tauri::AppHandle, directory: String, extensions: Option<Vec<String>>) -> Result<Vec<String>, String> {
+    info!("list_files called for directory: {}", directory);
+    
+    let path = scoped_path(&app, &directory)?;
+    if !path.exists() || !path.is_dir() {
+        return Err("Invalid directory path".to_string());
+    }
+    
+    let video_extensions = extensions.unwrap_or_else(|| {
+        vec!["mp4".to_string(), "mkv".to_string(), "avi".to_string(), "mov".to_string(), "webm".to_string(), "flv".to_string(), "wmv".to_string()]
+    });
+    
+    let mut files = Vec::new();
+    
+    if let Ok(entries) = std::fs::read_dir(&path) {
+        for entry in entries.flatten() {
+            if let Ok(file_type) = entry.file_type() {
+                if file_type.is_file() {
+                    if let Some(ext) = entry.path().extension() {
+                        let ext_str = ext.to_string_lossy().to_lowercase();
+                        if video_extensions.iter().any(|e| e.to_lowercase() == ext_str) {
+                            files.push(entry.path().to_string_lossy().to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    Ok(files)
+}
+
+const SEQUENCE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "tif", "tiff", "bmp", "tga", "exr", "dpx", "webp"];
+
+// Splits "frame_0001.png" into ("frame_", "0001", ".png") around the last run of digits
+fn split_sequence_name(file_name: &str) -> Option<(String, String, String)> {
+    let (stem, ext) = file_name.rsplit_once('.')?;
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end].rfind(|c: char| !c.is_ascii_digit()).map(|i| i + 1).unwrap_or(0);
+    Some((stem[..start].to_string(), stem[start..end].to_string(), format!("{}.{}", &stem[end..], ext)))
+}
+
+// Every frame number of the sequence that `file_name` belongs to, sorted
+fn sequence_frame_numbers(dir: &std::path::Path, prefix: &str, digits: &str, suffix: &str) -> Vec<u64> {
+    let padded = digits.starts_with('0') && digits.len() > 1;
+    let mut numbers: Vec<u64> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let (p, d, s) = split_sequence_name(&name)?;
+            // Padded sequences keep one width, unpadded ones (1, 2, ... 10) grow
+            let same_width = if padded { d.len() == digits.len() } else { !d.starts_with('0') || d.len() == 1 };
+            if p == prefix && s.eq_ignore_ascii_case(suffix) && same_width { d.parse().ok() } else { None }
+        })
+        .collect();
+    numbers.sort_unstable();
+    // frame_0001.png and frame_0001.PNG name the same frame
+    numbers.dedup();
+    numbers
+}
+
+// Splits "frames/shot_%04d.png" into ("shot_", 4, ".png"). Only the file name is matched, and
+// only for image extensions and paths that don't exist as typed, so "clip_%5d.mp4" or a folder
+// named "100%d" still open as ordinary files
+fn image_sequence_pattern(input: &str) -> Option<(String, usize, String)> {
+    let path = std::path::Path::new(input);
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if !SEQUENCE_EXTENSIONS.contains(&ext.as_str()) || path.exists() {
+        return None;
+    }
+    let token = regex::Regex::new(r"%0?(\d*)d").ok()?.captures(&file_name)?;
+    let width = token.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(1);
+    let whole = token.get(0)?;
+    Some((file_name[..whole.start()].to_string(), width, file_name[whole.end()..].to_string()))
+}
+
+// The output name for an input: "shot_%04d.png" becomes "shot" rather than a pattern of its own
+fn input_output_stem(input: &str) -> String {
+    let path = std::path::Path::new(input);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let Some((prefix, _, suffix)) = image_sequence_pattern(input) else {
+        return stem;
+    };
+    let rest = suffix.rsplit_once('.').map(|(rest, _)| rest).unwrap_or(&suffix);
+    let joined = format!("{}{}", prefix, rest);
+    let trimmed = joined.trim_matches(|c: char| c == '_' || c == '-' || c == '.' || c == ' ');
+    if trimmed.is_empty() { "sequence".to_string() } else { trimmed.to_string() }
+}
+
+// For "frames/shot_%04d.png" inputs: the image2 options ffmpeg needs before -i
+fn image_sequence_input_args(input: &str, input_fps: Option<&str>) -> Vec<String> {
+    let Some((prefix, width, suffix)) = image_sequence_pattern(input) else {
+        return Vec::new();
+    };
+    let dir = std::path::Path::new(input).parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
+    let sample_digits = if width > 1 { "0".repeat(width) } else { "1".to_string() };
+    let start = sequence_frame_numbers(&dir, &prefix, &sample_digits, &suffix).first().copied().unwrap_or(0);
+
+    let fps = input_fps.filter(|f| parse_frame_rate(f).is_some()).unwrap_or("24");
+    vec!["-framerate".to_string(), fps.to_string(), "-start_number".to_string(), start.to_string()]
+}
+
+// Recognises frame_0001.png as one frame of frame_%04d.png so it can be used as a video input
+#[tauri::command]
+async fn detect_image_sequence(app: tauri::AppHandle, path: String) -> Result<ImageSequence, String> {
+    info!("detect_image_sequence called for: {}", path);
+
+    let file = validate_path(&path).ok_or_else(|| msg("error.invalid_path"))?;
+    scoped_path(&app, &path)?;
+    let file_name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = file.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if !SEQUENCE_EXTENSIONS.contains(&ext.as_str()) {
+        return Err("Image sequences must be PNG, JPEG, TIFF, BMP, TGA, EXR, DPX or WebP frames".to_string());
+    }
+    let (prefix, digits, suffix) = split_sequence_name(&file_name).ok_or("The file name has no frame number")?;
+    let dir = file.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
+
+    let scan_dir = dir.clone();
+    let (scan_prefix, scan_digits, scan_suffix) = (prefix.clone(), digits.clone(), suffix.clone());
+    let numbers = tokio::task::spawn_blocking(move || sequence_frame_numbers(&scan_dir, &scan_prefix, &scan_digits, &scan_suffix))
+        .await
+        .map_err(|e| format!("Sequence scan failed: {}", e))?;
+    if numbers.len() < 2 {
+        return Err("No other numbered frames found next to this file".to_string());
+    }
+
+    let first = numbers[0];
+    let last = numbers[numbers.len() - 1];
+    let missing = ((last - first + 1) as usize).saturating_sub(numbers.len());
+    let padded = digits.starts_with('0') && digits.len() > 1;
+    let token = if padded { format!("%0{}d", digits.len()) } else { "%d".to_string() };
+    let pattern = dir.join(format!("{}{}{}", prefix, token, suffix)).to_string_lossy().to_string();
+    let (width, height) = image::image_dimensions(&file).map(|(w, h)| (Some(w), Some(h))).unwrap_or((None, None));
+
+    // Renders don't record a frame rate, so guess from the usual names and fall back to 24
+    let lower = prefix.to_lowercase();
+    let fps_guess = [("60fps", 60.0), ("50fps", 50.0), ("30fps", 30.0), ("25fps", 25.0), ("24fps", 24.0)]
+        .iter()
+        .find(|(tag, _)| lower.contains(tag))
+        .map(|(_, fps)| *fps)
+        .unwrap_or(24.0);
+
+    Ok(ImageSequence {
+        duration_seconds: numbers.len() as f64 / fps_guess,
+        pattern,
+        first_frame: first,
+        last_frame: last,
+        frame_count: numbers.len(),
+        missing_frames: missing,
+        fps_guess,
+        width,
+        height,
+    })
+}
+
+#[tauri::command]
+async fn get_app_version(app: tauri::AppHandle) -> Result<String, String> {
+    info!("get_app_version called");
+    Ok(app.package_info().version.to_string())
+}
+
+// ============================================================================
+// FFmpeg Commands
+// ============================================================================
+
+// Whether this ffmpeg build has a filter, e.g. rubberband, which needs --enable-librubberband
+async fn ffmpeg_has_filter(name: &str) -> bool {
+    let Ok(output) = child_output(&mut ffmpeg_command(["-hide_banner", "-filters"]), None).await else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout).lines().any(|line| line.split_whitespace().nth(1) == Some(name))
+}
+
+#[tauri::command]
+async fn get_encoders() -> Result<EncoderInfo, String> {
+    info!("get_encoders called");
+    
+    
+    let output = child_output(&mut ffmpeg_command(["-encoders"]), None)
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    let stderr_str = String::from_utf8_lossy(&output.stderr);
+    let combined = format!("{}{}", output_str, stderr_str);
+    
+    let encoders = EncoderInfo {
+        nvenc: combined.contains("h264_nvenc") || combined.contains("hevc_nvenc"),
+        amf: combined.contains("h264_amf") || combined.contains("hevc_amf"),
+        qsv: combined.contains("h264_qsv") || combined.contains("hevc_qsv"),
+    };
+    
+    Ok(encoders)
+}
+
+#[tauri::command]
+async fn get_metadata(app: tauri::AppHandle, file_path: String) -> Result<VideoMetadata, String> {
+    scoped_path(&app, &file_path)?;
+    probe_metadata(file_path).await
+}
+
+async fn probe_metadata(file_path: String) -> Result<VideoMetadata, String> {
+    info!("get_metadata called for: {}", file_path);
+    
+    let validated = validate_path(&file_path).ok_or_else(|| msg("error.invalid_path"))?;
+    let path_str = validated.to_string_lossy().to_string();
+    
+    // Use ffprobe to get basic metadata
+    let output = probe_output(ffprobe_command()
+        .args(&[
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-show_entries", "stream=width,height,r_frame_rate",
+            "-of", "csv=p=0",
+            &path_str
+        ]))
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    
+    // Parse resolution
+    let mut resolution = "Unknown".to_string();
+    let mut width: Option<u32> = None;
+    let mut height: Option<u32> = None;
+    let mut fps: Option<f64> = None;
+    
+    for line in output_str.lines() {
+        let parts: Vec<&str> = line.split(',').collect();
+        if parts.len() >= 2 {
+            width = parts[0].parse().ok();
+            height = parts[1].parse().ok();
+            if width.is_some() && height.is_some() {
+                resolution = format!("{}x{}", width.unwrap(), height.unwrap());
+            }
+            if parts.len() >= 3 {
+                if let Some((num, den)) = parts[2].split_once('/') {
+                    if let (Ok(n), Ok(d)) = (num.parse::<f64>(), den.parse::<f64>()) {
+                        if d > 0.0 {
+                            fps = Some(n / d);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    // Get duration and bitrate
+    let output2 = probe_output(ffprobe_command()
+        .args(&[
+            "-v", "error",
+            "-show_entries", "format=duration,bit_rate",
+            "-of", "csv=p=0",
+            &path_str
+        ]))
+        .await;
+    
+    let mut duration = "00:00:00".to_string();
+    let mut duration_seconds: Option<f64> = None;
+    let mut bitrate: Option<String> = None;
+    
+    if let Ok(output2) = output2 {
+        let output2_str = String::from_utf8_lossy(&output2.stdout);
+        for line in output2_str.lines() {
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() >= 1 {
+                if let Ok(dur) = parts[0].parse::<f64>() {
+                    duration_seconds = Some(dur);
+                    let hours = (dur / 3600.0).floor() as u32;
+                    let mins = ((dur % 3600.0) / 60.0).floor() as u32;
+                    let secs = (dur % 60.0).floor() as u32;
+                    duration = format!("{:02}:{:02}:{:02}", hours, mins, secs);
+                }
+            }
+            if parts.len() >= 2 {
+                if let Ok(br) = parts[1].parse::<u64>() {
+                    bitrate = Some(format!("{} kbps", br / 1000));
+                }
+            }
+        }
+    }
+    
+    Ok(VideoMetadata {
+        resolution: Some(resolution),
+        width,
+        height,
+        duration: Some(duration),
+        duration_seconds,
+        bitrate,
+        fps,
+    })
+}
+
+#[tauri::command]
+async fn get_metadata_full(app: tauri::AppHandle, file_path: String) -> Result<serde_json::Value, String> {
+    scoped_path(&app, &file_path)?;
+    probe_metadata_full(file_path).await
+}
+
+async fn probe_metadata_full(file_path: String) -> Result<serde_json::Value, String> {
+    info!("get_metadata_full called for: {}", file_path);
+    
+    let validated = validate_path(&file_path).ok_or_else(|| msg("error.invalid_path"))?;
+    let path_str = validated.to_string_lossy().to_string();
+    info!("Validated path: {}", path_str);
+    
+    let output = probe_output(ffprobe_command()
+        .args(&[
+            "-v", "error",
+            "-print_format", "json",
+            "-show_entries", "format=format_name,duration,size,bit_rate:format_tags=title,artist,album,date,genre,track,comment:stream=codec_type,codec_name,width,height,r_frame_rate,bit_rate,pix_fmt,sample_rate,channels,channel_layout:stream_tags=language",
+            "-show_format",
+            "-show_streams",
+            &path_str
+        ]))
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffprobe failed: {}", stderr));
+    }
+    
+    let json_str = String::from_utf8_lossy(&output.stdout);
+    serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))
+}
+
+#[tauri::command]
+async fn get_image_info(app: tauri::AppHandle, file_path: String) -> Result<ImageInfo, String> {
+    info!("get_image_info called for: {}", file_path);
+    
+    scoped_path(&app, &file_path)?;
+    let validated = validate_path(&file_path).ok_or_else(|| msg("error.invalid_path"))?;
+    let path_str = validated.to_string_lossy().to_string();
+    
+    let output = probe_output(ffprobe_command()
+        .args(&[
+            "-v", "error",
+            "-print_format", "json",
+            "-show_entries", "format=format_name,size:stream=codec_name,width,height,pix_fmt,color_space,color_primaries,bits_per_raw_sample,bits_per_sample",
+            &path_str
+        ]))
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    
+    if !output.status.success() {
+        return Err("ffprobe failed".to_string());
+    }
+    
+    let json_str = String::from_utf8_lossy(&output.stdout);
+    let data: serde_json::Value = serde_json::from_str(&json_str)
+        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    
+    let streams = data.get("streams").and_then(|s| s.as_array());
+    let stream = streams.and_then(|s| s.first());
+    
+    let format = data.get("format").and_then(|f| f.as_object());
+    
+    let width = stream.and_then(|s| s.get("width")).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    let height = stream.and_then(|s| s.get("height")).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    let codec = stream.and_then(|s| s.get("codec_name")).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let pixel_format = stream.and_then(|s| s.get("pix_fmt")).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let color_space = stream.and_then(|s| s.get("color_space")).or_else(|| stream.and_then(|s| s.get("color_primaries"))).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let bit_depth = stream.and_then(|s| s.get("bits_per_raw_sample")).or_else(|| stream.and_then(|s| s.get("bits_per_sample"))).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let format_name = format.and_then(|f| f.get("format_name")).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let mut size_bytes = format.and_then(|f| f.get("size")).and_then(|v| v.as_str()).and_then(|s| s.parse::<u64>().ok()).unwrap_or(0);
+    
+    let metadata = std::fs::metadata(&validated).ok();
+    if size_bytes == 0 {
+        size_bytes = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+    }
+    
+    let mtime_ms = metadata
+        .and_then(|m| m.modified().ok())
+        .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis() as u64)
+        .unwrap_or(0);
+    
+    Ok(ImageInfo {
+        width,
+        height,
+        codec,
+        pixel_format,
+        color_space,
+        bit_depth,
+        format: format_name,
+        size_bytes,
+        mtime_ms,
+    })
+}
+
+#[tauri::command]
+async fn save_metadata(app: tauri::AppHandle, file_path: String, metadata: serde_json::Value) -> Result<(), String> {
+    info!("save_metadata called for: {}", file_path);
+    
+    let validated = validate_path(&file_path).ok_or_else(|| msg("error.invalid_path"))?;
+    scoped_path(&app, &file_path)?;
+    let path_str = validated.to_string_lossy().to_string();
+    
+    // Build metadata arguments
+    let mut args = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        path_str.clone(),
+        "-c".to_string(),
+        "copy".to_string(),
+    ];
+    
+    // Add metadata
+    if let Some(title) = metadata.get("title").and_then(|v| v.as_str()) {
+        args.push("-metadata".to_string());
+        args.push(format!("title={}", title));
+    }
+    if let Some(artist) = metadata.get("artist").and_then(|v| v.as_str()) {
+        args.push("-metadata".to_string());
+        args.push(format!("artist={}", artist));
+    }
+    if let Some(album) = metadata.get("album").and_then(|v| v.as_str()) {
+        args.push("-metadata".to_string());
+        args.push(format!("album={}", album));
+    }
+    if let Some(year) = metadata.get("year").and_then(|v| v.as_str()) {
+        args.push("-metadata".to_string());
+        args.push(format!("date={}", year));
+    }
+    if let Some(genre) = metadata.get("genre").and_then(|v| v.as_str()) {
+        args.push("-metadata".to_string());
+        args.push(format!("genre={}", genre));
+    }
+    if let Some(track) = metadata.get("track").and_then(|v| v.as_str()) {
+        args.push("-metadata".to_string());
+        args.push(format!("track={}", track));
+    }
+    if let Some(comment) = metadata.get("comment").and_then(|v| v.as_str()) {
+        args.push("-metadata".to_string());
+        args.push(format!("comment={}", comment));
+    }
+    
+    // Output path
+    let parent = validated.parent().map(|p| p.to_path_buf());
+    let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = validated.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+    let temp_path = parent.map(|p| p.join(format!("{}_temp.{}", stem, ext))).unwrap_or_else(|| PathBuf::from("temp_output.mp4"));
+    args.push(temp_path.to_string_lossy().to_string());
+    
+    let output = child_output(&mut ffmpeg_command(&args), temp_path.to_str())
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg failed: {}", stderr));
+    }
+    
+    // Replace original with temp file
+    std::fs::rename(&temp_path, &validated).map_err(|e| format!("Failed to replace file: {}", e))?;
+    
+    Ok(())
+}
+
+// Raw elementary stream container for a codec: (extension, muxer, bitstream filter)
+fn elementary_stream_target(codec: &str) -> (&'static str, &'static str, Option<&'static str>) {
+    match codec {
+        "h264" => ("h264", "h264", Some("h264_mp4toannexb")),
+        "hevc" => ("hevc", "hevc", Some("hevc_mp4toannexb")),
+        "mpeg2video" => ("m2v", "mpeg2video", None),
+        "mpeg4" => ("m4v", "m4v", None),
+        "av1" => ("obu", "obu", None),
+        "vp8" | "vp9" => ("ivf", "ivf", None),
+        "aac" => ("aac", "adts", None),
+        "mp3" => ("mp3", "mp3", None),
+        "ac3" => ("ac3", "ac3", None),
+        "eac3" => ("eac3", "eac3", None),
+        "dts" => ("dts", "dts", None),
+        "truehd" => ("thd", "truehd", None),
+        "flac" => ("flac", "flac", None),
+        "opus" => ("opus", "opus", None),
+        "vorbis" => ("ogg", "ogg", None),
+        "subrip" => ("srt", "srt", None),
+        "ass" | "ssa" => ("ass", "ass", None),
+        "webvtt" => ("vtt", "webvtt", None),
+        "hdmv_pgs_subtitle" => ("sup", "sup", None),
+        c if c.starts_with("pcm_") => ("wav", "wav", None),
+        // Anything without a raw format of its own goes into a single-stream Matroska
+        _ => ("mkv", "matroska", None),
+    }
+}
+
+#[tauri::command]
+async fn extract_stream(app: tauri::AppHandle, input: String, stream_index: u32, output: Option<String>) -> Result<String, String> {
+    info!("extract_stream called for: {} (stream {})", input, stream_index);
+
+    scoped_path(&app, &input)?;
+    let validated = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    if let Some(output) = output.as_deref().filter(|o| !o.is_empty()) {
+        scoped_path(&app, output)?;
+    }
+
+    let probe = probe_output(ffprobe_command()
+        .args([
+            "-v", "error",
+            "-select_streams", &stream_index.to_string(),
+            "-show_entries", "stream=codec_name,codec_type",
+            "-of", "csv=p=0",
+            &input,
+        ]))
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+    let probe_str = String::from_utf8_lossy(&probe.stdout);
+    let line = probe_str.lines().find(|l| !l.trim().is_empty()).ok_or_else(|| format!("Stream {} not found", stream_index))?;
+    let codec = line.split(',').next().unwrap_or("").trim().to_string();
+
+    // mov_text only exists inside MP4, so text subs get converted to SRT on the way out
+    let (ext, muxer, bsf) = if codec == "mov_text" {
+        ("srt", "srt", None)
+    } else {
+        elementary_stream_target(&codec)
+    };
+
+    let output_path = match output.filter(|o| !o.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            resolve_output_dir(&validated, None).join(format!("{}_stream{}_{}.{}", stem, stream_index, codec, ext))
+        }
+    };
+    let output_path_str = output_path.to_string_lossy().to_string();
+
+    let mut args = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        format!("0:{}", stream_index),
+        "-map_metadata".to_string(),
+        "-1".to_string(),
+    ];
+    if codec == "mov_text" {
+        args.push("-c".to_string());
+        args.push("srt".to_string());
+    } else {
+        args.push("-c".to_string());
+        args.push("copy".to_string());
+    }
+    if let Some(filter) = bsf {
+        args.push("-bsf".to_string());
+        args.push(filter.to_string());
+    }
+    args.push("-f".to_string());
+    args.push(muxer.to_string());
+    args.push(output_path_str.clone());
+
+    let output = child_output(&mut ffmpeg_command(&args), Some(&output_path_str))
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to extract stream: {}", stderr));
+    }
+
+    Ok(output_path_str)
+}
+
+// ============================================================================
+// Job Events
+// ============================================================================
+
+// Bumped whenever a field is removed or changes meaning; adding fields keeps the version
+pub const JOB_EVENT_SCHEMA_VERSION: u32 = 1;
+
+// Payload of "encode-progress" / "download-progress" style events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobProgressEvent {
+    pub schema_version: u32,
+    pub job_type: String,
+    pub percent: f64,
+    pub time: Option<String>,
+    pub speed: Option<String>,
+    // Set by jobs that produce several files, e.g. one per chapter (index is zero-based)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_count: Option<usize>,
+    // Progress across all items of a batch, when percent is for the current file only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overall_percent: Option<f64>,
+}
+
+// Position of the current file within a multi-output job
+#[derive(Debug, Clone, Copy)]
+struct JobItem {
+    index: usize,
+    count: usize,
+}
+
+// Payload of "*-complete" events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCompleteEvent {
+    pub schema_version: u32,
+    pub job_type: String,
+    pub output_path: String,
+    // Non-fatal issues, e.g. tracks that had to be dropped
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    // Set when duplicate frames were removed during the encode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_stats: Option<FrameStats>,
+    // Every file written when one job produces several outputs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub output_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameStats {
+    pub source_frames: u64,
+    pub output_frames: u64,
+    pub dropped_frames: u64,
+}
+
+// Payload of "*-error" events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobErrorEvent {
+    pub schema_version: u32,
+    pub job_type: String,
+    pub message: String,
+    // Machine-readable failure class when known, e.g. "drm" or "login_required"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+// Payload of "*-cancelled" events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCancelledEvent {
+    pub schema_version: u32,
+    pub job_type: String,
+}
+
+// Download jobs report through download-* events, everything else through encode-*
+fn job_event_name(job_type: &str, kind: &str) -> String {
+    let prefix = if job_type == "download" { "download" } else { "encode" };
+    format!("{}-{}", prefix, kind)
+}
+
+// ffmpeg prints a progress line per frame batch, which at high encode speeds means
+// hundreds of events a second over the IPC bridge. This sends at most the configured
+// rate per window and key; events in between are coalesced and only the newest one is
+// sent once the interval is up, so the final value always gets through.
+fn emit_progress_throttled<R: tauri::Runtime, T: Serialize + Clone>(app: &tauri::AppHandle<R>, key: &str, event: &str, payload: T) {
+    emit_progress_throttled_to(app, job_window(), key, event, payload);
+}
+
+// For threads outside the job's task, such as rayon workers, which have to be told the window
+fn emit_progress_throttled_to<R: tauri::Runtime, T: Serialize + Clone>(app: &tauri::AppHandle<R>, window: Option<String>, key: &str, event: &str, payload: T) {
+    let rate = PROGRESS_EVENT_RATE.load(std::sync::atomic::Ordering::Relaxed);
+    if rate == 0 {
+        emit_job_event_to(app, window.as_deref(), key, event, payload);
+        return;
+    }
+    let interval = std::time::Duration::from_secs(1) / rate;
+    let state = app.state::<Arc<AppState>>();
+    let Ok(mut slots) = state.progress_throttle.lock() else {
+        emit_job_event_to(app, window.as_deref(), key, event, payload);
+        return;
+    };
+    let slot_key = throttle_key(window.as_deref(), key);
+    let slot = slots.entry(slot_key.clone()).or_default();
+    let now = std::time::Instant::now();
+    let elapsed = slot.last_emit.map(|t| now.duration_since(t));
+    if elapsed.map(|e| e >= interval).unwrap_or(true) {
+        slot.last_emit = Some(now);
+        slot.pending = None;
+        drop(slots);
+        emit_job_event_to(app, window.as_deref(), key, event, payload);
+        return;
+    }
+
+    let Ok(value) = serde_json::to_value(payload) else {
+        return;
+    };
+    slot.pending = Some((event.to_string(), value));
+    if slot.flush_scheduled {
+        return;
+    }
+    slot.flush_scheduled = true;
+    let wait = interval.saturating_sub(elapsed.unwrap_or_default());
+    let app = app.clone();
+    let key = key.to_string();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(wait).await;
+        let state = app.state::<Arc<AppState>>();
+        let pending = match state.progress_throttle.lock() {
+            Ok(mut slots) => slots.get_mut(&slot_key).and_then(|slot| {
+                slot.flush_scheduled = false;
+                let pending = slot.pending.take();
+                if pending.is_some() {
+                    slot.last_emit = Some(std::time::Instant::now());
+                }
+                pending
+            }),
+            Err(_) => None,
+        };
+        if let Some((event, value)) = pending {
+            emit_job_event_to(&app, window.as_deref(), &key, &event, value);
+        }
+    });
+}
+
+fn throttle_key(window: Option<&str>, key: &str) -> String {
+    format!("{}\n{}", window.unwrap_or_default(), key)
+}
+
+// Drops held-back progress once a job has ended, so it can't arrive after the result
+fn clear_progress_throttle(app: &tauri::AppHandle, key: &str) {
+    if let Ok(mut slots) = app.state::<Arc<AppState>>().progress_throttle.lock() {
+        slots.remove(&throttle_key(job_window().as_deref(), key));
+    }
+}
+
+// Window that started the job running on each async task. Job commands hold a JobWindow
+// for as long as they run, so the entry goes away however the command returns, and the
+// tasks they spawn to read progress or finish the job inherit it through spawn_job_task.
+static JOB_TASK_WINDOWS: std::sync::LazyLock<std::sync::Mutex<HashMap<tokio::task::Id, String>>> =
+    std::sync::LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+struct JobWindow {
+    task: Option<tokio::task::Id>,
+    previous: Option<String>,
+}
+
+impl JobWindow {
+    fn enter(label: &str) -> Self {
+        let task = tokio::task::try_id();
+        let previous = match (task, JOB_TASK_WINDOWS.lock()) {
+            (Some(task), Ok(mut windows)) => windows.insert(task, label.to_string()),
+            _ => None,
+        };
+        JobWindow { task, previous }
+    }
+}
+
+impl Drop for JobWindow {
+    fn drop(&mut self) {
+        if let (Some(task), Ok(mut windows)) = (self.task, JOB_TASK_WINDOWS.lock()) {
+            match self.previous.take() {
+                Some(previous) => windows.insert(task, previous),
+                None => windows.remove(&task),
+            };
+        }
+    }
+}
+
+fn job_window() -> Option<String> {
+    let task = tokio::task::try_id()?;
+    JOB_TASK_WINDOWS.lock().ok()?.get(&task).cloned()
+}
+
+fn spawn_job_task<F>(future: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let window = job_window();
+    tokio::spawn(async move {
+        let _window = window.as_deref().map(JobWindow::enter);
+        future.await
+    })
+}
+
+// Jobs started by the calling window normally only report to it; this sends its jobs of
+// the given type to every window instead, or back to just this one.
+#[tauri::command]
+async fn set_job_event_broadcast(app: tauri::AppHandle, window: tauri::WebviewWindow, job_type: String, broadcast: bool) -> Result<(), String> {
+    let state = app.state::<Arc<AppState>>();
+    let mut shared = state.job_broadcast.lock().map_err(|_| "Job window registry is unavailable".to_string())?;
+    let key = (window.label().to_string(), job_type);
+    if broadcast {
+        shared.insert(key);
+    } else {
+        shared.remove(&key);
+    }
+    Ok(())
+}
+
+fn forget_job_window(app: &tauri::AppHandle, label: &str) {
+    if let Ok(mut shared) = app.state::<Arc<AppState>>().job_broadcast.lock() {
+        shared.retain(|(window, _)| window != label);
+    }
+}
+
+fn emit_job_event<R: tauri::Runtime, S: Serialize + Clone>(app: &tauri::AppHandle<R>, job_type: &str, event: &str, payload: S) {
+    emit_job_event_to(app, job_window().as_deref(), job_type, event, payload);
+}
+
+// Emits a job event to the window that started the job. Jobs with no window (or whose
+// window has since closed) and job types the window asked to share go to every window.
+fn emit_job_event_to<R: tauri::Runtime, S: Serialize + Clone>(app: &tauri::AppHandle<R>, window: Option<&str>, job_type: &str, event: &str, payload: S) {
+    let owner = window.filter(|label| {
+        let shared = app
+            .state::<Arc<AppState>>()
+            .job_broadcast
+            .lock()
+            .map(|shared| shared.contains(&(label.to_string(), job_type.to_string())))
+            .unwrap_or(false);
+        !shared && app.get_webview_window(label).is_some()
+    });
+    let _ = match owner {
+        Some(label) => app.emit_to(label, event, payload),
+        None => app.emit(event, payload),
+    };
+}
+
+fn emit_job_progress<R: tauri::Runtime>(app: &tauri::AppHandle<R>, job_type: &str, item: Option<JobItem>, percent: f64, time: Option<String>, speed: Option<String>) {
+    emit_job_progress_to(app, job_window(), job_type, item, percent, time, speed);
+}
+
+fn emit_job_progress_to<R: tauri::Runtime>(app: &tauri::AppHandle<R>, window: Option<String>, job_type: &str, item: Option<JobItem>, percent: f64, time: Option<String>, speed: Option<String>) {
+    emit_progress_throttled_to(app, window, job_type, &job_event_name(job_type, "progress"), JobProgressEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: job_type.to_string(),
+        percent,
+        time,
+        speed,
+        item_index: item.map(|i| i.index),
+        item_count: item.map(|i| i.count),
+        overall_percent: None,
+    });
+}
+
+// Progress of one file in a batch, with the batch total worked out from its position
+fn emit_batch_progress<R: tauri::Runtime>(app: &tauri::AppHandle<R>, job_type: &str, item: JobItem, percent: f64, time: Option<String>, speed: Option<String>) {
+    let overall = (item.index as f64 * 100.0 + percent) / item.count.max(1) as f64;
+    emit_progress_throttled(app, job_type, &job_event_name(job_type, "progress"), JobProgressEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: job_type.to_string(),
+        percent,
+        time,
+        speed,
+        item_index: Some(item.index),
+        item_count: Some(item.count),
+        overall_percent: Some(overall.min(99.0).round()),
+    });
+}
+
+fn emit_job_complete(app: &tauri::AppHandle, job_type: &str, output_path: &str) {
+    emit_job_complete_with_warnings(app, job_type, output_path, Vec::new());
+}
+
+fn emit_job_complete_with_warnings(app: &tauri::AppHandle, job_type: &str, output_path: &str, warnings: Vec<String>) {
+    let event = JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: job_type.to_string(),
+        output_path: output_path.to_string(),
+        warnings,
+        frame_stats: None,
+        output_paths: Vec::new(),
+    };
+    emit_job_complete_event(app, event);
+}
+
+fn emit_job_complete_event(app: &tauri::AppHandle, event: JobCompleteEvent) {
+    clear_progress_throttle(app, &event.job_type);
+    notify_webhooks(app, "complete", &event);
+    let job_type = event.job_type.clone();
+    emit_job_event(app, &job_type, &job_event_name(&job_type, "complete"), event);
+}
+
+fn emit_job_error(app: &tauri::AppHandle, job_type: &str, message: String) {
+    emit_job_error_with_kind(app, job_type, message, None);
+}
+
+fn emit_job_error_with_kind(app: &tauri::AppHandle, job_type: &str, message: String, kind: Option<&str>) {
+    let event = JobErrorEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: job_type.to_string(),
+        message,
+        kind: kind.map(String::from),
+    };
+    clear_progress_throttle(app, job_type);
+    notify_webhooks(app, "error", &event);
+    emit_job_event(app, job_type, &job_event_name(job_type, "error"), event);
+}
+
+fn emit_job_cancelled(app: &tauri::AppHandle, job_type: &str) {
+    let event = JobCancelledEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: job_type.to_string(),
+    };
+    clear_progress_throttle(app, job_type);
+    notify_webhooks(app, "cancelled", &event);
+    emit_job_event(app, job_type, &job_event_name(job_type, "cancelled"), event);
+}
+
+// ============================================================================
+// Job Runner
+// ============================================================================
+
+// Stream layout of a file, enough to decide whether inputs can be joined without re-encoding
+#[derive(Debug, Clone, Default)]
+struct MediaSummary {
+    duration: Option<f64>,
+    video_codec: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    frame_rate: Option<String>,
+    pix_fmt: Option<String>,
+    audio_codec: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+}
+
+// Counts packets instead of decoding, which is exact for video and fast even on long files
+async fn count_video_frames(file_path: &str) -> Option<u64> {
+    let output = probe_output(ffprobe_command().args([
+        "-v", "error",
+        "-select_streams", "v:0",
+        "-count_packets",
+        "-show_entries", "stream=nb_read_packets",
+        "-of", "csv=p=0",
+        file_path,
+    ]))
+    .await
+    .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok()
+}
+
+async fn probe_media_summary(file_path: &str) -> Result<MediaSummary, String> {
+    let output = probe_output(ffprobe_command()
+        .args([
+            "-v", "error",
+            "-print_format", "json",
+            "-show_entries", "format=duration:stream=codec_type,codec_name,width,height,r_frame_rate,pix_fmt,sample_rate,channels",
+            file_path,
+        ]))
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffprobe failed: {}", stderr));
+    }
+
+    let data: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let streams = data.get("streams").and_then(|s| s.as_array()).cloned().unwrap_or_default();
+    let first_of = |kind: &str| streams.iter().find(|s| s.get("codec_type").and_then(|v| v.as_str()) == Some(kind)).cloned();
+    let video = first_of("video");
+    let audio = first_of("audio");
+    let str_field = |stream: &Option<serde_json::Value>, key: &str| {
+        stream.as_ref().and_then(|s| s.get(key)).and_then(|v| v.as_str()).map(String::from)
+    };
+
+    Ok(MediaSummary {
+        duration: data
+            .get("format")
+            .and_then(|f| f.get("duration"))
+            .and_then(|d| d.as_str())
+            .and_then(|d| d.parse::<f64>().ok()),
+        video_codec: str_field(&video, "codec_name"),
+        width: video.as_ref().and_then(|s| s.get("width")).and_then(|v| v.as_u64()).map(|v| v as u32),
+        height: video.as_ref().and_then(|s| s.get("height")).and_then(|v| v.as_u64()).map(|v| v as u32),
+        frame_rate: str_field(&video, "r_frame_rate"),
+        pix_fmt: str_field(&video, "pix_fmt"),
+        audio_codec: str_field(&audio, "codec_name"),
+        sample_rate: str_field(&audio, "sample_rate"),
+        channels: audio.as_ref().and_then(|s| s.get("channels")).and_then(|v| v.as_u64()).map(|v| v as u32),
+    })
+}
+
+// Arguments and output of a job, assembled before anything is spawned
+struct JobPlan {
+    args: Vec<String>,
+    output_path: String,
+    warnings: Vec<String>,
+    duration_secs: Option<f64>,
+    // Further files written by the same run, e.g. the right channel of a mono split
+    extra_outputs: Vec<String>,
+}
+
+struct FfmpegRun {
+    success: bool,
+    cancelled: bool,
+    code: Option<i32>,
+    stderr_tail: String,
+}
+
+// Runs ffmpeg as the current job: registers the pid and output path so cancel_encode
+// can stop it, streams encode-progress events, and removes partial output on cancel.
+// When duration_secs is None the duration is taken from ffmpeg's own "Duration:" line.
+async fn run_ffmpeg_job<R: tauri::Runtime>(app: &tauri::AppHandle<R>, job_type: &'static str, item: Option<JobItem>, args: &[String], output_path: &str, duration_secs: Option<f64>) -> Result<FfmpegRun, String> {
+    run_ffmpeg_job_with_frames(app, job_type, item, args, output_path, duration_secs, None).await
+}
+
+// Same as run_ffmpeg_job, but when total_frames is known progress follows ffmpeg's
+// frame counter instead of its timestamp, with each event naming the frame as its item
+async fn run_ffmpeg_job_with_frames<R: tauri::Runtime>(app: &tauri::AppHandle<R>, job_type: &'static str, item: Option<JobItem>, args: &[String], output_path: &str, duration_secs: Option<f64>, total_frames: Option<usize>) -> Result<FfmpegRun, String> {
+    let state = app.state::<Arc<AppState>>();
+    info!("Running FFmpeg job with args: {:?}", args);
+
+    let mut child = ffmpeg_command(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+    {
+        let mut pid = state.current_pid.lock().await;
+        *pid = child.id();
+        register_child(*pid, Some(output_path));
+    }
+    {
+        let mut current_output = state.current_output_path.lock().await;
+        *current_output = Some(output_path.to_string());
+    }
+
+    let reader_task = child.stderr.take().map(|stderr| {
+        let app_handle = app.clone();
+        spawn_job_task(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            let mut duration_in_seconds = duration_secs.filter(|d| *d > 0.0);
+            let mut tail: Vec<String> = Vec::new();
+
+            let duration_re = regex::Regex::new(r"Duration:\s*(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
+            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
+            let speed_re = regex::Regex::new(r"speed=\s*(\d+\.?\d*)x").ok();
+            let frame_re = regex::Regex::new(r"frame=\s*(\d+)").ok();
+
+            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
+                if n == 0 { break; }
+                let line = String::from_utf8_lossy(&buf).to_string();
+                buf.clear();
+
+                for part in line.split(['\r', '\n']).filter(|p| !p.trim().is_empty()) {
+                    if !part.contains("time=") {
+                        tail.push(part.trim().to_string());
+                        if tail.len() > 20 {
+                            tail.remove(0);
+                        }
+                    }
+                }
+
+                if duration_in_seconds.is_none() {
+                    if let Some(cap) = duration_re.as_ref().and_then(|re| re.captures(&line)) {
+                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        duration_in_seconds = Some(h * 3600.0 + m * 60.0 + s);
+                    }
+                }
+
+                if let Some(total) = total_frames.filter(|t| *t > 0) {
+                    if let Some(frame) = frame_re.as_ref()
+                        .and_then(|re| re.captures(&line))
+                        .and_then(|cap| cap.get(1))
+                        .and_then(|m| m.as_str().parse::<usize>().ok())
+                    {
+                        let frame = frame.min(total);
+                        let percent = (frame as f64 / total as f64 * 100.0).min(99.0).round();
+                        emit_job_progress(&app_handle, job_type, Some(JobItem { index: frame, count: total }), percent, None, None);
+                    }
+                } else if let Some(cap) = time_re.as_ref().and_then(|re| re.captures(&line)) {
+                    let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                    let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                    let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                    let current_time = h * 3600.0 + m * 60.0 + s;
+
+                    let percent = match duration_in_seconds {
+                        Some(dur) if dur > 0.0 => ((current_time / dur * 100.0).min(99.0)).round() as u32,
+                        _ => 0
+                    };
+
+                    let speed = speed_re.as_ref()
+                        .and_then(|re| re.captures(&line))
+                        .and_then(|cap| cap.get(1))
+                        .map(|m| format!("{}x", m.as_str()))
+                        .unwrap_or_else(|| "N/A".to_string());
+
+                    let time = Some(format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32));
+                    match item {
+                        Some(item) => emit_batch_progress(&app_handle, job_type, item, percent as f64, time, Some(speed)),
+                        None => emit_job_progress(&app_handle, job_type, None, percent as f64, time, Some(speed)),
+                    }
+                }
+            }
+
+            tail.join("\n")
+        })
+    });
+
+    let status = child.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
+    let stderr_tail = match reader_task {
+        Some(task) => task.await.unwrap_or_default(),
+        None => String::new(),
+    };
+
+    {
+        let mut pid = state.current_pid.lock().await;
+        unregister_child(pid.take());
+    }
+    {
+        let mut current_output = state.current_output_path.lock().await;
+        *current_output = None;
+    }
+
+    let cancelled = {
+        let mut cancel = state.is_cancelling.lock().await;
+        let was_cancelling = *cancel;
+        *cancel = false;
+        was_cancelling
+    };
+
+    if cancelled {
+        let path = std::path::Path::new(output_path);
+        if path.is_file() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    Ok(FfmpegRun {
+        success: status.success() && !cancelled,
+        cancelled,
+        code: status.code(),
+        stderr_tail,
+    })
+}
+
+// Emits the usual encode-complete / encode-error / encode-cancelled event for a finished job
+fn finish_ffmpeg_job(app: &tauri::AppHandle, job_type: &str, run: &FfmpegRun, output_path: &str) {
+    if run.cancelled {
+        emit_job_cancelled(app, job_type);
+    } else if run.success {
+        emit_job_complete(app, job_type, output_path);
+    } else {
+        error!("FFmpeg job failed: {}", run.stderr_tail);
+        emit_job_error(app, job_type, msg_with("error.process_exited", &[("code", &format!("{:?}", run.code))]));
+    }
+}
+
+// Escapes a path for an ffmpeg concat demuxer list file
+fn concat_list_entry(path: &str) -> String {
+    format!("file '{}'\n", path.replace('\\', "/").replace('\'', "'\\''"))
+}
+
+// ============================================================================
+// Encode Constraints
+// ============================================================================
+
+const MIN_OUTPUT_DIMENSION: u32 = 16;
+
+// (largest width/height, highest CRF) the encoder accepts
+fn codec_limits(codec: &str) -> (u32, u32) {
+    match codec {
+        "vp9" => (16384, 63),
+        "h264_nvenc" | "h264_amf" | "h264_qsv" => (4096, 51),
+        "hevc_nvenc" | "hevc_amf" | "hevc_qsv" => (8192, 51),
+        _ => (16384, 51),
+    }
+}
+
+// Accepts "30", "29.97" and "30000/1001"
+fn parse_frame_rate(fps: &str) -> Option<f64> {
+    let value = match fps.split_once('/') {
+        Some((num, den)) => {
+            let den = den.trim().parse::<f64>().ok()?;
+            if den <= 0.0 {
+                return None;
+            }
+            num.trim().parse::<f64>().ok()? / den
+        }
+        None => fps.trim().parse::<f64>().ok()?,
+    };
+    if value.is_finite() && value > 0.0 { Some(value) } else { None }
+}
+
+// 4:2:0 output needs even dimensions, round down so we never upscale
+fn even_dimension(value: u32) -> u32 {
+    (value / 2 * 2).max(2)
+}
+
+// Checks the encode settings against what the selected encoder can take and returns
+// the scale filter to apply, so bad values fail here with a readable message instead
+// of an ffmpeg error halfway through the job.
+fn apply_encode_constraints(options: &EncodeOptions, source: Option<&MediaSummary>) -> Result<Option<String>, String> {
+    let codec = match options.codec.as_deref() {
+        Some("copy") | None => return Ok(None),
+        Some(codec) => codec,
+    };
+    let (max_dimension, max_crf) = codec_limits(codec);
+
+    if options.rate_mode.as_deref() == Some("bitrate") {
+        if let Some(bitrate) = &options.bitrate {
+            match bitrate.trim().parse::<f64>() {
+                Ok(b) if b > 0.0 => {}
+                _ => return Err(format!("Video bitrate must be a positive number of kbps (got '{}')", bitrate)),
+            }
+        }
+    } else if let Some(crf) = options.crf {
+        if crf > max_crf {
+            return Err(format!("CRF {} is out of range for {}, use 0-{}", crf, codec, max_crf));
+        }
+    }
+
+    if let Some(fps) = options.fps.as_deref().filter(|f| *f != "source") {
+        match parse_frame_rate(fps) {
+            Some(rate) if (1.0..=240.0).contains(&rate) => {}
+            Some(rate) => return Err(format!("Frame rate {:.3} is out of range, use 1-240 fps", rate)),
+            None => return Err(format!("Invalid frame rate '{}'", fps)),
+        }
+    }
+
+    let source_size = source.and_then(|s| Some((s.width?, s.height?))).filter(|(w, h)| *w > 0 && *h > 0);
+    let resolution = options.resolution.as_deref().filter(|r| !r.is_empty() && *r != "source");
+
+    let target = match resolution {
+        Some(res) => {
+            let preset_height = match res {
+                "4320p" => Some(4320),
+                "2160p" => Some(2160),
+                "1080p" => Some(1080),
+                "720p" => Some(720),
+                "480p" => Some(480),
+                "360p" => Some(360),
+                _ => None,
+            };
+            match preset_height {
+                // Width follows the source aspect ratio; -2 keeps it even
+                Some(h) => {
+                    let width = source_size.map(|(sw, sh)| even_dimension((sw as f64 * h as f64 / sh as f64).round() as u32));
+                    Some((width, h, format!("scale=-2:{}", h)))
+                }
+                None => {
+                    let (w, h) = res
+                        .split_once(['x', 'X', ':'])
+                        .and_then(|(w, h)| Some((w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?)))
+                        .ok_or_else(|| format!("Invalid resolution '{}', expected e.g. 1280x720", res))?;
+                    let (w, h) = (even_dimension(w), even_dimension(h));
+                    Some((Some(w), h, format!("scale={}:{}", w, h)))
+                }
+            }
+        }
+        None => source_size.map(|(w, h)| {
+            let filter = if w % 2 != 0 || h % 2 != 0 { "scale=trunc(iw/2)*2:trunc(ih/2)*2".to_string() } else { String::new() };
+            (Some(even_dimension(w)), even_dimension(h), filter)
+        }),
+    };
+
+    let Some((width, height, filter)) = target else {
+        return Ok(None);
+    };
+
+    if height < MIN_OUTPUT_DIMENSION || width.map(|w| w < MIN_OUTPUT_DIMENSION).unwrap_or(false) {
+        return Err(format!("Output resolution is too small, the minimum is {}x{}", MIN_OUTPUT_DIMENSION, MIN_OUTPUT_DIMENSION));
+    }
+    if height > max_dimension || width.map(|w| w > max_dimension).unwrap_or(false) {
+        return Err(format!(
+            "{} supports at most {} pixels per side, choose a lower resolution or a different encoder",
+            codec, max_dimension
+        ));
+    }
+
+    Ok(if filter.is_empty() { None } else { Some(filter) })
+}
+
+// Speed/quality trade-off shared by every encoder: 1 is fastest, 7 is best quality.
+// Accepts the generic "speed" / "balanced" / "quality" names as well as x264-style and NVENC (p1-p7) presets.
+fn preset_level(preset: &str) -> u8 {
+    match preset {
+        "ultrafast" | "superfast" | "p1" => 1,
+        "speed" | "veryfast" | "p2" => 2,
+        "faster" | "p3" => 3,
+        "fast" | "p4" => 4,
+        "slow" | "quality" | "p6" => 6,
+        "slower" | "veryslow" | "p7" => 7,
+        _ => 5,
+    }
+}
+
+// Translates a preset into the flags the given encoder understands
+fn encoder_preset_args(encoder: &str, preset: &str) -> Vec<String> {
+    let level = preset_level(preset);
+    let pair = |flag: &str, value: &str| vec![flag.to_string(), value.to_string()];
+
+    if encoder.ends_with("_nvenc") {
+        pair("-preset", &format!("p{}", level))
+    } else if encoder.ends_with("_qsv") {
+        let name = match level {
+            1 | 2 => "veryfast",
+            3 => "faster",
+            4 => "fast",
+            6 => "slow",
+            7 => "slower",
+            _ => "medium",
+        };
+        pair("-preset", name)
+    } else if encoder.ends_with("_amf") {
+        let name = match level {
+            1..=4 => "speed",
+            5 => "balanced",
+            _ => "quality",
+        };
+        pair("-quality", name)
+    } else if encoder == "libvpx-vp9" {
+        // libvpx has no -preset; -cpu-used trades speed for quality instead
+        let mut args = pair("-deadline", "good");
+        args.extend(pair("-cpu-used", &(8 - level).min(5).to_string()));
+        args
+    } else {
+        let name = match preset {
+            "speed" => "veryfast",
+            "balanced" => "medium",
+            "quality" => "slow",
+            "ultrafast" | "superfast" | "veryfast" | "faster" | "fast" | "medium" | "slow" | "slower" | "veryslow" | "placebo" => preset,
+            _ => match level {
+                1 => "ultrafast",
+                2 => "veryfast",
+                3 => "faster",
+                4 => "fast",
+                6 => "slow",
+                7 => "slower",
+                _ => "medium",
+            },
+        };
+        pair("-preset", name)
+    }
+}
+
+// Hardware encoders ignore -crf, so translate the software-style rate control.
+// Returns None for software encoders.
+fn hardware_encoder_args(encoder: &str, options: &EncodeOptions) -> Option<Vec<String>> {
+    let family = if encoder.ends_with("_nvenc") {
+        "nvenc"
+    } else if encoder.ends_with("_qsv") {
+        "qsv"
+    } else if encoder.ends_with("_amf") {
+        "amf"
+    } else {
+        return None;
+    };
+
+    let mut args = encoder_preset_args(encoder, options.preset.as_deref().unwrap_or("balanced"));
+
+    if options.rate_mode.as_deref() == Some("bitrate") {
+        if let Some(bitrate) = &options.bitrate {
+            if family == "nvenc" {
+                args.extend(["-rc".to_string(), "vbr".to_string()]);
+            } else if family == "amf" {
+                args.extend(["-rc".to_string(), "vbr_peak".to_string()]);
+            }
+            args.extend(["-b:v".to_string(), format!("{}k", bitrate)]);
+        }
+    } else if let Some(crf) = options.crf {
+        let q = crf.to_string();
+        match family {
+            // Constant quality VBR; -b:v 0 lifts the default bitrate cap
+            "nvenc" => args.extend(["-rc".to_string(), "vbr".to_string(), "-cq".to_string(), q, "-b:v".to_string(), "0".to_string()]),
+            "qsv" => args.extend(["-global_quality".to_string(), q]),
+            _ => {
+                args.extend(["-rc".to_string(), "cqp".to_string(), "-qp_i".to_string(), q.clone(), "-qp_p".to_string(), q.clone()]);
+                if encoder == "h264_amf" {
+                    args.extend(["-qp_b".to_string(), q]);
+                }
+            }
+        }
+    }
+
+    Some(args)
+}
+
+// Reasonable kbps per channel for each lossy encoder, plus a hard cap on the total
+fn audio_bitrate_limits(encoder: &str) -> Option<(u32, u32, u32)> {
+    match encoder {
+        "aac" => Some((24, 160, 960)),
+        "libopus" => Some((12, 128, 510)),
+        "libmp3lame" => Some((32, 160, 320)),
+        "libvorbis" => Some((24, 160, 480)),
+        "ac3" => Some((32, 128, 640)),
+        _ => None,
+    }
+}
+
+// Clamps a requested audio bitrate ("192k" or "192") to what makes sense for the encoder
+// and channel count. Returns the bitrate to pass (None to omit it) and a note when it changed.
+fn constrain_audio_bitrate(encoder: &str, channels: Option<u32>, bitrate: &str) -> (Option<String>, Option<String>) {
+    if matches!(encoder, "flac" | "pcm_s16le" | "pcm_s24le" | "alac") {
+        return (None, Some(format!("Ignored audio bitrate {} for lossless {}", bitrate, encoder)));
+    }
+    let Some((min_per_channel, max_per_channel, max_total)) = audio_bitrate_limits(encoder) else {
+        return (Some(bitrate.to_string()), None);
+    };
+    let Some(requested) = bitrate.trim().trim_end_matches(['k', 'K']).parse::<u32>().ok() else {
+        return (Some(bitrate.to_string()), None);
+    };
+
+    // MP3 is at most stereo
+    let channels = channels.unwrap_or(2).max(1);
+    let channels = if encoder == "libmp3lame" { channels.min(2) } else { channels };
+    let min = (min_per_channel * channels).min(max_total);
+    let max = (max_per_channel * channels).min(max_total);
+    let clamped = requested.clamp(min, max);
+
+    if clamped == requested {
+        (Some(format!("{}k", requested)), None)
+    } else {
+        (
+            Some(format!("{}k", clamped)),
+            Some(format!("Audio bitrate adjusted from {}k to {}k for {} channel(s) with {}", requested, clamped, channels, encoder)),
+        )
+    }
+}
+
+// Typed x264/x265 options (tune, profile, level, GOP, b-frames and -x26x-params),
+// validated so a bad value is reported before ffmpeg starts
+fn advanced_codec_args(encoder: &str, options: &EncodeOptions) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let is_x264 = encoder == "libx264";
+    let is_x265 = encoder == "libx265";
+
+    if let Some(tune) = options.tune.as_deref().filter(|t| !t.is_empty() && *t != "none") {
+        let tunes: &[&str] = if is_x264 {
+            &["film", "animation", "grain", "stillimage", "fastdecode", "zerolatency", "psnr", "ssim"]
+        } else if is_x265 {
+            &["grain", "animation", "fastdecode", "zerolatency", "psnr", "ssim"]
+        } else {
+            return Err(format!("Tune is only available for x264 and x265, not {}", encoder));
+        };
+        if !tunes.contains(&tune) {
+            return Err(format!("Unknown tune '{}' for {}, expected one of: {}", tune, encoder, tunes.join(", ")));
+        }
+        args.extend(["-tune".to_string(), tune.to_string()]);
+    }
+
+    if let Some(profile) = options.profile.as_deref().filter(|p| !p.is_empty() && *p != "auto") {
+        let profiles: &[&str] = if encoder.starts_with("hevc") || is_x265 {
+            &["main", "main10", "main12", "main422-10", "main444-8", "main444-10", "mainstillpicture"]
+        } else if encoder.starts_with("h264") || is_x264 {
+            &["baseline", "main", "high", "high10", "high422", "high444"]
+        } else {
+            return Err(format!("Profiles are not supported for {}", encoder));
+        };
+        if !profiles.contains(&profile) {
+            return Err(format!("Unknown profile '{}' for {}, expected one of: {}", profile, encoder, profiles.join(", ")));
+        }
+        args.extend(["-profile:v".to_string(), profile.to_string()]);
+    }
+
+    if let Some(level) = options.level.as_deref().filter(|l| !l.is_empty() && *l != "auto") {
+        match level.parse::<f64>() {
+            Ok(l) if (1.0..=6.2).contains(&l) => args.extend(["-level".to_string(), level.to_string()]),
+            _ => return Err(format!("Invalid level '{}', expected a value like 4.1 between 1 and 6.2", level)),
+        }
+    }
+
+    if let Some(gop) = options.gop_size {
+        if !(1..=1000).contains(&gop) {
+            return Err(format!("GOP size {} is out of range, use 1-1000 frames", gop));
+        }
+        args.extend(["-g".to_string(), gop.to_string()]);
+    }
+
+    if let Some(b_frames) = options.b_frames {
+        if b_frames > 16 {
+            return Err(format!("B-frames {} is out of range, use 0-16", b_frames));
+        }
+        args.extend(["-bf".to_string(), b_frames.to_string()]);
+    }
+
+    if let Some(params) = options.codec_params.as_ref().filter(|p| !p.is_empty()) {
+        if !is_x264 && !is_x265 {
+            return Err(format!("Encoder parameters are only available for x264 and x265, not {}", encoder));
+        }
+        let mut pairs = Vec::new();
+        let mut keys: Vec<&String> = params.keys().collect();
+        keys.sort();
+        for key in keys {
+            let value = params[key].trim();
+            let valid_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            let valid_value = !value.is_empty() && !value.contains([':', '=']) && !value.chars().any(char::is_whitespace);
+            if !valid_key || !valid_value {
+                return Err(format!("Invalid encoder parameter '{}={}'", key, value));
+            }
+            pairs.push(format!("{}={}", key, value));
+        }
+        args.push(if is_x264 { "-x264-params" } else { "-x265-params" }.to_string());
+        args.push(pairs.join(":"));
+    }
+
+    Ok(args)
+}
+
+// Filters, muxer flags and extra x264 params for interlaced or telecined output
+#[derive(Default)]
+struct ScanPlan {
+    filter: Option<String>,
+    args: Vec<String>,
+    x264_params: Vec<String>,
+}
+
+// Broadcast deliveries often want interlaced (tff/bff) or 3:2 telecined video.
+// Only x264 encodes field pictures reliably here, so everything else is rejected up front.
+fn scan_type_plan(encoder: &str, options: &EncodeOptions, source: Option<&MediaSummary>) -> Result<ScanPlan, String> {
+    let field_order = options.field_order.as_deref().filter(|f| !f.is_empty() && *f != "progressive");
+    let telecine = options.telecine.as_deref().filter(|t| !t.is_empty() && *t != "none");
+    if field_order.is_none() && telecine.is_none() {
+        return Ok(ScanPlan::default());
+    }
+
+    if encoder != "libx264" {
+        return Err(format!("Interlaced and telecined output need the H.264 (x264) encoder, {} only encodes progressive video", encoder));
+    }
+    if options.format == "webm" {
+        return Err("WebM cannot store interlaced video, choose MP4, MKV or MOV".to_string());
+    }
+    let top_first = match field_order.unwrap_or("tff") {
+        "tff" => true,
+        "bff" => false,
+        other => return Err(format!("Unknown field order '{}', expected progressive, tff or bff", other)),
+    };
+    let source_fps = source.and_then(|s| s.frame_rate.as_deref()).and_then(parse_frame_rate);
+
+    let mut plan = ScanPlan::default();
+    match telecine {
+        Some(mode @ ("soft" | "hard")) => {
+            if options.fps.as_deref().is_some_and(|f| f != "source") {
+                return Err("Telecine sets the output frame rate itself, leave the frame rate on source".to_string());
+            }
+            if let Some(fps) = source_fps.filter(|fps| !(23.9..=24.05).contains(fps)) {
+                return Err(format!("Telecine expects 23.976 or 24 fps film content, this source is {:.3} fps", fps));
+            }
+            if mode == "soft" {
+                if field_order.is_some() {
+                    return Err("Soft telecine keeps progressive frames and only flags pulldown, it cannot be combined with interlaced output".to_string());
+                }
+                plan.x264_params.push("pulldown=32".to_string());
+            } else {
+                plan.filter = Some(format!("telecine=first_field={}:pattern=23", if top_first { "t" } else { "b" }));
+                plan.x264_params.push(if top_first { "tff=1" } else { "bff=1" }.to_string());
+            }
+        }
+        Some(other) => return Err(format!("Unknown telecine mode '{}', expected none, soft or hard", other)),
+        None => {
+            // 50p/59.94p weaves pairs of frames into fields; 25p/29.97p is only flagged as interlaced
+            let mode = if top_first { "interleave_top" } else { "interleave_bottom" };
+            plan.filter = Some(match source_fps {
+                Some(fps) if fps > 31.0 => format!("tinterlace=mode={}", mode),
+                _ => format!("setfield={}", field_order.unwrap_or("tff")),
+            });
+            plan.x264_params.push(if top_first { "tff=1" } else { "bff=1" }.to_string());
+        }
+    }
+    if telecine != Some("soft") {
+        plan.args.extend(["-field_order".to_string(), if top_first { "tt" } else { "bb" }.to_string()]);
+    }
+    Ok(plan)
+}
+
+// Field matching rebuilds the original film frames from 3:2 pulldown, yadif only touches
+// the odd frame that still combs, and decimate drops the one duplicate in every five
+const INVERSE_TELECINE_FILTER: &str = "fieldmatch=order=auto:combmatch=full,yadif=deint=interlaced,decimate";
+
+// Samples a thousand frames with idet. Hard-telecined 29.97 content combs on two frames in
+// every five, true interlaced video on nearly all of them.
+#[tauri::command]
+async fn detect_telecine(app: tauri::AppHandle, input: String) -> Result<TelecineReport, String> {
+    info!("detect_telecine called for: {}", input);
+
+    scoped_path(&app, &input)?;
+    validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.video_codec.is_none() {
+        return Err("This file has no video stream".to_string());
+    }
+    let frame_rate = summary.frame_rate.as_deref().and_then(parse_frame_rate);
+
+    let mut args: Vec<String> = vec!["-hide_banner".to_string()];
+    // Skip opening titles and logos, which are often progressive even on telecined discs
+    if let Some(duration) = summary.duration.filter(|d| *d > 120.0) {
+        args.extend(["-ss".to_string(), format!("{:.3}", duration * 0.25)]);
+    }
+    args.extend([
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        "0:v:0".to_string(),
+        "-vf".to_string(),
+        "idet".to_string(),
+        "-frames:v".to_string(),
+        "1000".to_string(),
+        "-an".to_string(),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ]);
+
+    let output = child_output(&mut ffmpeg_command(&args), None)
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!("Field analysis failed: {}", stderr.lines().last().unwrap_or("")));
+    }
+
+    let re = regex::Regex::new(r"Multi frame detection:\s*TFF:\s*(\d+)\s*BFF:\s*(\d+)\s*Progressive:\s*(\d+)\s*Undetermined:\s*(\d+)")
+        .map_err(|e| e.to_string())?;
+    let cap = re
+        .captures_iter(&stderr)
+        .last()
+        .ok_or_else(|| "FFmpeg did not report any field statistics".to_string())?;
+    let count = |i: usize| cap.get(i).and_then(|m| m.as_str().parse::<u64>().ok()).unwrap_or(0);
+    let interlaced = count(1) + count(2);
+    let progressive = count(3);
+    let undetermined = count(4);
+
+    let decided = (interlaced + progressive).max(1) as f64;
+    let combed = interlaced as f64 / decided;
+    let ntsc_rate = frame_rate.is_some_and(|fps| (29.9..=30.05).contains(&fps));
+    let verdict = if ntsc_rate && (0.15..=0.7).contains(&combed) {
+        "telecined"
+    } else if combed > 0.5 {
+        "interlaced"
+    } else {
+        "progressive"
+    };
+
+    Ok(TelecineReport {
+        verdict: verdict.to_string(),
+        frame_rate,
+        progressive_frames: progressive,
+        interlaced_frames: interlaced,
+        undetermined_frames: undetermined,
+    })
+}
+
+// ============================================================================
+// Loudness Delivery
+// ============================================================================
+
+// (id, name, integrated LUFS, true peak dBTP, loudness range LU)
+const LOUDNESS_PRESETS: &[(&str, &str, f64, f64, f64)] = &[
+    ("ebu_r128", "Broadcast (EBU R128)", -23.0, -1.0, 15.0),
+    ("atsc_a85", "Broadcast US (ATSC A/85)", -24.0, -2.0, 15.0),
+    ("podcast", "Podcast", -16.0, -1.0, 11.0),
+    ("streaming", "Streaming (YouTube, Spotify)", -14.0, -1.0, 11.0),
+    ("apple", "Apple Music / Podcasts", -16.0, -1.0, 11.0),
+];
+
+#[tauri::command]
+fn list_loudness_presets() -> Vec<LoudnessPreset> {
+    LOUDNESS_PRESETS
+        .iter()
+        .map(|(id, name, i, tp, lra)| LoudnessPreset {
+            id: id.to_string(),
+            name: name.to_string(),
+            integrated_lufs: *i,
+            true_peak_db: *tp,
+            loudness_range: *lra,
+        })
+        .collect()
+}
+
+// Single-pass loudnorm for the preset. The job measures first and swaps this for a
+// linear two-pass correction (see measure_planned_loudness), previews show it as is.
+fn loudnorm_filter(preset: &str) -> Result<String, String> {
+    let (_, _, i, tp, lra) = LOUDNESS_PRESETS
+        .iter()
+        .find(|p| p.0 == preset)
+        .ok_or_else(|| format!("Unknown loudness preset: {}", preset))?;
+    Ok(format!("loudnorm=I={}:TP={}:LRA={}", i, tp, lra))
+}
+
+// Runs the measurement pass for a planned loudnorm filter and rewrites it with the
+// measured values. Returns false if the user cancelled during measurement.
+async fn measure_planned_loudness(app: &tauri::AppHandle, job_type: &'static str, input: &str, args: &mut [String]) -> Result<bool, String> {
+    let Some(pos) = args.iter().position(|a| a.split(',').any(|f| f.starts_with("loudnorm="))) else {
+        return Ok(true);
+    };
+    // Filters before loudnorm in the chain (channel changes) are part of the measurement,
+    // the ones after it (fades) are kept as planned
+    let chain: Vec<String> = args[pos].split(',').map(String::from).collect();
+    let at = chain.iter().position(|f| f.starts_with("loudnorm=")).unwrap_or(0);
+    let before: String = chain[..at].iter().map(|f| format!("{},", f)).collect();
+    let planned = chain[at].clone();
+    let rest: String = chain[at + 1..].iter().map(|f| format!(",{}", f)).collect();
+    // Measure the same time range and audio stream the job will use
+    let arg_value = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned();
+    let seek = arg_value("-ss");
+    let length = arg_value("-t");
+    let stream = arg_value("-map").filter(|m| m.starts_with("0:a")).unwrap_or_else(|| "0:a:0".to_string());
+    let duration = match length.as_deref().and_then(|t| t.parse::<f64>().ok()) {
+        Some(length) => Some(length),
+        None => probe_media_summary(input).await.ok().and_then(|s| s.duration),
+    };
+
+    let mut measure_args: Vec<String> = vec!["-hide_banner".to_string()];
+    if let Some(seek) = seek {
+        measure_args.extend(["-ss".to_string(), seek]);
+    }
+    if let Some(length) = length {
+        measure_args.extend(["-t".to_string(), length]);
+    }
+    measure_args.extend([
+        "-i".to_string(),
+        input.to_string(),
+        "-map".to_string(),
+        stream,
+        "-af".to_string(),
+        format!("{}{}:print_format=json", before, planned),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ]);
+    let run = run_ffmpeg_job(app, job_type, None, &measure_args, "", duration).await?;
+    if run.cancelled {
+        finish_ffmpeg_job(app, job_type, &run, input);
+        return Ok(false);
+    }
+    if !run.success {
+        return Err(format!("Loudness measurement failed: {}", run.stderr_tail));
+    }
+
+    // loudnorm prints its measurement as a JSON object at the end of stderr
+    let json = match (run.stderr_tail.find('{'), run.stderr_tail.rfind('}')) {
+        (Some(start), Some(end)) if end > start => &run.stderr_tail[start..=end],
+        _ => return Err("Loudness measurement produced no result".to_string()),
+    };
+    let data: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse loudness measurement: {}", e))?;
+    let field = |key: &str| {
+        data.get(key)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| "The input has no measurable audio (silent or too short)".to_string())
+    };
+    args[pos] = format!(
+        "{}{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true{}",
+        before,
+        planned,
+        field("input_i")?,
+        field("input_tp")?,
+        field("input_lra")?,
+        field("input_thresh")?,
+        field("target_offset")?,
+        rest
+    );
+    Ok(true)
+}
+
+// Normalizes the loudness of one or more files to a LUFS/true-peak target with the
+// two-pass loudnorm workflow: each file is measured first and then corrected linearly
+// with the measured values, so the dynamics are left alone. Video is stream-copied.
+// Files that can't be measured (silent, no audio) are skipped with a warning.
+#[tauri::command]
+async fn normalize_audio(app: tauri::AppHandle, window: tauri::WebviewWindow, inputs: Vec<String>, target_lufs: f64, true_peak: f64, options: Option<NormalizeAudioOptions>) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("normalize_audio called for {} files ({} LUFS, {} dBTP)", inputs.len(), target_lufs, true_peak);
+
+    let options = options.unwrap_or_default();
+    if inputs.is_empty() {
+        return Err("Choose at least one file to normalize".to_string());
+    }
+    check_scope(&app, inputs.iter().chain(&options.output_folder))?;
+    // The ranges loudnorm accepts
+    if !(-70.0..=-5.0).contains(&target_lufs) {
+        return Err("The target loudness must be between -70 and -5 LUFS".to_string());
+    }
+    if !(-9.0..=0.0).contains(&true_peak) {
+        return Err("The true peak must be between -9 and 0 dBTP".to_string());
+    }
+    let lra = options.loudness_range.unwrap_or(11.0);
+    if !(1.0..=50.0).contains(&lra) {
+        return Err("The loudness range must be between 1 and 50 LU".to_string());
+    }
+    let filter = format!("loudnorm=I={}:TP={}:LRA={}", target_lufs, true_peak, lra);
+
+    let count = inputs.len();
+    let mut outputs = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let Some(input_path) = validate_path(input) else {
+            warnings.push(format!("{}: {}", input, msg("error.invalid_path")));
+            continue;
+        };
+        let summary = match probe_media_summary(input).await {
+            Ok(summary) if summary.audio_codec.is_some() => summary,
+            _ => {
+                warnings.push(format!("{}: no audio stream", input));
+                continue;
+            }
+        };
+
+        let ext = input_path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_else(|| "mp4".to_string());
+        let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let output_path_str = job_output_dir(&input_path, options.output_folder.as_ref(), "normalize")
+            .join(format!("{}_normalized.{}", stem, ext))
+            .to_string_lossy()
+            .to_string();
+
+        let (codec, lossless) = match ext.as_str() {
+            "mp3" => ("libmp3lame", false),
+            "flac" => ("flac", true),
+            "wav" => ("pcm_s16le", true),
+            "ogg" => ("libvorbis", false),
+            "opus" | "webm" => ("libopus", false),
+            _ => ("aac", false),
+        };
+        let mut args = vec!["-y".to_string(), "-i".to_string(), input.clone()];
+        if summary.video_codec.is_some() {
+            args.extend(["-map".to_string(), "0:v?".to_string(), "-map".to_string(), "0:a:0".to_string(), "-map".to_string(), "0:s?".to_string()]);
+            args.extend(["-c:v".to_string(), "copy".to_string(), "-c:s".to_string(), "copy".to_string()]);
+        } else {
+            args.extend(["-map".to_string(), "0:a:0".to_string()]);
+        }
+        args.extend(["-af".to_string(), filter.clone(), "-c:a".to_string(), codec.to_string()]);
+        if !lossless {
+            args.extend(["-b:a".to_string(), options.audio_bitrate.clone().unwrap_or_else(|| "192k".to_string())]);
+        }
+        // loudnorm resamples to 192 kHz internally; Opus only takes 48 kHz
+        let rate = match summary.sample_rate {
+            Some(rate) if codec != "libopus" => rate,
+            _ => "48000".to_string(),
+        };
+        args.extend(["-ar".to_string(), rate, output_path_str.clone()]);
+
+        match measure_planned_loudness(&app, "normalize", input, &mut args).await {
+            Ok(true) => {}
+            Ok(false) => return Err("Normalization cancelled".to_string()),
+            Err(e) => {
+                warnings.push(format!("{}: {}", input, e));
+                continue;
+            }
+        }
+
+        let item = JobItem { index, count };
+        let run = run_ffmpeg_job(&app, "normalize", Some(item), &args, &output_path_str, summary.duration).await?;
+        if run.cancelled {
+            finish_ffmpeg_job(&app, "normalize", &run, &output_path_str);
+            return Err("Normalization cancelled".to_string());
+        }
+        if !run.success {
+            error!("Normalizing {} failed: {}", input, run.stderr_tail);
+            warnings.push(format!("{}: {}", input, run.stderr_tail.lines().last().unwrap_or("ffmpeg failed")));
+            continue;
+        }
+        outputs.push(output_path_str);
+    }
+
+    if outputs.is_empty() {
+        let message = format!("No file could be normalized: {}", warnings.join("; "));
+        emit_job_error(&app, "normalize", message.clone());
+        return Err(message);
+    }
+    emit_job_complete_event(&app, JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: "normalize".to_string(),
+        output_path: outputs[0].clone(),
+        warnings,
+        frame_stats: None,
+        output_paths: outputs.clone(),
+    });
+    Ok(outputs)
+}
+
+// atempo only takes factors between 0.5 and 2.0 per instance, larger changes are
+// chained (3x becomes 2.0 then 1.5). Returns an empty list for a factor of 1.
+fn atempo_chain(factor: f64) -> Vec<String> {
+    let mut filters = Vec::new();
+    let mut rest = factor;
+    while rest > 2.0 {
+        filters.push("atempo=2.0".to_string());
+        rest /= 2.0;
+    }
+    while rest < 0.5 {
+        filters.push("atempo=0.5".to_string());
+        rest /= 0.5;
+    }
+    if (rest - 1.0).abs() > 1e-6 {
+        filters.push(format!("atempo={:.6}", rest));
+    }
+    filters
+}
+
+// Audio filters for a speed change. "preserve" keeps the pitch (atempo), "natural"
+// plays the audio faster like a tape so the pitch follows the speed. An extra pitch
+// shift goes through asetrate, with atempo undoing its effect on the duration.
+fn speed_audio_filters(speed: f64, pitch_mode: &str, semitones: f64, sample_rate: u32) -> Result<Vec<String>, String> {
+    if !(0.25..=4.0).contains(&speed) {
+        return Err("The speed must be between 0.25x and 4x".to_string());
+    }
+    if !(-12.0..=12.0).contains(&semitones) {
+        return Err("The pitch shift must be between -12 and 12 semitones".to_string());
+    }
+    match pitch_mode {
+        "preserve" => {
+            if semitones == 0.0 {
+                return Ok(atempo_chain(speed));
+            }
+            let pitch = 2f64.powf(semitones / 12.0);
+            let mut filters = vec![
+                format!("asetrate={}", (sample_rate as f64 * pitch).round() as u32),
+                format!("aresample={}", sample_rate),
+            ];
+            filters.extend(atempo_chain(speed / pitch));
+            Ok(filters)
+        }
+        "natural" => {
+            if semitones != 0.0 {
+                return Err("A pitch shift can only be combined with the pitch-preserving mode".to_string());
+            }
+            Ok(vec![
+                format!("asetrate={}", (sample_rate as f64 * speed).round() as u32),
+                format!("aresample={}", sample_rate),
+            ])
+        }
+        other => Err(format!("Unknown pitch mode: {}", other)),
+    }
+}
+
+// Bakes a speed change into an audio file (1.5x podcast versions and the like).
+// The pitch is kept by default; video streams are dropped.
+#[tauri::command]
+async fn change_audio_speed(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, speed: f64, options: Option<AudioSpeedOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("change_audio_speed called for {} ({}x)", input, speed);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.audio_codec.is_none() {
+        return Err("This file has no audio stream".to_string());
+    }
+    let sample_rate = summary.sample_rate.as_deref().and_then(|r| r.parse::<u32>().ok()).unwrap_or(48000);
+    let pitch_mode = options.pitch_mode.as_deref().unwrap_or("preserve");
+    let filters = speed_audio_filters(speed, pitch_mode, options.pitch_semitones.unwrap_or(0.0), sample_rate)?;
+    if filters.is_empty() {
+        return Err("Choose a speed other than 1x".to_string());
+    }
+
+    let source_ext = input_path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let ext = options.format.clone().filter(|f| !f.is_empty()).unwrap_or_else(|| match source_ext.as_str() {
+        "mp3" | "m4a" | "flac" | "wav" | "ogg" | "opus" => source_ext.clone(),
+        _ => "mp3".to_string(),
+    });
+    let (codec, lossless) = match ext.as_str() {
+        "mp3" => ("libmp3lame", false),
+        "m4a" | "aac" => ("aac", false),
+        "flac" => ("flac", true),
+        "wav" => ("pcm_s16le", true),
+        "ogg" => ("libvorbis", false),
+        "opus" => ("libopus", false),
+        other => return Err(format!("Unsupported audio format: {}", other)),
+    };
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_path_str = job_output_dir(&input_path, options.output_folder.as_ref(), "speed")
+        .join(format!("{}_{}x.{}", stem, speed, ext))
+        .to_string_lossy()
+        .to_string();
+
+    let mut args = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        "0:a:0".to_string(),
+        "-vn".to_string(),
+        "-af".to_string(),
+        filters.join(","),
+        "-c:a".to_string(),
+        codec.to_string(),
+    ];
+    if !lossless {
+        args.extend(["-b:a".to_string(), options.bitrate.clone().unwrap_or_else(|| "192k".to_string())]);
+    }
+    // Opus only takes 48 kHz
+    if codec == "libopus" {
+        args.extend(["-ar".to_string(), "48000".to_string()]);
+    }
+    args.push(output_path_str.clone());
+
+    let duration = summary.duration.map(|d| d / speed);
+    let run = run_ffmpeg_job(&app, "speed", None, &args, &output_path_str, duration).await?;
+    finish_ffmpeg_job(&app, "speed", &run, &output_path_str);
+    if run.cancelled {
+        return Err("Speed change cancelled".to_string());
+    }
+    if !run.success {
+        return Err(format!("Speed change failed: {}", run.stderr_tail));
+    }
+    Ok(output_path_str)
+}
+
+// Converts audio files between MP3, FLAC, WAV, Opus and AAC. Tags are carried over
+// (Ogg/Opus keep theirs on the stream rather than the file) and so is embedded cover
+// art where the target can hold it. Files without audio are skipped with a warning.
+#[tauri::command]
+async fn convert_audio(app: tauri::AppHandle, window: tauri::WebviewWindow, inputs: Vec<String>, options: ConvertAudioOptions) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("convert_audio called for {} files to {}", inputs.len(), options.format);
+
+    if inputs.is_empty() {
+        return Err("Choose at least one file to convert".to_string());
+    }
+    check_scope(&app, inputs.iter().chain(&options.output_folder))?;
+    let (codec, ext, lossless) = match options.format.as_str() {
+        "mp3" => ("libmp3lame", "mp3", false),
+        "flac" => ("flac", "flac", true),
+        "wav" => ("pcm_s16le", "wav", true),
+        "opus" => ("libopus", "opus", false),
+        "aac" | "m4a" => ("aac", "m4a", false),
+        other => return Err(format!("Unsupported audio format: {}", other)),
+    };
+    if let Some(rate) = options.sample_rate.as_deref().filter(|r| !AUDIO_SAMPLE_RATES.contains(r)) {
+        return Err(format!("Unsupported sample rate: {}", rate));
+    }
+    // Rubber Band sounds much better on large shifts; without it asetrate changes the
+    // pitch and atempo puts the tempo back
+    let semitones = options.pitch_semitones.filter(|s| *s != 0.0);
+    if let Some(semitones) = semitones {
+        if !semitones.is_finite() || !(-12.0..=12.0).contains(&semitones) {
+            return Err("The pitch shift must be between -12 and 12 semitones".to_string());
+        }
+    }
+    let rubberband = semitones.is_some() && ffmpeg_has_filter("rubberband").await;
+
+    let count = inputs.len();
+    let mut outputs = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let Some(input_path) = validate_path(input) else {
+            warnings.push(format!("{}: {}", input, msg("error.invalid_path")));
+            continue;
+        };
+        let summary = match probe_media_summary(input).await {
+            Ok(summary) if summary.audio_codec.is_some() => summary,
+            _ => {
+                warnings.push(format!("{}: no audio stream", input));
+                continue;
+            }
+        };
+
+        let mut stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        if let Some(semitones) = semitones {
+            stem.push_str(&format!("_pitch{:+}", semitones));
+        }
+        let output_dir = job_output_dir(&input_path, options.output_folder.as_ref(), "convert_audio");
+        let mut output_path = output_dir.join(format!("{}.{}", stem, ext));
+        if output_path == input_path {
+            output_path = output_dir.join(format!("{}_converted.{}", stem, ext));
+        }
+        let output_path_str = output_path.to_string_lossy().to_string();
+
+        let source_ext = input_path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let tag_source = if matches!(source_ext.as_str(), "ogg" | "opus" | "oga") { "0:s:a:0" } else { "0" };
+        let mut args = vec![
+            "-y".to_string(),
+            "-i".to_string(),
+            input.clone(),
+            "-map".to_string(),
+            "0:a:0".to_string(),
+            "-map_metadata".to_string(),
+            tag_source.to_string(),
+        ];
+        // Cover art is an attached picture stream; a real video track is left behind
+        let has_cover = summary.video_codec.as_deref().is_some_and(|c| matches!(c, "mjpeg" | "png")) && matches!(ext, "mp3" | "m4a" | "flac");
+        if has_cover {
+            args.extend([
+                "-map".to_string(),
+                "0:v:0".to_string(),
+                "-c:v".to_string(),
+                "copy".to_string(),
+                "-disposition:v:0".to_string(),
+                "attached_pic".to_string(),
+            ]);
+        }
+        if let Some(semitones) = semitones {
+            let filters = if rubberband {
+                vec![format!("rubberband=pitch={:.6}", 2f64.powf(semitones / 12.0))]
+            } else {
+                let rate = summary.sample_rate.as_deref().and_then(|r| r.parse::<u32>().ok()).unwrap_or(44100);
+                speed_audio_filters(1.0, "preserve", semitones, rate)?
+            };
+            args.extend(["-af".to_string(), filters.join(",")]);
+        }
+        args.extend(["-c:a".to_string(), codec.to_string()]);
+        if !lossless {
+            args.extend(["-b:a".to_string(), options.bitrate.clone().unwrap_or_else(|| "192k".to_string())]);
+        }
+        match (options.sample_rate.as_deref(), codec) {
+            // Opus only takes 48 kHz
+            (_, "libopus") => args.extend(["-ar".to_string(), "48000".to_string()]),
+            (Some(rate), _) => args.extend(["-ar".to_string(), rate.to_string()]),
+            _ => {}
+        }
+        if ext == "mp3" {
+            args.extend(["-id3v2_version".to_string(), "3".to_string()]);
+        }
+        args.push(output_path_str.clone());
+
+        let item = JobItem { index, count };
+        let run = run_ffmpeg_job(&app, "convert_audio", Some(item), &args, &output_path_str, summary.duration).await?;
+        if run.cancelled {
+            finish_ffmpeg_job(&app, "convert_audio", &run, &output_path_str);
+            return Err("Conversion cancelled".to_string());
+        }
+        if !run.success {
+            error!("Converting {} failed: {}", input, run.stderr_tail);
+            warnings.push(format!("{}: {}", input, run.stderr_tail.lines().last().unwrap_or("ffmpeg failed")));
+            continue;
+        }
+        outputs.push(output_path_str);
+    }
+
+    if outputs.is_empty() {
+        let message = format!("No file could be converted: {}", warnings.join("; "));
+        emit_job_error(&app, "convert_audio", message.clone());
+        return Err(message);
+    }
+    emit_job_complete_event(&app, JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: "convert_audio".to_string(),
+        output_path: outputs[0].clone(),
+        warnings,
+        frame_stats: None,
+        output_paths: outputs.clone(),
+    });
+    Ok(outputs)
+}
+
+// ReplayGain 2.0 reference level
+const REPLAYGAIN_REFERENCE_LUFS: f64 = -18.0;
+
+// Integrated loudness (LUFS) and true peak (dBTP) from the ebur128 summary
+fn parse_ebur128_summary(stderr: &str) -> Option<(f64, f64)> {
+    let integrated = regex::Regex::new(r"I:\s*(-?\d+(?:\.\d+)?)\s*LUFS").ok()?;
+    let peak = regex::Regex::new(r"Peak:\s*(-?\d+(?:\.\d+)?|-inf)\s*dBFS").ok()?;
+    let i = integrated.captures_iter(stderr).last()?.get(1)?.as_str().parse::<f64>().ok()?;
+    let tp = match peak.captures_iter(stderr).last()?.get(1)?.as_str() {
+        "-inf" => f64::NEG_INFINITY,
+        value => value.parse::<f64>().ok()?,
+    };
+    // -70 LUFS is the absolute gate, anything at it is silence
+    (i > -70.0).then_some((i, tp))
+}
+
+// The tags for one file. Ogg Opus players ignore REPLAYGAIN_* and read R128_* instead:
+// Q7.8 fixed-point gains relative to -23 LUFS (RFC 7845).
+fn replaygain_metadata(ext: &str, track: &ReplayGainTrack, album: Option<(f64, f64)>) -> Vec<String> {
+    let mut tags = Vec::new();
+    if ext == "opus" {
+        let q78 = |lufs: f64| ((-23.0 - lufs) * 256.0).round().clamp(-32768.0, 32767.0) as i32;
+        tags.push(format!("R128_TRACK_GAIN={}", q78(track.integrated_lufs)));
+        if let Some((gain, _)) = album {
+            let album_lufs = REPLAYGAIN_REFERENCE_LUFS - gain;
+            tags.push(format!("R128_ALBUM_GAIN={}", q78(album_lufs)));
+        }
+    } else {
+        tags.push(format!("REPLAYGAIN_TRACK_GAIN={:.2} dB", track.track_gain_db));
+        tags.push(format!("REPLAYGAIN_TRACK_PEAK={:.6}", track.track_peak));
+        if let Some((gain, peak)) = album {
+            tags.push(format!("REPLAYGAIN_ALBUM_GAIN={:.2} dB", gain));
+            tags.push(format!("REPLAYGAIN_ALBUM_PEAK={:.6}", peak));
+        }
+    }
+    tags
+}
+
+// Scans audio files with the ebur128 filter and writes ReplayGain track (and album) gain
+// and peak tags into MP3, FLAC and Opus files. Tagging is a stream copy into a temporary
+// file next to the original, which then replaces it, so the audio itself is untouched.
+#[tauri::command]
+async fn scan_replaygain(app: tauri::AppHandle, window: tauri::WebviewWindow, inputs: Vec<String>, options: Option<ReplayGainOptions>) -> Result<ReplayGainReport, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("scan_replaygain called for {} files", inputs.len());
+
+    let options = options.unwrap_or_default();
+    if inputs.is_empty() {
+        return Err("Choose at least one audio file".to_string());
+    }
+    check_scope(&app, &inputs)?;
+    let write_tags = options.write_tags.unwrap_or(true);
+    let album_mode = options.album.unwrap_or(inputs.len() > 1);
+
+    let count = inputs.len();
+    let mut tracks = Vec::new();
+    let mut durations = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let failed = |error: String| ReplayGainTrack {
+            path: input.clone(),
+            integrated_lufs: 0.0,
+            track_gain_db: 0.0,
+            track_peak: 0.0,
+            tagged: false,
+            error: Some(error),
+        };
+        if validate_path(input).is_none() {
+            tracks.push(failed(msg("error.invalid_path")));
+            continue;
+        }
+        let duration = probe_media_summary(input).await.ok().and_then(|s| s.duration);
+        let args: Vec<String> = vec![
+            "-hide_banner".to_string(),
+            "-i".to_string(),
+            input.clone(),
+            "-map".to_string(),
+            "0:a:0".to_string(),
+            "-af".to_string(),
+            "ebur128=peak=true".to_string(),
+            "-f".to_string(),
+            "null".to_string(),
+            "-".to_string(),
+        ];
+        let run = run_ffmpeg_job(&app, "replaygain", Some(JobItem { index, count }), &args, "", duration).await?;
+        if run.cancelled {
+            finish_ffmpeg_job(&app, "replaygain", &run, input);
+            return Err("ReplayGain scan cancelled".to_string());
+        }
+        match parse_ebur128_summary(&run.stderr_tail).filter(|_| run.success) {
+            Some((integrated, peak_db)) => {
+                tracks.push(ReplayGainTrack {
+                    path: input.clone(),
+                    integrated_lufs: integrated,
+                    track_gain_db: REPLAYGAIN_REFERENCE_LUFS - integrated,
+                    track_peak: 10f64.powf(peak_db / 20.0),
+                    tagged: false,
+                    error: None,
+                });
+                durations.push(duration.unwrap_or(1.0));
+            }
+            None if run.success => tracks.push(failed("No measurable audio (silent or too short)".to_string())),
+            None => tracks.push(failed(format!("Scan failed: {}", run.stderr_tail.lines().last().unwrap_or("ffmpeg failed")))),
+        }
+    }
+
+    // Album loudness as the duration-weighted energy mean of the tracks, close to what
+    // measuring the concatenated album gives without decoding everything a second time
+    let measured: Vec<&ReplayGainTrack> = tracks.iter().filter(|t| t.error.is_none()).collect();
+    let album = if album_mode && !measured.is_empty() {
+        let total: f64 = durations.iter().sum();
+        let energy: f64 = measured.iter().zip(&durations).map(|(t, d)| d * 10f64.powf(t.integrated_lufs / 10.0)).sum::<f64>() / total;
+        let album_lufs = 10.0 * energy.log10();
+        let album_peak = measured.iter().map(|t| t.track_peak).fold(0.0, f64::max);
+        Some((REPLAYGAIN_REFERENCE_LUFS - album_lufs, album_peak))
+    } else {
+        None
+    };
+
+    if write_tags {
+        for track in tracks.iter_mut().filter(|t| t.error.is_none()) {
+            let path = PathBuf::from(&track.path);
+            let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+            if !matches!(ext.as_str(), "mp3" | "flac" | "opus") {
+                track.error = Some(format!("ReplayGain tags are only written to MP3, FLAC and Opus files, not .{}", ext));
+                continue;
+            }
+            let temp_path = path.with_extension(format!("rgtmp.{}", ext));
+            let temp_path_str = temp_path.to_string_lossy().to_string();
+            let mut args = vec!["-y".to_string(), "-i".to_string(), track.path.clone(), "-map".to_string(), "0".to_string(), "-map_metadata".to_string(), "0".to_string(), "-c".to_string(), "copy".to_string()];
+            for tag in replaygain_metadata(&ext, track, album) {
+                args.extend(["-metadata".to_string(), tag]);
+            }
+            if ext == "mp3" {
+                // ID3v2.3 is what most players and taggers read reliably
+                args.extend(["-id3v2_version".to_string(), "3".to_string()]);
+            }
+            args.push(temp_path_str.clone());
+
+            let run = run_ffmpeg_job(&app, "replaygain", None, &args, &temp_path_str, None).await?;
+            if run.cancelled {
+                let _ = std::fs::remove_file(&temp_path);
+                finish_ffmpeg_job(&app, "replaygain", &run, &temp_path_str);
+                return Err("ReplayGain tagging cancelled".to_string());
+            }
+            if !run.success {
+                let _ = std::fs::remove_file(&temp_path);
+                track.error = Some(format!("Failed to write tags: {}", run.stderr_tail.lines().last().unwrap_or("ffmpeg failed")));
+                continue;
+            }
+            match std::fs::rename(&temp_path, &path) {
+                Ok(()) => track.tagged = true,
+                Err(e) => {
+                    let _ = std::fs::remove_file(&temp_path);
+                    track.error = Some(format!("Failed to replace the original file: {}", e));
+                }
+            }
+        }
+    }
+
+    if tracks.iter().all(|t| t.error.is_some()) {
+        let message = "None of the files could be scanned".to_string();
+        emit_job_error(&app, "replaygain", message.clone());
+        return Err(message);
+    }
+    let warnings = tracks.iter().filter_map(|t| t.error.as_ref().map(|e| format!("{}: {}", t.path, e))).collect();
+    emit_job_complete_event(&app, JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: "replaygain".to_string(),
+        output_path: inputs[0].clone(),
+        warnings,
+        frame_stats: None,
+        output_paths: tracks.iter().filter(|t| t.tagged).map(|t| t.path.clone()).collect(),
+    });
+    Ok(ReplayGainReport {
+        album_gain_db: album.map(|a| a.0),
+        album_peak: album.map(|a| a.1),
+        tracks,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioStats {
+    pub integrated_lufs: Option<f64>,
+    pub true_peak_dbtp: Option<f64>,
+    // EBU R128 loudness range in LU
+    pub loudness_range: Option<f64>,
+    pub peak_level_db: Option<f64>,
+    pub rms_level_db: Option<f64>,
+    // Highest peak minus the quietest RMS window, in dB
+    pub dynamic_range_db: Option<f64>,
+    // Mean sample value as a fraction of full scale; far from 0 means a DC bias
+    pub dc_offset: Option<f64>,
+    // Samples at full scale (0 if the peak never reaches it)
+    pub clipped_samples: u64,
+    pub duration: Option<f64>,
+    pub sample_rate: Option<String>,
+    pub channels: Option<u32>,
+}
+
+// Loudness and level statistics of the first audio stream in one decode: ebur128 for
+// the loudness figures, astats for levels, DC offset and clipping. astats reports
+// through a metadata log because its summary is longer than the kept stderr tail.
+#[tauri::command]
+async fn get_audio_stats(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String) -> Result<AudioStats, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("get_audio_stats called for: {}", input);
+
+    scoped_path(&app, &input)?;
+    validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.audio_codec.is_none() {
+        return Err("No audio stream found in file".to_string());
+    }
+
+    let stats_log = std::env::temp_dir().join(format!("video_toolbox_astats_{}.txt", uuid_like_seed(std::slice::from_ref(&input))));
+    let args: Vec<String> = vec![
+        "-hide_banner".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        "0:a:0".to_string(),
+        // Large frames keep the log short; reset=0 makes every entry cumulative
+        "-af".to_string(),
+        format!(
+            "asetnsamples=n=65536,astats=metadata=1:reset=0:measure_perchannel=none,ametadata=print:file={},ebur128=peak=true",
+            filter_path(&stats_log)
+        ),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ];
+    let run = run_ffmpeg_job(&app, "audio_stats", None, &args, "", summary.duration).await?;
+    let log = std::fs::read_to_string(&stats_log).unwrap_or_default();
+    let _ = std::fs::remove_file(&stats_log);
+    if !run.success {
+        finish_ffmpeg_job(&app, "audio_stats", &run, &input);
+        if run.cancelled {
+            return Err("Audio analysis cancelled".to_string());
+        }
+        return Err(format!("Audio analysis failed: {}", run.stderr_tail));
+    }
+
+    // The last entry of each key covers the whole stream
+    let stat = |key: &str| {
+        let prefix = format!("lavfi.astats.Overall.{}=", key);
+        log.lines()
+            .rev()
+            .find_map(|line| line.trim().strip_prefix(prefix.as_str()).map(String::from))
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| v.is_finite())
+    };
+    let peak_level_db = stat("Peak_level");
+    let rms_trough_db = stat("RMS_trough");
+    let clipped_samples = match (peak_level_db, stat("Peak_count")) {
+        (Some(peak), Some(count)) if peak >= -0.01 => count as u64,
+        _ => 0,
+    };
+
+    let loudness = parse_ebur128_summary(&run.stderr_tail);
+    let loudness_range = regex::Regex::new(r"LRA:\s*(-?\d+(?:\.\d+)?)\s*LU")
+        .ok()
+        .and_then(|re| re.captures_iter(&run.stderr_tail).last().and_then(|c| c.get(1)).and_then(|m| m.as_str().parse::<f64>().ok()));
+
+    let stats = AudioStats {
+        integrated_lufs: loudness.map(|l| l.0),
+        true_peak_dbtp: loudness.map(|l| l.1).filter(|p| p.is_finite()),
+        loudness_range,
+        peak_level_db,
+        rms_level_db: stat("RMS_level"),
+        dynamic_range_db: peak_level_db.zip(rms_trough_db).map(|(peak, trough)| peak - trough),
+        dc_offset: stat("DC_offset"),
+        clipped_samples,
+        duration: summary.duration,
+        sample_rate: summary.sample_rate,
+        channels: summary.channels,
+    };
+    emit_job_complete(&app, "audio_stats", &input);
+    Ok(stats)
+}
+
+// How a colour standard is named by the colorspace filter, the output flags and
+// the H.264/HEVC VUI (matrix, primaries, transfer codes from ITU-T H.273)
+struct ColorStandard {
+    id: &'static str,
+    filter: &'static str,
+    matrix: &'static str,
+    primaries: &'static str,
+    transfer: &'static str,
+    codes: (u8, u8, u8),
+}
+
+const COLOR_STANDARDS: &[ColorStandard] = &[
+    ColorStandard { id: "bt709", filter: "bt709", matrix: "bt709", primaries: "bt709", transfer: "bt709", codes: (1, 1, 1) },
+    ColorStandard { id: "bt601", filter: "smpte170m", matrix: "smpte170m", primaries: "smpte170m", transfer: "smpte170m", codes: (6, 6, 6) },
+    ColorStandard { id: "bt601_pal", filter: "bt601-6-625", matrix: "bt470bg", primaries: "bt470bg", transfer: "smpte170m", codes: (5, 5, 6) },
+    ColorStandard { id: "bt2020", filter: "bt2020", matrix: "bt2020nc", primaries: "bt2020", transfer: "bt2020-10", codes: (9, 9, 14) },
+];
+
+fn color_standard(id: &str) -> Result<&'static ColorStandard, String> {
+    COLOR_STANDARDS.iter().find(|c| c.id == id).ok_or_else(|| {
+        let ids: Vec<&str> = COLOR_STANDARDS.iter().map(|c| c.id).collect();
+        format!("Unknown colour standard '{}', expected one of: {}", id, ids.join(", "))
+    })
+}
+
+// Washed-out or oversaturated output is usually a BT.601/BT.709 mismatch. "convert" remaps
+// the pixels with the colorspace filter, "tag" only rewrites the metadata (which also works
+// on stream copy through the h264/hevc metadata bitstream filters).
+// Returns the video filter and the output arguments.
+fn color_args(options: &EncodeOptions, encoder: &str, source_codec: Option<&str>) -> Result<(Option<String>, Vec<String>), String> {
+    let Some(target) = options.color_space.as_deref().filter(|c| !c.is_empty() && *c != "source") else {
+        return Ok((None, Vec::new()));
+    };
+    let target = color_standard(target)?;
+    let range = match options.color_range.as_deref() {
+        Some("tv") | Some("limited") => Some("tv"),
+        Some("pc") | Some("full") => Some("pc"),
+        None | Some("") | Some("source") => None,
+        Some(other) => return Err(format!("Unknown colour range '{}', expected tv or pc", other)),
+    };
+    let mode = options.color_mode.as_deref().unwrap_or("convert");
+
+    if encoder == "copy" {
+        if mode != "tag" {
+            return Err("Colour conversion re-encodes the video, use tag-only mode with stream copy".to_string());
+        }
+        let bsf = match source_codec {
+            Some("h264") => "h264_metadata",
+            Some("hevc") => "hevc_metadata",
+            _ => return Err("Tag-only colour fixes on stream copy are supported for H.264 and HEVC sources".to_string()),
+        };
+        let (matrix, primaries, transfer) = target.codes;
+        let mut value = format!("{}=colour_primaries={}:transfer_characteristics={}:matrix_coefficients={}", bsf, primaries, transfer, matrix);
+        if let Some(range) = range {
+            value.push_str(&format!(":video_full_range_flag={}", if range == "pc" { 1 } else { 0 }));
+        }
+        return Ok((None, vec!["-bsf:v".to_string(), value]));
+    }
+
+    let filter = match mode {
+        "tag" => None,
+        "convert" => {
+            let mut filter = format!("colorspace=all={}", target.filter);
+            // Untagged or mistagged sources need their real standard spelled out
+            if let Some(source) = options.source_color_space.as_deref().filter(|s| !s.is_empty() && *s != "auto") {
+                filter.push_str(&format!(":iall={}", color_standard(source)?.filter));
+            }
+            if let Some(range) = range {
+                filter.push_str(&format!(":range={}", range));
+            }
+            Some(filter)
+        }
+        other => return Err(format!("Unknown colour mode '{}', expected convert or tag", other)),
+    };
+
+    let mut args = vec![
+        "-colorspace".to_string(),
+        target.matrix.to_string(),
+        "-color_primaries".to_string(),
+        target.primaries.to_string(),
+        "-color_trc".to_string(),
+        target.transfer.to_string(),
+    ];
+    if let Some(range) = range {
+        args.extend(["-color_range".to_string(), range.to_string()]);
+    }
+    Ok((filter, args))
+}
+
+// ============================================================================
+// Encoding Commands
+// ============================================================================
+
+// Everything start_encode would run, without running it
+async fn build_encode_args(app: &tauri::AppHandle, mut options: EncodeOptions) -> Result<JobPlan, String> {
+    if options.format.trim().is_empty() {
+        options.format = default_output_format(app, "encode").unwrap_or_else(|| "mp4".to_string());
+    }
+    check_output_format("encode", &options.format)?;
+    
+    // Image-based subtitles cannot go into MP4/MOV; the policy decides what happens to them
+    let subtitle_codecs = stream_codecs(&options.input, "s").await.unwrap_or_default();
+    let image_subtitles = subtitle_codecs.iter().filter(|c| is_image_subtitle(c)).count();
+    let mut kept_subtitles: Vec<usize> = (0..subtitle_codecs.len()).collect();
+    let mut warnings = Vec::new();
+    if image_subtitles > 0 && matches!(options.format.as_str(), "mp4" | "mov" | "m4v") {
+        match options.subtitle_policy.as_deref().unwrap_or("drop") {
+            "mkv" => {
+                options.format = "mkv".to_string();
+                warnings.push(format!("Output switched to MKV to keep {} image-based subtitle track(s)", image_subtitles));
+            }
+            "fail" => {
+                return Err(format!(
+                    "This file has {} image-based subtitle track(s) (PGS/VobSub) that {} cannot store. Choose MKV output or drop the subtitles.",
+                    image_subtitles,
+                    options.format.to_uppercase()
+                ));
+            }
+            _ => {
+                kept_subtitles.retain(|&i| !is_image_subtitle(&subtitle_codecs[i]));
+                warnings.push(format!("Dropped {} image-based subtitle track(s) that {} cannot store", image_subtitles, options.format.to_uppercase()));
+            }
+        }
+    }
+    
+    // Speed changes retime every stream, so both video and audio are re-encoded and
+    // subtitles (which would keep their old timing) are dropped
+    let speed = options.speed.filter(|s| (s - 1.0).abs() > 1e-6);
+    if let Some(speed) = speed {
+        if !(0.25..=4.0).contains(&speed) {
+            return Err("The speed must be between 0.25x and 4x".to_string());
+        }
+        if options.codec.as_deref().is_none_or(|c| c == "copy") {
+            return Err("A speed change re-encodes the video, choose a video codec instead of copy".to_string());
+        }
+        if options.audio_codec.as_deref() == Some("copy") {
+            return Err("A speed change re-encodes the audio, choose an audio codec instead of copy".to_string());
+        }
+        if !kept_subtitles.is_empty() {
+            warnings.push(format!("Dropped {} subtitle track(s) that would be out of sync after the speed change", kept_subtitles.len()));
+            kept_subtitles.clear();
+        }
+    }
+
+    // Build output path
+    let input_path = PathBuf::from(&options.input);
+    let stem = input_output_stem(&options.input);
+    let output_ext = options.format.clone();
+    let suffix = options.output_suffix.clone().unwrap_or_else(|| "_encoded".to_string());
+    let filename = format!("{}{}.{}", stem, suffix, output_ext);
+    
+    let output_path = planned_output_dir(&input_path, options.output_folder.as_ref(), "encode").join(&filename);
+    
+    let output_path_str = output_path.to_string_lossy().to_string();
+    
+    let source_summary = probe_media_summary(&options.input).await.ok();
+    let scale_filter = apply_encode_constraints(&options, source_summary.as_ref())?;
+    
+    // Build FFmpeg arguments; numbered image sequences need their frame rate and first frame up front
+    let mut args = image_sequence_input_args(&options.input, options.input_fps.as_deref());
+    let is_sequence = !args.is_empty();
+    let av_offset = options.audio_offset_ms.filter(|ms| *ms != 0 && !is_sequence);
+    if let Some(ms) = av_offset.filter(|ms| *ms < 0) {
+        args.extend(av_offset_arg(ms));
+    }
+    args.extend(["-i".to_string(), options.input.clone()]);
+    
+    // Add external audio tracks
+    if let Some(audio_tracks) = &options.audio_tracks {
+        for track in audio_tracks {
+            if let Some(path) = &track.path {
+                args.push("-i".to_string());
+                args.push(path.clone());
+            }
+        }
+    }
+    
+    // Add external subtitle tracks
+    if let Some(subtitle_tracks) = &options.subtitle_tracks {
+        for track in subtitle_tracks {
+            if let Some(path) = &track.path {
+                args.push("-i".to_string());
+                args.push(path.clone());
+            }
+        }
+    }
+    
+    // A/V offset: the audio comes from a second, separately shifted copy of the input
+    let audio_input = match av_offset {
+        Some(ms) => {
+            let index = args.iter().filter(|a| *a == "-i").count();
+            if ms > 0 {
+                args.extend(av_offset_arg(ms));
+            }
+            args.extend(["-i".to_string(), options.input.clone()]);
+            index
+        }
+        None => 0,
+    };
+    
+    args.push("-y".to_string());
+    args.push("-map".to_string());
+    args.push("0:v:0".to_string());
+    
+    // Audio mapping (image sequences have none)
+    if options.audio_codec.as_deref() == Some("none") || is_sequence {
+        args.push("-an".to_string());
+    } else {
+        args.push("-map".to_string());
+        args.push(format!("{}:a:0", audio_input));
+    }
+    
+    // Subtitle mapping
+    if kept_subtitles.len() == subtitle_codecs.len() {
+        args.push("-map".to_string());
+        args.push("0:s?".to_string());
+    } else {
+        for i in &kept_subtitles {
+            args.push("-map".to_string());
+            args.push(format!("0:s:{}", i));
+        }
+    }
+    
+    // Metadata and chapters
+    if options.preserve_metadata.unwrap_or(true) {
+        args.push("-map_metadata".to_string());
+        args.push("0".to_string());
+        args.push("-map_chapters".to_string());
+        args.push("0".to_string());
+        
+        // Stream-level tags (language, title) follow the streams we mapped above
+        args.push("-map_metadata:s:v:0".to_string());
+        args.push("0:s:v:0".to_string());
+        if options.audio_codec.as_deref() != Some("none") && !is_sequence {
+            args.push("-map_metadata:s:a:0".to_string());
+            args.push("0:s:a:0".to_string());
+        }
+        for (out_index, source_index) in kept_subtitles.iter().enumerate() {
+            args.push(format!("-map_metadata:s:s:{}", out_index));
+            args.push(format!("0:s:s:{}", source_index));
+        }
+    } else {
+        args.push("-map_metadata".to_string());
+        args.push("-1".to_string());
+        args.push("-map_chapters".to_string());
+        args.push("-1".to_string());
+    }
+    
+    // Video codec
+    if let Some(codec) = &options.codec {
+        if codec == "copy" {
+            args.push("-c:v".to_string());
+            args.push("copy".to_string());
+            let source_codec = source_summary.as_ref().and_then(|s| s.video_codec.as_deref());
+            args.extend(color_args(&options, "copy", source_codec)?.1);
+        } else {
+            let v_codec_map = HashMap::from([
+                ("h264", "libx264"),
+                ("h265", "libx265"),
+                ("vp9", "libvpx-vp9"),
+                ("h264_nvenc", "h264_nvenc"),
+                ("hevc_nvenc", "hevc_nvenc"),
+                ("h264_amf", "h264_amf"),
+                ("hevc_amf", "hevc_amf"),
+                ("h264_qsv", "h264_qsv"),
+                ("hevc_qsv", "hevc_qsv"),
+            ]);
+            
+            let v_codec = v_codec_map.get(codec.as_str()).unwrap_or(&"libx264");
+            args.push("-c:v".to_string());
+            args.push(v_codec.to_string());
+            args.extend(advanced_codec_args(v_codec, &options)?);
+            
+            // Interlacing/telecine params share -x264-params with the user's own
+            let scan = scan_type_plan(v_codec, &options, source_summary.as_ref())?;
+            if !scan.x264_params.is_empty() {
+                let extra = scan.x264_params.join(":");
+                match args.iter().position(|a| a == "-x264-params") {
+                    Some(pos) => args[pos + 1] = format!("{}:{}", args[pos + 1], extra),
+                    None => args.extend(["-x264-params".to_string(), extra]),
+                }
+            }
+            args.extend(scan.args);
+            
+            let (color_filter, color_flags) = color_args(&options, v_codec, None)?;
+            args.extend(color_flags);
+            
+            // Pulldown removal restores 23.976p before anything else sees the frames
+            let inverse_telecine = options.inverse_telecine.unwrap_or(false);
+            if inverse_telecine {
+                if options.fps.as_deref().is_some_and(|f| f != "source") || scan.filter.is_some() || !scan.x264_params.is_empty() || options.decimate.unwrap_or(false) {
+                    return Err("Inverse telecine sets the output frame rate itself and cannot be combined with a fixed frame rate, duplicate-frame removal, interlacing or telecine".to_string());
+                }
+                let source_fps = source_summary.as_ref().and_then(|s| s.frame_rate.as_deref()).and_then(parse_frame_rate);
+                if let Some(fps) = source_fps.filter(|fps| !(29.9..=30.05).contains(fps)) {
+                    return Err(format!("Inverse telecine expects 29.97 fps telecined content, this source is {:.3} fps", fps));
+                }
+            }
+
+            // Duplicate frames go first so later filters skip them, then colour, resolution and field conversion
+            let decimate = options.decimate.unwrap_or(false);
+            if decimate {
+                if options.fps.as_deref().is_some_and(|f| f != "source") || scan.filter.is_some() || !scan.x264_params.is_empty() {
+                    return Err("Duplicate-frame removal produces variable frame rate output and cannot be combined with a fixed frame rate, interlacing or telecine".to_string());
+                }
+                args.extend(["-vsync".to_string(), "vfr".to_string()]);
+            }
+            let video_filters: Vec<String> = inverse_telecine
+                .then(|| INVERSE_TELECINE_FILTER.to_string())
+                .into_iter()
+                .chain(decimate.then(|| "mpdecimate".to_string()))
+                .chain(color_filter)
+                .chain(scale_filter.iter().cloned())
+                .chain(scan.filter)
+                .chain(speed.map(|s| format!("setpts=PTS/{}", s)))
+                .collect();
+            if !video_filters.is_empty() {
+                args.push("-vf".to_string());
+                args.push(video_filters.join(","));
+            }
+            
+            if let Some(hw_args) = hardware_encoder_args(v_codec, &options) {
+                // Preset and rate control for NVENC/QSV/AMF
+                args.extend(hw_args);
+            } else {
+                // Preset
+                if let Some(preset) = &options.preset {
+                    args.extend(encoder_preset_args(v_codec, preset));
+                }
+                
+                // Rate control
+                if options.rate_mode.as_deref() == Some("bitrate") {
+                    if let Some(bitrate) = &options.bitrate {
+                        args.push("-b:v".to_string());
+                        args.push(format!("{}k", bitrate));
+                    }
+                } else {
+                    if let Some(crf) = options.crf {
+                        args.push("-crf".to_string());
+                        args.push(crf.to_string());
+                    }
+                }
+            }
+            
+            // FPS
+            if let Some(fps) = &options.fps {
+                if fps != "source" {
+                    args.push("-r".to_string());
+                    args.push(fps.clone());
+                }
+            }
+        }
+    }
+    
+    // Audio codec
+    if let Some(audio_codec) = &options.audio_codec {
+        if audio_codec != "none" {
+            if audio_codec == "copy" {
+                args.push("-c:a".to_string());
+                args.push("copy".to_string());
+            } else {
+                let a_codec_map = HashMap::from([
+                    ("aac", "aac"),
+                    ("opus", "libopus"),
+                    ("mp3", "libmp3lame"),
+                    ("ac3", "ac3"),
+                    ("flac", "flac"),
+                    ("pcm_s16le", "pcm_s16le"),
+                ]);
+                
+                let a_codec = a_codec_map.get(audio_codec.as_str()).unwrap_or(&"aac");
+                args.push("-c:a".to_string());
+                args.push(a_codec.to_string());
+                
+                if let Some(bitrate) = &options.audio_bitrate {
+                    let channels = source_summary.as_ref().and_then(|s| s.channels);
+                    let (bitrate, note) = constrain_audio_bitrate(a_codec, channels, bitrate);
+                    if let Some(bitrate) = bitrate {
+                        args.push("-b:a".to_string());
+                        args.push(bitrate);
+                    }
+                    warnings.extend(note);
+                }
+            }
+        }
+    }
+    
+    // Speed change (pitch preserved) and delivery loudness share one filter chain; the
+    // loudness is measured and applied as two-pass when the job runs
+    let has_audio = options.audio_codec.as_deref() != Some("none") && !is_sequence;
+    let mut audio_filters = Vec::new();
+    if let Some(speed) = speed.filter(|_| has_audio) {
+        let sample_rate = source_summary.as_ref().and_then(|s| s.sample_rate.as_deref()).and_then(|r| r.parse::<u32>().ok()).unwrap_or(48000);
+        audio_filters.extend(speed_audio_filters(speed, "preserve", 0.0, sample_rate)?);
+    }
+    let mut resample = false;
+    if let Some(preset) = options.loudness_preset.as_deref().filter(|p| !p.is_empty() && *p != "none") {
+        match options.audio_codec.as_deref() {
+            Some("copy") => return Err("Loudness correction re-encodes the audio, choose an audio codec instead of copy".to_string()),
+            Some("none") => {}
+            _ => {
+                audio_filters.push(loudnorm_filter(preset)?);
+                resample = true;
+            }
+        }
+    }
+    if !audio_filters.is_empty() {
+        args.extend(["-af".to_string(), audio_filters.join(",")]);
+    }
+    if resample {
+        // loudnorm resamples to 192 kHz internally, bring it back to the video standard rate
+        args.extend(["-ar".to_string(), "48000".to_string()]);
+    }
+    
+    // Subtitle codec
+    if output_ext == "mp4" || output_ext == "mov" {
+        args.push("-c:s".to_string());
+        args.push("mov_text".to_string());
+    } else {
+        args.push("-c:s".to_string());
+        args.push("copy".to_string());
+    }
+    
+    // MP4 container layout: fragmented output streams as it is written,
+    // faststart moves the moov atom to the front once encoding is done
+    if output_ext == "mp4" || output_ext == "mov" || output_ext == "m4v" {
+        if options.fragmented.unwrap_or(false) {
+            args.push("-movflags".to_string());
+            args.push("+frag_keyframe+empty_moov+default_base_moof".to_string());
+        } else if options.faststart.unwrap_or(false) {
+            args.push("-movflags".to_string());
+            args.push("+faststart".to_string());
+        }
+    }
+    
+    // Threads
+    if let Some(threads) = options.threads {
+        if threads > 0 {
+            args.push("-threads".to_string());
+            args.push(threads.to_string());
+        }
+    }
+    
+    // Custom args
+    if let Some(custom_args) = &options.custom_args {
+        args.extend(custom_args.split_whitespace().map(|s| s.to_string()));
+    }
+    
+    args.push(output_path_str.clone());
+    
+    let duration_secs = speed.and_then(|s| source_summary.as_ref().and_then(|m| m.duration).map(|d| d / s));
+    Ok(JobPlan { args, output_path: output_path_str, warnings, duration_secs, extra_outputs: Vec::new() })
+}
+
+#[tauri::command]
+async fn start_encode(app: tauri::AppHandle, window: tauri::WebviewWindow, options: EncodeOptions) -> Result<(), String> {
+    let _window = JobWindow::enter(window.label());
+    info!("start_encode called with options: {:?}", options);
+    
+    check_scope(&app, [&options.input].into_iter()
+        .chain(&options.output_folder)
+        .chain(&options.chapters_file)
+        .chain(options.audio_tracks.iter().flatten().filter_map(|t| t.path.as_ref()))
+        .chain(options.subtitle_tracks.iter().flatten().filter_map(|t| t.path.as_ref())))?;
+    let state = app.state::<Arc<AppState>>();
+    
+    let decimate = options.decimate.unwrap_or(false) && options.codec.as_deref().is_some_and(|c| c != "copy");
+    let input = options.input.clone();
+    let JobPlan { mut args, output_path: output_path_str, warnings, duration_secs, .. } = build_encode_args(&app, options).await?;
+    create_planned_output_dir(&output_path_str);
+    if !measure_planned_loudness(&app, "encode", &input, &mut args).await? {
+        return Ok(());
+    }
+    let output_path = PathBuf::from(&output_path_str);
+    
+    info!("Running FFmpeg with args: {:?}", args);
+    
+    // Spawn FFmpeg process
+    let mut child = ffmpeg_command(&args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+    
+    // Store process ID for later cancellation
+    let child_pid = child.id();
+    {
+        let mut pid = state.current_pid.lock().await;
+        *pid = child_pid;
+        register_child(child_pid, Some(&output_path_str));
+    }
+    {
+        let mut output_path = state.current_output_path.lock().await;
+        *output_path = Some(output_path_str.clone());
+    }
+    
+    // Read stderr for progress
+    if let Some(stderr) = child.stderr.take() {
+        let app_handle = app.clone();
+        
+        spawn_job_task(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            // A speed change makes the output shorter than the Duration ffmpeg reports
+            let mut duration_in_seconds: Option<f64> = duration_secs;
+            
+            // Pre-compile regex patterns for efficiency
+            let duration_re = regex::Regex::new(r"Duration:\s*(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
+            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
+            let speed_re = regex::Regex::new(r"speed=\s*(\d+\.?\d*)x").ok();
+            
+            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
+                if n == 0 { break; }
+                let line = String::from_utf8_lossy(&buf).to_string();
+                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n').to_string();
+                buf.clear();
+                
+                // Parse duration (only once)
+                if duration_in_seconds.is_none() {
+                    if let Some(ref re) = duration_re {
+                        if let Some(cap) = re.captures(&line) {
+                            let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                            let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                            let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                            duration_in_seconds = Some(h * 3600.0 + m * 60.0 + s);
+                            info!("Parsed duration: {}s", duration_in_seconds.unwrap());
+                        }
+                    }
+                }
+                
+                // Parse progress - emit even without duration (use 0% in that case)
+                if let Some(ref re) = time_re {
+                    if let Some(cap) = re.captures(&line) {
+                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let current_time = h * 3600.0 + m * 60.0 + s;
+                        
+                        // Calculate percent, defaulting to 0 if duration unknown
+                        // Round to integer for cleaner display
+                        let percent = match duration_in_seconds {
+                            Some(dur) if dur > 0.0 => ((current_time / dur * 100.0).min(99.0)).round() as u32,
+                            _ => 0
+                        };
+                        
+                        let speed = speed_re.as_ref()
+                            .and_then(|re| re.captures(&line))
+                            .and_then(|cap| cap.get(1))
+                            .map(|m| format!("{}x", m.as_str()))
+                            .unwrap_or_else(|| "N/A".to_string());
+                        
+                        emit_job_progress(&app_handle, "encode", None, percent as f64, Some(format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32)), Some(speed));
+                    }
+                }
+            }
+        });
+    }
+    
+    // Wait for completion
+    let status = child.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
+    
+    // Clear process reference
+    {
+        let mut pid = state.current_pid.lock().await;
+        unregister_child(pid.take());
+    }
+    {
+        let mut output_path = state.current_output_path.lock().await;
+        *output_path = None;
+    }
+    
+    // Check cancellation
+    let is_cancelling = {
+        let cancel = state.is_cancelling.lock().await;
+        *cancel
+    };
+    
+    if is_cancelling {
+        let mut cancel = state.is_cancelling.lock().await;
+        *cancel = false;
+        emit_job_cancelled(&app, "encode");
+        
+        // Delete incomplete output
+        if output_path.exists() {
+            let _ = std::fs::remove_file(&output_path);
+        }
+        
+        return Ok(());
+    }
+    
+    if status.success() {
+        // Report how much mpdecimate stripped by counting frames on both sides
+        let frame_stats = if decimate {
+            match (count_video_frames(&input).await, count_video_frames(&output_path_str).await) {
+                (Some(source_frames), Some(output_frames)) => Some(FrameStats {
+                    source_frames,
+                    output_frames,
+                    dropped_frames: source_frames.saturating_sub(output_frames),
+                }),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        emit_job_complete_event(&app, JobCompleteEvent {
+            schema_version: JOB_EVENT_SCHEMA_VERSION,
+            job_type: "encode".to_string(),
+            output_path: output_path_str,
+            warnings,
+            frame_stats,
+            output_paths: Vec::new(),
+        });
+    } else {
+        emit_job_error(&app, "encode", msg_with("error.process_exited", &[("code", &format!("{:?}", status.code()))]));
+    }
+    
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioStreamInfo {
+    // Position among the audio streams, what stream_index selects
+    pub index: usize,
+    // Absolute stream number in the file
+    pub stream_index: usize,
+    pub codec: Option<String>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub sample_rate: Option<String>,
+    pub is_default: bool,
+}
+
+#[tauri::command]
+async fn list_audio_streams(app: tauri::AppHandle, input: String) -> Result<Vec<AudioStreamInfo>, String> {
+    scoped_path(&app, &input)?;
+    probe_audio_streams(input).await
+}
+
+async fn probe_audio_streams(input: String) -> Result<Vec<AudioStreamInfo>, String> {
+    let validated = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let output = probe_output(ffprobe_command().args([
+        "-v",
+        "error",
+        "-select_streams",
+        "a",
+        "-show_entries",
+        "stream=index,codec_name,channels,channel_layout,sample_rate:stream_tags=language,title:stream_disposition=default",
+        "-of",
+        "json",
+        &validated.to_string_lossy(),
+    ]))
+    .await
+    .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let streams = json.get("streams").and_then(|s| s.as_array()).cloned().unwrap_or_default();
+    Ok(streams
+        .iter()
+        .enumerate()
+        .map(|(index, s)| {
+            let text = |key: &str| s.get(key).and_then(|v| v.as_str()).map(String::from);
+            let tag = |key: &str| s.get("tags").and_then(|t| t.get(key)).and_then(|v| v.as_str()).map(String::from);
+            AudioStreamInfo {
+                index,
+                stream_index: s.get("index").and_then(|v| v.as_u64()).unwrap_or(index as u64) as usize,
+                codec: text("codec_name"),
+                language: tag("language"),
+                title: tag("title"),
+                channels: s.get("channels").and_then(|v| v.as_u64()).map(|c| c as u32),
+                channel_layout: text("channel_layout"),
+                sample_rate: text("sample_rate"),
+                is_default: s.get("disposition").and_then(|d| d.get("default")).and_then(|v| v.as_i64()) == Some(1),
+            }
+        })
+        .collect())
+}
+
+// The stream stream_index or language asks for, None when neither is set
+async fn select_audio_stream(options: &ExtractAudioOptions) -> Result<Option<AudioStreamInfo>, String> {
+    if options.stream_index.is_none() && options.language.as_deref().map(|l| l.trim().is_empty()).unwrap_or(true) {
+        return Ok(None);
+    }
+    let streams = probe_audio_streams(options.input.clone()).await?;
+    if streams.is_empty() {
+        return Err("This file has no audio stream".to_string());
+    }
+    if let Some(index) = options.stream_index {
+        return streams
+            .into_iter()
+            .nth(index)
+            .map(Some)
+            .ok_or_else(|| format!("Audio stream {} does not exist, this file has fewer audio streams", index));
+    }
+
+    // Tags are usually ISO 639-2 ("jpn") but some muxers write "ja" or "Japanese"
+    let wanted = options.language.as_deref().unwrap_or("").trim().to_lowercase();
+    let wanted_code = iso639_2(Some(&wanted));
+    streams
+        .into_iter()
+        .find(|s| {
+            s.language.as_deref().map(|l| {
+                let l = l.to_lowercase();
+                l == wanted || (wanted_code != "und" && iso639_2(Some(&l)) == wanted_code)
+            }).unwrap_or(false)
+        })
+        .map(Some)
+        .ok_or_else(|| format!("No audio stream tagged '{}' in this file", wanted))
+}
+
+// pan filter for a channel_mode, given the source's channel count and layout name.
+// The stereo downmix is the ATSC A/52 Lo/Ro mix: centre and surrounds at -3 dB, LFE
+// dropped, with the gains normalised ("<") so a loud 5.1 mix can't clip.
+fn channel_mode_filter(mode: &str, channel: Option<&str>, channels: Option<u32>, layout: Option<&str>) -> Result<Option<String>, String> {
+    let layout = layout.unwrap_or("");
+    match mode {
+        "stereo" => {
+            if channels.map(|c| c <= 2).unwrap_or(false) {
+                return Ok(None);
+            }
+            let (left, right) = match layout {
+                l if l.starts_with("7.1") => ("+0.707*BL+0.707*SL", "+0.707*BR+0.707*SR"),
+                l if l.contains("side") => ("+0.707*SL", "+0.707*SR"),
+                l if l.starts_with("5.") || l.starts_with("quad") => ("+0.707*BL", "+0.707*BR"),
+                // Unknown layouts get ffmpeg's own downmix matrix
+                _ => return Ok(Some("aformat=channel_layouts=stereo".to_string())),
+            };
+            Ok(Some(format!("pan=stereo|FL<FL+0.707*FC{}|FR<FR+0.707*FC{}", left, right)))
+        }
+        "mono" => Ok(Some("aformat=channel_layouts=mono".to_string())),
+        "channel" => {
+            let channel = channel.map(|c| c.trim().to_uppercase()).filter(|c| !c.is_empty()).ok_or("Choose the channel to extract")?;
+            let valid = regex::Regex::new(r"^([A-Z]{1,4}|\d{1,2})$").map(|re| re.is_match(&channel)).unwrap_or(false);
+            if !valid {
+                return Err(format!("Invalid channel: {}", channel));
+            }
+            if let (Ok(index), Some(count)) = (channel.parse::<u32>(), channels) {
+                if index >= count {
+                    return Err(format!("Channel {} does not exist, the source has {} channels", index, count));
+                }
+            }
+            let source = if channel.chars().all(|c| c.is_ascii_digit()) { format!("c{}", channel) } else { channel };
+            Ok(Some(format!("pan=mono|c0={}", source)))
+        }
+        other => Err(format!("Unknown channel mode: {}", other)),
+    }
+}
+
+// Centre-channel filter for a vocal_mode. Lead vocals are usually mixed equally into
+// both channels, so turning the mid (L+R) signal down to stereotools' floor of -36 dB
+// leaves the instrumental, and turning the side (L-R) signal down leaves the centre.
+// Bass and kick drum also sit in the centre: they go with the vocals in the
+// instrumental, and are band-limited out of the isolated vocals.
+const AUDIO_SAMPLE_RATES: &[&str] = &["22050", "32000", "44100", "48000", "88200", "96000", "176400", "192000"];
+
+// Encoder and sample format options for a lossless bit depth. FLAC has no float
+// samples and stores 24-bit audio in s32 with the real depth marked alongside.
+fn bit_depth_args(format: &str, bit_depth: &str) -> Result<Vec<String>, String> {
+    let args: &[&str] = match (format, bit_depth) {
+        ("wav", "16") => &["-c:a", "pcm_s16le"],
+        ("wav", "24") => &["-c:a", "pcm_s24le"],
+        ("wav", "32f") => &["-c:a", "pcm_f32le"],
+        ("flac", "16") => &["-sample_fmt", "s16"],
+        ("flac", "24") => &["-sample_fmt", "s32", "-bits_per_raw_sample", "24"],
+        ("flac", "32f") => return Err("FLAC can't store floating point samples, use WAV for 32-bit float".to_string()),
+        ("wav" | "flac", other) => return Err(format!("Unsupported bit depth: {}", other)),
+        _ => return Err("Bit depth can only be set for WAV and FLAC output".to_string()),
+    };
+    Ok(args.iter().map(|a| a.to_string()).collect())
+}
+
+fn vocal_mode_filter(mode: &str) -> Result<String, String> {
+    match mode {
+        "instrumental" => Ok("stereotools=mlev=0.015625".to_string()),
+        "vocals" => Ok("stereotools=slev=0.015625,highpass=f=120,lowpass=f=8000".to_string()),
+        other => Err(format!("Unknown vocal mode: {}", other)),
+    }
+}
+
+async fn build_extract_audio_args<R: tauri::Runtime>(app: &tauri::AppHandle<R>, mut options: ExtractAudioOptions) -> Result<JobPlan, String> {
+    if options.format.trim().is_empty() {
+        options.format = default_output_format(app, "extract_audio").unwrap_or_else(|| "mp3".to_string());
+    }
+    check_output_format("extract_audio", &options.format)?;
+    
+    // Build output path
+    let input_path = PathBuf::from(&options.input);
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext_map = HashMap::from([
+        ("mp3", "mp3"),
+        ("aac", "m4a"),
+        ("flac", "flac"),
+        ("wav", "wav"),
+        ("ogg", "ogg"),
+        ("opus", "opus"),
+    ]);
+    let ext = ext_map.get(options.format.as_str()).unwrap_or(&"mp3");
+    let selected = select_audio_stream(&options).await?;
+    let range = match (options.start_seconds, options.end_seconds) {
+        (None, None) => None,
+        (start, end) => {
+            let start = start.unwrap_or(0.0);
+            if !start.is_finite() || start < 0.0 {
+                return Err("start_seconds must be 0 or more".to_string());
+            }
+            match end {
+                Some(end) if !end.is_finite() || end <= start => return Err("end_seconds must be after start_seconds".to_string()),
+                _ => Some((start, end)),
+            }
+        }
+    };
+    // Name the track when one was picked, so several languages can sit side by side
+    let mut filename = match selected.as_ref().and_then(|s| s.language.as_deref()) {
+        Some(language) => format!("{}_audio_{}", stem, sanitize_file_name(language)),
+        None => format!("{}_audio", stem),
+    };
+    // And the range, so clips from the same file don't overwrite each other
+    if let Some((start, end)) = range {
+        match end {
+            Some(end) => filename.push_str(&format!("_{}-{}s", start.floor() as u64, end.ceil() as u64)),
+            None => filename.push_str(&format!("_from_{}s", start.floor() as u64)),
+        }
+    }
+
+    let channel_mode = options.channel_mode.as_deref().filter(|m| !m.is_empty() && *m != "none");
+    let vocal_mode = options.vocal_mode.as_deref().filter(|m| !m.is_empty() && *m != "none");
+    let source_stream = match (&selected, channel_mode.or(vocal_mode)) {
+        (Some(stream), _) => Some(stream.clone()),
+        (None, Some(_)) => probe_audio_streams(options.input.clone()).await?.into_iter().next(),
+        (None, None) => None,
+    };
+    let source_channels = source_stream.as_ref().and_then(|s| s.channels);
+    let vocal_filter = match vocal_mode {
+        Some(mode) => {
+            let filter = vocal_mode_filter(mode)?;
+            if channel_mode.is_some() {
+                return Err("Vocal isolation can't be combined with a channel mode".to_string());
+            }
+            if source_channels != Some(2) {
+                return Err("Vocal isolation needs a stereo source".to_string());
+            }
+            filename.push_str(&format!("_{}", mode));
+            Some(filter)
+        }
+        None => None,
+    };
+    let split_mono = channel_mode == Some("split_mono");
+    let channel_filter = match channel_mode {
+        Some("split_mono") => {
+            if source_channels != Some(2) {
+                return Err("Splitting into mono files needs a stereo source".to_string());
+            }
+            if options.loudness_preset.as_deref().map(|p| !p.is_empty() && p != "none").unwrap_or(false) {
+                return Err("Loudness correction can't be combined with splitting into mono files".to_string());
+            }
+            None
+        }
+        Some(mode) => channel_mode_filter(mode, options.channel.as_deref(), source_channels, source_stream.as_ref().and_then(|s| s.channel_layout.as_deref()))?,
+        None => None,
+    };
+    let output_channels = match channel_mode {
+        Some("stereo") => source_channels.map(|c| c.min(2)),
+        Some(_) => Some(1),
+        None => source_channels,
+    };
+    match channel_mode {
+        Some("stereo") => filename.push_str("_stereo"),
+        Some("mono") => filename.push_str("_mono"),
+        Some("channel") => filename.push_str(&format!("_{}", sanitize_file_name(&options.channel.clone().unwrap_or_default().to_uppercase()))),
+        _ => {}
+    }
+    
+    let output_dir = planned_output_dir(&input_path, options.output_folder.as_ref(), "extract_audio");
+    let (output_path_str, right_path_str) = if split_mono {
+        (
+            output_dir.join(format!("{}_L.{}", filename, ext)).to_string_lossy().to_string(),
+            Some(output_dir.join(format!("{}_R.{}", filename, ext)).to_string_lossy().to_string()),
+        )
+    } else {
+        (output_dir.join(format!("{}.{}", filename, ext)).to_string_lossy().to_string(), None)
+    };
+    
+    let (cover_input, cover_args, cover_note) = album_art_plan(&options).await?;
+
+    // Build args, seeking on the input so a clip near the end doesn't decode everything before it
+    let mut args = vec!["-y".to_string()];
+    let mut duration_secs = None;
+    if let Some((start, end)) = range {
+        if start > 0.0 {
+            args.extend(["-ss".to_string(), start.to_string()]);
+        }
+        if let Some(end) = end {
+            args.extend(["-t".to_string(), (end - start).to_string()]);
+            duration_secs = Some(end - start);
+        } else {
+            duration_secs = probe_media_summary(&options.input).await.ok().and_then(|s| s.duration).map(|d| (d - start).max(0.1));
+        }
+    }
+    args.extend(["-i".to_string(), options.input.clone()]);
+    if let Some(cover) = cover_input {
+        args.extend(["-i".to_string(), cover]);
+    }
+    if cover_args.is_empty() {
+        args.push("-vn".to_string());
+    }
+    if let Some(stream) = selected.as_ref().filter(|_| !split_mono) {
+        args.extend(["-map".to_string(), format!("0:a:{}", stream.index)]);
+    } else if !cover_args.is_empty() && !split_mono {
+        // Mapping the cover turns off automatic stream selection
+        args.extend(["-map".to_string(), "0:a:0".to_string()]);
+    }
+    let output_args_start = args.len();
+    
+    // Audio codec
+    let codec_map = HashMap::from([
+        ("mp3", ("libmp3lame", None)),
+        ("aac", ("aac", None)),
+        ("flac", ("flac", Some("compression_level"))),
+        ("wav", ("pcm_s16le", None)),
+        ("ogg", ("libvorbis", None)),
+        ("opus", ("libopus", None)),
+    ]);
+    
+    let depth_args = match options.bit_depth.as_deref().filter(|d| !d.is_empty()) {
+        Some(depth) => bit_depth_args(&options.format, depth)?,
+        None => Vec::new(),
+    };
+    if depth_args.first().map(|a| a.as_str()) != Some("-c:a") {
+        if let Some((codec, _)) = codec_map.get(options.format.as_str()) {
+            args.push("-c:a".to_string());
+            args.push(codec.to_string());
+        }
+    }
+    args.extend(depth_args);
+    let mut warnings: Vec<String> = cover_note.into_iter().collect();
+    
+    // Sample rate
+    if let Some(sample_rate) = &options.sample_rate {
+        if AUDIO_SAMPLE_RATES.contains(&sample_rate.as_str()) {
+            args.push("-ar".to_string());
+            args.push(sample_rate.clone());
+        }
+    }
+    
+    // FLAC compression level
+    if options.format == "flac" {
+        if let Some(level) = &options.flac_level {
+            let allowed = ["0", "1", "2", "3", "4", "5", "6", "7", "8"];
+            if allowed.contains(&level.as_str()) {
+                args.push("-compression_level".to_string());
+                args.push(level.clone());
+            }
+        }
+    }
+    
+    // Opus rate control and effort, kept alongside whatever bitrate is set
+    if options.format == "opus" {
+        if let Some(mode) = options.opus_mode.as_deref().filter(|m| !m.is_empty()) {
+            let vbr = match mode {
+                "vbr" => "on",
+                "cvbr" => "constrained",
+                "cbr" => "off",
+                other => return Err(format!("Unknown Opus mode: {}", other)),
+            };
+            args.extend(["-vbr".to_string(), vbr.to_string()]);
+        }
+        if let Some(level) = options.opus_compression_level.as_deref().filter(|l| !l.is_empty()) {
+            if !level.parse::<u8>().map(|l| l <= 10).unwrap_or(false) {
+                return Err(format!("Opus compression level must be 0 to 10, got {}", level));
+            }
+            args.extend(["-compression_level".to_string(), level.to_string()]);
+        }
+    }
+
+    // MP3 and AAC quality
+    if let Some(mode) = options.aac_mode.as_deref().filter(|m| !m.is_empty() && !matches!(*m, "cbr" | "vbr")) {
+        return Err(format!("Unknown AAC mode: {}", mode));
+    }
+    if options.format == "mp3" && options.mp3_mode.as_deref() == Some("vbr") {
+        if let Some(quality) = &options.mp3_quality {
+            args.push("-q:a".to_string());
+            args.push(quality.clone());
+        }
+    } else if options.format == "aac" && options.aac_mode.as_deref() == Some("vbr") {
+        let quality = options.aac_quality.as_deref().unwrap_or("1");
+        if !quality.parse::<f64>().map(|q| (0.1..=2.0).contains(&q)).unwrap_or(false) {
+            return Err(format!("AAC quality must be between 0.1 and 2, got {}", quality));
+        }
+        args.extend(["-q:a".to_string(), quality.to_string()]);
+    } else if let Some(bitrate) = &options.bitrate {
+        let encoder = codec_map.get(options.format.as_str()).map(|(codec, _)| *codec).unwrap_or("libmp3lame");
+        let channels = match output_channels {
+            Some(channels) => Some(channels),
+            None => probe_media_summary(&options.input).await.ok().and_then(|s| s.channels),
+        };
+        let (bitrate, note) = constrain_audio_bitrate(encoder, channels, bitrate);
+        if let Some(bitrate) = bitrate {
+            args.push("-b:a".to_string());
+            args.push(bitrate);
+        }
+        warnings.extend(note);
+    }
+    
+    // Fades are timed on the output, which starts at 0 once the input has been seeked
+    let mut audio_filters = Vec::new();
+    let fade = |value: Option<f64>, name: &str| -> Result<Option<f64>, String> {
+        match value {
+            Some(v) if !v.is_finite() || v < 0.0 => Err(format!("{} must be 0 or more", name)),
+            Some(v) if v > 0.0 => Ok(Some(v)),
+            _ => Ok(None),
+        }
+    };
+    let fade_in = fade(options.fade_in_seconds, "fade_in_seconds")?;
+    let fade_out = fade(options.fade_out_seconds, "fade_out_seconds")?;
+    if fade_in.is_some() || fade_out.is_some() {
+        let length = match duration_secs {
+            Some(length) => Some(length),
+            None => probe_media_summary(&options.input).await.ok().and_then(|s| s.duration),
+        };
+        if let (Some(length), Some(total)) = (length, fade_in.zip(fade_out).map(|(i, o)| i + o)) {
+            if total > length {
+                return Err(format!("The fades ({:.1}s) are longer than the clip ({:.1}s)", total, length));
+            }
+        }
+        if let Some(d) = fade_in {
+            audio_filters.push(format!("afade=t=in:st=0:d={}", d));
+        }
+        if let Some(d) = fade_out {
+            let length = length.ok_or_else(|| "Could not read the input duration for the fade-out".to_string())?;
+            audio_filters.push(format!("afade=t=out:st={}:d={}", (length - d).max(0.0), d));
+        }
+    }
+
+    // Delivery loudness, measured and applied as two-pass when the job runs
+    if let Some(preset) = options.loudness_preset.as_deref().filter(|p| !p.is_empty() && *p != "none") {
+        audio_filters.insert(0, loudnorm_filter(preset)?);
+        if options.sample_rate.is_none() {
+            // loudnorm resamples to 192 kHz internally; Opus only takes 48 kHz
+            let source_rate = match selected.as_ref() {
+                Some(stream) => stream.sample_rate.clone(),
+                None => probe_media_summary(&options.input).await.ok().and_then(|s| s.sample_rate),
+            };
+            let rate = match source_rate {
+                Some(rate) if options.format != "opus" => rate,
+                _ => "48000".to_string(),
+            };
+            args.extend(["-ar".to_string(), rate]);
+        }
+    }
+    // Channel changes come first, so loudness is measured on what ends up in the file
+    if let Some(filter) = channel_filter.or(vocal_filter) {
+        audio_filters.insert(0, filter);
+    }
+
+    if let Some(right_path_str) = right_path_str {
+        // One decode feeding both files; the codec settings are repeated per output
+        let output_args = args.split_off(output_args_start);
+        let stream = selected.as_ref().map(|s| s.index).unwrap_or(0);
+        let mut chain = audio_filters.clone();
+        chain.push("channelsplit=channel_layout=stereo[left][right]".to_string());
+        args.extend(["-filter_complex".to_string(), format!("[0:a:{}]{}", stream, chain.join(","))]);
+        for (label, path) in [("[left]", &output_path_str), ("[right]", &right_path_str)] {
+            args.extend(["-map".to_string(), label.to_string()]);
+            args.extend(output_args.iter().cloned());
+            args.extend(cover_args.iter().cloned());
+            args.push(path.clone());
+        }
+        return Ok(JobPlan { args, output_path: output_path_str, warnings, duration_secs, extra_outputs: vec![right_path_str] });
+    }
+
+    if !audio_filters.is_empty() {
+        args.extend(["-af".to_string(), audio_filters.join(",")]);
+    }
+    args.extend(cover_args);
+    
+    args.push(output_path_str.clone());
+    
+    Ok(JobPlan { args, output_path: output_path_str, warnings, duration_secs, extra_outputs: Vec::new() })
+}
+
+const COVER_ART_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "bmp"];
+
+// options.album_art when it names an image file rather than a keyword or a URL
+fn album_art_path(options: &ExtractAudioOptions) -> Option<&String> {
+    options.album_art.as_ref().filter(|art| {
+        let art = art.trim();
+        !matches!(art, "" | "none" | "first_frame" | "thumbnail") && !validate_url(art)
+    })
+}
+
+// The extra input (if any) and output options that attach options.album_art as the
+// front cover, plus a warning when the format can't carry one. MP4 needs the
+// attached_pic disposition to treat the picture as cover art rather than a video track;
+// the "Cover (front)" comment sets the ID3 APIC / FLAC picture type.
+async fn album_art_plan(options: &ExtractAudioOptions) -> Result<(Option<String>, Vec<String>, Option<String>), String> {
+    let Some(album_art) = options.album_art.as_deref().map(str::trim).filter(|a| !a.is_empty() && *a != "none") else {
+        return Ok((None, Vec::new(), None));
+    };
+    if !matches!(options.format.as_str(), "mp3" | "aac" | "flac") {
+        return Ok((None, Vec::new(), Some("Cover art is only embedded in MP3, M4A and FLAC files, it was left out".to_string())));
+    }
+
+    let (cover_input, stream, codec) = match album_art {
+        "first_frame" => {
+            if stream_codecs(&options.input, "v").await.unwrap_or_default().is_empty() {
+                return Err("This file has no video to take the cover art from".to_string());
+            }
+            (None, "0:v:0", "mjpeg")
+        }
+        "thumbnail" => {
+            let input_path = PathBuf::from(&options.input);
+            let thumbnail = COVER_ART_EXTENSIONS
+                .iter()
+                .map(|ext| input_path.with_extension(ext))
+                .find(|p| p.is_file())
+                .ok_or("No downloaded thumbnail found next to this file")?;
+            let codec = if thumbnail.extension().is_some_and(|e| e.eq_ignore_ascii_case("png")) { "png" } else { "mjpeg" };
+            (Some(thumbnail.to_string_lossy().to_string()), "1:v:0", codec)
+        }
+        url if validate_url(url) => (Some(url.to_string()), "1:v:0", "mjpeg"),
+        path => {
+            let image = validate_path(path).ok_or_else(|| msg("error.invalid_path"))?;
+            let ext = image.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+            if !COVER_ART_EXTENSIONS.contains(&ext.as_str()) {
+                return Err("Cover art must be a JPEG, PNG, WebP or BMP image".to_string());
+            }
+            (Some(path.to_string()), "1:v:0", if ext == "png" { "png" } else { "mjpeg" })
+        }
+    };
+
+    let mut args = vec![
+        "-map".to_string(),
+        stream.to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-c:v".to_string(),
+        codec.to_string(),
+        "-disposition:v:0".to_string(),
+        "attached_pic".to_string(),
+        "-metadata:s:v:0".to_string(),
+        "title=Album cover".to_string(),
+        "-metadata:s:v:0".to_string(),
+        "comment=Cover (front)".to_string(),
+    ];
+    if options.format == "mp3" {
+        // ID3v2.3 pictures are what most players and car stereos read
+        args.extend(["-id3v2_version".to_string(), "3".to_string()]);
+    }
+    Ok((cover_input, args, None))
+}
+
+#[tauri::command]
+async fn extract_audio(app: tauri::AppHandle, window: tauri::WebviewWindow, options: ExtractAudioOptions) -> Result<(), String> {
+    let _window = JobWindow::enter(window.label());
+    info!("extract_audio called for: {}", options.input);
+    
+    check_scope(&app, [&options.input].into_iter().chain(&options.output_folder).chain(album_art_path(&options)))?;
+    let state = app.state::<Arc<AppState>>();
+    let input = options.input.clone();
+    let JobPlan { mut args, output_path: output_path_str, warnings, duration_secs, extra_outputs } = build_extract_audio_args(&app, options).await?;
+    create_planned_output_dir(&output_path_str);
+    if !measure_planned_loudness(&app, "extract_audio", &input, &mut args).await? {
+        return Ok(());
+    }
+    
+    // Spawn FFmpeg
+    let mut child = ffmpeg_command(&args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+    
+    // Store process reference
+    let child_pid = child.id();
+    {
+        let mut pid = state.current_pid.lock().await;
+        *pid = child_pid;
+        register_child(child_pid, Some(&output_path_str));
+    }
+    {
+        let mut output_path = state.current_output_path.lock().await;
+        *output_path = Some(output_path_str.clone());
+    }
+    
+    // Read stderr for progress
+    if let Some(stderr) = child.stderr.take() {
+        let app_handle = app.clone();
+        
+        spawn_job_task(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            // ffmpeg's Duration line is the whole input, a clipped range knows its own length
+            let mut duration_in_seconds: Option<f64> = duration_secs;
+            
+            // Pre-compile regex patterns for efficiency
+            let duration_re = regex::Regex::new(r"Duration:\s*(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
+            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
+            
+            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
+                if n == 0 { break; }
+                let line = String::from_utf8_lossy(&buf).to_string();
+                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n').to_string();
+                buf.clear();
+                
+                // Parse duration (only once)
+                if duration_in_seconds.is_none() {
+                    if let Some(ref re) = duration_re {
+                        if let Some(cap) = re.captures(&line) {
+                            let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                            let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                            let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                            duration_in_seconds = Some(h * 3600.0 + m * 60.0 + s);
+                        }
+                    }
+                }
+                
+                // Parse progress - emit even without duration
+                if let Some(ref re) = time_re {
+                    if let Some(cap) = re.captures(&line) {
+                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let current_time = h * 3600.0 + m * 60.0 + s;
+                        
+                        // Round to integer for cleaner display
+                        let percent = match duration_in_seconds {
+                            Some(dur) if dur > 0.0 => ((current_time / dur * 100.0).min(99.0)).round() as u32,
+                            _ => 0
+                        };
+                        
+                        emit_job_progress(&app_handle, "extract_audio", None, percent as f64, Some(format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32)), None);
+                    }
+                }
+            }
+        });
+    }
+    
+    let status = child.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
+    
+    // Clear process reference
+    {
+        let mut pid = state.current_pid.lock().await;
+        unregister_child(pid.take());
+    }
+    {
+        let mut output_path = state.current_output_path.lock().await;
+        *output_path = None;
+    }
+    
+    let is_cancelling = {
+        let cancel = state.is_cancelling.lock().await;
+        *cancel
+    };
+    
+    if is_cancelling {
+        let mut cancel = state.is_cancelling.lock().await;
+        *cancel = false;
+        // cancel_encode only knows the first output
+        for path in &extra_outputs {
+            let _ = std::fs::remove_file(path);
+        }
+        emit_job_cancelled(&app, "extract_audio");
+        return Ok(());
+    }
+    
+    if status.success() && !extra_outputs.is_empty() {
+        let mut output_paths = vec![output_path_str.clone()];
+        output_paths.extend(extra_outputs);
+        emit_job_complete_event(&app, JobCompleteEvent {
+            schema_version: JOB_EVENT_SCHEMA_VERSION,
+            job_type: "extract_audio".to_string(),
+            output_path: output_path_str,
+            warnings,
+            frame_stats: None,
+            output_paths,
+        });
+    } else if status.success() {
+        emit_job_complete_with_warnings(&app, "extract_audio", &output_path_str, warnings);
+    } else {
+        emit_job_error(&app, "extract_audio", msg_with("error.process_exited", &[("code", &format!("{:?}", status.code()))]));
+    }
+    
+    Ok(())
+}
+
+// Karaoke and vocal tracks: the extraction pipeline with vocal_mode set
+#[tauri::command]
+async fn extract_vocal_track(app: tauri::AppHandle, window: tauri::WebviewWindow, mode: String, options: ExtractAudioOptions) -> Result<(), String> {
+    extract_audio(app, window, ExtractAudioOptions { vocal_mode: Some(mode), ..options }).await
+}
+
+// Extracts the audio of several files in one job, either the given inputs or every
+// video in a folder. Files without an audio stream (or that fail) are skipped and
+// reported as warnings; progress events carry the file's position and the batch total.
+#[tauri::command]
+async fn extract_audio_batch(app: tauri::AppHandle, window: tauri::WebviewWindow, inputs: Option<Vec<String>>, directory: Option<String>, options: ExtractAudioOptions) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    let mut files = inputs.unwrap_or_default();
+    if let Some(directory) = directory.filter(|d| !d.is_empty()) {
+        let mut listed = list_files(app.clone(), directory, None).await?;
+        listed.sort();
+        files.extend(listed);
+    }
+    info!("extract_audio_batch called for {} files", files.len());
+    if files.is_empty() {
+        return Err("No video files to extract audio from".to_string());
+    }
+    check_scope(&app, files.iter().chain(&options.output_folder).chain(album_art_path(&options)))?;
+
+    let count = files.len();
+    let mut outputs = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, input) in files.iter().enumerate() {
+        if validate_path(input).is_none() {
+            warnings.push(format!("{}: {}", input, msg("error.invalid_path")));
+            continue;
+        }
+        if !has_audio_stream(input).await.unwrap_or(false) {
+            warnings.push(format!("{}: no audio stream, skipped", input));
+            continue;
+        }
+
+        let file_options = ExtractAudioOptions { input: input.clone(), ..options.clone() };
+        let mut plan = match build_extract_audio_args(&app, file_options).await {
+            Ok(plan) => plan,
+            Err(e) => {
+                warnings.push(format!("{}: {}", input, e));
+                continue;
+            }
+        };
+        match measure_planned_loudness(&app, "extract_audio", input, &mut plan.args).await {
+            Ok(true) => {}
+            Ok(false) => return Err("Audio extraction cancelled".to_string()),
+            Err(e) => {
+                warnings.push(format!("{}: {}", input, e));
+                continue;
+            }
+        }
+
+        let item = JobItem { index, count };
+        create_planned_output_dir(&plan.output_path);
+        let run = run_ffmpeg_job(&app, "extract_audio", Some(item), &plan.args, &plan.output_path, plan.duration_secs).await?;
+        if run.cancelled {
+            // run_ffmpeg_job only knows the first output
+            for path in &plan.extra_outputs {
+                let _ = std::fs::remove_file(path);
+            }
+            emit_job_cancelled(&app, "extract_audio");
+            return Err("Audio extraction cancelled".to_string());
+        }
+        if !run.success {
+            error!("Extracting audio from {} failed: {}", input, run.stderr_tail);
+            warnings.push(format!("{}: {}", input, run.stderr_tail.lines().last().unwrap_or("ffmpeg failed")));
+            continue;
+        }
+        warnings.extend(plan.warnings.into_iter().map(|w| format!("{}: {}", input, w)));
+        outputs.push(plan.output_path);
+        outputs.extend(plan.extra_outputs);
+    }
+
+    if outputs.is_empty() {
+        let message = format!("No audio could be extracted: {}", warnings.join("; "));
+        emit_job_error(&app, "extract_audio", message.clone());
+        return Err(message);
+    }
+    emit_job_complete_event(&app, JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: "extract_audio".to_string(),
+        output_path: outputs[0].clone(),
+        warnings,
+        frame_stats: None,
+        output_paths: outputs.clone(),
+    });
+    Ok(outputs)
+}
+
+async fn build_trim_args(app: &tauri::AppHandle, mut options: TrimVideoOptions) -> Result<JobPlan, String> {
+    apply_output_format_default(app, "trim", &mut options.format)?;
+    
+    let start = options.start_seconds.max(0.0);
+    let end = options.end_seconds.max(start + 1.0);
+    let duration = end - start;
+    
+    // Build output path
+    let input_path = PathBuf::from(&options.input);
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = options.format.clone().unwrap_or_else(|| "mp4".to_string());
+    let filename = format!("{}_trimmed.{}", stem, ext);
+    let output_path = planned_output_dir(&input_path, options.output_folder.as_ref(), "trim").join(&filename);
+    let output_path_str = output_path.to_string_lossy().to_string();
+    
+    let args = vec![
+        "-y".to_string(),
+        "-ss".to_string(),
+        start.to_string(),
+        "-i".to_string(),
+        options.input.clone(),
+        "-t".to_string(),
+        duration.to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+        output_path_str.clone(),
+    ];
+    
+    Ok(JobPlan { args, output_path: output_path_str, warnings: Vec::new(), duration_secs: Some(duration), extra_outputs: Vec::new() })
+}
+
+#[tauri::command]
+async fn trim_video(app: tauri::AppHandle, window: tauri::WebviewWindow, options: TrimVideoOptions) -> Result<(), String> {
+    let _window = JobWindow::enter(window.label());
+    info!("trim_video called for: {} ({}s - {}s)", options.input, options.start_seconds, options.end_seconds);
+    
+    check_scope(&app, [&options.input].into_iter().chain(&options.output_folder))?;
+    let state = app.state::<Arc<AppState>>();
+    let JobPlan { args, output_path: output_path_str, duration_secs, .. } = build_trim_args(&app, options).await?;
+    create_planned_output_dir(&output_path_str);
+    let duration = duration_secs.unwrap_or(0.0);
+    
+    // Spawn FFmpeg
+    let mut child = ffmpeg_command(&args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+    
+    let child_pid = child.id();
+    {
+        let mut pid = state.current_pid.lock().await;
+        *pid = child_pid;
+        register_child(child_pid, Some(&output_path_str));
+    }
+    {
+        let mut output_path = state.current_output_path.lock().await;
+        *output_path = Some(output_path_str.clone());
+    }
+    
+    // Read stderr for progress
+    if let Some(stderr) = child.stderr.take() {
+        let app_handle = app.clone();
+        
+        spawn_job_task(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            
+            // Pre-compile regex pattern
+            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
+            
+            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
+                if n == 0 { break; }
+                let line = String::from_utf8_lossy(&buf).to_string();
+                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n').to_string();
+                buf.clear();
+                
+                if let Some(ref re) = time_re {
+                    if let Some(cap) = re.captures(&line) {
+                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let current = h * 3600.0 + m * 60.0 + s;
+
+                        let percent = if duration > 0.0 {
+                            ((current / duration * 100.0).min(99.0)).round() as u32
+                        } else {
+                            0
+                        };
+                        
+                        emit_job_progress(&app_handle, "trim", None, percent as f64, Some(format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32)), None);
+                    }
+                }
+            }
+        });
+    }
+    
+    let status = child.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
+    
+    {
+        let mut pid = state.current_pid.lock().await;
+        unregister_child(pid.take());
+    }
+    {
+        let mut output_path = state.current_output_path.lock().await;
+        *output_path = None;
+    }
+    
+    let is_cancelling = {
+        let cancel = state.is_cancelling.lock().await;
+        *cancel
+    };
+    
+    if is_cancelling {
+        let mut cancel = state.is_cancelling.lock().await;
+        *cancel = false;
+        emit_job_cancelled(&app, "trim");
+        return Ok(());
+    }
+    
+    if status.success() {
+        emit_job_complete(&app, "trim", &output_path_str);
+    } else {
+        emit_job_error(&app, "trim", msg_with("error.process_exited", &[("code", &format!("{:?}", status.code()))]));
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn cancel_encode<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Result<(), String> {
+    info!("cancel_encode called");
+    
+    let state = app.state::<Arc<AppState>>();
+    
+    // Set cancelling flag
+    {
+        let mut cancel = state.is_cancelling.lock().await;
+        *cancel = true;
+    }
+    
+    // Kill the process
+    let mut pid = state.current_pid.lock().await;
+    if let Some(child_pid) = *pid {
+        kill_process_tree(child_pid).await;
+    }
+    
+    // Delete incomplete output
+    let output_path = {
+        let path = state.current_output_path.lock().await;
+        path.clone()
+    };
+    
+    if let Some(path_str) = output_path {
+        let path = PathBuf::from(&path_str);
+        if path.exists() {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+    
+    unregister_child(pid.take());
+    
+    Ok(())
+}
+
+
+// palettegen and paletteuse settings for the GIF options. "single" stats need a new
+// palette per frame, which paletteuse only takes when told to.
+fn gif_palette_filters(max_colors: Option<u32>, stats_mode: Option<&str>, dither: Option<&str>, bayer_scale: Option<u32>, rectangles: bool) -> Result<(String, String), String> {
+    let max_colors = max_colors.unwrap_or(256);
+    if !(2..=256).contains(&max_colors) {
+        return Err("The palette must have between 2 and 256 colours".to_string());
+    }
+    let stats_mode = stats_mode.unwrap_or("diff");
+    if !matches!(stats_mode, "full" | "diff" | "single") {
+        return Err(format!("Unknown palette stats mode: {}", stats_mode));
+    }
+    let dither = match dither.unwrap_or("sierra2_4a") {
+        "bayer" => {
+            let scale = bayer_scale.unwrap_or(2);
+            if scale > 5 {
+                return Err("bayer_scale must be between 0 and 5".to_string());
+            }
+            format!("bayer:bayer_scale={}", scale)
+        }
+        dither @ ("floyd_steinberg" | "sierra2" | "sierra2_4a" | "none") => dither.to_string(),
+        other => return Err(format!("Unknown dither mode: {}", other)),
+    };
+    let mut palette_use = format!("paletteuse=dither={}", dither);
+    if stats_mode == "single" {
+        palette_use.push_str(":new=1");
+    }
+    if rectangles {
+        palette_use.push_str(":diff_mode=rectangle");
+    }
+    Ok((format!("palettegen=max_colors={}:stats_mode={}", max_colors, stats_mode), palette_use))
+}
+
+// gifsicle pass over a finished GIF, lossless unless a --lossy level (1-200) is given.
+// Returns a note when it couldn't run.
+async fn gifsicle_pass(path: &str, lossy: Option<u32>) -> Option<String> {
+    let mut args = vec!["-O3".to_string(), "--batch".to_string()];
+    if let Some(level) = lossy.filter(|l| *l > 0) {
+        args.push(format!("--lossy={}", level.min(200)));
+    }
+    args.push(path.to_string());
+    match child_output(new_command("gifsicle").args(&args), None).await {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(format!("gifsicle could not optimize the GIF: {}", String::from_utf8_lossy(&output.stderr).trim())),
+        Err(_) => Some("gifsicle is not installed, the extra optimization pass was skipped".to_string()),
+    }
+}
+
+async fn build_gif_args(app: &tauri::AppHandle, mut options: VideoToGifOptions) -> Result<JobPlan, String> {
+    apply_output_format_default(app, "gif", &mut options.format)?;
+    let (ext, encoder_args) = animation_output_args(options.format.as_deref(), options.webp_quality, options.webp_lossless, options.loop_count)?;
+    
+    // Get original duration for progress tracking
+    let mut duration_secs = 100.0;
+    if let Ok(metadata) = probe_metadata(options.input.clone()).await {
+        if let Some(ds) = metadata.duration_seconds {
+            duration_secs = ds;
+        }
+    }
+
+    // If a trim range is specified, use it for progress so percentages make sense
+    let mut effective_duration_secs = duration_secs;
+    if let (Some(start), Some(end)) = (options.start_seconds, options.end_seconds) {
+        if end > start {
+            effective_duration_secs = (end - start).max(0.1);
+        }
+    }
+    
+    // Build output path
+    let input_path = PathBuf::from(&options.input);
+    let stem = input_output_stem(&options.input);
+    let filename = format!("{}_converted.{}", stem, ext);
+    let output_path = planned_output_dir(&input_path, options.output_folder.as_ref(), "gif").join(&filename);
+    let output_path_str = output_path.to_string_lossy().to_string();
+    
+    let fps = options.fps.unwrap_or(15);
+    let scale = options.width.unwrap_or(480);
+    let speed = options.speed.unwrap_or(1.0);
+
+    let mut crop_filter = String::new();
+    if let Some(crop_val) = &options.crop {
+        let x = crop_val.get("x").and_then(|v| v.as_i64()).unwrap_or(0);
+        let y = crop_val.get("y").and_then(|v| v.as_i64()).unwrap_or(0);
+        let w = crop_val.get("w").and_then(|v| v.as_i64()).unwrap_or(0);
+        let h = crop_val.get("h").and_then(|v| v.as_i64()).unwrap_or(0);
+        if w > 0 && h > 0 {
+            crop_filter = format!(",crop={}:{}:{}:{}", w, h, x, y);
+        }
+    }
+
+    let mut speed_filter = String::new();
+    if speed.is_finite() && (speed - 1.0).abs() > 0.00001 {
+        // speed up: setpts=PTS/<speed>
+        speed_filter = format!(",setpts=PTS/{}", speed);
+    }
+    
+    // FFmpeg command to create a GIF
+    // Using a palettegen/paletteuse filter chain for better quality GIFs
+    // Order: crop -> speed -> fps -> scale -> direction -> caption -> hold -> palette
+    // Animated WebP and APNG are full colour, so they skip the palette step
+    // reverse keeps every frame in memory, so it's done on the small scaled frames, and
+    // the ping-pong's way back skips the frame it turns on so it doesn't show twice
+    let played_secs = effective_duration_secs / if speed.is_finite() && speed > 0.0 { speed } else { 1.0 };
+    let direction_filter = match options.direction.as_deref().unwrap_or("forward") {
+        "forward" => String::new(),
+        direction @ ("reverse" | "pingpong") => {
+            if played_secs > 60.0 {
+                return Err("Reversed GIFs are limited to 60 seconds, choose a shorter range".to_string());
+            }
+            if direction == "reverse" {
+                ",reverse".to_string()
+            } else {
+                effective_duration_secs *= 2.0;
+                "[fw];[fw]split[f][b];[b]reverse,trim=start_frame=1,setpts=PTS-STARTPTS[bw];[f][bw]concat=n=2:v=1:a=0".to_string()
+            }
+        }
+        other => return Err(format!("Unknown direction: {}", other)),
+    };
+    let hold_secs = options.hold_last_frame_ms.unwrap_or(0) as f64 / 1000.0;
+    let caption_filter = match options.caption.as_ref() {
+        Some(caption) => format!(",{}", caption_filter(caption)?),
+        None => String::new(),
+    };
+    let hold_filter = if hold_secs > 0.0 { format!(",tpad=stop_mode=clone:stop_duration={:.3}", hold_secs) } else { String::new() };
+    let filters = if ext != "gif" {
+        format!("[0:v]{}{}fps={},scale={}:-1:flags=lanczos{}{}{}[out]", crop_filter, speed_filter, fps, scale, direction_filter, caption_filter, hold_filter)
+    } else {
+        let (palette_gen, palette_use) = gif_palette_filters(
+            options.max_colors,
+            options.stats_mode.as_deref(),
+            options.dither.as_deref(),
+            options.bayer_scale,
+            options.optimize.unwrap_or(false),
+        )?;
+        format!(
+            "[0:v]{}{}fps={},scale={}:-1:flags=lanczos{}{}{}[v];[v]split[v1][v2];[v1]{}[p];[v2][p]{}[out]",
+            crop_filter,
+            speed_filter,
+            fps,
+            scale,
+            direction_filter,
+            caption_filter,
+            hold_filter,
+            palette_gen,
+            palette_use
+        )
+    };
     
-    let output_str = String::from_utf8_lossy(&output.stdout);
+    let mut args = vec!["-y".to_string()];
+
+    // Trim: use input-side -ss to reduce work (fast seek), and -t for length.
+    if let Some(start) = options.start_seconds {
+        if start.is_finite() && start > 0.0 {
+            args.push("-ss".to_string());
+            args.push(format!("{:.3}", start));
+        }
+    }
+
+    // The length limits the input too, so the held last frame isn't cut off
+    if let (Some(start), Some(end)) = (options.start_seconds, options.end_seconds) {
+        if start.is_finite() && end.is_finite() && end > start {
+            args.push("-t".to_string());
+            args.push(format!("{:.3}", end - start));
+        }
+    }
+
+    args.extend(image_sequence_input_args(&options.input, options.input_fps.as_deref()));
+    args.push("-i".to_string());
+    args.push(options.input.clone());
+
+    args.push("-filter_complex".to_string());
+    args.push(filters);
+    args.push("-map".to_string());
+    args.push("[out]".to_string());
+    args.extend(encoder_args);
+    args.push(output_path_str.clone());
     
-    // Parse resolution
-    let mut resolution = "Unknown".to_string();
-    let mut width: Option<u32> = None;
-    let mut height: Option<u32> = None;
-    let mut fps: Option<f64> = None;
+    Ok(JobPlan { args, output_path: output_path_str, warnings: Vec::new(), duration_secs: Some(effective_duration_secs), extra_outputs: Vec::new() })
+}
+
+#[tauri::command]
+async fn video_to_gif(app: tauri::AppHandle, window: tauri::WebviewWindow, options: VideoToGifOptions) -> Result<(), String> {
+    let _window = JobWindow::enter(window.label());
+    info!("video_to_gif called for: {}", options.input);
     
-    for line in output_str.lines() {
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() >= 2 {
-            width = parts[0].parse().ok();
-            height = parts[1].parse().ok();
-            if width.is_some() && height.is_some() {
-                resolution = format!("{}x{}", width.unwrap(), height.unwrap());
+    let font_file = options.caption.as_ref().and_then(|c| c.font.as_ref()).filter(|f| std::path::Path::new(f.trim()).is_file());
+    check_scope(&app, [&options.input].into_iter().chain(&options.output_folder).chain(font_file))?;
+    let app_state = app.state::<Arc<AppState>>().inner().clone();
+    #[cfg(target_os = "windows")]
+    let work_priority = options.work_priority.clone();
+    let optimize = options.optimize.unwrap_or(false);
+    let JobPlan { args, output_path: output_path_str, duration_secs, .. } = build_gif_args(&app, options).await?;
+    create_planned_output_dir(&output_path_str);
+    let effective_duration_secs = duration_secs.unwrap_or(0.0);
+    
+    // Spawn FFmpeg
+    let mut child = ffmpeg_command(&args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+    
+    // Store process reference
+    let child_pid = child.id();
+    {
+        let mut pid = app_state.current_pid.lock().await;
+        *pid = child_pid;
+        register_child(child_pid, Some(&output_path_str));
+    }
+    {
+        let mut output_path = app_state.current_output_path.lock().await;
+        *output_path = Some(output_path_str.clone());
+    }
+    
+    // Track priority using system tools
+    #[cfg(target_os = "windows")]
+    if let Some(priority) = &work_priority {
+        if let Some(pid_val) = child_pid {
+            use std::os::windows::process::CommandExt;
+            match priority.as_str() {
+                "low" | "idle" => {
+                    let _ = std::process::Command::new("powershell")
+                        .args(&["-Command", &format!("(Get-Process -Id {}).PriorityClass = 'Idle'", pid_val)])
+                        .creation_flags(0x08000000)
+                        .spawn();
+                }
+                "high" => {
+                    let _ = std::process::Command::new("powershell")
+                        .args(&["-Command", &format!("(Get-Process -Id {}).PriorityClass = 'High'", pid_val)])
+                        .creation_flags(0x08000000)
+                        .spawn();
+                }
+                _ => {} // Normal priority is default
+            }
+        }
+    }
+    
+    // Read stderr for progress
+    if let Some(stderr) = child.stderr.take() {
+        let app_handle = app.clone();
+        let app_state_clone = app_state.clone();
+        
+        spawn_job_task(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            
+            // Pre-compile regex pattern
+            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
+            
+            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
+                if n == 0 { break; }
+                let line = String::from_utf8_lossy(&buf).to_string();
+                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n').to_string();
+                buf.clear();
+                
+                // Check for cancellation during the stderr reading loop
+                if app_state_clone.is_cancelling.lock().await.clone() {
+                    break;
+                }
+                
+                // Extract time using pre-compiled regex
+                if let Some(ref re) = time_re {
+                    if let Some(cap) = re.captures(&line) {
+                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
+                        let current_secs = h * 3600.0 + m * 60.0 + s;
+                        
+                        // Round to integer for cleaner display, cap at 99%
+                        let percent = if effective_duration_secs > 0.0 {
+                            ((current_secs / effective_duration_secs * 100.0).min(99.0)).round() as u32
+                        } else {
+                            0
+                        };
+                        
+                        emit_job_progress(&app_handle, "gif", None, percent as f64, Some(format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32)), None);
+                    }
+                }
+            }
+        });
+    }
+    
+    let app_handle_wait = app.clone();
+    
+    let status = child.wait().await.map_err(|e| format!("Failed to wait for child: {}", e))?;
+    
+    // Clean up after process completes
+    let mut pid_guard = app_state.current_pid.lock().await;
+    unregister_child(pid_guard.take());
+    
+    let mut path_guard = app_state.current_output_path.lock().await;
+    *path_guard = None;
+    
+    let is_cancelled = *app_state.is_cancelling.lock().await;
+    if is_cancelled {
+        info!("Video to GIF creation cancelled");
+        emit_job_cancelled(&app_handle_wait, "gif");
+        
+        // Try to clean up partial output
+        if std::path::Path::new(&output_path_str).exists() {
+            let _ = std::fs::remove_file(&output_path_str);
+        }
+        
+        return Ok(());
+    }
+    
+    if status.success() {
+        info!("Video to GIF creation completed successfully");
+        let warnings = if optimize && output_path_str.ends_with(".gif") { gifsicle_pass(&output_path_str, None).await.into_iter().collect() } else { Vec::new() };
+        emit_job_complete_with_warnings(&app_handle_wait, "gif", &output_path_str, warnings);
+        Ok(())
+    } else {
+        error!("Video to GIF creation failed with status: {}", status);
+        emit_job_error(&app_handle_wait, "gif", format!("Process exited with status: {}", status));
+        Err(format!("Process exited with status: {}", status))
+    }
+}
+
+// Re-processes an existing GIF to make it smaller: fewer pixels, frames or colours,
+// then optionally gifsicle's lossy compression. The palette is rebuilt from the GIF's
+// own frames, so this works without the video it was made from.
+#[tauri::command]
+async fn optimize_gif(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, options: Option<GifOptimizeOptions>) -> Result<GifOptimizeReport, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("optimize_gif called for: {}", input);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let original_bytes = std::fs::metadata(&input_path).map(|m| m.len()).map_err(|e| format!("Failed to read the GIF: {}", e))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.video_codec.as_deref() != Some("gif") {
+        return Err("This file is not a GIF".to_string());
+    }
+
+    let mut filters = Vec::new();
+    if let Some(fps) = options.fps {
+        if fps == 0 || fps > 50 {
+            return Err("fps must be between 1 and 50".to_string());
+        }
+        filters.push(format!("fps={}", fps));
+    }
+    if let Some(width) = options.width {
+        if width < 16 {
+            return Err("width must be at least 16 pixels".to_string());
+        }
+        filters.push(format!("scale='min({},iw)':-1:flags=lanczos", width));
+    }
+    let (palette_gen, palette_use) = gif_palette_filters(options.max_colors, Some("diff"), options.dither.as_deref(), options.bayer_scale, true)?;
+    let filters = if filters.is_empty() { "[0:v]".to_string() } else { format!("[0:v]{},", filters.join(",")) };
+    let filter = format!(
+        "{}split[v1][v2];[v1]{}:reserve_transparent=1[p];[v2][p]{}:alpha_threshold=128[out]",
+        filters, palette_gen, palette_use
+    );
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_path = job_output_dir(&input_path, options.output_folder.as_ref(), "gif").join(format!("{}_optimized.gif", stem));
+    let output_path_str = output_path.to_string_lossy().to_string();
+    let args: Vec<String> = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-filter_complex".to_string(),
+        filter,
+        "-map".to_string(),
+        "[out]".to_string(),
+        // ffmpeg doesn't carry the source's loop count over, so loop forever like most GIFs
+        "-loop".to_string(),
+        "0".to_string(),
+        output_path_str.clone(),
+    ];
+
+    let run = run_ffmpeg_job(&app, "gif", None, &args, &output_path_str, summary.duration).await?;
+    if !run.success {
+        finish_ffmpeg_job(&app, "gif", &run, &output_path_str);
+        if run.cancelled {
+            return Err("GIF optimization cancelled".to_string());
+        }
+        return Err(format!("Failed to optimize the GIF: {}", run.stderr_tail));
+    }
+
+    let warnings: Vec<String> = match options.lossy.filter(|l| *l > 0) {
+        Some(level) => gifsicle_pass(&output_path_str, Some(level)).await.into_iter().collect(),
+        None => Vec::new(),
+    };
+    let optimized_bytes = std::fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
+    let saved_percent = if original_bytes > 0 {
+        ((1.0 - optimized_bytes as f64 / original_bytes as f64) * 1000.0).round() / 10.0
+    } else {
+        0.0
+    };
+    emit_job_complete_with_warnings(&app, "gif", &output_path_str, warnings.clone());
+    Ok(GifOptimizeReport { output_path: output_path_str, original_bytes, optimized_bytes, saved_percent, warnings })
+}
+
+// A platform's sticker/emote upload rules. square pads the frame to width x width,
+// otherwise the longer side is scaled to width.
+struct StickerPreset {
+    id: &'static str,
+    format: &'static str,
+    width: u32,
+    square: bool,
+    max_bytes: u64,
+    max_seconds: Option<f64>,
+    fps: u32,
+}
+
+const STICKER_PRESETS: &[StickerPreset] = &[
+    StickerPreset { id: "discord_emote", format: "gif", width: 256, square: true, max_bytes: 256 * 1024, max_seconds: None, fps: 20 },
+    StickerPreset { id: "telegram_sticker", format: "webm", width: 512, square: false, max_bytes: 256 * 1024, max_seconds: Some(3.0), fps: 30 },
+    StickerPreset { id: "slack_emoji", format: "gif", width: 128, square: true, max_bytes: 128 * 1024, max_seconds: None, fps: 15 },
+];
+
+fn sticker_preset(id: &str) -> Result<&'static StickerPreset, String> {
+    STICKER_PRESETS.iter().find(|p| p.id == id).ok_or_else(|| {
+        let ids: Vec<&str> = STICKER_PRESETS.iter().map(|p| p.id).collect();
+        format!("Unknown sticker preset '{}', expected one of: {}", id, ids.join(", "))
+    })
+}
+
+// Exports a clip to a platform's sticker or emote format, re-encoding with lower
+// settings until the file fits the platform's size limit. GIFs step down the frame
+// rate and palette; WebM steps down the bitrate, which starts from what the limit
+// allows for the clip's length. The smallest attempt is kept if none fits.
+#[tauri::command]
+async fn export_sticker(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, preset: String, options: Option<StickerOptions>) -> Result<StickerExportReport, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("export_sticker called for {} ({})", input, preset);
+
+    let options = options.unwrap_or_default();
+    let preset = sticker_preset(&preset)?;
+    check_scope(&app, [&input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.video_codec.is_none() {
+        return Err("This file has no video or images to make a sticker from".to_string());
+    }
+
+    let start = options.start_seconds.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(0.0);
+    let available = summary.duration.map(|d| (d - start).max(0.1));
+    let mut length = match options.end_seconds {
+        Some(end) if end <= start => return Err("end_seconds must be after start_seconds".to_string()),
+        Some(end) => Some(end - start),
+        None => available,
+    };
+    let mut warnings = Vec::new();
+    match (preset.max_seconds, length) {
+        (Some(max), Some(current)) if current > max => {
+            warnings.push(format!("Trimmed to {} seconds, the longest this sticker can be", max));
+            length = Some(max);
+        }
+        (Some(max), None) => length = Some(max),
+        _ => {}
+    }
+
+    let mut base_filter = String::new();
+    if let Some(crop) = &options.crop {
+        let value = |key: &str| crop.get(key).and_then(|v| v.as_i64()).unwrap_or(0);
+        if value("w") > 0 && value("h") > 0 {
+            base_filter.push_str(&format!("crop={}:{}:{}:{},", value("w"), value("h"), value("x"), value("y")));
+        }
+    }
+    let w = preset.width;
+    if preset.square {
+        base_filter.push_str(&format!(
+            "scale={w}:{w}:force_original_aspect_ratio=decrease:flags=lanczos,format=rgba,pad={w}:{w}:(ow-iw)/2:(oh-ih)/2:color=black@0",
+            w = w
+        ));
+    } else {
+        base_filter.push_str(&format!("scale='if(gte(iw,ih),{w},-2)':'if(gte(iw,ih),-2,{w})':flags=lanczos,format=rgba", w = w));
+    }
+
+    // (fps, quality) per attempt; quality is the palette size or the bitrate in kbit/s
+    let attempts: Vec<(u32, u32)> = if preset.format == "gif" {
+        vec![(preset.fps, 256), (preset.fps, 128), (15.min(preset.fps), 96), (12, 64), (10, 48), (8, 32)]
+    } else {
+        let seconds = length.unwrap_or(3.0).max(0.5);
+        let budget_kbps = (preset.max_bytes as f64 * 8.0 / 1000.0 / seconds) as u32;
+        [0.9, 0.75, 0.6, 0.45, 0.3].iter().map(|share| (preset.fps, ((budget_kbps as f64 * share) as u32).max(20))).collect()
+    };
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let out_dir = job_output_dir(&input_path, options.output_folder.as_ref(), "gif");
+    let output_path = out_dir.join(format!("{}_{}.{}", stem, preset.id, preset.format));
+    let output_path_str = output_path.to_string_lossy().to_string();
+    let attempt_path = out_dir.join(format!("{}_{}.attempt.{}", stem, preset.id, preset.format));
+    let attempt_str = attempt_path.to_string_lossy().to_string();
+
+    let mut best: Option<(u64, u32, u32)> = None;
+    let mut tried = 0;
+    for (index, (fps, quality)) in attempts.iter().copied().enumerate() {
+        tried = index + 1;
+        let mut args = vec!["-y".to_string()];
+        if start > 0.0 {
+            args.extend(["-ss".to_string(), format!("{:.3}", start)]);
+        }
+        if let Some(length) = length {
+            args.extend(["-t".to_string(), format!("{:.3}", length)]);
+        }
+        args.extend(["-i".to_string(), input.clone(), "-an".to_string()]);
+        if preset.format == "gif" {
+            let (palette_gen, palette_use) = gif_palette_filters(Some(quality), Some("diff"), None, None, true)?;
+            args.extend([
+                "-filter_complex".to_string(),
+                format!(
+                    "[0:v]fps={},{},split[v1][v2];[v1]{}:reserve_transparent=1[p];[v2][p]{}:alpha_threshold=128[out]",
+                    fps, base_filter, palette_gen, palette_use
+                ),
+                "-map".to_string(),
+                "[out]".to_string(),
+                "-loop".to_string(),
+                "0".to_string(),
+                "-gifflags".to_string(),
+                "-transdiff".to_string(),
+            ]);
+        } else {
+            args.extend([
+                "-vf".to_string(),
+                format!("fps={},{}", fps, base_filter),
+                "-c:v".to_string(),
+                "libvpx-vp9".to_string(),
+                "-pix_fmt".to_string(),
+                "yuva420p".to_string(),
+                "-b:v".to_string(),
+                format!("{}k", quality),
+                "-maxrate".to_string(),
+                format!("{}k", quality),
+                "-bufsize".to_string(),
+                format!("{}k", quality * 2),
+                "-deadline".to_string(),
+                "good".to_string(),
+                "-f".to_string(),
+                "webm".to_string(),
+            ]);
+        }
+        args.push(attempt_str.clone());
+
+        let item = JobItem { index, count: attempts.len() };
+        let run = run_ffmpeg_job(&app, "sticker", Some(item), &args, &attempt_str, length).await?;
+        if !run.success {
+            finish_ffmpeg_job(&app, "sticker", &run, &output_path_str);
+            let _ = std::fs::remove_file(&attempt_path);
+            if run.cancelled {
+                let _ = std::fs::remove_file(&output_path);
+                return Err("Sticker export cancelled".to_string());
+            }
+            return Err(format!("Failed to export the sticker: {}", run.stderr_tail));
+        }
+
+        let bytes = std::fs::metadata(&attempt_path).map(|m| m.len()).unwrap_or(u64::MAX);
+        if best.map(|(smallest, _, _)| bytes < smallest).unwrap_or(true) {
+            std::fs::rename(&attempt_path, &output_path).map_err(|e| format!("Failed to save the sticker: {}", e))?;
+            best = Some((bytes, fps, quality));
+        } else {
+            let _ = std::fs::remove_file(&attempt_path);
+        }
+        if bytes <= preset.max_bytes {
+            break;
+        }
+    }
+
+    let (bytes, fps, quality) = best.ok_or("No sticker could be exported")?;
+    let within_limit = bytes <= preset.max_bytes;
+    if !within_limit {
+        warnings.push(format!(
+            "The smallest version is {} KB, over the {} KB limit; try a shorter clip or a tighter crop",
+            bytes.div_ceil(1024),
+            preset.max_bytes / 1024
+        ));
+    }
+    emit_job_complete_with_warnings(&app, "sticker", &output_path_str, warnings);
+    Ok(StickerExportReport {
+        output_path: output_path_str,
+        preset: preset.id.to_string(),
+        bytes,
+        max_bytes: preset.max_bytes,
+        within_limit,
+        fps,
+        quality: if preset.format == "gif" { format!("{} colours", quality) } else { format!("{} kbit/s", quality) },
+        attempts: tried,
+    })
+}
+
+// Transition names accepted by the xfade filter
+const XFADE_TRANSITIONS: &[&str] = &[
+    "fade", "fadeblack", "fadewhite", "dissolve", "wipeleft", "wiperight", "wipeup", "wipedown",
+    "slideleft", "slideright", "slideup", "slidedown", "circleopen", "circleclose", "radial",
+    "smoothleft", "smoothright", "pixelize", "distance", "hblur", "zoomin",
+];
+
+// Normalises clips to one size, frame rate and audio layout and joins them into [v][a],
+// with hard cuts or an xfade/acrossfade of `duration` seconds between each pair.
+// Returns the graph and the joined length.
+fn join_clips_filter(summaries: &[MediaSummary], width: u32, height: u32, fps: &str, transition: Option<(&str, f64)>) -> (String, f64) {
+    let durations: Vec<f64> = summaries.iter().map(|s| s.duration.unwrap_or(0.0)).collect();
+    let mut filter = String::new();
+    for (i, summary) in summaries.iter().enumerate() {
+        filter.push_str(&format!(
+            "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}];",
+            i = i, w = width, h = height, fps = fps
+        ));
+        if summary.audio_codec.is_some() {
+            filter.push_str(&format!("[{i}:a]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo[a{i}];", i = i));
+        } else {
+            // Silent clips still need an audio segment or concat drops out of sync
+            filter.push_str(&format!(
+                "anullsrc=r=48000:cl=stereo,atrim=duration={d:.3}[a{i}];",
+                i = i, d = durations[i]
+            ));
+        }
+    }
+
+    let total: f64 = durations.iter().sum();
+    let Some((kind, duration)) = transition.filter(|_| summaries.len() > 1) else {
+        let concat_inputs: String = (0..summaries.len()).map(|i| format!("[v{i}][a{i}]", i = i)).collect();
+        filter.push_str(&format!("{}concat=n={}:v=1:a=1[v][a]", concat_inputs, summaries.len()));
+        return (filter, total);
+    };
+
+    // A transition can't be longer than half of the shortest clip
+    let shortest = durations.iter().cloned().fold(f64::MAX, f64::min);
+    let duration = duration.clamp(0.1, (shortest / 2.0).max(0.1));
+    let mut offset = 0.0;
+    let (mut video, mut audio) = ("[v0]".to_string(), "[a0]".to_string());
+    for i in 1..summaries.len() {
+        offset += durations[i - 1] - duration;
+        let last = i == summaries.len() - 1;
+        let (next_video, next_audio) = if last { ("[v]".to_string(), "[a]".to_string()) } else { (format!("[vx{}]", i), format!("[ax{}]", i)) };
+        filter.push_str(&format!(
+            "{}[v{i}]xfade=transition={}:duration={:.3}:offset={:.3}{};{}[a{i}]acrossfade=d={:.3}{}{}",
+            video, kind, duration, offset, next_video, audio, duration, next_audio,
+            if last { "" } else { ";" },
+            i = i
+        ));
+        video = next_video;
+        audio = next_audio;
+    }
+    (filter, total - duration * (summaries.len() - 1) as f64)
+}
+
+#[tauri::command]
+async fn concat_videos(app: tauri::AppHandle, window: tauri::WebviewWindow, inputs: Vec<String>, mut options: ConcatOptions) -> Result<(), String> {
+    let _window = JobWindow::enter(window.label());
+    info!("concat_videos called with {} inputs", inputs.len());
+
+    apply_output_format_default(&app, "concat", &mut options.format)?;
+
+    if inputs.len() < 2 {
+        return Err("Select at least two videos to merge".to_string());
+    }
+    check_scope(&app, inputs.iter().chain(&options.output_path).chain(&options.output_folder))?;
+    let mut summaries = Vec::new();
+    for input in &inputs {
+        validate_path(input).ok_or_else(|| msg("error.invalid_path"))?;
+        summaries.push(probe_media_summary(input).await?);
+    }
+
+    let first = &summaries[0];
+    let first_path = PathBuf::from(&inputs[0]);
+    let mut total_duration: f64 = summaries.iter().filter_map(|s| s.duration).sum();
+
+    // The concat demuxer only works when every stream parameter matches exactly
+    let identical = summaries.iter().all(|s| {
+        s.video_codec == first.video_codec
+            && s.width == first.width
+            && s.height == first.height
+            && s.frame_rate == first.frame_rate
+            && s.pix_fmt == first.pix_fmt
+            && s.audio_codec == first.audio_codec
+            && s.sample_rate == first.sample_rate
+            && s.channels == first.channels
+    });
+    // Transitions blend frames, so they always re-encode
+    let transition = options.transition.as_deref().filter(|t| !t.is_empty() && *t != "none");
+    if let Some(kind) = transition {
+        if !XFADE_TRANSITIONS.contains(&kind) {
+            return Err(format!("Unknown transition '{}', expected one of: {}", kind, XFADE_TRANSITIONS.join(", ")));
+        }
+    }
+    let use_copy = match options.mode.as_deref() {
+        Some("copy") => {
+            if transition.is_some() {
+                return Err("Transitions need re-encoding, switch the merge mode to re-encode".to_string());
+            }
+            if !identical {
+                return Err("Inputs use different codecs or resolutions and cannot be merged without re-encoding".to_string());
+            }
+            true
+        }
+        Some("reencode") => false,
+        _ => identical && transition.is_none(),
+    };
+
+    let source_ext = first_path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_else(|| "mp4".to_string());
+    let format = options.format.clone().filter(|f| !f.is_empty()).unwrap_or_else(|| if use_copy { source_ext } else { "mp4".to_string() });
+    let output_path = match options.output_path.as_ref().filter(|p| !p.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let stem = first_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            job_output_dir(&first_path, options.output_folder.as_ref(), "concat").join(format!("{}_merged.{}", stem, format))
+        }
+    };
+    let output_path_str = output_path.to_string_lossy().to_string();
+
+    let mut list_file = None;
+    let mut args = vec!["-y".to_string()];
+
+    if use_copy {
+        let mut list_path = std::env::temp_dir();
+        list_path.push(format!("video_toolbox_concat_{}.txt", uuid_like_seed(&inputs)));
+        let list: String = inputs.iter().map(|p| concat_list_entry(p)).collect();
+        std::fs::write(&list_path, list).map_err(|e| format!("Failed to write temp concat file: {}", e))?;
+
+        args.extend([
+            "-f".to_string(),
+            "concat".to_string(),
+            "-safe".to_string(),
+            "0".to_string(),
+            "-i".to_string(),
+            list_path.to_string_lossy().to_string(),
+            "-map".to_string(),
+            "0".to_string(),
+            "-c".to_string(),
+            "copy".to_string(),
+        ]);
+        list_file = Some(list_path);
+    } else {
+        // Normalise every clip to the first clip's geometry and frame rate (or the requested ones)
+        let width = options.width.or(first.width).unwrap_or(1920) / 2 * 2;
+        let height = options.height.or(first.height).unwrap_or(1080) / 2 * 2;
+        let fps = options
+            .fps
+            .clone()
+            .filter(|f| !f.is_empty() && f != "source")
+            .or_else(|| first.frame_rate.clone())
+            .unwrap_or_else(|| "30".to_string());
+
+        for input in &inputs {
+            args.push("-i".to_string());
+            args.push(input.clone());
+        }
+
+        let transition = transition.map(|kind| (kind, options.transition_seconds.unwrap_or(1.0)));
+        let (filter, joined) = join_clips_filter(&summaries, width, height, &fps, transition);
+        total_duration = joined;
+
+        args.extend([
+            "-filter_complex".to_string(),
+            filter,
+            "-map".to_string(),
+            "[v]".to_string(),
+            "-map".to_string(),
+            "[a]".to_string(),
+        ]);
+        if format == "webm" {
+            args.extend(["-c:v".to_string(), "libvpx-vp9".to_string(), "-b:v".to_string(), "0".to_string()]);
+            args.extend(["-crf".to_string(), options.crf.unwrap_or(32).to_string(), "-c:a".to_string(), "libopus".to_string()]);
+        } else {
+            args.extend(["-c:v".to_string(), "libx264".to_string(), "-preset".to_string(), "medium".to_string()]);
+            args.extend(["-crf".to_string(), options.crf.unwrap_or(20).to_string(), "-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), "192k".to_string()]);
+        }
+    }
+
+    args.push(output_path_str.clone());
+
+    let run = run_ffmpeg_job(&app, "concat", None, &args, &output_path_str, Some(total_duration)).await;
+    if let Some(path) = list_file {
+        let _ = std::fs::remove_file(path);
+    }
+    let run = run?;
+    finish_ffmpeg_job(&app, "concat", &run, &output_path_str);
+
+    Ok(())
+}
+
+// Joins audio files into one, e.g. chapter files into an audiobook. Every file is
+// converted to a common sample rate and channel layout first, then they are butted
+// together, separated by silence or crossfaded. Each source becomes a chapter.
+#[tauri::command]
+async fn concat_audio(app: tauri::AppHandle, window: tauri::WebviewWindow, inputs: Vec<String>, options: ConcatAudioOptions) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("concat_audio called for {} files", inputs.len());
+
+    if inputs.len() < 2 {
+        return Err("Choose at least two files to join".to_string());
+    }
+    check_scope(&app, inputs.iter().chain(&options.output_folder))?;
+    let gap = options.gap_ms.filter(|g| *g > 0).map(|g| g as f64 / 1000.0);
+    let crossfade = options.crossfade_ms.filter(|c| *c > 0).map(|c| c as f64 / 1000.0);
+    if gap.is_some() && crossfade.is_some() {
+        return Err("Choose either a gap or a crossfade between the files, not both".to_string());
+    }
+    let (codec, ext, lossless) = match options.format.as_str() {
+        "mp3" => ("libmp3lame", "mp3", false),
+        "m4a" | "aac" => ("aac", "m4a", false),
+        "m4b" => ("aac", "m4b", false),
+        "flac" => ("flac", "flac", true),
+        "wav" => ("pcm_s16le", "wav", true),
+        "opus" => ("libopus", "opus", false),
+        other => return Err(format!("Unsupported audio format: {}", other)),
+    };
+
+    // Durations place the chapters, rates and channel counts pick the common format
+    let mut durations = Vec::new();
+    let mut rate = 0u32;
+    let mut stereo = false;
+    for input in &inputs {
+        validate_path(input).ok_or_else(|| format!("{}: {}", input, msg("error.invalid_path")))?;
+        let summary = probe_media_summary(input).await?;
+        if summary.audio_codec.is_none() {
+            return Err(format!("{} has no audio stream", input));
+        }
+        let duration = summary.duration.filter(|d| *d > 0.0).ok_or_else(|| format!("Could not read the duration of {}", input))?;
+        if let Some(crossfade) = crossfade.filter(|c| *c >= duration) {
+            return Err(format!("The crossfade ({:.1}s) is longer than {} ({:.1}s)", crossfade, input, duration));
+        }
+        durations.push(duration);
+        rate = rate.max(summary.sample_rate.as_deref().and_then(|r| r.parse().ok()).unwrap_or(44100));
+        stereo |= summary.channels.map(|c| c >= 2).unwrap_or(true);
+    }
+    if codec == "libopus" {
+        rate = 48000;
+    }
+    let layout = if stereo { "stereo" } else { "mono" };
+
+    let count = inputs.len();
+    let mut args = vec!["-y".to_string()];
+    for input in &inputs {
+        args.extend(["-i".to_string(), input.clone()]);
+    }
+    let mut graph: Vec<String> = (0..count)
+        .map(|i| format!("[{}:a:0]aresample={},aformat=sample_fmts=fltp:channel_layouts={}[a{}]", i, rate, layout, i))
+        .collect();
+    let mut starts = Vec::new();
+    let mut position = 0.0;
+    match crossfade {
+        Some(crossfade) => {
+            // Each fade overlaps the end of one file with the start of the next
+            let mut previous = "a0".to_string();
+            for i in 1..count {
+                let label = if i == count - 1 { "out".to_string() } else { format!("x{}", i) };
+                graph.push(format!("[{}][a{}]acrossfade=d={}:c1=tri:c2=tri[{}]", previous, i, crossfade, label));
+                previous = label;
+            }
+            for duration in &durations {
+                starts.push(position);
+                position += duration - crossfade;
+            }
+            position += crossfade;
+        }
+        None => {
+            let mut parts = String::new();
+            for (i, duration) in durations.iter().enumerate() {
+                starts.push(position);
+                position += duration;
+                parts.push_str(&format!("[a{}]", i));
+                if let Some(gap) = gap.filter(|_| i < count - 1) {
+                    graph.push(format!("anullsrc=r={}:cl={},atrim=duration={}[g{}]", rate, layout, gap, i));
+                    parts.push_str(&format!("[g{}]", i));
+                    position += gap;
+                }
+            }
+            let segments = if gap.is_some() { count * 2 - 1 } else { count };
+            graph.push(format!("{}concat=n={}:v=0:a=1[out]", parts, segments));
+        }
+    }
+    let total = position;
+
+    let input_path = PathBuf::from(&inputs[0]);
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_path_str = job_output_dir(&input_path, options.output_folder.as_ref(), "concat_audio")
+        .join(format!("{}_joined.{}", stem, ext))
+        .to_string_lossy()
+        .to_string();
+
+    let mut warnings = Vec::new();
+    let mut metadata_path = None;
+    if options.chapters.unwrap_or(true) {
+        if ext == "wav" {
+            warnings.push("WAV files can't hold chapters, none were written".to_string());
+        } else {
+            let chapters: Vec<(f64, f64, String)> = inputs
+                .iter()
+                .enumerate()
+                .map(|(i, input)| {
+                    let title = PathBuf::from(input).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| format!("Chapter {}", i + 1));
+                    (starts[i], starts.get(i + 1).copied().unwrap_or(total), title)
+                })
+                .collect();
+            let path = std::env::temp_dir().join(format!("video_toolbox_audio_chapters_{}.txt", uuid_like_seed(&inputs)));
+            std::fs::write(&path, named_chapter_metadata(&chapters)).map_err(|e| format!("Failed to write chapter list: {}", e))?;
+            args.extend(["-i".to_string(), path.to_string_lossy().to_string()]);
+            metadata_path = Some(path);
+        }
+    }
+
+    args.extend(["-filter_complex".to_string(), graph.join(";"), "-map".to_string(), "[out]".to_string()]);
+    // The first file's own chapters would otherwise be copied over
+    let chapter_source = if metadata_path.is_some() { count.to_string() } else { "-1".to_string() };
+    args.extend(["-map_chapters".to_string(), chapter_source]);
+    // Album-level tags come from the first file
+    args.extend(["-map_metadata".to_string(), "0".to_string(), "-c:a".to_string(), codec.to_string()]);
+    if !lossless {
+        args.extend(["-b:a".to_string(), options.bitrate.clone().unwrap_or_else(|| "192k".to_string())]);
+    }
+    if ext == "mp3" {
+        args.extend(["-id3v2_version".to_string(), "3".to_string()]);
+    }
+    args.push(output_path_str.clone());
+
+    let run = run_ffmpeg_job(&app, "concat_audio", None, &args, &output_path_str, Some(total)).await;
+    if let Some(path) = metadata_path {
+        let _ = std::fs::remove_file(path);
+    }
+    let run = run?;
+    if run.success {
+        emit_job_complete_with_warnings(&app, "concat_audio", &output_path_str, warnings);
+        Ok(output_path_str)
+    } else {
+        finish_ffmpeg_job(&app, "concat_audio", &run, &output_path_str);
+        if run.cancelled {
+            Err("Joining cancelled".to_string())
+        } else {
+            Err(format!("Failed to join the audio files: {}", run.stderr_tail))
+        }
+    }
+}
+
+// Renders an audio file to an MP4 with an animated waveform or spectrum over a colour
+// or image, for publishing podcasts and music to video sites. The spectrum styles draw
+// on black, which is keyed out so the background shows through.
+#[tauri::command]
+async fn render_audio_visualizer(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, options: AudioVisualizerOptions) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("render_audio_visualizer called for: {}", input);
+
+    check_scope(&app, [&input].into_iter().chain(&options.background_image).chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.audio_codec.is_none() {
+        return Err("No audio stream found in file".to_string());
+    }
+    let width = options.width.unwrap_or(1280).clamp(320, 3840) / 2 * 2;
+    let height = options.height.unwrap_or(720).clamp(240, 2160) / 2 * 2;
+    let fps = options.fps.unwrap_or(30).clamp(10, 60);
+    let hex_re = regex::Regex::new(r"^#?([0-9a-fA-F]{6})$").map_err(|e| e.to_string())?;
+    let hex = |value: Option<&str>, fallback: &str| -> Result<String, String> {
+        let value = value.map(str::trim).filter(|v| !v.is_empty()).unwrap_or(fallback);
+        hex_re.captures(value).map(|c| format!("0x{}", &c[1])).ok_or_else(|| format!("Invalid colour: {}", value))
+    };
+    let color = hex(options.color.as_deref(), "#63f1af")?;
+    let background_color = hex(options.background_color.as_deref(), "#101014")?;
+
+    // The visualization takes the middle half of the frame for waves, most of it otherwise
+    let style = options.style.as_deref().unwrap_or("waves");
+    let band_height = match style {
+        "waves" => height / 4 * 2,
+        _ => height / 8 * 6,
+    };
+    let visual = match style {
+        "waves" => format!("showwaves=s={}x{}:mode=cline:rate={}:colors={}", width, band_height, fps, color),
+        "spectrum" => format!("showspectrum=s={}x{}:slide=scroll:mode=combined:scale=log,fps={},format=rgba,colorkey=black:0.08:0.1", width, band_height, fps),
+        "cqt" => format!("showcqt=s={}x{}:r={}:axis=0,format=rgba,colorkey=black:0.08:0.1", width, band_height, fps),
+        other => return Err(format!("Unknown visualizer style: {}", other)),
+    };
+
+    let mut args = vec!["-y".to_string(), "-i".to_string(), input.clone()];
+    let background = match options.background_image.as_deref().map(str::trim).filter(|b| !b.is_empty()) {
+        Some(image) => {
+            validate_path(image).ok_or_else(|| msg("error.invalid_path"))?;
+            args.extend(["-loop".to_string(), "1".to_string(), "-framerate".to_string(), fps.to_string(), "-i".to_string(), image.to_string()]);
+            format!("[1:v]scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h},setsar=1,fps={fps}[bg]", w = width, h = height, fps = fps)
+        }
+        None => {
+            args.extend(["-f".to_string(), "lavfi".to_string(), "-i".to_string(), format!("color=c={}:s={}x{}:r={}", background_color, width, height, fps)]);
+            "[1:v]setsar=1[bg]".to_string()
+        }
+    };
+    let filter = format!("{};[0:a:0]{}[vis];[bg][vis]overlay=(W-w)/2:(H-h)/2:shortest=1,format=yuv420p[v]", background, visual);
+    let output_path = job_output_dir(&input_path, options.output_folder.as_ref(), "visualizer")
+        .join(format!("{}_visualizer.mp4", input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()));
+    let output_path_str = output_path.to_string_lossy().to_string();
+    args.extend([
+        "-filter_complex".to_string(),
+        filter,
+        "-map".to_string(),
+        "[v]".to_string(),
+        "-map".to_string(),
+        "0:a:0".to_string(),
+        "-c:v".to_string(),
+        "libx264".to_string(),
+        "-preset".to_string(),
+        "medium".to_string(),
+        "-crf".to_string(),
+        "20".to_string(),
+        "-c:a".to_string(),
+        "aac".to_string(),
+        "-b:a".to_string(),
+        "192k".to_string(),
+        "-shortest".to_string(),
+        "-movflags".to_string(),
+        "+faststart".to_string(),
+        output_path_str.clone(),
+    ]);
+
+    let run = run_ffmpeg_job(&app, "visualizer", None, &args, &output_path_str, summary.duration).await?;
+    if run.success {
+        emit_job_complete(&app, "visualizer", &output_path_str);
+        Ok(output_path_str)
+    } else {
+        finish_ffmpeg_job(&app, "visualizer", &run, &output_path_str);
+        if run.cancelled {
+            Err("Rendering cancelled".to_string())
+        } else {
+            Err(format!("Failed to render the visualizer: {}", run.stderr_tail))
+        }
+    }
+}
+
+// Prepends an intro and/or appends an outro to the main video. Both are fitted to the
+// main video's size and frame rate; "fade" dips through black, "crossfade" blends.
+#[tauri::command]
+async fn add_intro_outro(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, options: IntroOutroOptions) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("add_intro_outro called for: {}", input);
+
+    check_scope(&app, [&input].into_iter().chain(&options.intro).chain(&options.outro).chain(&options.output_path).chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let mut clips: Vec<String> = Vec::new();
+    clips.extend(options.intro.clone().filter(|p| !p.is_empty()));
+    clips.push(input.clone());
+    clips.extend(options.outro.clone().filter(|p| !p.is_empty()));
+    if clips.len() < 2 {
+        return Err("Choose an intro, an outro or both".to_string());
+    }
+
+    let mut summaries = Vec::new();
+    for clip in &clips {
+        validate_path(clip).ok_or_else(|| msg("error.invalid_path"))?;
+        summaries.push(probe_media_summary(clip).await?);
+    }
+    let main = &summaries[if options.intro.as_deref().is_some_and(|p| !p.is_empty()) { 1 } else { 0 }];
+    let (width, height) = match (main.width, main.height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => (even_dimension(w), even_dimension(h)),
+        _ => return Err("The main video has no video stream".to_string()),
+    };
+    let fps = main.frame_rate.clone().filter(|f| parse_frame_rate(f).is_some()).unwrap_or_else(|| "30".to_string());
+
+    let transition = match options.transition.as_deref().unwrap_or("none") {
+        "none" => None,
+        "fade" => Some("fadeblack"),
+        "crossfade" => Some("fade"),
+        other => return Err(format!("Unknown transition: {}", other)),
+    };
+    let duration = options.transition_seconds.unwrap_or(1.0);
+    let (filter, total_duration) = join_clips_filter(&summaries, width, height, &fps, transition.map(|t| (t, duration)));
+
+    let output_path_str = match options.output_path.as_ref().filter(|p| !p.is_empty()) {
+        Some(p) => p.clone(),
+        None => {
+            let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            job_output_dir(&input_path, options.output_folder.as_ref(), "intro_outro")
+                .join(format!("{}_bookended.mp4", stem))
+                .to_string_lossy()
+                .to_string()
+        }
+    };
+
+    let mut args = vec!["-y".to_string()];
+    for clip in &clips {
+        args.extend(["-i".to_string(), clip.clone()]);
+    }
+    args.extend([
+        "-filter_complex".to_string(),
+        filter,
+        "-map".to_string(),
+        "[v]".to_string(),
+        "-map".to_string(),
+        "[a]".to_string(),
+        "-c:v".to_string(),
+        "libx264".to_string(),
+        "-preset".to_string(),
+        "medium".to_string(),
+        "-crf".to_string(),
+        options.crf.unwrap_or(20).to_string(),
+        "-c:a".to_string(),
+        "aac".to_string(),
+        "-b:a".to_string(),
+        "192k".to_string(),
+        "-movflags".to_string(),
+        "+faststart".to_string(),
+        output_path_str.clone(),
+    ]);
+
+    let run = run_ffmpeg_job(&app, "intro_outro", None, &args, &output_path_str, Some(total_duration)).await?;
+    finish_ffmpeg_job(&app, "intro_outro", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Intro/outro cancelled".to_string())
+    } else {
+        Err(format!("Failed to add intro/outro: {}", run.stderr_tail))
+    }
+}
+
+async fn probe_chapters(input: &str) -> Result<Vec<ChapterInfo>, String> {
+    let output = probe_output(ffprobe_command()
+        .args(["-v", "error", "-print_format", "json", "-show_chapters", input]))
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffprobe failed: {}", stderr));
+    }
+
+    let data: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let chapters = data.get("chapters").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+    let parse_time = |chapter: &serde_json::Value, key: &str| {
+        chapter.get(key).and_then(|v| v.as_str()).and_then(|v| v.parse::<f64>().ok())
+    };
+
+    Ok(chapters
+        .iter()
+        .enumerate()
+        .filter_map(|(index, chapter)| {
+            let start = parse_time(chapter, "start_time")?;
+            let end = parse_time(chapter, "end_time")?;
+            let title = chapter
+                .get("tags")
+                .and_then(|t| t.get("title"))
+                .and_then(|t| t.as_str())
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty());
+            Some(ChapterInfo { index, title, start, end })
+        })
+        .filter(|c| c.end > c.start)
+        .collect())
+}
+
+#[tauri::command]
+async fn split_by_chapters(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, output_folder: Option<String>) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("split_by_chapters called for: {}", input);
+
+    check_scope(&app, [&input].into_iter().chain(&output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let chapters = probe_chapters(&input).await?;
+    if chapters.is_empty() {
+        return Err("This file has no chapters".to_string());
+    }
+
+    let out_dir = job_output_dir(&input_path, output_folder.as_ref(), "chapter_split");
+    std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = input_path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "mp4".to_string());
+    let count = chapters.len();
+    let mut outputs = Vec::new();
+
+    for chapter in &chapters {
+        let title = chapter
+            .title
+            .as_deref()
+            .map(sanitize_file_name)
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| format!("Chapter {}", chapter.index + 1));
+        let file_name = format!("{} - {:02} - {}.{}", stem, chapter.index + 1, title, ext);
+        let output_path_str = out_dir.join(file_name).to_string_lossy().to_string();
+
+        // Input-side seek with stream copy cuts on the nearest keyframe, which is as close as lossless gets
+        let args: Vec<String> = vec![
+            "-y".to_string(),
+            "-ss".to_string(),
+            format!("{:.3}", chapter.start),
+            "-i".to_string(),
+            input.clone(),
+            "-t".to_string(),
+            format!("{:.3}", chapter.end - chapter.start),
+            "-map".to_string(),
+            "0".to_string(),
+            "-map_chapters".to_string(),
+            "-1".to_string(),
+            "-c".to_string(),
+            "copy".to_string(),
+            "-avoid_negative_ts".to_string(),
+            "make_zero".to_string(),
+            "-metadata".to_string(),
+            format!("title={}", chapter.title.clone().unwrap_or_else(|| title.clone())),
+            output_path_str.clone(),
+        ];
+
+        let item = JobItem { index: chapter.index, count };
+        let run = run_ffmpeg_job(&app, "chapter_split", Some(item), &args, &output_path_str, Some(chapter.end - chapter.start)).await?;
+        if !run.success {
+            finish_ffmpeg_job(&app, "chapter_split", &run, &output_path_str);
+            return Ok(outputs);
+        }
+        outputs.push(output_path_str);
+    }
+
+    emit_job_complete(&app, "chapter_split", &out_dir.to_string_lossy());
+    Ok(outputs)
+}
+
+// Exports each chapter of an audiobook or podcast as its own audio file, tagged as a
+// track of an album named after the source, with the source's cover carried over.
+// Unlike split_by_chapters this re-encodes, so the cuts land on the chapter times.
+#[tauri::command]
+async fn split_audio_by_chapters(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, format: Option<String>, output_folder: Option<String>) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("split_audio_by_chapters called for: {}", input);
+
+    check_scope(&app, [&input].into_iter().chain(&output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    let Some(source_codec) = summary.audio_codec.clone() else {
+        return Err("No audio stream found in file".to_string());
+    };
+    let chapters = probe_chapters(&input).await?;
+    if chapters.is_empty() {
+        return Err("This file has no chapters".to_string());
+    }
+
+    // By default stay in the family of the source codec
+    let format = format.filter(|f| !f.is_empty()).unwrap_or_else(|| match source_codec.as_str() {
+        "mp3" | "flac" | "opus" => source_codec.clone(),
+        _ => "m4a".to_string(),
+    });
+    let (codec, ext) = match format.as_str() {
+        "mp3" => ("libmp3lame", "mp3"),
+        "m4a" | "aac" => ("aac", "m4a"),
+        "flac" => ("flac", "flac"),
+        "opus" => ("libopus", "opus"),
+        "wav" => ("pcm_s16le", "wav"),
+        other => return Err(format!("Unsupported output format: {}", other)),
+    };
+
+    // Tag keys are lower case in MP4 and ID3 but often upper case in Vorbis comments
+    let probe = probe_metadata_full(input.clone()).await.unwrap_or_default();
+    let source_tag = |key: &str| {
+        probe
+            .get("format")
+            .and_then(|f| f.get("tags"))
+            .and_then(|t| t.as_object())
+            .and_then(|tags| tags.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)))
+            .and_then(|(_, v)| v.as_str())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let album = source_tag("album").or_else(|| source_tag("title")).unwrap_or_else(|| stem.clone());
+    let artist = source_tag("artist");
+    let has_cover = summary.video_codec.as_deref().is_some_and(|c| matches!(c, "mjpeg" | "png")) && matches!(ext, "mp3" | "m4a" | "flac");
+
+    let out_dir = job_output_dir(&input_path, output_folder.as_ref(), "chapter_split").join(sanitize_file_name(&album));
+    std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+    let count = chapters.len();
+    let mut outputs = Vec::new();
+    for chapter in &chapters {
+        let number = chapter.index + 1;
+        let title = chapter.title.clone().unwrap_or_else(|| format!("Chapter {}", number));
+        let output_path_str = out_dir
+            .join(format!("{:02} - {}.{}", number, sanitize_file_name(&title), ext))
+            .to_string_lossy()
+            .to_string();
+
+        let mut args = vec![
+            "-y".to_string(),
+            "-ss".to_string(),
+            format!("{:.6}", chapter.start),
+            "-t".to_string(),
+            format!("{:.6}", chapter.end - chapter.start),
+            "-i".to_string(),
+            input.clone(),
+            "-map".to_string(),
+            "0:a:0".to_string(),
+            "-map_metadata".to_string(),
+            "-1".to_string(),
+            "-map_chapters".to_string(),
+            "-1".to_string(),
+        ];
+        if has_cover {
+            args.extend([
+                "-map".to_string(),
+                "0:v:0".to_string(),
+                "-c:v".to_string(),
+                "copy".to_string(),
+                "-disposition:v:0".to_string(),
+                "attached_pic".to_string(),
+            ]);
+        }
+        args.extend(["-c:a".to_string(), codec.to_string()]);
+        match codec {
+            "libmp3lame" | "aac" => args.extend(["-b:a".to_string(), "128k".to_string()]),
+            "libopus" => args.extend(["-b:a".to_string(), "64k".to_string(), "-ar".to_string(), "48000".to_string()]),
+            _ => {}
+        }
+        let tags = [
+            ("title", Some(title.clone())),
+            ("album", Some(album.clone())),
+            ("artist", artist.clone()),
+            ("album_artist", artist.clone()),
+            ("track", Some(format!("{}/{}", number, count))),
+            ("date", source_tag("date")),
+            ("genre", source_tag("genre")),
+        ];
+        for (key, value) in tags {
+            if let Some(value) = value {
+                args.extend(["-metadata".to_string(), format!("{}={}", key, value)]);
+            }
+        }
+        if ext == "mp3" {
+            args.extend(["-id3v2_version".to_string(), "3".to_string()]);
+        }
+        args.push(output_path_str.clone());
+
+        let item = JobItem { index: chapter.index, count };
+        let run = run_ffmpeg_job(&app, "chapter_split", Some(item), &args, &output_path_str, Some(chapter.end - chapter.start)).await?;
+        if !run.success {
+            finish_ffmpeg_job(&app, "chapter_split", &run, &output_path_str);
+            if run.cancelled {
+                for path in &outputs {
+                    let _ = std::fs::remove_file(path);
+                }
+                return Err("Chapter split cancelled".to_string());
+            }
+            return Err(format!("Failed to write chapter {}: {}", number, run.stderr_tail));
+        }
+        outputs.push(output_path_str);
+    }
+
+    emit_job_complete_event(&app, JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: "chapter_split".to_string(),
+        output_path: out_dir.to_string_lossy().to_string(),
+        warnings: Vec::new(),
+        frame_stats: None,
+        output_paths: outputs.clone(),
+    });
+    Ok(outputs)
+}
+
+// Splits a long recording (a vinyl side, a concert) into numbered tracks at the gaps of
+// silence between them. The silence is found first, then each track is cut from the end
+// of one gap to the start of the next, leaving a little of the silence on both sides.
+#[tauri::command]
+async fn split_on_silence(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, options: Option<SilenceSplitOptions>) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("split_on_silence called for: {}", input);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.audio_codec.is_none() {
+        return Err("The file has no audio stream".to_string());
+    }
+    let duration = summary.duration.filter(|d| *d > 0.0).ok_or("Could not determine the duration")?;
+    let noise_db = options.noise_db.unwrap_or(-40.0).clamp(-90.0, -10.0);
+    let min_silence = options.min_silence_seconds.unwrap_or(2.0).clamp(0.1, 60.0);
+    let min_track = options.min_track_seconds.unwrap_or(20.0).max(1.0);
+    let keep = options.keep_silence_seconds.unwrap_or(0.3).clamp(0.0, min_silence / 2.0);
+
+    let log_path = std::env::temp_dir().join(format!("video_toolbox_silence_{}.txt", uuid_like_seed(std::slice::from_ref(&input))));
+    let args: Vec<String> = vec![
+        "-hide_banner".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        "0:a:0".to_string(),
+        "-af".to_string(),
+        format!("silencedetect=noise={}dB:d={},ametadata=mode=print:file={}", noise_db, min_silence, filter_path(&log_path)),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ];
+    let run = run_ffmpeg_job(&app, "silence_split", None, &args, "", Some(duration)).await?;
+    let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+    let _ = std::fs::remove_file(&log_path);
+    if !run.success {
+        if run.cancelled {
+            finish_ffmpeg_job(&app, "silence_split", &run, &input);
+            return Err("Silence split cancelled".to_string());
+        }
+        return Err(format!("Silence detection failed: {}", run.stderr_tail));
+    }
+
+    // (start, end) of every gap; a gap still open at the end of the file runs to the end
+    let mut gaps: Vec<(f64, f64)> = Vec::new();
+    let mut open: Option<f64> = None;
+    for line in log.lines().map(|l| l.trim()) {
+        if let Some(value) = line.strip_prefix("lavfi.silence_start=") {
+            open = value.parse::<f64>().ok().map(|v| v.max(0.0));
+        } else if let Some(value) = line.strip_prefix("lavfi.silence_end=") {
+            if let (Some(start), Ok(end)) = (open.take(), value.parse::<f64>()) {
+                gaps.push((start, end));
+            }
+        }
+    }
+    if let Some(start) = open {
+        gaps.push((start, duration));
+    }
+
+    // Track boundaries: leading and trailing silence is trimmed, inner gaps become cuts
+    // unless they would leave a track shorter than min_track
+    let mut tracks: Vec<(f64, f64)> = Vec::new();
+    let mut track_start = match gaps.first() {
+        Some((start, end)) if *start <= 0.5 => (end - keep).max(0.0),
+        _ => 0.0,
+    };
+    let mut end_of_audio = duration;
+    for (start, end) in &gaps {
+        if *end >= duration - 0.5 {
+            end_of_audio = (start + keep).min(duration);
+            break;
+        }
+        if *start <= track_start + keep {
+            continue;
+        }
+        if start - track_start >= min_track {
+            tracks.push((track_start, start + keep));
+            track_start = end - keep;
+        }
+    }
+    if end_of_audio - track_start >= 1.0 {
+        match tracks.last_mut() {
+            // A short tail is most likely an applause or run-out, keep it with the last track
+            Some(last) if end_of_audio - track_start < min_track => last.1 = end_of_audio,
+            _ => tracks.push((track_start, end_of_audio)),
+        }
+    }
+    if tracks.len() < 2 {
+        return Err("No silence long enough to split on was found, try a higher noise level or shorter gap".to_string());
+    }
+
+    let (codec, ext) = match options.format.as_deref() {
+        None | Some("") | Some("copy") => {
+            let ext = input_path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_else(|| "mka".to_string());
+            // Video containers get an audio-only counterpart
+            let ext = match ext.as_str() {
+                "mp4" | "mov" | "m4v" => "m4a".to_string(),
+                "mkv" | "webm" | "avi" => "mka".to_string(),
+                _ => ext,
+            };
+            ("copy", ext)
+        }
+        Some("mp3") => ("libmp3lame", "mp3".to_string()),
+        Some("m4a") | Some("aac") => ("aac", "m4a".to_string()),
+        Some("flac") => ("flac", "flac".to_string()),
+        Some("wav") => ("pcm_s16le", "wav".to_string()),
+        Some("opus") => ("libopus", "opus".to_string()),
+        Some(other) => return Err(format!("Unsupported output format: {}", other)),
+    };
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let prefix = options.name_prefix.as_deref().map(sanitize_file_name).filter(|p| !p.is_empty()).unwrap_or_else(|| stem.clone());
+    let out_dir = job_output_dir(&input_path, options.output_folder.as_ref(), "silence_split").join(format!("{}_tracks", sanitize_file_name(&stem)));
+    std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+
+    let first_number = options.start_number.unwrap_or(1);
+    let count = tracks.len();
+    let width = (first_number as usize + count - 1).to_string().len().max(2);
+    let mut outputs = Vec::new();
+    for (index, (start, end)) in tracks.iter().enumerate() {
+        let number = first_number as usize + index;
+        let output_path_str = out_dir.join(format!("{} {:0width$}.{}", prefix, number, ext, width = width)).to_string_lossy().to_string();
+        let mut args = vec![
+            "-y".to_string(),
+            "-ss".to_string(),
+            format!("{:.3}", start),
+            "-t".to_string(),
+            format!("{:.3}", end - start),
+            "-i".to_string(),
+            input.clone(),
+            "-map".to_string(),
+            "0:a:0".to_string(),
+            "-map_chapters".to_string(),
+            "-1".to_string(),
+            "-c:a".to_string(),
+            codec.to_string(),
+            "-metadata".to_string(),
+            format!("track={}/{}", number, first_number as usize + count - 1),
+            "-metadata".to_string(),
+            format!("title={} {}", prefix, number),
+        ];
+        if matches!(codec, "libmp3lame" | "aac" | "libopus") {
+            args.extend(["-b:a".to_string(), "256k".to_string()]);
+        }
+        args.push(output_path_str.clone());
+
+        let run = run_ffmpeg_job(&app, "silence_split", Some(JobItem { index, count }), &args, &output_path_str, Some(end - start)).await?;
+        if !run.success {
+            finish_ffmpeg_job(&app, "silence_split", &run, &output_path_str);
+            if run.cancelled {
+                for path in &outputs {
+                    let _ = std::fs::remove_file(path);
+                }
+                return Err("Silence split cancelled".to_string());
+            }
+            return Err(format!("Failed to write track {}: {}", number, run.stderr_tail));
+        }
+        outputs.push(output_path_str);
+    }
+
+    emit_job_complete_event(&app, JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: "silence_split".to_string(),
+        output_path: out_dir.to_string_lossy().to_string(),
+        warnings: Vec::new(),
+        frame_stats: None,
+        output_paths: outputs.clone(),
+    });
+    Ok(outputs)
+}
+
+struct CueSheet {
+    title: Option<String>,
+    performer: Option<String>,
+    date: Option<String>,
+    genre: Option<String>,
+    files: Vec<String>,
+    tracks: Vec<CueTrack>,
+}
+
+struct CueTrack {
+    number: u32,
+    title: Option<String>,
+    performer: Option<String>,
+    isrc: Option<String>,
+    // INDEX 01 in seconds; the pregap (INDEX 00) stays with the track before
+    start: f64,
+}
+
+// Value of a CUE command: the quoted string, or the first word when unquoted
+fn cue_value(rest: &str) -> String {
+    let rest = rest.trim();
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or("").to_string(),
+        None => rest.split_whitespace().next().unwrap_or("").to_string(),
+    }
+}
+
+// mm:ss:ff with 75 frames per second (minutes can go past 59)
+fn cue_time(value: &str) -> Option<f64> {
+    let parts: Vec<f64> = value.split(':').map(|p| p.trim().parse::<f64>().ok()).collect::<Option<_>>()?;
+    match parts.as_slice() {
+        [m, s, f] if *s < 60.0 && *f < 75.0 => Some(m * 60.0 + s + f / 75.0),
+        _ => None,
+    }
+}
+
+fn parse_cue_sheet(text: &str) -> Result<CueSheet, String> {
+    let mut sheet = CueSheet { title: None, performer: None, date: None, genre: None, files: Vec::new(), tracks: Vec::new() };
+    let mut current: Option<CueTrack> = None;
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => sheet.files.push(cue_value(rest)),
+            "TRACK" => {
+                if let Some(track) = current.take() {
+                    sheet.tracks.push(track);
+                }
+                let number = rest.split_whitespace().next().and_then(|n| n.parse().ok()).ok_or_else(|| format!("Invalid TRACK line: {}", line))?;
+                current = Some(CueTrack { number, title: None, performer: None, isrc: None, start: -1.0 });
+            }
+            "TITLE" => match current.as_mut() {
+                Some(track) => track.title = Some(cue_value(rest)),
+                None => sheet.title = Some(cue_value(rest)),
+            },
+            "PERFORMER" => match current.as_mut() {
+                Some(track) => track.performer = Some(cue_value(rest)),
+                None => sheet.performer = Some(cue_value(rest)),
+            },
+            "ISRC" => {
+                if let Some(track) = current.as_mut() {
+                    track.isrc = Some(cue_value(rest));
+                }
+            }
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                if let (Some("01"), Some(time), Some(track)) = (parts.next(), parts.next(), current.as_mut()) {
+                    track.start = cue_time(time).ok_or_else(|| format!("Invalid INDEX time: {}", time))?;
+                }
+            }
+            "REM" => {
+                let (key, value) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest.trim(), ""));
+                match key.to_ascii_uppercase().as_str() {
+                    "DATE" => sheet.date = Some(cue_value(value)),
+                    "GENRE" => sheet.genre = Some(cue_value(value)),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(track) = current.take() {
+        sheet.tracks.push(track);
+    }
+
+    if sheet.tracks.is_empty() {
+        return Err("The CUE sheet lists no tracks".to_string());
+    }
+    if let Some(track) = sheet.tracks.iter().find(|t| t.start < 0.0) {
+        return Err(format!("Track {} has no INDEX 01 start time", track.number));
+    }
+    if sheet.tracks.windows(2).any(|pair| pair[1].start <= pair[0].start) {
+        return Err("The CUE sheet's track times are not in order".to_string());
+    }
+    Ok(sheet)
+}
+
+// Splits one long image (FLAC, APE, WAV...) into tagged per-track files following its
+// CUE sheet. An empty audio_path uses the FILE named in the sheet, next to it.
+#[tauri::command]
+async fn split_by_cue(app: tauri::AppHandle, window: tauri::WebviewWindow, audio_path: String, cue_path: String, format: Option<String>, output_folder: Option<String>) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("split_by_cue called for: {} ({})", audio_path, cue_path);
+
+    check_scope(&app, [&audio_path, &cue_path].into_iter().chain(&output_folder))?;
+    let cue_file = validate_path(&cue_path).ok_or_else(|| msg("error.invalid_path"))?;
+    let bytes = std::fs::read(&cue_file).map_err(|e| format!("Failed to read CUE sheet: {}", e))?;
+    // Older rippers write CUE sheets in Latin-1
+    let text = String::from_utf8(bytes.clone()).unwrap_or_else(|_| bytes.iter().map(|&b| b as char).collect());
+    let sheet = parse_cue_sheet(&text)?;
+    if sheet.files.len() > 1 {
+        return Err("This CUE sheet describes several audio files, only single-file images can be split".to_string());
+    }
+
+    let audio_path = if audio_path.trim().is_empty() {
+        let file = sheet.files.first().ok_or("The CUE sheet names no audio file")?;
+        cue_file.parent().map(|dir| dir.join(file)).unwrap_or_else(|| PathBuf::from(file)).to_string_lossy().to_string()
+    } else {
+        audio_path
+    };
+    let input_path = validate_path(&audio_path).ok_or_else(|| msg("error.invalid_path"))?;
+    let duration = probe_media_summary(&audio_path).await.ok().and_then(|s| s.duration);
+    if let (Some(duration), Some(last)) = (duration, sheet.tracks.last()) {
+        if last.start >= duration {
+            return Err(format!("Track {} starts after the end of the audio, is this the right file?", last.number));
+        }
+    }
+
+    // Always re-encoded: copying cuts on codec frames and ffmpeg can't write APE
+    let (codec, ext) = match format.as_deref() {
+        None | Some("") | Some("flac") => ("flac", "flac"),
+        Some("mp3") => ("libmp3lame", "mp3"),
+        Some("m4a") | Some("aac") => ("aac", "m4a"),
+        Some("wav") => ("pcm_s16le", "wav"),
+        Some("opus") => ("libopus", "opus"),
+        Some(other) => return Err(format!("Unsupported output format: {}", other)),
+    };
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let folder_name = match (&sheet.performer, &sheet.title) {
+        (Some(performer), Some(title)) => format!("{} - {}", performer, title),
+        (None, Some(title)) => title.clone(),
+        _ => stem,
+    };
+    let out_dir = job_output_dir(&input_path, output_folder.as_ref(), "cue_split").join(sanitize_file_name(&folder_name));
+    std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+
+    let count = sheet.tracks.len();
+    let total = sheet.tracks.last().map(|t| t.number).unwrap_or(count as u32);
+    let mut outputs = Vec::new();
+    for (index, track) in sheet.tracks.iter().enumerate() {
+        let end = sheet.tracks.get(index + 1).map(|next| next.start);
+        let title = track.title.clone().filter(|t| !t.trim().is_empty()).unwrap_or_else(|| format!("Track {}", track.number));
+        let output_path_str = out_dir
+            .join(format!("{:02} - {}.{}", track.number, sanitize_file_name(&title), ext))
+            .to_string_lossy()
+            .to_string();
+
+        let mut args = vec!["-y".to_string(), "-ss".to_string(), format!("{:.6}", track.start)];
+        if let Some(end) = end {
+            args.extend(["-t".to_string(), format!("{:.6}", end - track.start)]);
+        }
+        args.extend([
+            "-i".to_string(),
+            audio_path.clone(),
+            "-map".to_string(),
+            "0:a:0".to_string(),
+            "-map_metadata".to_string(),
+            "-1".to_string(),
+            "-map_chapters".to_string(),
+            "-1".to_string(),
+            "-c:a".to_string(),
+            codec.to_string(),
+        ]);
+        if matches!(codec, "libmp3lame" | "aac" | "libopus") {
+            args.extend(["-b:a".to_string(), "256k".to_string()]);
+        }
+        let tags = [
+            ("title", Some(title.clone())),
+            ("artist", track.performer.clone().or_else(|| sheet.performer.clone())),
+            ("album_artist", sheet.performer.clone()),
+            ("album", sheet.title.clone()),
+            ("track", Some(format!("{}/{}", track.number, total))),
+            ("date", sheet.date.clone()),
+            ("genre", sheet.genre.clone()),
+            ("ISRC", track.isrc.clone()),
+        ];
+        for (key, value) in tags {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                args.extend(["-metadata".to_string(), format!("{}={}", key, value)]);
+            }
+        }
+        args.push(output_path_str.clone());
+
+        let length = end.or(duration).map(|e| e - track.start);
+        let run = run_ffmpeg_job(&app, "cue_split", Some(JobItem { index, count }), &args, &output_path_str, length).await?;
+        if !run.success {
+            finish_ffmpeg_job(&app, "cue_split", &run, &output_path_str);
+            if run.cancelled {
+                for path in &outputs {
+                    let _ = std::fs::remove_file(path);
+                }
+                return Err("CUE split cancelled".to_string());
+            }
+            return Err(format!("Failed to write track {}: {}", track.number, run.stderr_tail));
+        }
+        outputs.push(output_path_str);
+    }
+
+    emit_job_complete_event(&app, JobCompleteEvent {
+        schema_version: JOB_EVENT_SCHEMA_VERSION,
+        job_type: "cue_split".to_string(),
+        output_path: out_dir.to_string_lossy().to_string(),
+        warnings: Vec::new(),
+        frame_stats: None,
+        output_paths: outputs.clone(),
+    });
+    Ok(outputs)
+}
+
+#[tauri::command]
+async fn split_video(app: tauri::AppHandle, window: tauri::WebviewWindow, options: SplitVideoOptions) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("split_video called for: {} (mode {})", options.input, options.mode);
+
+    check_scope(&app, [&options.input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&options.input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&options.input).await?;
+    let duration = summary.duration.filter(|d| *d > 0.0).ok_or("Could not determine video duration")?;
+
+    let segment_seconds = match options.mode.as_str() {
+        "count" => {
+            let parts = options.parts.unwrap_or(2).max(1);
+            duration / parts as f64
+        }
+        "duration" => options.segment_seconds.filter(|s| *s > 0.0).ok_or("Segment length must be greater than zero")?,
+        "size" => {
+            let max_bytes = options.max_size_mb.filter(|m| *m > 0.0).ok_or("Maximum part size must be greater than zero")? * 1024.0 * 1024.0;
+            let file_size = std::fs::metadata(&input_path).map_err(|e| format!("Failed to read file size: {}", e))?.len() as f64;
+            let bytes_per_second = file_size / duration;
+            // Cuts land on the next keyframe, so leave headroom under the cap
+            (max_bytes / bytes_per_second * 0.9).max(1.0)
+        }
+        other => return Err(format!("Unknown split mode: {}", other)),
+    };
+
+    let out_dir = job_output_dir(&input_path, options.output_folder.as_ref(), "split");
+    std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = input_path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "mp4".to_string());
+    let pattern = out_dir.join(format!("{}_part%03d.{}", stem, ext)).to_string_lossy().to_string();
+
+    let segment_args = vec!["-segment_time".to_string(), format!("{:.3}", segment_seconds)];
+    run_segment_split(&app, "split", &options.input, &out_dir, &pattern, &segment_args, duration).await
+}
+
+// Stream-copies the input into segment-muxer parts and returns the files written.
+// Cuts land on the keyframe at or after each requested split point.
+async fn run_segment_split(
+    app: &tauri::AppHandle,
+    job_type: &'static str,
+    input: &str,
+    out_dir: &std::path::Path,
+    pattern: &str,
+    segment_args: &[String],
+    duration: f64,
+) -> Result<Vec<String>, String> {
+    let mut list_path = std::env::temp_dir();
+    list_path.push(format!("video_toolbox_split_{}.txt", uuid_like_seed(&[input.to_string()])));
+
+    let mut args: Vec<String> = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        input.to_string(),
+        "-map".to_string(),
+        "0".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+        "-f".to_string(),
+        "segment".to_string(),
+    ];
+    args.extend(segment_args.iter().cloned());
+    args.extend([
+        "-reset_timestamps".to_string(),
+        "1".to_string(),
+        "-segment_list".to_string(),
+        list_path.to_string_lossy().to_string(),
+        "-segment_list_type".to_string(),
+        "flat".to_string(),
+        pattern.to_string(),
+    ]);
+
+    let run = run_ffmpeg_job(app, job_type, None, &args, pattern, Some(duration)).await?;
+    let listed = std::fs::read_to_string(&list_path).unwrap_or_default();
+    let _ = std::fs::remove_file(&list_path);
+
+    let outputs: Vec<String> = listed
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let p = PathBuf::from(l);
+            if p.is_absolute() { p } else { out_dir.join(p) }
+        })
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    if run.cancelled {
+        // The pattern is not a real file, so remove the parts written so far here
+        for path in &outputs {
+            let _ = std::fs::remove_file(path);
+        }
+        finish_ffmpeg_job(app, job_type, &run, pattern);
+        return Ok(Vec::new());
+    }
+    if !run.success {
+        finish_ffmpeg_job(app, job_type, &run, pattern);
+        return Ok(outputs);
+    }
+
+    emit_job_complete(app, job_type, &out_dir.to_string_lossy());
+    Ok(outputs)
+}
+
+// Scene change detection: select keeps frames whose scene score beats the threshold and
+// metadata=print writes their timestamps, so one decode pass at low resolution is enough.
+#[tauri::command]
+async fn detect_scenes(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, threshold: Option<f64>, options: Option<SceneDetectOptions>) -> Result<SceneReport, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("detect_scenes called for: {}", input);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let threshold = threshold.unwrap_or(0.3).clamp(0.05, 0.95);
+    let min_scene = options.min_scene_seconds.unwrap_or(1.0).max(0.0);
+    let summary = probe_media_summary(&input).await?;
+    let duration = summary.duration.filter(|d| *d > 0.0).ok_or("Could not determine video duration")?;
+
+    let log_path = std::env::temp_dir().join(format!("video_toolbox_scenes_{}.txt", uuid_like_seed(std::slice::from_ref(&input))));
+    let args: Vec<String> = vec![
+        "-hide_banner".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-an".to_string(),
+        "-sn".to_string(),
+        "-vf".to_string(),
+        format!("scale=320:-2,select='gt(scene,{:.3})',metadata=print:file={}", threshold, filter_path(&log_path)),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ];
+
+    let run = run_ffmpeg_job(&app, "scenes", None, &args, "", Some(duration)).await?;
+    let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+    let _ = std::fs::remove_file(&log_path);
+    if !run.success {
+        if run.cancelled {
+            finish_ffmpeg_job(&app, "scenes", &run, &input);
+            return Err("Scene detection cancelled".to_string());
+        }
+        return Err(format!("Scene detection failed: {}", run.stderr_tail));
+    }
+
+    // Each selected frame is a "frame:N pts:X pts_time:T" line followed by its lavfi.scene_score
+    let mut cuts: Vec<(f64, f64)> = Vec::new();
+    let mut pending_time: Option<f64> = None;
+    for line in log.lines() {
+        if let Some(time) = line.split_whitespace().find_map(|part| part.strip_prefix("pts_time:")) {
+            pending_time = time.parse::<f64>().ok();
+        } else if let Some(score) = line.trim().strip_prefix("lavfi.scene_score=") {
+            if let (Some(time), Ok(score)) = (pending_time.take(), score.parse::<f64>()) {
+                let last = cuts.last().map(|c| c.0).unwrap_or(0.0);
+                if time - last >= min_scene && duration - time >= min_scene {
+                    cuts.push((time, score));
+                }
             }
-            if parts.len() >= 3 {
-                if let Some((num, den)) = parts[2].split_once('/') {
-                    if let (Ok(n), Ok(d)) = (num.parse::<f64>(), den.parse::<f64>()) {
-                        if d > 0.0 {
-                            fps = Some(n / d);
-                        }
+        }
+    }
+
+    let mut scenes: Vec<SceneInfo> = Vec::new();
+    let mut start = 0.0;
+    let mut score = None;
+    for (index, end) in cuts.iter().map(|c| c.0).chain(std::iter::once(duration)).enumerate() {
+        scenes.push(SceneInfo { index, start, end, duration: end - start, score, thumbnail_path: None });
+        start = end;
+        score = cuts.get(index).map(|c| c.1);
+    }
+
+    let out_dir = job_output_dir(&input_path, options.output_folder.as_ref(), "scenes");
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let mut output_paths = Vec::new();
+    match options.export.as_deref() {
+        Some("split") if !cuts.is_empty() => {
+            std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+            let ext = input_path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "mp4".to_string());
+            let pattern = out_dir.join(format!("{}_scene%03d.{}", stem, ext)).to_string_lossy().to_string();
+            let times = cuts.iter().map(|c| format!("{:.3}", c.0)).collect::<Vec<_>>().join(",");
+            let segment_args = vec!["-segment_times".to_string(), times];
+            output_paths = run_segment_split(&app, "scenes", &input, &out_dir, &pattern, &segment_args, duration).await?;
+        }
+        Some("thumbnails") => {
+            std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+            let ext = match options.thumbnail_format.as_deref() {
+                Some("png") => "png",
+                Some("webp") => "webp",
+                _ => "jpg",
+            };
+            let count = scenes.len();
+            let state = app.state::<Arc<AppState>>();
+            for scene in scenes.iter_mut() {
+                // Thumbnails aren't tracked as the running process, so a cancel only shows in the flag
+                if std::mem::take(&mut *state.is_cancelling.lock().await) {
+                    for path in &output_paths {
+                        let _ = std::fs::remove_file(path);
                     }
+                    emit_job_cancelled(&app, "scenes");
+                    return Err("Scene detection cancelled".to_string());
                 }
+                // The middle of the shot avoids the transition frames at either end
+                let path = out_dir.join(format!("{}_scene{:03}.{}", stem, scene.index + 1, ext));
+                extract_frame_at(&input, scene.start + scene.duration / 2.0, &path, ext, None, None).await?;
+                emit_job_progress(&app, "scenes", Some(JobItem { index: scene.index, count }), (scene.index + 1) as f64 / count as f64 * 100.0, None, None);
+                let path = path.to_string_lossy().to_string();
+                output_paths.push(path.clone());
+                scene.thumbnail_path = Some(path);
+            }
+            emit_job_complete(&app, "scenes", &out_dir.to_string_lossy());
+        }
+        Some("split") | Some("none") | None => emit_job_complete(&app, "scenes", &input),
+        Some(other) => return Err(format!("Unknown scene export: {}", other)),
+    }
+
+    Ok(SceneReport { input, threshold, scenes, output_paths })
+}
+
+// Quotes an argument for display so the preview can be pasted into a shell
+fn quote_command_arg(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '/' | ':' | '=' | ',' | '-')) {
+        arg.to_string()
+    } else {
+        // Nothing is special inside single quotes; a quote itself closes, escapes and reopens
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+// Dry run: the exact ffmpeg invocation encode/gif/trim/extract would use, without running it
+#[tauri::command]
+async fn build_command_preview(app: tauri::AppHandle, operation: String, options: serde_json::Value) -> Result<CommandPreview, String> {
+    info!("build_command_preview called for: {}", operation);
+
+    // The builders probe the input and name the output folder, so both have to be in scope
+    // just as they are for the job itself
+    let paths: Vec<String> = ["input", "output_folder"]
+        .iter()
+        .filter_map(|key| options.get(*key).and_then(|v| v.as_str()).map(String::from))
+        .collect();
+    check_scope(&app, &paths)?;
+    let parse_error = |e: serde_json::Error| format!("Invalid options for {}: {}", operation, e);
+    let plan = match operation.as_str() {
+        "encode" => build_encode_args(&app, serde_json::from_value(options).map_err(parse_error)?).await?,
+        "extract_audio" => build_extract_audio_args(&app, serde_json::from_value(options).map_err(parse_error)?).await?,
+        "trim" => build_trim_args(&app, serde_json::from_value(options).map_err(parse_error)?).await?,
+        "gif" => build_gif_args(&app, serde_json::from_value(options).map_err(parse_error)?).await?,
+        other => return Err(format!("Unknown operation: {}", other)),
+    };
+
+    let program = get_ffmpeg_path();
+    let args = safe_input_args(plan.args);
+    let command_line = std::iter::once(program.as_str())
+        .chain(args.iter().map(|a| a.as_str()))
+        .map(quote_command_arg)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(CommandPreview {
+        program,
+        args,
+        output_path: plan.output_path,
+        command_line,
+        warnings: plan.warnings,
+    })
+}
+
+// Path inside a filtergraph option, quoted with the drive colon escaped
+fn filter_path(path: &std::path::Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\\', "/").replace(':', "\\:"))
+}
+
+fn metric_from_frames(name: &str, frames: Vec<f64>, harmonic_mean: Option<f64>) -> Option<QualityMetric> {
+    let finite: Vec<f64> = frames.iter().copied().filter(|v| v.is_finite()).collect();
+    if finite.is_empty() {
+        return None;
+    }
+    Some(QualityMetric {
+        name: name.to_string(),
+        mean: finite.iter().sum::<f64>() / finite.len() as f64,
+        min: finite.iter().copied().fold(f64::INFINITY, f64::min),
+        max: finite.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        harmonic_mean,
+        frames,
+    })
+}
+
+// Reads "key:value" pairs from a psnr/ssim stats line
+fn stats_value(line: &str, key: &str) -> Option<f64> {
+    line.split_whitespace()
+        .find_map(|part| part.strip_prefix(key).and_then(|v| v.strip_prefix(':')))
+        .map(|v| if v == "inf" { f64::INFINITY } else { v.parse::<f64>().unwrap_or(f64::NAN) })
+}
+
+#[tauri::command]
+async fn compare_quality(app: tauri::AppHandle, window: tauri::WebviewWindow, reference: String, encoded: String, metrics: Option<Vec<String>>) -> Result<QualityReport, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("compare_quality called: {} vs {}", encoded, reference);
+
+    check_scope(&app, [&reference, &encoded])?;
+    validate_path(&reference).ok_or_else(|| msg("error.invalid_path"))?;
+    validate_path(&encoded).ok_or_else(|| msg("error.invalid_path"))?;
+    let metrics: Vec<String> = metrics
+        .unwrap_or_else(|| vec!["psnr".to_string(), "ssim".to_string()])
+        .into_iter()
+        .map(|m| m.to_lowercase())
+        .filter(|m| matches!(m.as_str(), "psnr" | "ssim" | "vmaf"))
+        .collect();
+    if metrics.is_empty() {
+        return Err("Choose at least one of psnr, ssim or vmaf".to_string());
+    }
+
+    let ref_summary = probe_media_summary(&reference).await?;
+    let enc_summary = probe_media_summary(&encoded).await?;
+    let (width, height) = match (ref_summary.width, ref_summary.height) {
+        (Some(w), Some(h)) => (w, h),
+        _ => return Err("Reference file has no video stream".to_string()),
+    };
+
+    let seed = uuid_like_seed(&[reference.clone(), encoded.clone()]);
+    let stats_path = |metric: &str| std::env::temp_dir().join(format!("video_toolbox_{}_{}.log", metric, seed));
+
+    // Both sides start at zero and share the reference resolution, as the metric filters require
+    let n = metrics.len();
+    let mut filter = format!(
+        "[0:v]scale={}:{}:flags=bicubic,setpts=PTS-STARTPTS,format=yuv420p,split={}{};[1:v]setpts=PTS-STARTPTS,format=yuv420p,split={}{}",
+        width,
+        height,
+        n,
+        (0..n).map(|i| format!("[d{}]", i)).collect::<String>(),
+        n,
+        (0..n).map(|i| format!("[r{}]", i)).collect::<String>(),
+    );
+    for (i, metric) in metrics.iter().enumerate() {
+        let path = filter_path(&stats_path(metric));
+        let graph = match metric.as_str() {
+            "psnr" => format!("psnr=stats_file={}", path),
+            "ssim" => format!("ssim=stats_file={}", path),
+            _ => format!("libvmaf=log_fmt=json:log_path={}", path),
+        };
+        filter.push_str(&format!(";[d{i}][r{i}]{g}", i = i, g = graph));
+    }
+
+    let args: Vec<String> = vec![
+        "-hide_banner".to_string(),
+        "-i".to_string(),
+        encoded.clone(),
+        "-i".to_string(),
+        reference.clone(),
+        "-lavfi".to_string(),
+        filter,
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ];
+
+    let run = run_ffmpeg_job(&app, "quality", None, &args, "", enc_summary.duration).await?;
+    let read_stats = |metric: &str| {
+        let path = stats_path(metric);
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        let _ = std::fs::remove_file(&path);
+        text
+    };
+
+    if !run.success {
+        for metric in &metrics {
+            read_stats(metric);
+        }
+        finish_ffmpeg_job(&app, "quality", &run, &encoded);
+        if run.cancelled {
+            return Err("Quality comparison cancelled".to_string());
+        }
+        if run.stderr_tail.contains("libvmaf") {
+            return Err("VMAF is not available in this FFmpeg build, use PSNR or SSIM instead".to_string());
+        }
+        return Err(format!("Quality comparison failed: {}", run.stderr_tail));
+    }
+
+    let mut results = Vec::new();
+    for metric in &metrics {
+        let text = read_stats(metric);
+        let result = match metric.as_str() {
+            "psnr" => metric_from_frames("psnr", text.lines().filter_map(|l| stats_value(l, "psnr_avg")).collect(), None),
+            "ssim" => metric_from_frames("ssim", text.lines().filter_map(|l| stats_value(l, "All")).collect(), None),
+            _ => {
+                let data: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+                let frames = data
+                    .get("frames")
+                    .and_then(|f| f.as_array())
+                    .map(|f| {
+                        f.iter()
+                            .filter_map(|frame| frame.get("metrics").and_then(|m| m.get("vmaf")).and_then(|v| v.as_f64()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let harmonic = data
+                    .get("pooled_metrics")
+                    .and_then(|p| p.get("vmaf"))
+                    .and_then(|v| v.get("harmonic_mean"))
+                    .and_then(|v| v.as_f64());
+                metric_from_frames("vmaf", frames, harmonic)
+            }
+        };
+        results.extend(result);
+    }
+
+    emit_job_complete(&app, "quality", &encoded);
+    Ok(QualityReport {
+        frame_count: results.iter().map(|m| m.frames.len()).max().unwrap_or(0),
+        reference,
+        encoded,
+        metrics: results,
+    })
+}
+
+// Escapes text for the drawtext filter's text option
+fn escape_drawtext(text: &str) -> String {
+    text.replace('\\', "\\\\\\\\")
+        .replace('\'', "\u{2019}")
+        .replace(':', "\\:")
+        .replace('%', "\\%")
+        .replace(',', "\\,")
+}
+
+// drawtext needs an explicit font on Windows builds without fontconfig
+fn drawtext_font() -> String {
+    let candidates = ["C:/Windows/Fonts/arial.ttf", "C:/Windows/Fonts/segoeui.ttf"];
+    candidates
+        .iter()
+        .find(|p| std::path::Path::new(p).exists())
+        .map(|p| format!("fontfile={}:", filter_path(std::path::Path::new(p))))
+        .unwrap_or_default()
+}
+
+// Folders fonts are installed to on this platform, system-wide and per user
+fn font_directories() -> Vec<PathBuf> {
+    let mut dirs_found: Vec<PathBuf> = Vec::new();
+    if cfg!(target_os = "windows") {
+        let windir = std::env::var("WINDIR").unwrap_or_else(|_| "C:/Windows".to_string());
+        dirs_found.push(PathBuf::from(windir).join("Fonts"));
+        if let Some(local) = dirs::data_local_dir() {
+            dirs_found.push(local.join("Microsoft").join("Windows").join("Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs_found.extend(["/System/Library/Fonts", "/System/Library/Fonts/Supplemental", "/Library/Fonts"].map(PathBuf::from));
+    } else {
+        dirs_found.extend(["/usr/share/fonts", "/usr/local/share/fonts"].map(PathBuf::from));
+        if let Some(home) = dirs::home_dir() {
+            dirs_found.push(home.join(".fonts"));
+        }
+    }
+    dirs_found.extend(dirs::font_dir());
+    dirs_found
+}
+
+// Finds a font file by family name, e.g. "Impact" or "DejaVu Sans Bold", matching file
+// names without spaces, dashes or case. Linux keeps fonts in nested vendor folders.
+fn find_font_file(family: &str) -> Option<PathBuf> {
+    let normalize = |name: &str| name.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+    let wanted = normalize(family);
+    fn walk(dir: &std::path::Path, wanted: &str, normalize: &dyn Fn(&str) -> String, depth: u32) -> Option<PathBuf> {
+        let mut subdirs = Vec::new();
+        for entry in std::fs::read_dir(dir).ok()?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                subdirs.push(path);
+                continue;
+            }
+            let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+            let stem = path.file_stem().map(|s| normalize(&s.to_string_lossy())).unwrap_or_default();
+            if matches!(ext.as_str(), "ttf" | "otf" | "ttc") && stem == wanted {
+                return Some(path);
+            }
+        }
+        if depth == 0 {
+            return None;
+        }
+        subdirs.iter().find_map(|sub| walk(sub, wanted, normalize, depth - 1))
+    }
+    font_directories().iter().find_map(|dir| walk(dir, &wanted, &normalize, 3))
+}
+
+// drawtext font option for a caption font: a file path, a family found on disk, or
+// the family name for fontconfig to resolve on builds that have it
+fn caption_font(font: Option<&str>) -> Result<String, String> {
+    let font = font.map(str::trim).filter(|f| !f.is_empty());
+    if let Some(path) = font.map(std::path::Path::new).filter(|p| p.is_file()) {
+        return Ok(format!("fontfile={}:", filter_path(path)));
+    }
+    let families: Vec<&str> = match font {
+        Some(family) => vec![family],
+        None => vec!["Impact", "Arial Bold", "arialbd", "DejaVuSans-Bold", "Helvetica"],
+    };
+    if let Some(path) = families.iter().find_map(|f| find_font_file(f)) {
+        return Ok(format!("fontfile={}:", filter_path(&path)));
+    }
+    match font {
+        Some(family) if cfg!(target_os = "windows") => Err(format!("Font not found: {}", family)),
+        Some(family) => Ok(format!("font='{}':", family.replace(['\\', '\'', ':'], ""))),
+        None => Ok(drawtext_font()),
+    }
+}
+
+// ffmpeg colour for a caption: a colour name, or hex with an optional alpha byte
+fn caption_color(color: Option<&str>, fallback: &str) -> Result<String, String> {
+    let color = color.map(str::trim).filter(|c| !c.is_empty()).unwrap_or(fallback);
+    let hex = regex::Regex::new(r"^#?([0-9a-fA-F]{6}(?:[0-9a-fA-F]{2})?)$").map_err(|e| e.to_string())?;
+    if let Some(caps) = hex.captures(color) {
+        return Ok(format!("0x{}", &caps[1]));
+    }
+    if color.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Ok(color.to_string());
+    }
+    Err(format!("Invalid colour: {}", color))
+}
+
+// Meme-style drawtext: centred text with an outline, optionally shown for a time range
+fn caption_filter(caption: &GifCaption) -> Result<String, String> {
+    let text = caption.text.trim();
+    if text.is_empty() {
+        return Err("The caption has no text".to_string());
+    }
+    let size = match caption.size {
+        Some(size) if (6..=400).contains(&size) => size.to_string(),
+        Some(_) => return Err("Caption size must be between 6 and 400 pixels".to_string()),
+        None => "h/10".to_string(),
+    };
+    let y = match caption.position.as_deref().unwrap_or("top") {
+        "top" => "h*0.04",
+        "bottom" => "h-text_h-h*0.04",
+        "center" => "(h-text_h)/2",
+        other => return Err(format!("Unknown caption position: {}", other)),
+    };
+    let mut filter = format!(
+        "drawtext={}text='{}':fontsize={}:fontcolor={}:borderw={}:bordercolor={}:x=(w-text_w)/2:y={}",
+        caption_font(caption.font.as_deref())?,
+        escape_drawtext(text),
+        size,
+        caption_color(caption.color.as_deref(), "white")?,
+        caption.outline_width.unwrap_or(3).min(20),
+        caption_color(caption.outline_color.as_deref(), "black")?,
+        y
+    );
+    match (caption.start_seconds, caption.end_seconds) {
+        (None, None) => {}
+        (start, end) => {
+            let start = start.unwrap_or(0.0).max(0.0);
+            match end {
+                Some(end) if end <= start => return Err("The caption must end after it starts".to_string()),
+                Some(end) => filter.push_str(&format!(":enable='between(t,{:.3},{:.3})'", start, end)),
+                None => filter.push_str(&format!(":enable='gte(t,{:.3})'", start)),
+            }
+        }
+    }
+    Ok(filter)
+}
+
+fn label_filter(label: &str, x: &str) -> String {
+    format!(
+        "drawtext={}text='{}':x={}:y=h-th-20:fontsize=h/24:fontcolor=white:box=1:boxcolor=black@0.55:boxborderw=8",
+        drawtext_font(),
+        escape_drawtext(label),
+        x
+    )
+}
+
+// Renders two videos next to each other (side_by_side), as a fixed half/half split, or as a
+// wipe that sweeps from A to B over the clip, to eyeball encoder settings
+#[tauri::command]
+async fn create_comparison_video(app: tauri::AppHandle, window: tauri::WebviewWindow, video_a: String, video_b: String, options: ComparisonOptions) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("create_comparison_video called: {} vs {}", video_a, video_b);
+
+    check_scope(&app, [&video_a, &video_b].into_iter().chain(&options.output_path).chain(&options.output_folder))?;
+    let path_a = validate_path(&video_a).ok_or_else(|| msg("error.invalid_path"))?;
+    validate_path(&video_b).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary_a = probe_media_summary(&video_a).await?;
+    let summary_b = probe_media_summary(&video_b).await?;
+
+    let (src_w, src_h) = match (summary_a.width, summary_a.height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => (w, h),
+        _ => return Err("First video has no video stream".to_string()),
+    };
+    let height = even_dimension(options.height.unwrap_or(src_h).min(2160));
+    let width = even_dimension((src_w as f64 * height as f64 / src_h as f64).round() as u32);
+
+    let start = options.start_seconds.filter(|s| *s > 0.0);
+    let available = summary_a.duration.unwrap_or(0.0).min(summary_b.duration.unwrap_or(f64::MAX)) - start.unwrap_or(0.0);
+    let duration = options.duration_seconds.filter(|d| *d > 0.0).unwrap_or(available).min(available.max(0.1));
+
+    let mode = options.mode.clone().unwrap_or_else(|| "side_by_side".to_string());
+    let prep = |i: usize| format!("[{i}:v]scale={w}:{h}:flags=bicubic,setsar=1,setpts=PTS-STARTPTS,format=yuv420p", i = i, w = width, h = height);
+    let label_a = options.label_a.clone().filter(|l| !l.is_empty());
+    let label_b = options.label_b.clone().filter(|l| !l.is_empty());
+
+    let mut filter = match mode.as_str() {
+        "side_by_side" => {
+            let pane_label = |label: &Option<String>| label.as_deref().map(|l| format!(",{}", label_filter(l, "20"))).unwrap_or_default();
+            format!("{}{}[a];{}{}[b];[a][b]hstack=inputs=2[out]", prep(0), pane_label(&label_a), prep(1), pane_label(&label_b))
+        }
+        "split" => format!("{}[a];{},crop=iw/2:ih:iw/2:0[b];[a][b]overlay=W/2:0[out]", prep(0), prep(1)),
+        "wipe" => format!(
+            "{}[a];{}[b];[b][a]blend=all_expr='if(lt(X,W*min(T/{:.3},1)),A,B)'[out]",
+            prep(0),
+            prep(1),
+            duration.max(0.1)
+        ),
+        other => return Err(format!("Unknown comparison mode: {}", other)),
+    };
+
+    // Split and wipe share one frame, so the labels sit on either side of it
+    if mode != "side_by_side" && (label_a.is_some() || label_b.is_some()) {
+        let mut labels = Vec::new();
+        if let Some(l) = &label_a {
+            labels.push(label_filter(l, "20"));
+        }
+        if let Some(l) = &label_b {
+            labels.push(label_filter(l, "w-tw-20"));
+        }
+        filter = format!("{}[pre];[pre]{}[out]", filter.trim_end_matches("[out]"), labels.join(","));
+    }
+
+    let output_path = match options.output_path.as_ref().filter(|p| !p.is_empty()) {
+        Some(p) => PathBuf::from(p),
+        None => {
+            let stem_a = path_a.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let stem_b = PathBuf::from(&video_b).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            job_output_dir(&path_a, options.output_folder.as_ref(), "comparison").join(format!("{}_vs_{}_{}.mp4", stem_a, stem_b, mode))
+        }
+    };
+    let output_path_str = output_path.to_string_lossy().to_string();
+
+    let mut args = vec!["-y".to_string()];
+    for input in [&video_a, &video_b] {
+        if let Some(s) = start {
+            args.extend(["-ss".to_string(), format!("{:.3}", s)]);
+        }
+        args.extend(["-t".to_string(), format!("{:.3}", duration), "-i".to_string(), input.clone()]);
+    }
+    args.extend([
+        "-filter_complex".to_string(),
+        filter,
+        "-map".to_string(),
+        "[out]".to_string(),
+        "-map".to_string(),
+        "0:a?".to_string(),
+        "-c:v".to_string(),
+        "libx264".to_string(),
+        "-preset".to_string(),
+        "medium".to_string(),
+        "-crf".to_string(),
+        options.crf.unwrap_or(18).to_string(),
+        "-pix_fmt".to_string(),
+        "yuv420p".to_string(),
+        "-c:a".to_string(),
+        "aac".to_string(),
+        "-b:a".to_string(),
+        "160k".to_string(),
+        "-shortest".to_string(),
+        output_path_str.clone(),
+    ]);
+
+    let run = run_ffmpeg_job(&app, "comparison", None, &args, &output_path_str, Some(duration)).await?;
+    finish_ffmpeg_job(&app, "comparison", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Comparison cancelled".to_string())
+    } else {
+        Err(format!("Failed to create comparison video: {}", run.stderr_tail))
+    }
+}
+
+// Animated GIF/APNG/WebP to MP4/WebM/MKV. Transparent pixels are flattened onto the
+// background colour (WebM can keep them), odd sizes are padded to even for yuv420p,
+// and the animation plays `loops` times or until it covers min_duration_seconds.
+#[tauri::command]
+async fn gif_to_video(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, format: String, options: Option<GifToVideoOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("gif_to_video called for: {} -> {}", input, format);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let format = format.to_lowercase();
+    if !matches!(format.as_str(), "mp4" | "webm" | "mkv") {
+        return Err(format!("Unsupported output format: {}", format));
+    }
+
+    let summary = probe_media_summary(&input).await.map_err(|_| "Could not read this animation, the FFmpeg build may not decode animated WebP".to_string())?;
+    let clip_duration = summary.duration.filter(|d| *d > 0.0).unwrap_or(1.0);
+    let mut plays = options.loops.unwrap_or(1).clamp(1, 100);
+    if let Some(min) = options.min_duration_seconds.filter(|m| *m > 0.0) {
+        plays = plays.max((min / clip_duration).ceil() as u32).min(100);
+    }
+
+    let background = options
+        .background
+        .as_deref()
+        .filter(|c| !c.is_empty() && c.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '#'))
+        .unwrap_or("black")
+        .to_string();
+    let keep_alpha = format == "webm" && options.keep_transparency.unwrap_or(false);
+
+    let mut filter = String::from("[0:v]");
+    if let Some(width) = options.width.filter(|w| *w > 0) {
+        filter.push_str(&format!("scale={}:-2:flags=lanczos,", even_dimension(width)));
+    }
+    filter.push_str("pad=ceil(iw/2)*2:ceil(ih/2)*2:color=black@0,format=rgba");
+    if plays > 1 {
+        // The loop filter buffers the frames, which is fine at animation sizes
+        filter.push_str(&format!(",loop=loop={}:size=32767:start=0", plays - 1));
+    }
+    if keep_alpha {
+        filter.push_str(",format=yuva420p[out]");
+    } else {
+        filter.push_str(&format!(
+            ",split[fg][bg];[bg]drawbox=x=0:y=0:w=iw:h=ih:color={}:t=fill[base];[base][fg]overlay=format=auto,format=yuv420p[out]",
+            background
+        ));
+    }
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_path_str = job_output_dir(&input_path, options.output_folder.as_ref(), "gif_to_video")
+        .join(format!("{}_video.{}", stem, format))
+        .to_string_lossy()
+        .to_string();
+
+    let mut args: Vec<String> = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-filter_complex".to_string(),
+        filter,
+        "-map".to_string(),
+        "[out]".to_string(),
+        "-an".to_string(),
+    ];
+    if format == "webm" {
+        args.extend(["-c:v".to_string(), "libvpx-vp9".to_string(), "-b:v".to_string(), "0".to_string()]);
+        args.extend(["-crf".to_string(), options.crf.unwrap_or(32).to_string()]);
+    } else {
+        args.extend(["-c:v".to_string(), "libx264".to_string(), "-preset".to_string(), "medium".to_string()]);
+        args.extend(["-crf".to_string(), options.crf.unwrap_or(20).to_string()]);
+        if format == "mp4" {
+            args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+        }
+    }
+    args.push(output_path_str.clone());
+
+    let run = run_ffmpeg_job(&app, "gif_to_video", None, &args, &output_path_str, Some(clip_duration * plays as f64)).await?;
+    finish_ffmpeg_job(&app, "gif_to_video", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Conversion cancelled".to_string())
+    } else {
+        Err(format!("Failed to convert animation: {}", run.stderr_tail))
+    }
+}
+
+// Speeds a long recording up by keeping every Nth frame (or a speed factor). Blending
+// softens the strobing: "tblend" averages each kept frame with the previous one, "mix"
+// averages all the frames that get skipped (motion blur) and "minterpolate" blends
+// between neighbours while resampling to the output rate.
+#[tauri::command]
+async fn create_timelapse(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, options: Option<TimelapseOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("create_timelapse called for: {}", input);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+
+    let speed = match (options.every_nth_frame, options.speed_factor) {
+        (Some(n), _) if n >= 2 => n as f64,
+        (_, Some(s)) if s.is_finite() && s > 1.0 => s,
+        _ => return Err("Timelapse needs every_nth_frame of 2 or more, or a speed factor above 1".to_string()),
+    };
+    let speed = speed.min(10000.0);
+
+    let summary = probe_media_summary(&input).await?;
+    let source_fps = summary.frame_rate.as_deref().and_then(parse_frame_rate).unwrap_or(30.0);
+    let out_fps = options
+        .fps
+        .filter(|f| f.is_finite() && *f > 0.0)
+        .unwrap_or_else(|| source_fps.min(60.0))
+        .min(120.0);
+
+    let blend = options.blend.as_deref().unwrap_or("none");
+    let mut filters = Vec::new();
+    match blend {
+        "mix" => {
+            // tmix gets slow with huge windows, the blur is already strong by 16 frames
+            let frames = (speed.round() as u32).clamp(2, 16);
+            filters.push(format!("tmix=frames={}", frames));
+            filters.push(format!("setpts=PTS/{}", speed));
+            filters.push(format!("fps={}", out_fps));
+        }
+        "tblend" => {
+            filters.push(format!("setpts=PTS/{}", speed));
+            filters.push(format!("fps={}", out_fps));
+            filters.push("tblend=all_mode=average".to_string());
+        }
+        "minterpolate" => {
+            filters.push(format!("setpts=PTS/{}", speed));
+            filters.push(format!("minterpolate=fps={}:mi_mode=blend", out_fps));
+        }
+        "none" | "" => {
+            filters.push(format!("setpts=PTS/{}", speed));
+            filters.push(format!("fps={}", out_fps));
+        }
+        other => return Err(format!("Unknown blend mode: {}", other)),
+    }
+    filters.push("format=yuv420p".to_string());
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_path_str = job_output_dir(&input_path, options.output_folder.as_ref(), "timelapse")
+        .join(format!("{}_timelapse_{}x.mp4", stem, speed))
+        .to_string_lossy()
+        .to_string();
+
+    let args: Vec<String> = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        "0:v:0".to_string(),
+        "-vf".to_string(),
+        filters.join(","),
+        "-an".to_string(),
+        "-c:v".to_string(),
+        "libx264".to_string(),
+        "-preset".to_string(),
+        "medium".to_string(),
+        "-crf".to_string(),
+        options.crf.unwrap_or(20).to_string(),
+        "-movflags".to_string(),
+        "+faststart".to_string(),
+        output_path_str.clone(),
+    ];
+
+    let output_duration = summary.duration.map(|d| d / speed);
+    let run = run_ffmpeg_job(&app, "timelapse", None, &args, &output_path_str, output_duration).await?;
+    finish_ffmpeg_job(&app, "timelapse", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Timelapse cancelled".to_string())
+    } else {
+        Err(format!("Failed to create timelapse: {}", run.stderr_tail))
+    }
+}
+
+// Output flags for one rendition: the video or audio map, codecs and rate control
+fn rendition_args(rendition: &Rendition, video_label: Option<&str>, has_audio: bool) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let audio_codec = |codec: &str| -> Result<&'static str, String> {
+        match codec {
+            "aac" => Ok("aac"),
+            "mp3" => Ok("libmp3lame"),
+            "opus" => Ok("libopus"),
+            other => Err(format!("Unsupported audio codec: {}", other)),
+        }
+    };
+
+    let Some(label) = video_label else {
+        if !has_audio {
+            return Err("The input has no audio for an audio-only rendition".to_string());
+        }
+        let codec = audio_codec(rendition.codec.as_deref().or(rendition.audio_codec.as_deref()).unwrap_or("aac"))?;
+        args.extend(["-map".to_string(), "0:a:0".to_string(), "-vn".to_string(), "-c:a".to_string(), codec.to_string()]);
+        args.extend(["-b:a".to_string(), format!("{}k", rendition.audio_bitrate.unwrap_or(192))]);
+        return Ok(args);
+    };
+
+    let encoder = match rendition.codec.as_deref().unwrap_or("h264") {
+        "h264" => "libx264",
+        "h265" | "hevc" => "libx265",
+        "vp9" => "libvpx-vp9",
+        other => return Err(format!("Unsupported rendition codec: {}", other)),
+    };
+    args.extend(["-map".to_string(), format!("[{}]", label), "-c:v".to_string(), encoder.to_string()]);
+    args.extend(encoder_preset_args(encoder, rendition.preset.as_deref().unwrap_or("medium")));
+    match rendition.bitrate.filter(|b| *b > 0) {
+        Some(kbps) => {
+            args.extend(["-b:v".to_string(), format!("{}k", kbps)]);
+            args.extend(["-maxrate".to_string(), format!("{}k", kbps * 3 / 2)]);
+            args.extend(["-bufsize".to_string(), format!("{}k", kbps * 2)]);
+        }
+        None => {
+            let crf = rendition.crf.unwrap_or(if encoder == "libvpx-vp9" { 32 } else { 23 });
+            args.extend(["-crf".to_string(), crf.min(63).to_string()]);
+            if encoder == "libvpx-vp9" {
+                // Constant quality mode in libvpx needs the bitrate cap turned off
+                args.extend(["-b:v".to_string(), "0".to_string()]);
             }
         }
     }
-    
-    // Get duration and bitrate
-    let output2 = new_command(&ffprobe_path)
-        .args(&[
-            "-v", "error",
-            "-show_entries", "format=duration,bit_rate",
-            "-of", "csv=p=0",
-            &path_str
-        ])
-        .output()
-        .await;
-    
-    let mut duration = "00:00:00".to_string();
-    let mut duration_seconds: Option<f64> = None;
-    let mut bitrate: Option<String> = None;
-    
-    if let Ok(output2) = output2 {
-        let output2_str = String::from_utf8_lossy(&output2.stdout);
-        for line in output2_str.lines() {
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() >= 1 {
-                if let Ok(dur) = parts[0].parse::<f64>() {
-                    duration_seconds = Some(dur);
-                    let hours = (dur / 3600.0).floor() as u32;
-                    let mins = ((dur % 3600.0) / 60.0).floor() as u32;
-                    let secs = (dur % 60.0).floor() as u32;
-                    duration = format!("{:02}:{:02}:{:02}", hours, mins, secs);
-                }
+    args.extend(["-pix_fmt".to_string(), "yuv420p".to_string()]);
+
+    if has_audio {
+        let default_audio = if encoder == "libvpx-vp9" { "opus" } else { "aac" };
+        let codec = audio_codec(rendition.audio_codec.as_deref().unwrap_or(default_audio))?;
+        args.extend(["-map".to_string(), "0:a:0".to_string(), "-c:a".to_string(), codec.to_string()]);
+        args.extend(["-b:a".to_string(), format!("{}k", rendition.audio_bitrate.unwrap_or(160))]);
+    }
+    Ok(args)
+}
+
+// Encodes several renditions (e.g. 1080p, 720p and audio-only) in one ffmpeg run. The
+// source is decoded once and split into a scaled branch per rendition, which is much
+// faster than a job per output. Renditions taller than the source keep its height.
+#[tauri::command]
+async fn encode_renditions(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, renditions: Vec<Rendition>, options: Option<RenditionOptions>) -> Result<Vec<String>, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("encode_renditions called for: {} ({} renditions)", input, renditions.len());
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    if renditions.is_empty() || renditions.len() > 8 {
+        return Err("Choose between 1 and 8 renditions".to_string());
+    }
+
+    let summary = probe_media_summary(&input).await?;
+    let has_audio = has_audio_stream(&input).await?;
+    let source_height = summary.height.unwrap_or(0);
+
+    let is_video = |r: &Rendition| r.kind.as_deref() != Some("audio");
+    let video_count = renditions.iter().filter(|r| is_video(r)).count();
+    if video_count > 0 && source_height == 0 {
+        return Err("The input has no video stream for the video renditions".to_string());
+    }
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_dir = job_output_dir(&input_path, options.output_folder.as_ref(), "renditions");
+
+    let mut graph = Vec::new();
+    if video_count > 0 {
+        let splits: String = (0..video_count).map(|i| format!("[v{}]", i)).collect();
+        graph.push(format!("[0:v:0]split={}{}", video_count, splits));
+    }
+
+    let mut args: Vec<String> = vec!["-y".to_string(), "-i".to_string(), input.clone()];
+    let mut output_args = Vec::new();
+    let mut output_paths: Vec<String> = Vec::new();
+    let mut video_index = 0;
+    for rendition in &renditions {
+        let label = if is_video(rendition) {
+            let height = rendition.height.filter(|h| *h >= MIN_OUTPUT_DIMENSION).unwrap_or(source_height).min(source_height);
+            // Even height keeps yuv420p encoders happy
+            let height = height - height % 2;
+            graph.push(format!("[v{}]scale=-2:{}[o{}]", video_index, height, video_index));
+            video_index += 1;
+            Some((format!("o{}", video_index - 1), height))
+        } else {
+            None
+        };
+
+        let default_format = match (&label, rendition.codec.as_deref()) {
+            (Some(_), Some("vp9")) => "webm",
+            (Some(_), _) => "mp4",
+            (None, Some("mp3")) => "mp3",
+            (None, Some("opus")) => "opus",
+            (None, _) => "m4a",
+        };
+        let format = rendition.format.as_deref().unwrap_or(default_format).trim_start_matches('.').to_lowercase();
+        let name = rendition
+            .name
+            .as_deref()
+            .map(sanitize_file_name)
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| match &label {
+                Some((_, height)) => format!("{}p", height),
+                None => "audio".to_string(),
+            });
+        let output_path = output_dir.join(format!("{}_{}.{}", stem, name, format)).to_string_lossy().to_string();
+        if output_paths.contains(&output_path) {
+            return Err(format!("Two renditions would both be written to {}", output_path));
+        }
+
+        output_args.extend(rendition_args(rendition, label.as_ref().map(|(l, _)| l.as_str()), has_audio)?);
+        if format == "mp4" || format == "m4a" {
+            output_args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+        }
+        output_args.push(output_path.clone());
+        output_paths.push(output_path);
+    }
+
+    if !graph.is_empty() {
+        args.extend(["-filter_complex".to_string(), graph.join(";")]);
+    }
+    args.extend(output_args);
+
+    let run = run_ffmpeg_job(&app, "renditions", None, &args, &output_paths[0], summary.duration).await?;
+    if run.success {
+        emit_job_complete_event(&app, JobCompleteEvent {
+            schema_version: JOB_EVENT_SCHEMA_VERSION,
+            job_type: "renditions".to_string(),
+            output_path: output_paths[0].clone(),
+            warnings: Vec::new(),
+            frame_stats: None,
+            output_paths: output_paths.clone(),
+        });
+        return Ok(output_paths);
+    }
+
+    // Only the first output is tracked as the job's output, so clean up the rest here
+    for path in &output_paths {
+        let _ = std::fs::remove_file(path);
+    }
+    finish_ffmpeg_job(&app, "renditions", &run, &output_paths[0]);
+    if run.cancelled {
+        Err("Rendition encode cancelled".to_string())
+    } else {
+        Err(format!("Failed to encode renditions: {}", run.stderr_tail))
+    }
+}
+
+// Puts 2-4 videos next to each other (horizontal), on top of each other (vertical) or in
+// a 2x2 grid. Every input is fitted into the first video's cell size and frame rate.
+#[tauri::command]
+async fn stack_videos(app: tauri::AppHandle, window: tauri::WebviewWindow, inputs: Vec<String>, layout: String, options: Option<StackVideosOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("stack_videos called: {} inputs, layout {}", inputs.len(), layout);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, inputs.iter().chain(&options.audio).chain(&options.output_path).chain(&options.output_folder))?;
+    let max_inputs = if layout == "grid" { 4 } else { 6 };
+    if inputs.len() < 2 || inputs.len() > max_inputs {
+        return Err(format!("The {} layout takes 2 to {} videos", layout, max_inputs));
+    }
+    let mut summaries = Vec::new();
+    for input in &inputs {
+        validate_path(input).ok_or_else(|| msg("error.invalid_path"))?;
+        summaries.push(probe_media_summary(input).await?);
+    }
+
+    let first = &summaries[0];
+    let (src_w, src_h) = match (first.width, first.height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => (w, h),
+        _ => return Err("The first video has no video stream".to_string()),
+    };
+    let cell_h = even_dimension(options.cell_height.unwrap_or(src_h.min(1080)).clamp(MIN_OUTPUT_DIMENSION, 2160));
+    let cell_w = even_dimension((src_w as f64 * cell_h as f64 / src_h as f64).round() as u32);
+    let fps = first.frame_rate.clone().filter(|f| parse_frame_rate(f).is_some()).unwrap_or_else(|| "30".to_string());
+    let shortest = options.shortest.unwrap_or(true);
+
+    let mut filter = String::new();
+    for i in 0..inputs.len() {
+        filter.push_str(&format!(
+            "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}];",
+            i = i, w = cell_w, h = cell_h, fps = fps
+        ));
+    }
+    let labels: String = (0..inputs.len()).map(|i| format!("[v{}]", i)).collect();
+    let n = inputs.len();
+    let shortest_flag = if shortest { 1 } else { 0 };
+    filter.push_str(&match layout.as_str() {
+        "horizontal" => format!("{}hstack=inputs={}:shortest={}[v]", labels, n, shortest_flag),
+        "vertical" => format!("{}vstack=inputs={}:shortest={}[v]", labels, n, shortest_flag),
+        // A missing fourth cell is filled with black
+        "grid" => format!(
+            "{}xstack=inputs={}:layout={}:fill=black:shortest={}[v]",
+            labels,
+            n,
+            ["0_0", "w0_0", "0_h0", "w0_h0"][..n].join("|"),
+            shortest_flag
+        ),
+        other => return Err(format!("Unknown stack layout: {}", other)),
+    });
+
+    let with_audio: Vec<usize> = summaries.iter().enumerate().filter(|(_, s)| s.audio_codec.is_some()).map(|(i, _)| i).collect();
+    let audio_map = match options.audio.as_deref().unwrap_or("first") {
+        "none" => None,
+        "mix" if with_audio.len() > 1 => {
+            let duration_mode = if shortest { "shortest" } else { "longest" };
+            let mix_inputs: String = with_audio.iter().map(|i| format!("[{}:a]", i)).collect();
+            filter.push_str(&format!(";{}amix=inputs={}:duration={}:normalize=0[a]", mix_inputs, with_audio.len(), duration_mode));
+            Some("[a]".to_string())
+        }
+        "first" | "mix" => with_audio.first().map(|i| format!("{}:a:0", i)),
+        other => return Err(format!("Unknown audio option: {}", other)),
+    };
+
+    let durations: Vec<f64> = summaries.iter().filter_map(|s| s.duration).collect();
+    let duration = if shortest {
+        durations.iter().cloned().fold(f64::MAX, f64::min)
+    } else {
+        durations.iter().cloned().fold(0.0, f64::max)
+    };
+
+    let output_path_str = match options.output_path.filter(|p| !p.is_empty()) {
+        Some(p) => p,
+        None => {
+            let first_path = PathBuf::from(&inputs[0]);
+            let stem = first_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            job_output_dir(&first_path, options.output_folder.as_ref(), "stack")
+                .join(format!("{}_stack_{}.mp4", stem, layout))
+                .to_string_lossy()
+                .to_string()
+        }
+    };
+
+    let mut args = vec!["-y".to_string()];
+    for input in &inputs {
+        args.extend(["-i".to_string(), input.clone()]);
+    }
+    args.extend(["-filter_complex".to_string(), filter, "-map".to_string(), "[v]".to_string()]);
+    match audio_map {
+        Some(map) => args.extend(["-map".to_string(), map, "-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), "192k".to_string()]),
+        None => args.push("-an".to_string()),
+    }
+    if shortest {
+        args.push("-shortest".to_string());
+    }
+    args.extend([
+        "-c:v".to_string(),
+        "libx264".to_string(),
+        "-preset".to_string(),
+        "medium".to_string(),
+        "-crf".to_string(),
+        options.crf.unwrap_or(20).to_string(),
+        "-movflags".to_string(),
+        "+faststart".to_string(),
+        output_path_str.clone(),
+    ]);
+
+    let duration = if duration > 0.0 && duration < f64::MAX { Some(duration) } else { None };
+    let run = run_ffmpeg_job(&app, "stack", None, &args, &output_path_str, duration).await?;
+    finish_ffmpeg_job(&app, "stack", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Stacking cancelled".to_string())
+    } else {
+        Err(format!("Failed to stack videos: {}", run.stderr_tail))
+    }
+}
+
+// Forum-ready source vs encode comparison: a still split at `position` (0-1) with a divider,
+// or a short clip whose wipe slides back and forth across the frame like a slider.
+#[tauri::command]
+async fn export_wipe_comparison(app: tauri::AppHandle, window: tauri::WebviewWindow, source: String, encoded: String, options: Option<WipeExportOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("export_wipe_comparison called: {} vs {}", source, encoded);
+
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&source, &encoded].into_iter().chain(&options.output_path))?;
+    let source_path = validate_path(&source).ok_or_else(|| msg("error.invalid_path"))?;
+    validate_path(&encoded).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&source).await?;
+    let (src_w, src_h) = match (summary.width, summary.height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => (w, h),
+        _ => return Err("Source has no video stream".to_string()),
+    };
+    let width = even_dimension(options.width.unwrap_or(src_w).clamp(MIN_OUTPUT_DIMENSION, src_w.max(MIN_OUTPUT_DIMENSION)));
+    let height = even_dimension((src_h as f64 * width as f64 / src_w as f64).round() as u32);
+
+    let is_video = options.kind.as_deref() == Some("video");
+    let format = options.format.clone().unwrap_or_else(|| if is_video { "mp4".to_string() } else { "png".to_string() });
+    let allowed: &[&str] = if is_video { &["mp4", "gif", "webp"] } else { &["png", "jpg", "webp"] };
+    if !allowed.contains(&format.as_str()) {
+        return Err(format!("Unsupported format for a wipe {}: {}", if is_video { "video" } else { "image" }, format));
+    }
+
+    let timestamp = options.timestamp.unwrap_or(summary.duration.unwrap_or(0.0) / 3.0).max(0.0);
+    let duration = options.duration_seconds.unwrap_or(6.0).clamp(1.0, 30.0);
+    let prep = |i: usize| format!("[{i}:v]scale={w}:{h}:flags=bicubic,setsar=1,setpts=PTS-STARTPTS,format=yuv420p", i = i, w = width, h = height);
+
+    let mut filter = if is_video {
+        // One full left-right-left sweep per clip
+        format!(
+            "{}[a];{}[b];[a][b]blend=all_expr='if(lt(X,W*(0.5+0.45*sin(2*PI*T/{:.3}))),A,B)'",
+            prep(0),
+            prep(1),
+            duration
+        )
+    } else {
+        let split = (width as f64 * options.position.unwrap_or(0.5).clamp(0.05, 0.95)).round() as u32;
+        format!(
+            "{}[a];{},crop={}:{}:{}:0[b];[a][b]overlay={}:0,drawbox=x={}:y=0:w=2:h=ih:color=white@0.9:t=fill",
+            prep(0),
+            prep(1),
+            width - split,
+            height,
+            split,
+            split,
+            split.saturating_sub(1)
+        )
+    };
+    if let Some(label) = options.label_a.as_deref().filter(|l| !l.is_empty()) {
+        filter.push_str(&format!(",{}", label_filter(label, "20")));
+    }
+    if let Some(label) = options.label_b.as_deref().filter(|l| !l.is_empty()) {
+        filter.push_str(&format!(",{}", label_filter(label, "w-tw-20")));
+    }
+    if format == "gif" {
+        filter.push_str(",fps=15,split[s0][s1];[s0]palettegen=stats_mode=diff[p];[s1][p]paletteuse=dither=sierra2_4a");
+    }
+    filter.push_str("[out]");
+
+    let output_path_str = match options.output_path.filter(|p| !p.is_empty()) {
+        Some(p) => p,
+        None => {
+            let stem = source_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            resolve_output_dir(&source_path, None).join(format!("{}_wipe.{}", stem, format)).to_string_lossy().to_string()
+        }
+    };
+
+    let mut args = vec!["-y".to_string()];
+    for input in [&source, &encoded] {
+        args.extend(["-ss".to_string(), format!("{:.3}", timestamp)]);
+        if is_video {
+            args.extend(["-t".to_string(), format!("{:.3}", duration)]);
+        }
+        args.extend(["-i".to_string(), input.clone()]);
+    }
+    args.extend(["-filter_complex".to_string(), filter, "-map".to_string(), "[out]".to_string(), "-an".to_string()]);
+    match format.as_str() {
+        "mp4" => args.extend([
+            "-c:v".to_string(),
+            "libx264".to_string(),
+            "-crf".to_string(),
+            "16".to_string(),
+            "-pix_fmt".to_string(),
+            "yuv420p".to_string(),
+            "-movflags".to_string(),
+            "+faststart".to_string(),
+        ]),
+        "webp" => {
+            args.extend(["-c:v".to_string(), "libwebp".to_string(), "-quality".to_string(), "90".to_string()]);
+            if is_video {
+                args.extend(["-loop".to_string(), "0".to_string()]);
             }
-            if parts.len() >= 2 {
-                if let Ok(br) = parts[1].parse::<u64>() {
-                    bitrate = Some(format!("{} kbps", br / 1000));
+        }
+        "gif" => args.extend(["-loop".to_string(), "0".to_string()]),
+        "jpg" => args.extend(["-q:v".to_string(), "2".to_string()]),
+        _ => {}
+    }
+    if !is_video {
+        args.extend(["-frames:v".to_string(), "1".to_string()]);
+    }
+    args.push(output_path_str.clone());
+
+    let run = run_ffmpeg_job(&app, "wipe", None, &args, &output_path_str, Some(if is_video { duration } else { 1.0 })).await?;
+    finish_ffmpeg_job(&app, "wipe", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Wipe export cancelled".to_string())
+    } else {
+        Err(format!("Failed to export wipe comparison: {}", run.stderr_tail))
+    }
+}
+
+// Positive offsets delay the audio, negative ones delay the video instead, so nothing
+// ever needs negative timestamps. The returned -itsoffset goes before the shifted input.
+fn av_offset_arg(offset_ms: i64) -> [String; 2] {
+    ["-itsoffset".to_string(), format!("{:.3}", offset_ms.unsigned_abs() as f64 / 1000.0)]
+}
+
+// Fixes audio that runs ahead (positive offset_ms delays it) or behind (negative) the picture.
+// Both streams are stream-copied, so this takes seconds even on long recordings.
+#[tauri::command]
+async fn fix_av_sync(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, offset_ms: i64, options: Option<AvSyncOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("fix_av_sync called for: {} ({} ms)", input, offset_ms);
+
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    if offset_ms == 0 {
+        return Err("The offset is zero, nothing to fix".to_string());
+    }
+    if offset_ms.abs() > 60_000 {
+        return Err("Offsets larger than 60 seconds are not supported".to_string());
+    }
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&input].into_iter().chain(&options.output_path).chain(&options.output_folder))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.audio_codec.is_none() || summary.video_codec.is_none() {
+        return Err("The file needs both a video and an audio stream".to_string());
+    }
+
+    let output_path_str = match options.output_path.filter(|p| !p.is_empty()) {
+        Some(p) => p,
+        None => {
+            let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let ext = input_path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "mp4".to_string());
+            job_output_dir(&input_path, options.output_folder.as_ref(), "av_sync")
+                .join(format!("{}_synced.{}", stem, ext))
+                .to_string_lossy()
+                .to_string()
+        }
+    };
+
+    let mut args = vec!["-y".to_string()];
+    if offset_ms < 0 {
+        args.extend(av_offset_arg(offset_ms));
+    }
+    args.extend(["-i".to_string(), input.clone()]);
+    if offset_ms > 0 {
+        args.extend(av_offset_arg(offset_ms));
+    }
+    args.extend([
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        "0:v".to_string(),
+        "-map".to_string(),
+        "1:a".to_string(),
+        "-map".to_string(),
+        "0:s?".to_string(),
+        "-map_metadata".to_string(),
+        "0".to_string(),
+        "-map_chapters".to_string(),
+        "0".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+        output_path_str.clone(),
+    ]);
+
+    let run = run_ffmpeg_job(&app, "av_sync", None, &args, &output_path_str, summary.duration).await?;
+    finish_ffmpeg_job(&app, "av_sync", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Sync fix cancelled".to_string())
+    } else {
+        Err(format!("Failed to fix A/V sync: {}", run.stderr_tail))
+    }
+}
+
+// Offsets within this are below what viewers notice (roughly the EBU R37 tolerance)
+const AV_SYNC_TOLERANCE_MS: f64 = 45.0;
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+// First timestamp of each stream type, from the container
+async fn stream_start_times(input: &str) -> (Option<f64>, Option<f64>) {
+    let output = probe_output(ffprobe_command().args([
+        "-v",
+        "error",
+        "-show_entries",
+        "stream=codec_type,start_time",
+        "-of",
+        "json",
+        input,
+    ]))
+    .await;
+    let Ok(output) = output else {
+        return (None, None);
+    };
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_default();
+    let streams = json.get("streams").and_then(|s| s.as_array()).cloned().unwrap_or_default();
+    let start = |kind: &str| {
+        streams
+            .iter()
+            .find(|s| s.get("codec_type").and_then(|v| v.as_str()) == Some(kind))
+            .and_then(|s| s.get("start_time").and_then(|v| v.as_str()))
+            .and_then(|v| v.parse::<f64>().ok())
+    };
+    (start("video"), start("audio"))
+}
+
+// Estimates how far the audio is off the picture. Hard scene cuts usually come with a
+// sound onset (a door, a clap, a new shot's dialogue), so the typical gap between a cut
+// and the nearest loud onset is the offset. Comparing that gap early and late in the
+// file tells a steady offset, which fix_av_sync can correct, from drift, which it can't.
+#[tauri::command]
+async fn detect_av_desync(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String) -> Result<AvSyncReport, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("detect_av_desync called for: {}", input);
+
+    scoped_path(&app, &input)?;
+    validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.audio_codec.is_none() || summary.video_codec.is_none() {
+        return Err("The file needs both a video and an audio stream".to_string());
+    }
+    let duration = summary.duration.filter(|d| *d > 0.0).ok_or("Could not determine video duration")?;
+    let (video_start, audio_start) = stream_start_times(&input).await;
+    let container_offset_ms = ((audio_start.unwrap_or(0.0) - video_start.unwrap_or(0.0)) * 1000.0).round() as i64;
+
+    let seed = uuid_like_seed(std::slice::from_ref(&input));
+    let scene_log = std::env::temp_dir().join(format!("video_toolbox_avsync_v_{}.txt", seed));
+    let level_log = std::env::temp_dir().join(format!("video_toolbox_avsync_a_{}.txt", seed));
+    let args: Vec<String> = vec![
+        "-hide_banner".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        "0:v:0".to_string(),
+        "-map".to_string(),
+        "0:a:0".to_string(),
+        "-vf".to_string(),
+        format!("scale=160:-2,select='gt(scene,0.4)',metadata=print:file={}", filter_path(&scene_log)),
+        // 10 ms RMS windows at 8 kHz, fine enough to place onsets within a frame
+        "-af".to_string(),
+        format!(
+            "aresample=8000,asetnsamples=n=80,astats=metadata=1:reset=1,ametadata=print:key=lavfi.astats.Overall.RMS_level:file={}",
+            filter_path(&level_log)
+        ),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ];
+
+    let run = run_ffmpeg_job(&app, "av_sync_check", None, &args, "", Some(duration)).await?;
+    let scenes = std::fs::read_to_string(&scene_log).unwrap_or_default();
+    let levels = std::fs::read_to_string(&level_log).unwrap_or_default();
+    let _ = std::fs::remove_file(&scene_log);
+    let _ = std::fs::remove_file(&level_log);
+    if !run.success {
+        if run.cancelled {
+            finish_ffmpeg_job(&app, "av_sync_check", &run, &input);
+            return Err("Sync check cancelled".to_string());
+        }
+        return Err(format!("Sync check failed: {}", run.stderr_tail));
+    }
+
+    // Both logs are "frame:N pts:X pts_time:T" lines followed by the metadata value
+    let parse_log = |log: &str, key: &str| -> Vec<(f64, f64)> {
+        let mut points = Vec::new();
+        let mut pending_time: Option<f64> = None;
+        for line in log.lines() {
+            if let Some(time) = line.split_whitespace().find_map(|part| part.strip_prefix("pts_time:")) {
+                pending_time = time.parse::<f64>().ok();
+            } else if let Some(value) = line.trim().strip_prefix(key) {
+                if let (Some(time), Ok(value)) = (pending_time.take(), value.parse::<f64>()) {
+                    points.push((time, value));
                 }
             }
         }
+        points
+    };
+    let cuts: Vec<f64> = parse_log(&scenes, "lavfi.scene_score=").into_iter().map(|(t, _)| t).collect();
+    let levels = parse_log(&levels, "lavfi.astats.Overall.RMS_level=");
+
+    // An onset is a window at least 12 dB louder than the 50 ms before it, and not near silence
+    let mut onsets = Vec::new();
+    for i in 5..levels.len() {
+        let (time, level) = levels[i];
+        let before = levels[i - 5..i].iter().map(|l| l.1).fold(f64::NEG_INFINITY, f64::max);
+        let loud_onset = level.is_finite() && level > -40.0 && level - before.max(-90.0) >= 12.0;
+        if loud_onset && onsets.last().map(|t| time - t > 0.1).unwrap_or(true) {
+            onsets.push(time);
+        }
     }
-    
-    Ok(VideoMetadata {
-        resolution: Some(resolution),
-        width,
-        height,
-        duration: Some(duration),
-        duration_seconds,
-        bitrate,
-        fps,
+
+    // Pair each cut with the closest onset within half a second
+    let mut matches: Vec<(f64, f64)> = Vec::new();
+    for cut in &cuts {
+        let nearest = onsets
+            .iter()
+            .map(|onset| onset - cut)
+            .filter(|delta| delta.abs() <= 0.5)
+            .min_by(|a, b| a.abs().partial_cmp(&b.abs()).unwrap_or(std::cmp::Ordering::Equal));
+        if let Some(delta) = nearest {
+            matches.push((*cut, delta * 1000.0));
+        }
+    }
+
+    if matches.len() < 5 {
+        return Ok(AvSyncReport {
+            verdict: "inconclusive".to_string(),
+            container_offset_ms,
+            estimated_offset_ms: None,
+            drift_ms: None,
+            matched_events: matches.len(),
+            confidence: 0.0,
+            suggested_offset_ms: None,
+            segments: Vec::new(),
+        });
+    }
+
+    let mut offsets: Vec<f64> = matches.iter().map(|m| m.1).collect();
+    let overall = median(&mut offsets);
+    // Coincidental pairings scatter, real ones cluster around the offset
+    let agreeing = matches.iter().filter(|m| (m.1 - overall).abs() <= 40.0).count();
+    let confidence = agreeing as f64 / matches.len() as f64;
+
+    let mut segments = Vec::new();
+    for quarter in 0..4 {
+        let start = duration * quarter as f64 / 4.0;
+        let end = duration * (quarter + 1) as f64 / 4.0;
+        let mut in_range: Vec<f64> = matches.iter().filter(|m| m.0 >= start && m.0 < end).map(|m| m.1).collect();
+        if in_range.len() >= 3 {
+            segments.push(AvSyncSegment { start_seconds: start, end_seconds: end, offset_ms: median(&mut in_range).round() as i64, matches: in_range.len() });
+        }
+    }
+    let drift_ms = match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) if segments.len() >= 2 => Some(last.offset_ms - first.offset_ms),
+        _ => None,
+    };
+
+    let verdict = if confidence < 0.4 {
+        "inconclusive"
+    } else if drift_ms.map(|d| d.abs() as f64 > AV_SYNC_TOLERANCE_MS).unwrap_or(false) {
+        "drift"
+    } else if overall.abs() > AV_SYNC_TOLERANCE_MS {
+        "offset"
+    } else {
+        "in_sync"
+    };
+    // Late audio (positive) is pulled earlier, so the fix offset has the opposite sign
+    let suggested_offset_ms = (verdict == "offset").then(|| -(overall.round() as i64));
+
+    Ok(AvSyncReport {
+        verdict: verdict.to_string(),
+        container_offset_ms,
+        estimated_offset_ms: Some(overall.round() as i64),
+        drift_ms,
+        matched_events: matches.len(),
+        confidence,
+        suggested_offset_ms,
+        segments,
     })
 }
 
+// Swaps the soundtrack: the video is stream-copied and the new audio is cut ("trim") or
+// repeated ("loop") to the video's length, or left as is ("none").
 #[tauri::command]
-async fn get_metadata_full(file_path: String) -> Result<serde_json::Value, String> {
-    info!("get_metadata_full called for: {}", file_path);
-    
-    let validated = validate_path(&file_path).ok_or("Invalid file path")?;
-    let path_str = validated.to_string_lossy().to_string();
-    info!("Validated path: {}", path_str);
-    
-    let ffprobe_path = get_ffprobe_path();
-    let output = new_command(&ffprobe_path)
-        .args(&[
-            "-v", "error",
-            "-print_format", "json",
-            "-show_entries", "format=format_name,duration,size,bit_rate:format_tags=title,artist,album,date,genre,track,comment:stream=codec_type,codec_name,width,height,r_frame_rate,bit_rate,pix_fmt,sample_rate,channels,channel_layout:stream_tags=language",
-            "-show_format",
-            "-show_streams",
-            &path_str
-        ])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("ffprobe failed: {}", stderr));
+async fn replace_audio(app: tauri::AppHandle, window: tauri::WebviewWindow, video: String, audio: String, options: Option<ReplaceAudioOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("replace_audio called: {} <- {}", video, audio);
+
+    let video_path = validate_path(&video).ok_or_else(|| msg("error.invalid_path"))?;
+    validate_path(&audio).ok_or_else(|| msg("error.invalid_path"))?;
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&video, &audio].into_iter().chain(&options.output_path).chain(&options.output_folder))?;
+    let video_summary = probe_media_summary(&video).await?;
+    let audio_summary = probe_media_summary(&audio).await?;
+    if video_summary.video_codec.is_none() {
+        return Err("The first file has no video stream".to_string());
+    }
+    if audio_summary.audio_codec.is_none() {
+        return Err("The audio file has no audio stream".to_string());
+    }
+    let video_duration = video_summary.duration.filter(|d| *d > 0.0);
+
+    let fit = options.fit.as_deref().unwrap_or("trim");
+    if !matches!(fit, "trim" | "loop" | "none") {
+        return Err(format!("Unknown fit mode: {}", fit));
+    }
+
+    let output_path_str = match options.output_path.clone().filter(|p| !p.is_empty()) {
+        Some(p) => p,
+        None => {
+            let stem = video_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let ext = video_path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "mp4".to_string());
+            job_output_dir(&video_path, options.output_folder.as_ref(), "replace_audio")
+                .join(format!("{}_new_audio.{}", stem, ext))
+                .to_string_lossy()
+                .to_string()
+        }
+    };
+
+    let mut args = vec!["-y".to_string(), "-i".to_string(), video.clone()];
+    if fit == "loop" {
+        args.extend(["-stream_loop".to_string(), "-1".to_string()]);
+    }
+    args.extend([
+        "-i".to_string(),
+        audio.clone(),
+        "-map".to_string(),
+        "0:v".to_string(),
+        "-map".to_string(),
+        "1:a:0".to_string(),
+        "-map".to_string(),
+        "0:s?".to_string(),
+        "-c:v".to_string(),
+        "copy".to_string(),
+        "-c:s".to_string(),
+        "copy".to_string(),
+    ]);
+
+    // Copying the audio keeps it bit-exact, but cuts can only land on audio frame boundaries
+    match options.audio_codec.as_deref().unwrap_or("aac") {
+        "copy" => args.extend(["-c:a".to_string(), "copy".to_string()]),
+        codec => {
+            let encoder = match codec {
+                "aac" => "aac",
+                "opus" => "libopus",
+                "mp3" => "libmp3lame",
+                "flac" => "flac",
+                "ac3" => "ac3",
+                other => return Err(format!("Unsupported audio codec: {}", other)),
+            };
+            args.extend(["-c:a".to_string(), encoder.to_string()]);
+            let bitrate = options.audio_bitrate.clone().unwrap_or_else(|| "192".to_string());
+            let (bitrate, _) = constrain_audio_bitrate(encoder, audio_summary.channels, &bitrate);
+            if let Some(bitrate) = bitrate {
+                args.extend(["-b:a".to_string(), bitrate]);
+            }
+        }
+    }
+    if fit != "none" {
+        match video_duration {
+            Some(duration) => args.extend(["-t".to_string(), format!("{:.3}", duration)]),
+            None => args.push("-shortest".to_string()),
+        }
+    }
+    args.push(output_path_str.clone());
+
+    let progress_duration = if fit == "none" { video_duration.into_iter().chain(audio_summary.duration).reduce(f64::max) } else { video_duration };
+    let run = run_ffmpeg_job(&app, "replace_audio", None, &args, &output_path_str, progress_duration).await?;
+    finish_ffmpeg_job(&app, "replace_audio", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Audio replacement cancelled".to_string())
+    } else {
+        Err(format!("Failed to replace audio: {}", run.stderr_tail))
     }
-    
-    let json_str = String::from_utf8_lossy(&output.stdout);
-    serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))
 }
 
+// Muted copy of a video: every stream except audio is stream-copied, so it takes seconds
 #[tauri::command]
-async fn get_image_info(file_path: String) -> Result<ImageInfo, String> {
-    info!("get_image_info called for: {}", file_path);
-    
-    let validated = validate_path(&file_path).ok_or("Invalid file path")?;
-    let path_str = validated.to_string_lossy().to_string();
-    
-    let ffprobe_path = get_ffprobe_path();
-    let output = new_command(&ffprobe_path)
-        .args(&[
-            "-v", "error",
-            "-print_format", "json",
-            "-show_entries", "format=format_name,size:stream=codec_name,width,height,pix_fmt,color_space,color_primaries,bits_per_raw_sample,bits_per_sample",
-            &path_str
-        ])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
-    
-    if !output.status.success() {
-        return Err("ffprobe failed".to_string());
+async fn remove_audio(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, output_folder: Option<String>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("remove_audio called for: {}", input);
+
+    check_scope(&app, [&input].into_iter().chain(&output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.video_codec.is_none() {
+        return Err("This file has no video stream".to_string());
     }
-    
-    let json_str = String::from_utf8_lossy(&output.stdout);
-    let data: serde_json::Value = serde_json::from_str(&json_str)
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-    
-    let streams = data.get("streams").and_then(|s| s.as_array());
-    let stream = streams.and_then(|s| s.first());
-    
-    let format = data.get("format").and_then(|f| f.as_object());
-    
-    let width = stream.and_then(|s| s.get("width")).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-    let height = stream.and_then(|s| s.get("height")).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-    let codec = stream.and_then(|s| s.get("codec_name")).and_then(|v| v.as_str()).unwrap_or("").to_string();
-    let pixel_format = stream.and_then(|s| s.get("pix_fmt")).and_then(|v| v.as_str()).unwrap_or("").to_string();
-    let color_space = stream.and_then(|s| s.get("color_space")).or_else(|| stream.and_then(|s| s.get("color_primaries"))).and_then(|v| v.as_str()).unwrap_or("").to_string();
-    let bit_depth = stream.and_then(|s| s.get("bits_per_raw_sample")).or_else(|| stream.and_then(|s| s.get("bits_per_sample"))).and_then(|v| v.as_str()).unwrap_or("").to_string();
-    let format_name = format.and_then(|f| f.get("format_name")).and_then(|v| v.as_str()).unwrap_or("").to_string();
-    let mut size_bytes = format.and_then(|f| f.get("size")).and_then(|v| v.as_str()).and_then(|s| s.parse::<u64>().ok()).unwrap_or(0);
-    
-    let metadata = std::fs::metadata(&validated).ok();
-    if size_bytes == 0 {
-        size_bytes = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = input_path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "mp4".to_string());
+    let output_path_str = job_output_dir(&input_path, output_folder.as_ref(), "remove_audio")
+        .join(format!("{}_muted.{}", stem, ext))
+        .to_string_lossy()
+        .to_string();
+
+    let args: Vec<String> = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        input.clone(),
+        "-map".to_string(),
+        "0".to_string(),
+        "-map".to_string(),
+        "-0:a".to_string(),
+        "-an".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+        output_path_str.clone(),
+    ];
+
+    let run = run_ffmpeg_job(&app, "remove_audio", None, &args, &output_path_str, summary.duration).await?;
+    finish_ffmpeg_job(&app, "remove_audio", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Muting cancelled".to_string())
+    } else {
+        Err(format!("Failed to remove audio: {}", run.stderr_tail))
     }
-    
-    let mtime_ms = metadata
-        .and_then(|m| m.modified().ok())
-        .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis() as u64)
-        .unwrap_or(0);
-    
-    Ok(ImageInfo {
-        width,
-        height,
-        codec,
-        pixel_format,
-        color_space,
-        bit_depth,
-        format: format_name,
-        size_bytes,
-        mtime_ms,
-    })
 }
 
-#[tauri::command]
-async fn save_metadata(file_path: String, metadata: serde_json::Value) -> Result<(), String> {
-    info!("save_metadata called for: {}", file_path);
-    
-    let validated = validate_path(&file_path).ok_or("Invalid file path")?;
-    let path_str = validated.to_string_lossy().to_string();
-    
-    // Build metadata arguments
-    let mut args = vec![
-        "-y".to_string(),
+// Mixes a music track under a video's own audio, for tutorials and montages. The music
+// is looped or cut to the video's length and faded out at the end; with ducking, a
+// sidechain compressor keyed by the original audio pulls the music down under speech.
+// The video is stream-copied.
+#[tauri::command]
+async fn mix_background_music(app: tauri::AppHandle, window: tauri::WebviewWindow, video: String, music: String, options: Option<MusicMixOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("mix_background_music called: {} + {}", video, music);
+
+    let video_path = validate_path(&video).ok_or_else(|| msg("error.invalid_path"))?;
+    validate_path(&music).ok_or_else(|| msg("error.invalid_path"))?;
+    let options = options.unwrap_or_default();
+    check_scope(&app, [&video, &music].into_iter().chain(&options.output_folder))?;
+    let video_summary = probe_media_summary(&video).await?;
+    let music_summary = probe_media_summary(&music).await?;
+    if video_summary.video_codec.is_none() {
+        return Err("The first file has no video stream".to_string());
+    }
+    if music_summary.audio_codec.is_none() {
+        return Err("The music file has no audio stream".to_string());
+    }
+    let duration = video_summary.duration.filter(|d| *d > 0.0).ok_or("Could not determine video duration")?;
+
+    let volume = options.music_volume.unwrap_or(0.25);
+    if !volume.is_finite() || !(0.0..=1.0).contains(&volume) {
+        return Err("music_volume must be between 0 and 1".to_string());
+    }
+    let seconds = |value: Option<f64>, default: f64, name: &str| -> Result<f64, String> {
+        match value.unwrap_or(default) {
+            v if !v.is_finite() || v < 0.0 => Err(format!("{} must be 0 or more", name)),
+            v => Ok(v.min(duration / 2.0)),
+        }
+    };
+    let fade_in = seconds(options.fade_in_seconds, 0.0, "fade_in_seconds")?;
+    let fade_out = seconds(options.fade_out_seconds, 3.0, "fade_out_seconds")?;
+
+    let mut music_chain = format!(
+        "[1:a:0]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo,atrim=duration={:.3},asetpts=PTS-STARTPTS,volume={:.3}",
+        duration, volume
+    );
+    if fade_in > 0.0 {
+        music_chain.push_str(&format!(",afade=t=in:st=0:d={:.3}", fade_in));
+    }
+    if fade_out > 0.0 {
+        music_chain.push_str(&format!(",afade=t=out:st={:.3}:d={:.3}", duration - fade_out, fade_out));
+    }
+    let filter = if video_summary.audio_codec.is_some() {
+        let voice = "[0:a:0]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo";
+        if options.ducking.unwrap_or(true) {
+            format!(
+                "{}[music];{},asplit=2[voice][key];[music][key]sidechaincompress=threshold=0.03:ratio=8:attack=20:release=400[ducked];[voice][ducked]amix=inputs=2:duration=first:normalize=0[a]",
+                music_chain, voice
+            )
+        } else {
+            format!("{}[music];{}[voice];[voice][music]amix=inputs=2:duration=first:normalize=0[a]", music_chain, voice)
+        }
+    } else {
+        // Nothing to mix with, the music becomes the soundtrack
+        format!("{}[a]", music_chain)
+    };
+
+    let stem = video_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = video_path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "mp4".to_string());
+    let output_path_str = job_output_dir(&video_path, options.output_folder.as_ref(), "music_mix")
+        .join(format!("{}_with_music.{}", stem, ext))
+        .to_string_lossy()
+        .to_string();
+
+    let mut args = vec!["-y".to_string(), "-i".to_string(), video.clone()];
+    if options.loop_music.unwrap_or(true) {
+        args.extend(["-stream_loop".to_string(), "-1".to_string()]);
+    }
+    args.extend([
         "-i".to_string(),
-        path_str.clone(),
-        "-c".to_string(),
+        music.clone(),
+        "-filter_complex".to_string(),
+        filter,
+        "-map".to_string(),
+        "0:v".to_string(),
+        "-map".to_string(),
+        "[a]".to_string(),
+        "-map".to_string(),
+        "0:s?".to_string(),
+        "-c:v".to_string(),
         "copy".to_string(),
-    ];
-    
-    // Add metadata
-    if let Some(title) = metadata.get("title").and_then(|v| v.as_str()) {
-        args.push("-metadata".to_string());
-        args.push(format!("title={}", title));
+        "-c:s".to_string(),
+        "copy".to_string(),
+        "-c:a".to_string(),
+        "aac".to_string(),
+    ]);
+    let bitrate = options.audio_bitrate.clone().unwrap_or_else(|| "192".to_string());
+    if let (Some(bitrate), _) = constrain_audio_bitrate("aac", Some(2), &bitrate) {
+        args.extend(["-b:a".to_string(), bitrate]);
     }
-    if let Some(artist) = metadata.get("artist").and_then(|v| v.as_str()) {
-        args.push("-metadata".to_string());
-        args.push(format!("artist={}", artist));
+    args.extend(["-t".to_string(), format!("{:.3}", duration), output_path_str.clone()]);
+
+    let run = run_ffmpeg_job(&app, "music_mix", None, &args, &output_path_str, Some(duration)).await?;
+    finish_ffmpeg_job(&app, "music_mix", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Music mix cancelled".to_string())
+    } else {
+        Err(format!("Failed to mix in the music: {}", run.stderr_tail))
     }
-    if let Some(album) = metadata.get("album").and_then(|v| v.as_str()) {
-        args.push("-metadata".to_string());
-        args.push(format!("album={}", album));
+}
+
+// ISO 639-2 code for a stream language tag. MP4 only stores three-letter codes, so the
+// common two-letter ones are mapped and anything unrecognised becomes "und".
+fn iso639_2(code: Option<&str>) -> String {
+    let code = code.unwrap_or("").trim().to_lowercase();
+    if code.len() == 3 && code.chars().all(|c| c.is_ascii_lowercase()) {
+        return code;
     }
-    if let Some(year) = metadata.get("year").and_then(|v| v.as_str()) {
-        args.push("-metadata".to_string());
-        args.push(format!("date={}", year));
+    match code.as_str() {
+        "en" => "eng", "es" => "spa", "fr" => "fra", "de" => "deu", "it" => "ita",
+        "pt" => "por", "nl" => "nld", "sv" => "swe", "no" => "nor", "da" => "dan",
+        "fi" => "fin", "pl" => "pol", "ru" => "rus", "uk" => "ukr", "cs" => "ces",
+        "tr" => "tur", "el" => "ell", "ar" => "ara", "he" => "heb", "hi" => "hin",
+        "ja" => "jpn", "ko" => "kor", "zh" => "zho", "th" => "tha", "vi" => "vie",
+        _ => "und",
     }
-    if let Some(genre) = metadata.get("genre").and_then(|v| v.as_str()) {
-        args.push("-metadata".to_string());
-        args.push(format!("genre={}", genre));
+    .to_string()
+}
+
+// Adds an audio-description track and caption files to a video without re-encoding it.
+// The AD track is flagged visual_impaired (never default) and SDH captions
+// hearing_impaired, so players and accessibility checkers pick them up.
+#[tauri::command]
+async fn mux_accessibility_tracks(app: tauri::AppHandle, window: tauri::WebviewWindow, input: String, options: AccessibilityMuxOptions) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("mux_accessibility_tracks called for: {}", input);
+
+    check_scope(&app, [&input].into_iter()
+        .chain(&options.audio_description)
+        .chain(options.captions.iter().flatten().map(|c| &c.path))
+        .chain(&options.output_path)
+        .chain(&options.output_folder))?;
+    let input_path = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let captions = options.captions.clone().unwrap_or_default();
+    if options.audio_description.is_none() && captions.is_empty() {
+        return Err("Choose an audio description track or at least one caption file".to_string());
     }
-    if let Some(track) = metadata.get("track").and_then(|v| v.as_str()) {
-        args.push("-metadata".to_string());
-        args.push(format!("track={}", track));
+    if let Some(ad) = &options.audio_description {
+        validate_path(ad).ok_or_else(|| msg("error.invalid_path"))?;
+        if !has_audio_stream(ad).await? {
+            return Err("The audio description file has no audio stream".to_string());
+        }
     }
-    if let Some(comment) = metadata.get("comment").and_then(|v| v.as_str()) {
-        args.push("-metadata".to_string());
-        args.push(format!("comment={}", comment));
+    for caption in &captions {
+        validate_path(&caption.path).ok_or_else(|| msg("error.invalid_path"))?;
     }
-    
-    // Output path
-    let parent = validated.parent().map(|p| p.to_path_buf());
-    let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
-    let ext = validated.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
-    let temp_path = parent.map(|p| p.join(format!("{}_temp.{}", stem, ext))).unwrap_or_else(|| PathBuf::from("temp_output.mp4"));
-    args.push(temp_path.to_string_lossy().to_string());
-    
-    let ffmpeg_path = get_ffmpeg_path();
-    let output = new_command(&ffmpeg_path)
-        .args(&args)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("ffmpeg failed: {}", stderr));
+
+    let summary = probe_media_summary(&input).await?;
+    if summary.video_codec.is_none() {
+        return Err("This file has no video stream".to_string());
+    }
+
+    let ext = input_path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .filter(|e| matches!(e.as_str(), "mp4" | "m4v" | "mov" | "mkv"))
+        .unwrap_or_else(|| "mkv".to_string());
+    let is_mp4 = ext != "mkv";
+    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_path_str = match options.output_path.as_ref().filter(|p| !p.is_empty()) {
+        Some(path) => path.clone(),
+        None => job_output_dir(&input_path, options.output_folder.as_ref(), "accessibility")
+            .join(format!("{}_accessible.{}", stem, ext))
+            .to_string_lossy()
+            .to_string(),
+    };
+
+    // Existing audio is kept as is; existing subtitles too, except bitmap ones MP4 can't hold
+    let probe = probe_metadata_full(input.clone()).await?;
+    let streams = probe.get("streams").and_then(|s| s.as_array()).cloned().unwrap_or_default();
+    let audio_count = streams
+        .iter()
+        .filter(|s| s.get("codec_type").and_then(|t| t.as_str()) == Some("audio"))
+        .count();
+    let kept_subtitles: Vec<usize> = streams
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.get("codec_type").and_then(|t| t.as_str()) == Some("subtitle"))
+        .filter(|(_, s)| !is_mp4 || !is_image_subtitle(s.get("codec_name").and_then(|c| c.as_str()).unwrap_or("")))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut args: Vec<String> = vec!["-y".to_string(), "-i".to_string(), input.clone()];
+    if let Some(ad) = &options.audio_description {
+        args.extend(["-i".to_string(), ad.clone()]);
+    }
+    for caption in &captions {
+        args.extend(["-i".to_string(), caption.path.clone()]);
+    }
+
+    args.extend(["-map".to_string(), "0:v".to_string(), "-map".to_string(), "0:a?".to_string()]);
+    let mut next_input = 1;
+    if options.audio_description.is_some() {
+        args.extend(["-map".to_string(), format!("{}:a:0", next_input)]);
+        next_input += 1;
+    }
+    for index in &kept_subtitles {
+        args.extend(["-map".to_string(), format!("0:{}", index)]);
+    }
+    for offset in 0..captions.len() {
+        args.extend(["-map".to_string(), format!("{}:s:0", next_input + offset)]);
+    }
+
+    args.extend(["-c".to_string(), "copy".to_string()]);
+    if is_mp4 {
+        args.extend(["-c:s".to_string(), "mov_text".to_string()]);
+    }
+
+    if let Some(language) = options.main_audio_language.as_deref() {
+        for index in 0..audio_count {
+            args.extend([format!("-metadata:s:a:{}", index), format!("language={}", iso639_2(Some(language)))]);
+        }
+    }
+    if options.audio_description.is_some() {
+        let index = audio_count;
+        if is_mp4 {
+            // Description tracks are often WAV, which MP4 can't carry
+            args.extend([format!("-c:a:{}", index), "aac".to_string(), format!("-b:a:{}", index), "192k".to_string()]);
+        }
+        args.extend([
+            format!("-metadata:s:a:{}", index),
+            format!("language={}", iso639_2(options.audio_description_language.as_deref().or(options.main_audio_language.as_deref()))),
+            format!("-metadata:s:a:{}", index),
+            "title=Audio Description".to_string(),
+            format!("-disposition:a:{}", index),
+            "visual_impaired".to_string(),
+        ]);
+        if audio_count > 0 {
+            args.extend(["-disposition:a:0".to_string(), "default".to_string()]);
+        }
+    }
+
+    for (offset, caption) in captions.iter().enumerate() {
+        let index = kept_subtitles.len() + offset;
+        let (flag, title) = match caption.kind.as_deref() {
+            Some("sdh") => (Some("hearing_impaired"), Some("SDH")),
+            Some("forced") => (Some("forced"), Some("Forced")),
+            _ => (None, None),
+        };
+        let disposition = match (caption.default.unwrap_or(false), flag) {
+            (true, Some(flag)) => format!("default+{}", flag),
+            (true, None) => "default".to_string(),
+            (false, Some(flag)) => flag.to_string(),
+            (false, None) => "0".to_string(),
+        };
+        args.extend([
+            format!("-metadata:s:s:{}", index),
+            format!("language={}", iso639_2(caption.language.as_deref())),
+            format!("-disposition:s:{}", index),
+            disposition,
+        ]);
+        if let Some(title) = title {
+            args.extend([format!("-metadata:s:s:{}", index), format!("title={}", title)]);
+        }
+    }
+
+    if is_mp4 {
+        args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+    }
+    args.push(output_path_str.clone());
+
+    let run = run_ffmpeg_job(&app, "accessibility", None, &args, &output_path_str, summary.duration).await?;
+    finish_ffmpeg_job(&app, "accessibility", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Muxing cancelled".to_string())
+    } else {
+        Err(format!("Failed to add accessibility tracks: {}", run.stderr_tail))
     }
-    
-    // Replace original with temp file
-    std::fs::rename(&temp_path, &validated).map_err(|e| format!("Failed to replace file: {}", e))?;
-    
-    Ok(())
 }
 
 // ============================================================================
-// Encoding Commands
+// Media Processing Commands
 // ============================================================================
 
 #[tauri::command]
-async fn start_encode(app: tauri::AppHandle, options: EncodeOptions) -> Result<(), String> {
-    info!("start_encode called with options: {:?}", options);
-    
-    let state = app.state::<Arc<AppState>>();
-    
-    let ffmpeg_path = get_ffmpeg_path();
+async fn get_audio_waveform(app: tauri::AppHandle, file_path: String, mode: Option<String>, width: Option<u32>, height: Option<u32>, palette: Option<String>, palette_color: Option<String>) -> Result<String, String> {
+    info!("get_audio_waveform called for: {}", file_path);
     
-    // Build output path
-    let input_path = PathBuf::from(&options.input);
-    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
-    let output_ext = options.format.clone();
-    let suffix = options.output_suffix.clone().unwrap_or_else(|| "_encoded".to_string());
-    let filename = format!("{}{}.{}", stem, suffix, output_ext);
+    scoped_path(&app, &file_path)?;
+    let has_audio = has_audio_stream(&file_path).await?;
+    if !has_audio {
+        return Err("No audio stream found in file".to_string());
+    }
+    let w = width.unwrap_or(800);
+    let h = height.unwrap_or(120);
+    let p = palette.unwrap_or_else(|| "heatmap".to_string());
+    let pc = palette_color.unwrap_or_else(|| "63f1af".to_string());
+    let m = mode.unwrap_or_else(|| "waveform".to_string());
     
-    let output_path = if let Some(folder) = &options.output_folder {
-        if !folder.is_empty() {
-            PathBuf::from(folder).join(&filename)
-        } else {
-            input_path.parent().map(|p| p.join(&filename)).unwrap_or_else(|| PathBuf::from(&filename))
-        }
+    let filter = if m == "spectrogram" {
+        format!("[0:a]showspectrumpic=s={}x{}:legend=0:color=rainbow:scale=log", w, h)
+    } else if p == "heatmap" {
+        format!("[0:a]aformat=channel_layouts=mono,showwavespic=s={}x{}:colors=white:scale=log,format=gray,r='if(lte(val,128),0,2*(val-128))':g='if(lte(val,128),2*val,255-2*(val-128))':b='if(lte(val,128),255-2*val,0)'", w, h)
+    } else if p == "accent" {
+        format!("[0:a]aformat=channel_layouts=mono,showwavespic=s={}x{}:colors=0x{}:scale=log", w, h, pc.replace("#", ""))
     } else {
-        input_path.parent().map(|p| p.join(&filename)).unwrap_or_else(|| PathBuf::from(&filename))
+        format!("[0:a]aformat=channel_layouts=mono,showwavespic=s={}x{}:colors=white:scale=log", w, h)
     };
     
-    let output_path_str = output_path.to_string_lossy().to_string();
-    
-    // Build FFmpeg arguments
-    let mut args = vec![
+    let args = vec![
+        "-y".to_string(),
         "-i".to_string(),
-        options.input.clone(),
+        file_path,
+        "-filter_complex".to_string(),
+        filter,
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-f".to_string(),
+        "image2".to_string(),
+        "pipe:1".to_string(),
     ];
     
-    // Add external audio tracks
-    if let Some(audio_tracks) = &options.audio_tracks {
-        for track in audio_tracks {
-            if let Some(path) = &track.path {
-                args.push("-i".to_string());
-                args.push(path.clone());
-            }
-        }
-    }
+    let output = child_output(ffmpeg_command(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped()), None)
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
     
-    // Add external subtitle tracks
-    if let Some(subtitle_tracks) = &options.subtitle_tracks {
-        for track in subtitle_tracks {
-            if let Some(path) = &track.path {
-                args.push("-i".to_string());
-                args.push(path.clone());
-            }
-        }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        error!("FFmpeg waveform failed: stderr={}, stdout={}", stderr, stdout);
+        return Err(format!("FFmpeg failed to generate waveform: {}", stderr));
     }
     
-    args.push("-y".to_string());
-    args.push("-map".to_string());
-    args.push("0:v:0".to_string());
-    
-    // Audio mapping
-    if options.audio_codec.as_deref() == Some("none") {
-        args.push("-an".to_string());
-    } else {
-        args.push("-map".to_string());
-        args.push("0:a:0".to_string());
+    if output.stdout.is_empty() {
+        error!("FFmpeg waveform generated empty output");
+        return Err("FFmpeg generated empty waveform output".to_string());
     }
     
-    // Subtitle mapping
-    args.push("-map".to_string());
-    args.push("0:s?".to_string());
-    
-    // Video codec
-    if let Some(codec) = &options.codec {
-        if codec == "copy" {
-            args.push("-c:v".to_string());
-            args.push("copy".to_string());
-        } else {
-            let v_codec_map = HashMap::from([
-                ("h264", "libx264"),
-                ("h265", "libx265"),
-                ("vp9", "libvpx-vp9"),
-                ("h264_nvenc", "h264_nvenc"),
-                ("hevc_nvenc", "hevc_nvenc"),
-                ("h264_amf", "h264_amf"),
-                ("hevc_amf", "hevc_amf"),
-                ("h264_qsv", "h264_qsv"),
-                ("hevc_qsv", "hevc_qsv"),
-            ]);
-            
-            let v_codec = v_codec_map.get(codec.as_str()).unwrap_or(&"libx264");
-            args.push("-c:v".to_string());
-            args.push(v_codec.to_string());
-            
-            // Resolution scaling
-            if let Some(resolution) = &options.resolution {
-                if resolution != "source" {
-                    let scale_heights = HashMap::from([
-                        ("4320p", "4320"),
-                        ("2160p", "2160"),
-                        ("1080p", "1080"),
-                        ("720p", "720"),
-                        ("480p", "480"),
-                        ("360p", "360"),
-                    ]);
-                    if let Some(h) = scale_heights.get(resolution.as_str()) {
-                        args.push("-vf".to_string());
-                        args.push(format!("scale=-2:{}", h));
-                    }
-                }
-            }
-            
-            // Preset
-            if let Some(preset) = &options.preset {
-                args.push("-preset".to_string());
-                args.push(preset.clone());
-            }
-            
-            // Rate control
-            if options.rate_mode.as_deref() == Some("bitrate") {
-                if let Some(bitrate) = &options.bitrate {
-                    args.push("-b:v".to_string());
-                    args.push(format!("{}k", bitrate));
-                }
-            } else {
-                if let Some(crf) = options.crf {
-                    args.push("-crf".to_string());
-                    args.push(crf.to_string());
-                }
-            }
-            
-            // FPS
-            if let Some(fps) = &options.fps {
-                if fps != "source" {
-                    args.push("-r".to_string());
-                    args.push(fps.clone());
-                }
-            }
-        }
+    Ok(base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &output.stdout))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveformPeaks {
+    pub sample_rate: u32,
+    pub samples_per_pixel: u32,
+    pub duration: f64,
+    // Number of min/max pairs
+    pub length: usize,
+    // Peaks from -1.0 to 1.0 of the mono mix, one pair per samples_per_pixel samples
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub min: Vec<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub max: Vec<f32>,
+    // Binary form instead: base64 of interleaved min,max pairs as little-endian i16
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+// Most min/max pairs get_waveform_peaks returns; long files get more samples per pixel
+const MAX_WAVEFORM_PEAKS: usize = 1_000_000;
+
+// Reduces 16-bit samples to a min/max pair per samples_per_pixel samples. Should the
+// pairs outgrow max_len (an even number) anyway, because the duration was missing or
+// wrong, neighbouring pairs are merged and samples_per_pixel doubles.
+struct PeakReducer {
+    peaks: Vec<(i16, i16)>,
+    low: i16,
+    high: i16,
+    in_pixel: u32,
+    samples_per_pixel: u32,
+    max_len: usize,
+}
+
+impl PeakReducer {
+    fn new(samples_per_pixel: u32, max_len: usize) -> Self {
+        PeakReducer { peaks: Vec::new(), low: i16::MAX, high: i16::MIN, in_pixel: 0, samples_per_pixel, max_len }
     }
-    
-    // Audio codec
-    if let Some(audio_codec) = &options.audio_codec {
-        if audio_codec != "none" {
-            if audio_codec == "copy" {
-                args.push("-c:a".to_string());
-                args.push("copy".to_string());
-            } else {
-                let a_codec_map = HashMap::from([
-                    ("aac", "aac"),
-                    ("opus", "libopus"),
-                    ("mp3", "libmp3lame"),
-                    ("ac3", "ac3"),
-                    ("flac", "flac"),
-                    ("pcm_s16le", "pcm_s16le"),
-                ]);
-                
-                let a_codec = a_codec_map.get(audio_codec.as_str()).unwrap_or(&"aac");
-                args.push("-c:a".to_string());
-                args.push(a_codec.to_string());
-                
-                if let Some(bitrate) = &options.audio_bitrate {
-                    args.push("-b:a".to_string());
-                    args.push(bitrate.clone());
-                }
+
+    fn add(&mut self, sample: i16) {
+        if self.in_pixel == self.samples_per_pixel {
+            if self.peaks.len() < self.max_len {
+                self.push_pixel();
+            } else {
+                // The full pixel in progress carries on as the first half of a wider one
+                self.merge_pairs();
             }
         }
+        self.low = self.low.min(sample);
+        self.high = self.high.max(sample);
+        self.in_pixel += 1;
     }
-    
-    // Subtitle codec
-    if output_ext == "mp4" || output_ext == "mov" {
-        args.push("-c:s".to_string());
-        args.push("mov_text".to_string());
-    } else {
-        args.push("-c:s".to_string());
-        args.push("copy".to_string());
+
+    fn push_pixel(&mut self) {
+        self.peaks.push((self.low, self.high));
+        (self.low, self.high, self.in_pixel) = (i16::MAX, i16::MIN, 0);
     }
-    
-    // Threads
-    if let Some(threads) = options.threads {
-        if threads > 0 {
-            args.push("-threads".to_string());
-            args.push(threads.to_string());
+
+    fn merge_pairs(&mut self) {
+        self.peaks = self
+            .peaks
+            .chunks(2)
+            .map(|pair| (pair.iter().map(|p| p.0).min().unwrap_or(0), pair.iter().map(|p| p.1).max().unwrap_or(0)))
+            .collect();
+        self.samples_per_pixel = self.samples_per_pixel.saturating_mul(2);
+    }
+
+    fn finish(mut self) -> (Vec<(i16, i16)>, u32) {
+        if self.in_pixel > 0 {
+            if self.peaks.len() >= self.max_len {
+                self.merge_pairs();
+            }
+            self.push_pixel();
         }
+        (self.peaks, self.samples_per_pixel)
     }
-    
-    // Custom args
-    if let Some(custom_args) = &options.custom_args {
-        args.extend(custom_args.split_whitespace().map(|s| s.to_string()));
+}
+
+// Min/max peaks for drawing a zoomable waveform in the UI. ffmpeg decodes to 16-bit
+// mono PCM on stdout, which is reduced as it streams in so long files don't have to
+// fit in memory.
+#[tauri::command]
+async fn get_waveform_peaks(app: tauri::AppHandle, file_path: String, samples_per_pixel: Option<u32>, binary: Option<bool>) -> Result<WaveformPeaks, String> {
+    info!("get_waveform_peaks called for: {}", file_path);
+
+    scoped_path(&app, &file_path)?;
+    validate_path(&file_path).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&file_path).await?;
+    if summary.audio_codec.is_none() {
+        return Err("No audio stream found in file".to_string());
     }
-    
-    args.push(output_path_str.clone());
-    
-    info!("Running FFmpeg with args: {:?}", args);
-    
-    // Spawn FFmpeg process
-    let mut child = new_command(&ffmpeg_path)
-        .args(&args)
-        .stdout(Stdio::null())
+    let mut samples_per_pixel = samples_per_pixel.unwrap_or(256);
+    if !(1..=1_000_000).contains(&samples_per_pixel) {
+        return Err("samples_per_pixel must be between 1 and 1000000".to_string());
+    }
+    let sample_rate = summary.sample_rate.as_deref().and_then(|r| r.parse::<u32>().ok()).unwrap_or(44100);
+    // A fine zoom on a long file would be millions of pairs, so it is coarsened up front
+    if let Some(duration) = summary.duration.filter(|d| d.is_finite() && *d > 0.0) {
+        let total = (duration * sample_rate as f64).ceil() as u64;
+        let needed = total.div_ceil(MAX_WAVEFORM_PEAKS as u64).min(u32::MAX as u64) as u32;
+        samples_per_pixel = samples_per_pixel.max(needed);
+    }
+
+    let args = [
+        "-v", "error",
+        "-i", file_path.as_str(),
+        "-map", "0:a:0",
+        "-ac", "1",
+        "-ar", &sample_rate.to_string(),
+        "-c:a", "pcm_s16le",
+        "-f", "s16le",
+        "pipe:1",
+    ]
+    .map(String::from);
+    let mut child = ffmpeg_command(&args)
+        .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .kill_on_drop(true)
         .spawn()
-        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
-    
-    // Store process ID for later cancellation
-    let child_pid = child.id();
-    {
-        let mut pid = state.current_pid.lock().await;
-        *pid = child_pid;
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let _registration = ChildRegistration::new(child.id(), None);
+    let mut stdout = child.stdout.take().ok_or("ffmpeg produced no output")?;
+    let stderr_task = child.stderr.take().map(|mut stderr| {
+        tokio::spawn(async move {
+            let mut text = String::new();
+            let _ = tokio::io::AsyncReadExt::read_to_string(&mut stderr, &mut text).await;
+            text
+        })
+    });
+
+    let mut reducer = PeakReducer::new(samples_per_pixel, MAX_WAVEFORM_PEAKS);
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut carry: Option<u8> = None;
+    loop {
+        let n = tokio::io::AsyncReadExt::read(&mut stdout, &mut buf).await.map_err(|e| format!("Failed to read decoded audio: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        let mut bytes = &buf[..n];
+        // A sample can be split across two reads
+        if let Some(first) = carry.take() {
+            reducer.add(i16::from_le_bytes([first, bytes[0]]));
+            bytes = &bytes[1..];
+        }
+        let mut chunks = bytes.chunks_exact(2);
+        for pair in chunks.by_ref() {
+            reducer.add(i16::from_le_bytes([pair[0], pair[1]]));
+        }
+        carry = chunks.remainder().first().copied();
     }
-    {
-        let mut output_path = state.current_output_path.lock().await;
-        *output_path = Some(output_path_str.clone());
+    let (peaks, samples_per_pixel) = reducer.finish();
+
+    let status = child.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
+    if !status.success() {
+        let stderr = match stderr_task {
+            Some(task) => task.await.unwrap_or_default(),
+            None => String::new(),
+        };
+        error!("FFmpeg peak decoding failed: {}", stderr);
+        return Err(format!("FFmpeg failed to decode the audio: {}", stderr.trim()));
     }
-    
-    // Read stderr for progress
-    if let Some(stderr) = child.stderr.take() {
-        let app_handle = app.clone();
-        
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr);
-            let mut buf = Vec::new();
-            let mut duration_in_seconds: Option<f64> = None;
-            
-            // Pre-compile regex patterns for efficiency
-            let duration_re = regex::Regex::new(r"Duration:\s*(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
-            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
-            let speed_re = regex::Regex::new(r"speed=\s*(\d+\.?\d*)x").ok();
-            
-            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
-                if n == 0 { break; }
-                let line = String::from_utf8_lossy(&buf).to_string();
-                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n').to_string();
-                buf.clear();
-                
-                // Parse duration (only once)
-                if duration_in_seconds.is_none() {
-                    if let Some(ref re) = duration_re {
-                        if let Some(cap) = re.captures(&line) {
-                            let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                            let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                            let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                            duration_in_seconds = Some(h * 3600.0 + m * 60.0 + s);
-                            info!("Parsed duration: {}s", duration_in_seconds.unwrap());
-                        }
-                    }
-                }
-                
-                // Parse progress - emit even without duration (use 0% in that case)
-                if let Some(ref re) = time_re {
-                    if let Some(cap) = re.captures(&line) {
-                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let current_time = h * 3600.0 + m * 60.0 + s;
-                        
-                        // Calculate percent, defaulting to 0 if duration unknown
-                        // Round to integer for cleaner display
-                        let percent = match duration_in_seconds {
-                            Some(dur) if dur > 0.0 => ((current_time / dur * 100.0).min(99.0)).round() as u32,
-                            _ => 0
-                        };
-                        
-                        let speed = speed_re.as_ref()
-                            .and_then(|re| re.captures(&line))
-                            .and_then(|cap| cap.get(1))
-                            .map(|m| format!("{}x", m.as_str()))
-                            .unwrap_or_else(|| "N/A".to_string());
-                        
-                        let _ = app_handle.emit("encode-progress", serde_json::json!({
-                            "percent": percent,
-                            "time": format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32),
-                            "speed": speed
-                        }));
-                    }
-                }
-            }
+
+    let length = peaks.len();
+    let duration = summary.duration.unwrap_or(length as f64 * samples_per_pixel as f64 / sample_rate as f64);
+    if binary.unwrap_or(false) {
+        let bytes: Vec<u8> = peaks.iter().flat_map(|(low, high)| low.to_le_bytes().into_iter().chain(high.to_le_bytes())).collect();
+        return Ok(WaveformPeaks {
+            sample_rate,
+            samples_per_pixel,
+            duration,
+            length,
+            min: Vec::new(),
+            max: Vec::new(),
+            data: Some(base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes)),
         });
     }
+    let scale = |v: i16| (v as f32 / 32768.0 * 10000.0).round() / 10000.0;
+    Ok(WaveformPeaks {
+        sample_rate,
+        samples_per_pixel,
+        duration,
+        length,
+        min: peaks.iter().map(|(low, _)| scale(*low)).collect(),
+        max: peaks.iter().map(|(_, high)| scale(*high)).collect(),
+        data: None,
+    })
+}
+
+// Start of the loudest `length` seconds, from one RMS reading per second of audio.
+// Levels are summed as power so a single loud bang can't outweigh a loud chorus.
+async fn loudest_section_start(input: &str, length: f64) -> Result<f64, String> {
+    let log_path = std::env::temp_dir().join(format!("video_toolbox_preview_levels_{}.txt", uuid_like_seed(&[input.to_string()])));
+    let output = child_output(&mut ffmpeg_command([
+            "-hide_banner",
+            "-i",
+            input,
+            "-map",
+            "0:a:0",
+            "-af",
+            &format!(
+                "aresample=8000,asetnsamples=n=8000,astats=metadata=1:reset=1,ametadata=print:key=lavfi.astats.Overall.RMS_level:file={}",
+                filter_path(&log_path)
+            ),
+            "-f",
+            "null",
+            "-",
+        ]), None)
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+    let _ = std::fs::remove_file(&log_path);
+    if !output.status.success() {
+        return Err(format!("Failed to measure levels: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let power: Vec<f64> = log
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("lavfi.astats.Overall.RMS_level="))
+        .map(|db| db.parse::<f64>().ok().filter(|db| db.is_finite()).map(|db| 10f64.powf(db / 10.0)).unwrap_or(0.0))
+        .collect();
+    let window = (length.ceil() as usize).max(1);
+    if power.len() <= window {
+        return Ok(0.0);
+    }
+    let mut sum: f64 = power[..window].iter().sum();
+    let (mut best, mut best_start) = (sum, 0);
+    for start in 1..=power.len() - window {
+        sum += power[start + window - 1] - power[start - 1];
+        if sum > best {
+            best = sum;
+            best_start = start;
+        }
+    }
+    Ok(best_start as f64)
+}
+
+// A short low-bitrate snippet for listening checks, written to the temp folder.
+// position is "middle" (default), "loudest", "start" or a time in seconds.
+#[tauri::command]
+async fn make_audio_preview(app: tauri::AppHandle, input: String, duration: Option<f64>, position: Option<String>, format: Option<String>) -> Result<String, String> {
+    info!("make_audio_preview called for: {}", input);
+
+    scoped_path(&app, &input)?;
+    validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let summary = probe_media_summary(&input).await?;
+    if summary.audio_codec.is_none() {
+        return Err("No audio stream found in file".to_string());
+    }
+    let length = duration.unwrap_or(30.0);
+    if !length.is_finite() || !(1.0..=300.0).contains(&length) {
+        return Err("Preview duration must be between 1 and 300 seconds".to_string());
+    }
+    let (codec, bitrate, ext) = match format.as_deref().unwrap_or("mp3") {
+        "mp3" => ("libmp3lame", "96k", "mp3"),
+        "opus" => ("libopus", "48k", "opus"),
+        other => return Err(format!("Unsupported preview format: {}", other)),
+    };
+
+    let total = summary.duration.filter(|d| *d > 0.0);
+    let length = total.map(|t| length.min(t)).unwrap_or(length);
+    let start = match position.as_deref().unwrap_or("middle") {
+        "start" => 0.0,
+        "middle" => total.map(|t| (t - length) / 2.0).unwrap_or(0.0),
+        "loudest" => loudest_section_start(&input, length).await?,
+        seconds => seconds.parse::<f64>().ok().filter(|s| s.is_finite() && *s >= 0.0).ok_or_else(|| format!("Invalid preview position: {}", seconds))?,
+    };
+    let start = total.map(|t| start.min((t - length).max(0.0))).unwrap_or(start).max(0.0);
+    let fade = (length / 10.0).min(0.5);
+
+    let output_path = std::env::temp_dir().join(format!(
+        "video_toolbox_preview_{}.{}",
+        uuid_like_seed(&[input.clone(), format!("{:.3}-{:.3}", start, length)]),
+        ext
+    ));
+    let output_str = output_path.to_string_lossy().to_string();
+    let output = child_output(&mut ffmpeg_command([
+            "-y",
+            "-hide_banner",
+            "-ss",
+            &format!("{:.3}", start),
+            "-t",
+            &format!("{:.3}", length),
+            "-i",
+            &input,
+            "-map",
+            "0:a:0",
+            "-af",
+            &format!("afade=t=in:d={fade},afade=t=out:st={:.3}:d={fade}", (length - fade).max(0.0)),
+            "-c:a",
+            codec,
+            "-b:a",
+            bitrate,
+            "-map_metadata",
+            "-1",
+            &output_str,
+        ]), Some(&output_str))
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&output_path);
+        return Err(format!("Failed to render preview: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(output_str)
+}
+
+#[tauri::command]
+async fn get_video_thumbnails(app: tauri::AppHandle, file_path: String, duration: f64, count: Option<u32>) -> Result<ThumbnailResult, String> {
+    info!("get_video_thumbnails called for: {}", file_path);
     
-    // Wait for completion
-    let status = child.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
+    scoped_path(&app, &file_path)?;
+    let _ffmpeg_path = "ffmpeg".to_string();
     
-    // Clear process reference
-    {
-        let mut pid = state.current_pid.lock().await;
-        *pid = None;
+    // Get file size
+    let file_size_mb = std::fs::metadata(&file_path)
+        .map(|m| m.len() as f64 / (1024.0 * 1024.0))
+        .unwrap_or(0.0);
+    
+    // Adjust parameters based on file size
+    let (target_height, quality, max_count) = if file_size_mb > 600.0 {
+        (160, 6, 80)
+    } else if file_size_mb > 300.0 {
+        (180, 5, 100)
+    } else if file_size_mb > 120.0 {
+        (200, 4, 140)
+    } else if file_size_mb > 40.0 {
+        (220, 3, 180)
+    } else {
+        (240, 2, 300)
+    };
+    
+    let desired_count = count.unwrap_or(50).min(max_count).min(300);
+    let cols = 10;
+    let rows = (desired_count as f64 / cols as f64).ceil() as u32;
+    // Recalculate count to be exactly what the tile layout expects, to avoid FFmpeg getting stuck
+    let actual_count = cols * rows;
+    
+    let fps = (actual_count as f64) / duration;
+    
+    let tile_layout = format!("{}x{}", cols, rows);
+    let vf = format!("fps={},scale=-1:{},tile={}", fps, target_height, tile_layout);
+    
+    let args = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        file_path,
+        "-vf".to_string(),
+        vf,
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-q:v".to_string(),
+        quality.to_string(),
+        "-f".to_string(),
+        "image2".to_string(),
+        "pipe:1".to_string(),
+    ];
+    
+    let output = child_output(ffmpeg_command(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped()), None)
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("FFmpeg thumbnail generation failed: {}", stderr);
+        return Err(format!("FFmpeg failed to generate thumbnails: {}", stderr));
     }
-    {
-        let mut output_path = state.current_output_path.lock().await;
-        *output_path = None;
+    
+    if output.stdout.is_empty() {
+        return Err("FFmpeg produced no output".to_string());
     }
     
-    // Check cancellation
-    let is_cancelling = {
-        let cancel = state.is_cancelling.lock().await;
-        *cancel
+    Ok(ThumbnailResult {
+        data: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &output.stdout),
+        count: desired_count,
+        cols,
+        rows,
+        interval: duration / desired_count as f64,
+    })
+}
+
+#[tauri::command]
+async fn capture_screenshot_burst(app: tauri::AppHandle, options: ScreenshotBurstOptions) -> Result<Vec<String>, String> {
+    info!("capture_screenshot_burst called for: {} at {}s", options.input, options.timestamp);
+
+    check_scope(&app, [&options.input].into_iter().chain(&options.output_folder))?;
+    let validated = validate_path(&options.input).ok_or_else(|| msg("error.invalid_path"))?;
+    if !options.timestamp.is_finite() || options.timestamp < 0.0 {
+        return Err("Invalid timestamp".to_string());
+    }
+
+    let count = options.count.unwrap_or(9).clamp(1, 60);
+    let window = options.window_seconds.unwrap_or(1.0).clamp(0.0, 30.0);
+    let ext = match options.format.as_deref().map(|f| f.to_lowercase()) {
+        Some(f) if f == "jpg" || f == "jpeg" => "jpg",
+        Some(f) if f == "webp" => "webp",
+        _ => "png",
     };
-    
-    if is_cancelling {
-        let mut cancel = state.is_cancelling.lock().await;
-        *cancel = false;
-        let _ = app.emit("encode-cancelled", ());
-        
-        // Delete incomplete output
-        if output_path.exists() {
-            let _ = std::fs::remove_file(&output_path);
+
+    // Keep the burst inside the file so the last frames don't fail on EOF
+    let duration = probe_metadata(options.input.clone())
+        .await
+        .ok()
+        .and_then(|m| m.duration_seconds)
+        .unwrap_or(f64::MAX);
+    let start = (options.timestamp - window).max(0.0);
+    let end = (options.timestamp + window).min((duration - 0.05).max(0.0)).max(start);
+    let step = if count > 1 { (end - start) / (count - 1) as f64 } else { 0.0 };
+
+    let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "frame".to_string());
+    let output_dir = job_output_dir(&validated, options.output_folder.as_ref(), "screenshots");
+    std::fs::create_dir_all(&output_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+
+    let mut paths = Vec::new();
+    for index in 0..count {
+        let seconds = if count > 1 { start + step * index as f64 } else { options.timestamp.min(end) };
+        let output_path = output_dir.join(format!("{}_burst_{:02}_{:.3}s.{}", stem, index + 1, seconds, ext));
+        extract_frame_at(&options.input, seconds, &output_path, ext, None, None).await?;
+        paths.push(output_path.to_string_lossy().to_string());
+    }
+
+    Ok(paths)
+}
+
+#[tauri::command]
+async fn extract_frames(app: tauri::AppHandle, input: String, timestamps: Vec<f64>, format: Option<String>, quality: Option<u32>, chroma_subsampling: Option<String>, output_folder: Option<String>) -> Result<Vec<String>, String> {
+    info!("extract_frames called for: {} at {} timestamps", input, timestamps.len());
+
+    check_scope(&app, [&input].into_iter().chain(&output_folder))?;
+    let validated = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    if timestamps.is_empty() {
+        return Err("No timestamps given".to_string());
+    }
+    if let Some(bad) = timestamps.iter().find(|t| !t.is_finite() || **t < 0.0) {
+        return Err(format!("Invalid timestamp: {}", bad));
+    }
+    let ext = match format.as_deref().map(|f| f.to_lowercase()) {
+        Some(f) if f == "jpg" || f == "jpeg" => "jpg",
+        Some(f) if f == "webp" => "webp",
+        _ => "png",
+    };
+
+    let duration = probe_metadata(input.clone())
+        .await
+        .ok()
+        .and_then(|m| m.duration_seconds);
+    if let Some(duration) = duration {
+        if let Some(late) = timestamps.iter().find(|t| **t >= duration) {
+            return Err(format!("Timestamp {:.3}s is past the end of the video ({:.3}s)", late, duration));
         }
-        
-        return Ok(());
     }
-    
-    if status.success() {
-        let _ = app.emit("encode-complete", serde_json::json!({ "outputPath": output_path_str }));
-    } else {
-        let _ = app.emit("encode-error", serde_json::json!({ "message": format!("FFmpeg exited with code {:?}", status.code()) }));
+
+    let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "frame".to_string());
+    let output_dir = job_output_dir(&validated, output_folder.as_ref(), "screenshots");
+    std::fs::create_dir_all(&output_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+
+    let mut paths = Vec::new();
+    for seconds in timestamps {
+        let output_path = output_dir.join(format!("{}_frame_{:.3}s.{}", stem, seconds, ext));
+        extract_frame_at(&input, seconds, &output_path, ext, quality, chroma_subsampling.as_deref()).await?;
+        paths.push(output_path.to_string_lossy().to_string());
     }
-    
-    Ok(())
+
+    Ok(paths)
 }
 
+// Dumps every frame of a range (or a resampled rate) to a numbered image sequence
 #[tauri::command]
-async fn extract_audio(app: tauri::AppHandle, options: ExtractAudioOptions) -> Result<(), String> {
-    info!("extract_audio called for: {}", options.input);
-    
-    let state = app.state::<Arc<AppState>>();
-    let ffmpeg_path = get_ffmpeg_path();
-    
-    // Build output path
-    let input_path = PathBuf::from(&options.input);
-    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
-    let ext_map = HashMap::from([
-        ("mp3", "mp3"),
-        ("aac", "m4a"),
-        ("flac", "flac"),
-        ("wav", "wav"),
-        ("ogg", "ogg"),
-        ("opus", "opus"),
-    ]);
-    let ext = ext_map.get(options.format.as_str()).unwrap_or(&"mp3");
-    let filename = format!("{}_audio.{}", stem, ext);
-    
-    let output_path = if let Some(folder) = &options.output_folder {
-        if !folder.is_empty() {
-            PathBuf::from(folder).join(&filename)
-        } else {
-            input_path.parent().map(|p| p.join(&filename)).unwrap_or_else(|| PathBuf::from(&filename))
-        }
-    } else {
-        input_path.parent().map(|p| p.join(&filename)).unwrap_or_else(|| PathBuf::from(&filename))
+async fn video_to_frames(window: tauri::WebviewWindow, input: String, start: Option<f64>, end: Option<f64>, fps: Option<f64>, format: Option<String>, output_folder: Option<String>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    let app = window.app_handle().clone();
+    info!("video_to_frames called for: {}", input);
+
+    check_scope(&app, [&input].into_iter().chain(&output_folder))?;
+    let validated = validate_path(&input).ok_or_else(|| msg("error.invalid_path"))?;
+    let ext = match format.as_deref().map(|f| f.to_lowercase()) {
+        Some(f) if f == "jpg" || f == "jpeg" => "jpg",
+        Some(f) if f == "webp" => "webp",
+        Some(f) if f == "tif" || f == "tiff" => "tiff",
+        _ => "png",
     };
-    
-    let output_path_str = output_path.to_string_lossy().to_string();
-    
-    // Build args
-    let mut args = vec![
-        "-y".to_string(),
-        "-i".to_string(),
-        options.input.clone(),
-        "-vn".to_string(),
-    ];
-    
-    // Audio codec
-    let codec_map = HashMap::from([
-        ("mp3", ("libmp3lame", None)),
-        ("aac", ("aac", None)),
-        ("flac", ("flac", Some("compression_level"))),
-        ("wav", ("pcm_s16le", None)),
-        ("ogg", ("libvorbis", None)),
-        ("opus", ("libopus", None)),
-    ]);
-    
-    if let Some((codec, _)) = codec_map.get(options.format.as_str()) {
-        args.push("-c:a".to_string());
-        args.push(codec.to_string());
+
+    let duration = probe_media_summary(&input).await.ok().and_then(|s| s.duration);
+    let start = start.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(0.0);
+    let end = end.filter(|e| e.is_finite() && *e > start).or(duration);
+    if let Some(duration) = duration {
+        if start > 0.0 && start >= duration {
+            return Err(format!("Start {:.3}s is past the end of the video ({:.3}s)", start, duration));
+        }
     }
-    
-    // Sample rate
-    if let Some(sample_rate) = &options.sample_rate {
-        let allowed = ["44100", "48000", "96000"];
-        if allowed.contains(&sample_rate.as_str()) {
-            args.push("-ar".to_string());
-            args.push(sample_rate.clone());
+    if let Some(rate) = fps {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err("Invalid frame rate".to_string());
         }
     }
-    
-    // FLAC compression level
-    if options.format == "flac" {
-        if let Some(level) = &options.flac_level {
-            let allowed = ["0", "1", "2", "3", "4", "5", "6", "7", "8"];
-            if allowed.contains(&level.as_str()) {
-                args.push("-compression_level".to_string());
-                args.push(level.clone());
+
+    let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "frames".to_string());
+    let export_folder = job_output_dir(&validated, output_folder.as_ref(), "frames").join(format!("{}_frames", stem));
+    std::fs::create_dir_all(&export_folder).map_err(|e| format!("Failed to create export folder: {}", e))?;
+    let output_pattern = export_folder.join(format!("{}_%06d.{}", stem, ext)).to_string_lossy().to_string();
+
+    let mut args = vec!["-y".to_string()];
+    if start > 0.0 {
+        args.extend(["-ss".to_string(), format!("{:.3}", start)]);
+    }
+    args.extend(["-i".to_string(), input.clone()]);
+    if let Some(end) = end {
+        args.extend(["-t".to_string(), format!("{:.3}", end - start)]);
+    }
+    args.extend(["-map".to_string(), "0:v:0".to_string()]);
+    match fps {
+        Some(rate) => args.extend(["-vf".to_string(), format!("fps={}", rate)]),
+        // One image per decoded frame, no duplicates or drops from the muxer
+        None => args.extend(["-vsync".to_string(), "0".to_string()]),
+    }
+    match ext {
+        "jpg" => args.extend(["-q:v".to_string(), "2".to_string()]),
+        "webp" => args.extend(["-c:v".to_string(), "libwebp".to_string(), "-quality".to_string(), "95".to_string()]),
+        _ => {}
+    }
+    args.push(output_pattern);
+
+    let export_path = export_folder.to_string_lossy().to_string();
+    let run = run_ffmpeg_job(&app, "frames", None, &args, &export_path, end.map(|e| e - start)).await?;
+
+    if run.cancelled {
+        let _ = std::fs::remove_dir_all(&export_folder);
+    }
+    finish_ffmpeg_job(&app, "frames", &run, &export_path);
+
+    if run.cancelled {
+        Err("Frame export cancelled".to_string())
+    } else if run.success {
+        Ok(export_path)
+    } else {
+        Err(format!("Failed to export frames: {}", run.stderr_tail))
+    }
+}
+
+// Heuristic frame quality score in 0..1, plus the individual components.
+// Sharpness is the variance of a Laplacian, brightness prefers mid-grey exposure,
+// and skin-tone coverage is a cheap stand-in for "there is a face in shot".
+fn score_thumbnail_frame(img: &DynamicImage, time_seconds: f64) -> ThumbnailCandidate {
+    let gray = img.to_luma8();
+    let (w, h) = gray.dimensions();
+    if w < 3 || h < 3 {
+        return ThumbnailCandidate {
+            path: String::new(),
+            time_seconds,
+            score: 0.0,
+            sharpness: 0.0,
+            brightness: 0.0,
+            contrast: 0.0,
+            edge_density: 0.0,
+            skin_ratio: 0.0,
+        };
+    }
+
+    let pixel_count = (w * h) as f64;
+    let mean = gray.pixels().map(|p| p[0] as f64).sum::<f64>() / pixel_count;
+    let variance = gray.pixels().map(|p| (p[0] as f64 - mean).powi(2)).sum::<f64>() / pixel_count;
+    let std_dev = variance.sqrt();
+
+    let mut lap_sum = 0.0;
+    let mut lap_sq_sum = 0.0;
+    let mut edge_pixels = 0u64;
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let c = gray.get_pixel(x, y)[0] as f64;
+            let l = gray.get_pixel(x - 1, y)[0] as f64;
+            let r = gray.get_pixel(x + 1, y)[0] as f64;
+            let t = gray.get_pixel(x, y - 1)[0] as f64;
+            let b = gray.get_pixel(x, y + 1)[0] as f64;
+            let lap = l + r + t + b - 4.0 * c;
+            lap_sum += lap;
+            lap_sq_sum += lap * lap;
+            let gradient = ((r - l).powi(2) + (b - t).powi(2)).sqrt();
+            if gradient > 40.0 {
+                edge_pixels += 1;
             }
         }
     }
-    
-    // MP3 quality
-    if options.format == "mp3" && options.mp3_mode.as_deref() == Some("vbr") {
-        if let Some(quality) = &options.mp3_quality {
-            args.push("-q:a".to_string());
-            args.push(quality.clone());
-        }
-    } else if let Some(bitrate) = &options.bitrate {
-        args.push("-b:a".to_string());
-        args.push(bitrate.clone());
+    let inner = ((w - 2) * (h - 2)) as f64;
+    let lap_mean = lap_sum / inner;
+    let lap_variance = lap_sq_sum / inner - lap_mean * lap_mean;
+
+    let rgb = img.to_rgb8();
+    let skin_pixels = rgb
+        .pixels()
+        .filter(|p| {
+            let (r, g, b) = (p[0] as i32, p[1] as i32, p[2] as i32);
+            r > 95 && g > 40 && b > 20 && r > g && r > b && (r - g).abs() > 15 && r.max(g).max(b) - r.min(g).min(b) > 15
+        })
+        .count() as f64;
+
+    let sharpness = (lap_variance.max(0.0).ln_1p() / 8.0).min(1.0);
+    let brightness = 1.0 - ((mean - 128.0).abs() / 128.0);
+    let contrast = (std_dev / 64.0).min(1.0);
+    let edge_density = edge_pixels as f64 / inner;
+    // Too few edges is a flat frame, too many is noise or motion blur mush
+    let edge_score = (1.0 - ((edge_density - 0.12).abs() / 0.12)).clamp(0.0, 1.0);
+    let skin_ratio = skin_pixels / pixel_count;
+    let skin_score = (skin_ratio / 0.15).min(1.0);
+
+    let mut score = 0.40 * sharpness + 0.25 * brightness + 0.15 * contrast + 0.10 * edge_score + 0.10 * skin_score;
+    // Fade-to-black, title cards and other near-uniform frames make bad covers
+    if std_dev < 10.0 || !(20.0..=235.0).contains(&mean) {
+        score *= 0.1;
     }
-    
-    args.push(output_path_str.clone());
-    
-    // Spawn FFmpeg
-    let mut child = new_command(&ffmpeg_path)
-        .args(&args)
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
-    
-    // Store process reference
-    let child_pid = child.id();
-    {
-        let mut pid = state.current_pid.lock().await;
-        *pid = child_pid;
+
+    ThumbnailCandidate {
+        path: String::new(),
+        time_seconds,
+        score,
+        sharpness,
+        brightness,
+        contrast,
+        edge_density,
+        skin_ratio,
     }
-    {
-        let mut output_path = state.current_output_path.lock().await;
-        *output_path = Some(output_path_str.clone());
+}
+
+#[tauri::command]
+async fn suggest_thumbnails(app: tauri::AppHandle, options: BestThumbnailOptions) -> Result<Vec<ThumbnailCandidate>, String> {
+    info!("suggest_thumbnails called for: {}", options.input);
+
+    check_scope(&app, [&options.input].into_iter().chain(&options.output_folder))?;
+    let validated = validate_path(&options.input).ok_or_else(|| msg("error.invalid_path"))?;
+    let duration = probe_metadata(options.input.clone())
+        .await?
+        .duration_seconds
+        .filter(|d| *d > 0.0)
+        .ok_or("Could not determine video duration")?;
+
+    let candidate_count = options.candidates.unwrap_or(24).clamp(4, 120);
+    let top_k = options.top_k.unwrap_or(3).clamp(1, candidate_count);
+    let ext = match options.format.as_deref().map(|f| f.to_lowercase()) {
+        Some(f) if f == "png" => "png",
+        Some(f) if f == "webp" => "webp",
+        _ => "jpg",
+    };
+
+    // Skip the first and last 5% where intros, fades and end cards live
+    let span_start = duration * 0.05;
+    let span = duration * 0.90;
+    let interval = span / candidate_count as f64;
+
+    let mut sample_dir = std::env::temp_dir();
+    sample_dir.push(format!("video_toolbox_thumbs_{}", uuid_like_seed(std::slice::from_ref(&options.input))));
+    std::fs::create_dir_all(&sample_dir).map_err(|e| format!("Failed to create temp folder: {}", e))?;
+
+    // One decode pass at low resolution is plenty for scoring
+    let sample_pattern = sample_dir.join("cand_%04d.png").to_string_lossy().to_string();
+    let output = child_output(&mut ffmpeg_command([
+            "-y",
+            "-ss",
+            &format!("{:.3}", span_start),
+            "-i",
+            &options.input,
+            "-t",
+            &format!("{:.3}", span),
+            "-vf",
+            &format!("fps=1/{:.6},scale=320:-2", interval),
+            "-frames:v",
+            &candidate_count.to_string(),
+            &sample_pattern,
+        ]), None)
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_dir_all(&sample_dir);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to sample frames: {}", stderr));
     }
-    
-    // Read stderr for progress
-    if let Some(stderr) = child.stderr.take() {
-        let app_handle = app.clone();
-        
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr);
-            let mut buf = Vec::new();
-            let mut duration_in_seconds: Option<f64> = None;
-            
-            // Pre-compile regex patterns for efficiency
-            let duration_re = regex::Regex::new(r"Duration:\s*(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
-            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
-            
-            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
-                if n == 0 { break; }
-                let line = String::from_utf8_lossy(&buf).to_string();
-                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n').to_string();
-                buf.clear();
-                
-                // Parse duration (only once)
-                if duration_in_seconds.is_none() {
-                    if let Some(ref re) = duration_re {
-                        if let Some(cap) = re.captures(&line) {
-                            let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                            let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                            let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                            duration_in_seconds = Some(h * 3600.0 + m * 60.0 + s);
-                        }
-                    }
-                }
-                
-                // Parse progress - emit even without duration
-                if let Some(ref re) = time_re {
-                    if let Some(cap) = re.captures(&line) {
-                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let current_time = h * 3600.0 + m * 60.0 + s;
-                        
-                        // Round to integer for cleaner display
-                        let percent = match duration_in_seconds {
-                            Some(dur) if dur > 0.0 => ((current_time / dur * 100.0).min(99.0)).round() as u32,
-                            _ => 0
-                        };
-                        
-                        let _ = app_handle.emit("encode-progress", serde_json::json!({
-                            "percent": percent,
-                            "time": format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32),
-                            "speed": "N/A"
-                        }));
-                    }
-                }
+
+    let scan_dir = sample_dir.clone();
+    let mut scored = tokio::task::spawn_blocking(move || {
+        let mut results = Vec::new();
+        for index in 0..candidate_count {
+            let path = scan_dir.join(format!("cand_{:04}.png", index + 1));
+            if let Ok(img) = image::open(&path) {
+                results.push(score_thumbnail_frame(&img, span_start + interval * index as f64));
             }
-        });
+        }
+        results
+    })
+    .await
+    .map_err(|e| format!("Thumbnail scoring failed: {}", e))?;
+    let _ = std::fs::remove_dir_all(&sample_dir);
+
+    if scored.is_empty() {
+        return Err("No candidate frames could be sampled".to_string());
     }
-    
-    let status = child.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
-    
-    // Clear process reference
-    {
-        let mut pid = state.current_pid.lock().await;
-        *pid = None;
+    scored.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+
+    // Don't hand back three near-identical frames from the same shot
+    let min_gap = interval * 1.5;
+    let mut picked: Vec<ThumbnailCandidate> = Vec::new();
+    for candidate in scored {
+        if picked.len() as u32 >= top_k {
+            break;
+        }
+        if picked.iter().all(|p| (p.time_seconds - candidate.time_seconds).abs() >= min_gap) {
+            picked.push(candidate);
+        }
     }
-    {
-        let mut output_path = state.current_output_path.lock().await;
-        *output_path = None;
+
+    let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "video".to_string());
+    let output_dir = job_output_dir(&validated, options.output_folder.as_ref(), "thumbnails");
+    std::fs::create_dir_all(&output_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+
+    for (rank, candidate) in picked.iter_mut().enumerate() {
+        let output_path = output_dir.join(format!("{}_thumb_{}.{}", stem, rank + 1, ext));
+        extract_frame_at(&options.input, candidate.time_seconds, &output_path, ext, None, None).await?;
+        candidate.path = output_path.to_string_lossy().to_string();
     }
-    
-    let is_cancelling = {
-        let cancel = state.is_cancelling.lock().await;
-        *cancel
+
+    Ok(picked)
+}
+
+// ============================================================================
+// Repair Commands
+// ============================================================================
+
+// Walk the top-level ISO BMFF boxes (ftyp/moov/mdat/...). Stops at the first box
+// that runs past the end of the file, which is what a crashed recording looks like.
+fn scan_mp4_atoms(path: &std::path::Path) -> Vec<Mp4Atom> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut atoms = Vec::new();
+    let Ok(mut file) = std::fs::File::open(path) else {
+        return atoms;
     };
-    
-    if is_cancelling {
-        let mut cancel = state.is_cancelling.lock().await;
-        *cancel = false;
-        let _ = app.emit("encode-cancelled", ());
-        return Ok(());
+    let file_len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut offset = 0u64;
+
+    while offset + 8 <= file_len && atoms.len() < 1024 {
+        let mut header = [0u8; 8];
+        if file.seek(SeekFrom::Start(offset)).is_err() || file.read_exact(&mut header).is_err() {
+            break;
+        }
+        let kind_bytes = &header[4..8];
+        if !kind_bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            break;
+        }
+        let kind = String::from_utf8_lossy(kind_bytes).to_string();
+        let mut size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            if file.read_exact(&mut large).is_err() {
+                break;
+            }
+            size = u64::from_be_bytes(large);
+        } else if size == 0 {
+            size = file_len - offset;
+        }
+        if size < 8 {
+            break;
+        }
+        // A corrupt 64-bit size can point past u64::MAX, which is as truncated as it gets
+        let end = offset.checked_add(size).filter(|end| *end <= file_len);
+        atoms.push(Mp4Atom { kind, offset, size, truncated: end.is_none() });
+        let Some(end) = end else {
+            break;
+        };
+        offset = end;
     }
-    
-    if status.success() {
-        let _ = app.emit("encode-complete", serde_json::json!({ "outputPath": output_path_str }));
-    } else {
-        let _ = app.emit("encode-error", serde_json::json!({ "message": format!("FFmpeg exited with code {:?}", status.code()) }));
+
+    atoms
+}
+
+// (duration, has video, has audio) if ffprobe can make sense of the file at all
+async fn probe_playable(path: &str) -> Option<(Option<f64>, bool, bool)> {
+    let output = probe_output(ffprobe_command()
+        .args([
+            "-v", "error",
+            "-print_format", "json",
+            "-show_entries", "format=duration:stream=codec_type",
+            path,
+        ]))
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
     }
-    
-    Ok(())
+    let data: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let duration = data
+        .get("format")
+        .and_then(|f| f.get("duration"))
+        .and_then(|d| d.as_str())
+        .and_then(|d| d.parse::<f64>().ok());
+    let streams = data.get("streams").and_then(|s| s.as_array()).cloned().unwrap_or_default();
+    let has_type = |t: &str| streams.iter().any(|s| s.get("codec_type").and_then(|v| v.as_str()) == Some(t));
+    Some((duration, has_type("video"), has_type("audio")))
 }
 
-#[tauri::command]
-async fn trim_video(app: tauri::AppHandle, options: TrimVideoOptions) -> Result<(), String> {
-    info!("trim_video called for: {} ({}s - {}s)", options.input, options.start_seconds, options.end_seconds);
-    
-    let state = app.state::<Arc<AppState>>();
-    let ffmpeg_path = get_ffmpeg_path();
-    
-    let start = options.start_seconds.max(0.0);
-    let end = options.end_seconds.max(start + 1.0);
-    let duration = end - start;
-    
-    // Build output path
-    let input_path = PathBuf::from(&options.input);
-    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
-    let filename = format!("{}_trimmed.mp4", stem);
-    let output_path = if let Some(folder) = &options.output_folder {
-        if !folder.is_empty() {
-            PathBuf::from(folder).join(&filename)
-        } else {
-            input_path.parent().map(|p| p.join(&filename)).unwrap_or_else(|| PathBuf::from(&filename))
+fn repair_strategy_args(strategy: &str, input: &str, output: &str, is_mp4: bool) -> Vec<String> {
+    let mut args = vec!["-y".to_string()];
+    match strategy {
+        "genpts" => {
+            args.extend(["-fflags".to_string(), "+genpts+igndts".to_string()]);
         }
-    } else {
-        input_path.parent().map(|p| p.join(&filename)).unwrap_or_else(|| PathBuf::from(&filename))
-    };
-    let output_path_str = output_path.to_string_lossy().to_string();
-    
-    let args = vec![
-        "-y".to_string(),
-        "-ss".to_string(),
-        start.to_string(),
+        "ignore_errors" => {
+            args.extend([
+                "-err_detect".to_string(),
+                "ignore_err".to_string(),
+                "-fflags".to_string(),
+                "+genpts+igndts+discardcorrupt".to_string(),
+            ]);
+        }
+        _ => {}
+    }
+    args.extend([
         "-i".to_string(),
-        options.input.clone(),
-        "-t".to_string(),
-        duration.to_string(),
+        input.to_string(),
+        "-map".to_string(),
+        "0".to_string(),
         "-c".to_string(),
         "copy".to_string(),
-        output_path_str.clone(),
-    ];
-    
-    // Spawn FFmpeg
-    let mut child = new_command(&ffmpeg_path)
-        .args(&args)
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
-    
-    let child_pid = child.id();
-    {
-        let mut pid = state.current_pid.lock().await;
-        *pid = child_pid;
-    }
-    {
-        let mut output_path = state.current_output_path.lock().await;
-        *output_path = Some(output_path_str.clone());
+        "-ignore_unknown".to_string(),
+    ]);
+    // Writing the index up front doubles as a check that it was rebuilt in full
+    if is_mp4 {
+        args.extend(["-movflags".to_string(), "+faststart".to_string()]);
     }
-    
-    // Read stderr for progress
-    if let Some(stderr) = child.stderr.take() {
-        let app_handle = app.clone();
-        
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr);
-            let mut buf = Vec::new();
-            
-            // Pre-compile regex pattern
-            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
-            
-            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
-                if n == 0 { break; }
-                let line = String::from_utf8_lossy(&buf).to_string();
-                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n').to_string();
-                buf.clear();
-                
-                if let Some(ref re) = time_re {
-                    if let Some(cap) = re.captures(&line) {
-                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let current = h * 3600.0 + m * 60.0 + s;
+    args.push(output.to_string());
+    args
+}
 
-                        let percent = if duration > 0.0 {
-                            ((current / duration * 100.0).min(99.0)).round() as u32
-                        } else {
-                            0
-                        };
-                        
-                        let _ = app_handle.emit("encode-progress", serde_json::json!({
-                            "percent": percent,
-                            "time": format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32),
-                            "speed": "N/A"
-                        }));
-                    }
-                }
+#[tauri::command]
+async fn repair_video(app: tauri::AppHandle, options: RepairOptions) -> Result<RepairReport, String> {
+    info!("repair_video called for: {}", options.input);
+
+    check_scope(&app, [&options.input].into_iter().chain(&options.output_folder))?;
+    let validated = validate_path(&options.input).ok_or_else(|| msg("error.invalid_path"))?;
+    let ext = validated.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let is_mp4 = matches!(ext.as_str(), "mp4" | "mov" | "m4v" | "m4a" | "3gp");
+    let out_ext = if is_mp4 || ext == "mkv" || ext == "webm" { ext.clone() } else { "mkv".to_string() };
+
+    let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_dir = job_output_dir(&validated, options.output_folder.as_ref(), "repair");
+    let output_path = output_dir.join(format!("{}_repaired.{}", stem, out_ext));
+    let output_path_str = output_path.to_string_lossy().to_string();
+
+    let input_atoms = if is_mp4 { scan_mp4_atoms(&validated) } else { Vec::new() };
+
+    let strategies: Vec<&str> = match options.strategy.as_deref() {
+        Some("remux") => vec!["remux"],
+        Some("genpts") => vec!["genpts"],
+        Some("ignore_errors") => vec!["ignore_errors"],
+        _ => vec!["remux", "genpts", "ignore_errors"],
+    };
+
+    let mut attempts = Vec::new();
+    let mut used_strategy = None;
+    let mut probe_result = None;
+
+    for strategy in strategies {
+        let args = repair_strategy_args(strategy, &options.input, &output_path_str, is_mp4);
+        let output = child_output(&mut ffmpeg_command(&args), Some(&output_path_str))
+            .await
+            .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let last_line = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("").to_string();
+            attempts.push(RepairAttempt { strategy: strategy.to_string(), success: false, message: Some(last_line) });
+            continue;
+        }
+
+        // ffmpeg exiting cleanly isn't enough, the result has to probe with real streams
+        match probe_playable(&output_path_str).await {
+            Some((duration, has_video, has_audio)) if (has_video || has_audio) && duration.unwrap_or(0.0) > 0.0 => {
+                attempts.push(RepairAttempt { strategy: strategy.to_string(), success: true, message: None });
+                used_strategy = Some(strategy.to_string());
+                probe_result = Some((duration, has_video, has_audio));
+                break;
             }
-        });
+            _ => {
+                attempts.push(RepairAttempt {
+                    strategy: strategy.to_string(),
+                    success: false,
+                    message: Some("Output has no playable streams".to_string()),
+                });
+            }
+        }
     }
-    
-    let status = child.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
-    
-    {
-        let mut pid = state.current_pid.lock().await;
-        *pid = None;
+
+    if used_strategy.is_none() && output_path.exists() {
+        let _ = std::fs::remove_file(&output_path);
     }
-    {
-        let mut output_path = state.current_output_path.lock().await;
-        *output_path = None;
+
+    let output_atoms = if used_strategy.is_some() && is_mp4 { scan_mp4_atoms(&output_path) } else { Vec::new() };
+    let moov_recovered = output_atoms.iter().any(|a| a.kind == "moov" && !a.truncated);
+    let (duration_seconds, has_video, has_audio) = probe_result.unwrap_or((None, false, false));
+
+    Ok(RepairReport {
+        output_path: used_strategy.as_ref().map(|_| output_path_str),
+        strategy: used_strategy,
+        attempts,
+        input_atoms,
+        output_atoms,
+        moov_recovered,
+        duration_seconds,
+        has_video,
+        has_audio,
+    })
+}
+
+#[tauri::command]
+async fn recover_recording(app: tauri::AppHandle, options: RecoverRecordingOptions) -> Result<RecoveryResult, String> {
+    info!("recover_recording called for: {}", options.input);
+
+    check_scope(&app, [&options.input].into_iter().chain(&options.reference).chain(&options.output_folder))?;
+    let validated = validate_path(&options.input).ok_or_else(|| msg("error.invalid_path"))?;
+    let ext = validated.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let is_mp4 = matches!(ext.as_str(), "mp4" | "mov" | "m4v" | "3gp");
+    let stem = validated.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let output_dir = job_output_dir(&validated, options.output_folder.as_ref(), "repair");
+
+    let atoms = if is_mp4 { scan_mp4_atoms(&validated) } else { Vec::new() };
+    let has_moov = atoms.iter().any(|a| a.kind == "moov" && !a.truncated);
+
+    // MKV/FLV/TS recordings carry their own timing and only need a tolerant remux.
+    // MP4 with an intact moov is just a damaged index, the same story.
+    if !is_mp4 || has_moov {
+        let out_ext = if is_mp4 { ext.as_str() } else { "mkv" };
+        let output_path = output_dir.join(format!("{}_recovered.{}", stem, out_ext));
+        let output_path_str = output_path.to_string_lossy().to_string();
+        let args = repair_strategy_args("ignore_errors", &options.input, &output_path_str, is_mp4);
+        let output = child_output(&mut ffmpeg_command(&args), Some(&output_path_str))
+            .await
+            .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+        let playable = probe_playable(&output_path_str).await;
+        if !output.status.success() || playable.is_none() {
+            let _ = std::fs::remove_file(&output_path);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to recover recording: {}", stderr));
+        }
+        return Ok(RecoveryResult {
+            output_path: output_path_str,
+            method: "remux".to_string(),
+            duration_seconds: playable.and_then(|p| p.0),
+            message: None,
+        });
     }
-    
-    let is_cancelling = {
-        let cancel = state.is_cancelling.lock().await;
-        *cancel
-    };
-    
-    if is_cancelling {
-        let mut cancel = state.is_cancelling.lock().await;
-        *cancel = false;
-        let _ = app.emit("encode-cancelled", ());
-        return Ok(());
+
+    // Without a moov atom there is no sample table, so the only way back is to borrow
+    // codec parameters from a healthy file recorded with the same device and settings.
+    let reference = options
+        .reference
+        .as_ref()
+        .filter(|r| !r.is_empty())
+        .ok_or("This recording was never finalized (no moov atom). Select a working reference file recorded with the same device and settings to recover it.")?;
+    let reference_path = validate_path(reference).ok_or("Invalid reference file path")?;
+    if !scan_mp4_atoms(&reference_path).iter().any(|a| a.kind == "moov" && !a.truncated) {
+        return Err("The reference file is not a finalized MP4/MOV recording".to_string());
     }
-    
-    if status.success() {
-        let _ = app.emit("encode-complete", serde_json::json!({ "outputPath": output_path_str }));
-    } else {
-        let _ = app.emit("encode-error", serde_json::json!({ "message": format!("FFmpeg exited with code {:?}", status.code()) }));
+
+    let mut failures = Vec::new();
+    match recover_with_reference(&validated, reference, &output_dir, &stem, &ext).await {
+        Ok(result) => return Ok(result),
+        Err(e) => failures.push(e),
     }
-    
-    Ok(())
+
+    // untrunc also rebuilds the audio track, but it is an optional extra install
+    if let Some(untrunc_path) = find_untrunc() {
+        match recover_with_untrunc(&untrunc_path, &validated, reference, &output_dir, &stem, &ext).await {
+            Ok(result) => return Ok(result),
+            Err(e) => failures.push(e),
+        }
+    }
+
+    Err(format!("Failed to recover recording: {}", failures.join("; ")))
 }
 
-#[tauri::command]
-async fn cancel_encode(app: tauri::AppHandle) -> Result<(), String> {
-    info!("cancel_encode called");
-    
-    let state = app.state::<Arc<AppState>>();
-    
-    // Set cancelling flag
-    {
-        let mut cancel = state.is_cancelling.lock().await;
-        *cancel = true;
+// Rebuilds the video track of a moov-less MP4 with ffmpeg: the samples in mdat are
+// rewritten as a raw H.264/HEVC stream behind the reference file's parameter sets,
+// then remuxed at the reference frame rate
+async fn recover_with_reference(
+    input: &std::path::Path,
+    reference: &str,
+    output_dir: &std::path::Path,
+    stem: &str,
+    ext: &str,
+) -> Result<RecoveryResult, String> {
+    let output = probe_output(ffprobe_command()
+        .args([
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-show_entries", "stream=codec_name,r_frame_rate",
+            "-of", "csv=p=0",
+            reference,
+        ]))
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut fields = stdout.trim().split(',');
+    let codec = fields.next().unwrap_or("").to_string();
+    let fps = fields.next().and_then(parse_frame_rate).unwrap_or(30.0);
+    let hevc = match codec.as_str() {
+        "h264" => false,
+        "hevc" => true,
+        "" => return Err("The reference file has no video stream".to_string()),
+        other => return Err(format!("Recovering {} recordings needs untrunc", other)),
+    };
+    let raw_format = if hevc { "hevc" } else { "h264" };
+
+    // The bitstream filter puts the parameter sets in front of the first keyframe
+    let output = child_output(&mut ffmpeg_command([
+            "-v", "error",
+            "-i", reference,
+            "-map", "0:v:0",
+            "-c:v", "copy",
+            "-bsf:v", &format!("{}_mp4toannexb", raw_format),
+            "-frames:v", "1",
+            "-f", raw_format,
+            "-",
+        ]), None)
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let parameter_sets = annexb_parameter_sets(&output.stdout, hevc);
+    if parameter_sets.is_empty() {
+        return Err("Could not read the codec parameters of the reference file".to_string());
     }
-    
-    // Kill the process
-    let mut pid = state.current_pid.lock().await;
-    if let Some(child_pid) = *pid {
-        #[cfg(windows)]
-        {
-            let _ = Command::new("taskkill")
-                .args(&["/F", "/T", "/PID", &child_pid.to_string()])
-                .output()
-                .await;
+
+    let mdat = scan_mp4_atoms(input).into_iter().find(|a| a.kind == "mdat").ok_or("The recording has no media data")?;
+    let file_len = std::fs::metadata(input).map(|m| m.len()).unwrap_or(0);
+    // A size field of 1 means a 64-bit size follows the box type
+    let large_size = {
+        use std::io::{Read, Seek, SeekFrom};
+        let mut size_field = [0u8; 4];
+        let mut file = std::fs::File::open(input).map_err(|e| format!("Failed to read the recording: {}", e))?;
+        file.seek(SeekFrom::Start(mdat.offset)).and_then(|_| file.read_exact(&mut size_field)).is_ok()
+            && u32::from_be_bytes(size_field) == 1
+    };
+    // The size of an interrupted recording's mdat can be anything, so it only ever ends at the end of the file
+    let mdat_end = mdat.offset.checked_add(mdat.size).map_or(file_len, |end| end.min(file_len));
+    let mdat_start = (mdat.offset + if large_size { 16 } else { 8 }).min(mdat_end);
+
+    let raw_path = std::env::temp_dir().join(format!(
+        "video_toolbox_recover_{}.{}",
+        uuid_like_seed(&[input.to_string_lossy().to_string()]),
+        raw_format
+    ));
+    let scan_input = input.to_path_buf();
+    let scan_raw = raw_path.clone();
+    let nal_count = tokio::task::spawn_blocking(move || -> std::io::Result<u64> {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(&scan_raw)?);
+        std::io::Write::write_all(&mut out, &parameter_sets)?;
+        let count = salvage_nal_units(&scan_input, mdat_start, mdat_end, hevc, &mut out)?;
+        std::io::Write::flush(&mut out)?;
+        Ok(count)
+    })
+    .await
+    .map_err(|e| format!("Recovery task failed: {}", e))?
+    .map_err(|e| format!("Failed to read the recording: {}", e));
+    let nal_count = match nal_count {
+        Ok(count) if count > 0 => count,
+        Ok(_) => {
+            let _ = std::fs::remove_file(&raw_path);
+            return Err("No video frames were found in the recording".to_string());
         }
-        #[cfg(not(windows))]
-        {
-            let _ = Command::new("kill")
-                .arg(&child_pid.to_string())
-                .output()
-                .await;
+        Err(e) => {
+            let _ = std::fs::remove_file(&raw_path);
+            return Err(e);
         }
+    };
+    info!("recover_recording salvaged {} NAL units", nal_count);
+
+    let output_path = output_dir.join(format!("{}_recovered.{}", stem, ext));
+    let output_path_str = output_path.to_string_lossy().to_string();
+    let raw_path_str = raw_path.to_string_lossy().to_string();
+    let fps_arg = format!("{:.6}", fps);
+    let output = child_output(&mut ffmpeg_command([
+            "-y",
+            "-fflags", "+genpts",
+            "-framerate", &fps_arg,
+            "-f", raw_format,
+            "-i", &raw_path_str,
+            "-c", "copy",
+            "-movflags", "+faststart",
+            &output_path_str,
+        ]), Some(&output_path_str))
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e));
+    let _ = std::fs::remove_file(&raw_path);
+    let output = output?;
+
+    let playable = probe_playable(&output_path_str).await;
+    if !output.status.success() || !playable.map(|p| p.1).unwrap_or(false) {
+        let _ = std::fs::remove_file(&output_path);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg could not rebuild the video: {}", stderr.trim()));
     }
-    
-    // Delete incomplete output
-    let output_path = {
-        let path = state.current_output_path.lock().await;
-        path.clone()
+
+    Ok(RecoveryResult {
+        output_path: output_path_str,
+        method: "remux".to_string(),
+        duration_seconds: playable.and_then(|p| p.0),
+        message: Some("Recovered the video using codec parameters from the reference file. The audio track could not be rebuilt.".to_string()),
+    })
+}
+
+async fn recover_with_untrunc(
+    untrunc_path: &str,
+    input: &std::path::Path,
+    reference: &str,
+    output_dir: &std::path::Path,
+    stem: &str,
+    ext: &str,
+) -> Result<RecoveryResult, String> {
+    let output = child_output(new_command(untrunc_path)
+        .arg(reference)
+        .arg(input), None)
+        .await
+        .map_err(|e| format!("Failed to run untrunc: {}", e))?;
+
+    // untrunc writes next to the broken file, as "<name>_fixed.<ext>"
+    let fixed_candidates = [
+        PathBuf::from(format!("{}_fixed.{}", input.to_string_lossy(), ext)),
+        input.with_file_name(format!("{}_fixed.{}", stem, ext)),
+    ];
+    let fixed = fixed_candidates.iter().find(|p| p.exists()).cloned();
+    let Some(fixed) = fixed.filter(|_| output.status.success()) else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("untrunc could not recover the recording: {}", stderr.trim()));
     };
-    
-    if let Some(path_str) = output_path {
-        let path = PathBuf::from(&path_str);
-        if path.exists() {
-            let _ = std::fs::remove_file(&path);
-        }
+
+    let output_path = output_dir.join(format!("{}_recovered.{}", stem, ext));
+    if std::fs::rename(&fixed, &output_path).is_err() {
+        std::fs::copy(&fixed, &output_path).map_err(|e| format!("Failed to move recovered file: {}", e))?;
+        let _ = std::fs::remove_file(&fixed);
     }
-    
-    *pid = None;
-    
-    Ok(())
+    let output_path_str = output_path.to_string_lossy().to_string();
+    let duration_seconds = probe_playable(&output_path_str).await.and_then(|p| p.0);
+
+    Ok(RecoveryResult {
+        output_path: output_path_str,
+        method: "untrunc".to_string(),
+        duration_seconds,
+        message: Some("Recovered using codec parameters from the reference file".to_string()),
+    })
 }
 
+// Whether a byte can start a NAL unit worth keeping: slices, SEI, delimiters and
+// parameter sets, with H.264's nal_ref_idc matching the unit type
+fn plausible_nal_header(header: u8, hevc: bool) -> bool {
+    if header & 0x80 != 0 {
+        return false;
+    }
+    if hevc {
+        let kind = (header >> 1) & 0x3f;
+        return kind <= 21 || (32..=40).contains(&kind);
+    }
+    let referenced = header & 0x60 != 0;
+    match header & 0x1f {
+        1 => true,
+        5 | 7 | 8 => referenced,
+        6 | 9 => !referenced,
+        _ => false,
+    }
+}
 
-#[tauri::command]
-async fn video_to_gif(app: tauri::AppHandle, options: VideoToGifOptions) -> Result<(), String> {
-    info!("video_to_gif called for: {}", options.input);
-    
-    let app_state = app.state::<Arc<AppState>>().inner().clone();
-    let ffmpeg_path = get_ffmpeg_path();
-    
-    // Get original duration for progress tracking
-    let mut duration_secs = 100.0;
-    if let Ok(metadata) = get_metadata(options.input.clone()).await {
-        if let Some(ds) = metadata.duration_seconds {
-            duration_secs = ds;
+// SPS/PPS (and the HEVC VPS) out of an Annex B stream, each behind a 4-byte start code
+fn annexb_parameter_sets(data: &[u8], hevc: bool) -> Vec<u8> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            starts.push(i + 3);
+            i += 3;
+        } else {
+            i += 1;
         }
     }
 
-    // If a trim range is specified, use it for progress so percentages make sense
-    let mut effective_duration_secs = duration_secs;
-    if let (Some(start), Some(end)) = (options.start_seconds, options.end_seconds) {
-        if end > start {
-            effective_duration_secs = (end - start).max(0.1);
+    let mut out = Vec::new();
+    for (n, &start) in starts.iter().enumerate() {
+        let mut end = starts.get(n + 1).map(|next| next - 3).unwrap_or(data.len());
+        // A 4-byte start code leaves its leading zero on the previous unit
+        while end > start && data[end - 1] == 0 {
+            end -= 1;
         }
-    }
-    
-    // Build output path
-    let input_path = PathBuf::from(&options.input);
-    let stem = input_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
-    let filename = format!("{}_converted.gif", stem);
-    let output_path = if let Some(folder) = &options.output_folder {
-        if !folder.is_empty() {
-            PathBuf::from(folder).join(&filename)
+        let Some(&header) = data.get(start).filter(|_| end > start) else {
+            continue;
+        };
+        let keep = if hevc {
+            matches!((header >> 1) & 0x3f, 32..=34)
         } else {
-            input_path.parent().map(|p| p.join(&filename)).unwrap_or_else(|| PathBuf::from(&filename))
+            matches!(header & 0x1f, 7 | 8)
+        };
+        if keep {
+            out.extend_from_slice(&[0, 0, 0, 1]);
+            out.extend_from_slice(&data[start..end]);
         }
-    } else {
-        input_path.parent().map(|p| p.join(&filename)).unwrap_or_else(|| PathBuf::from(&filename))
+    }
+    out
+}
+
+// Reads a file through a movable window, so a multi-GB mdat can be scanned byte by
+// byte without loading all of it
+struct FileWindow {
+    file: std::fs::File,
+    start: u64,
+    buf: Vec<u8>,
+}
+
+impl FileWindow {
+    fn bytes(&mut self, pos: u64, len: usize) -> Option<&[u8]> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        if pos < self.start || pos + len as u64 > self.start + self.buf.len() as u64 {
+            self.file.seek(SeekFrom::Start(pos)).ok()?;
+            self.buf.clear();
+            (&mut self.file).take(len.max(4 << 20) as u64).read_to_end(&mut self.buf).ok()?;
+            self.start = pos;
+            if self.buf.len() < len {
+                return None;
+            }
+        }
+        let at = (pos - self.start) as usize;
+        Some(&self.buf[at..at + len])
+    }
+}
+
+// Largest NAL unit accepted when salvaging; anything bigger is taken as noise
+const MAX_SALVAGE_NAL_BYTES: u64 = 32 << 20;
+
+// Rewrites the length-prefixed NAL units of an mdat as Annex B. Audio chunks sit
+// between the video chunks; after one the scan resyncs a byte at a time and only
+// accepts a unit once the unit after it checks out as well. Returns the units written.
+fn salvage_nal_units(
+    path: &std::path::Path,
+    mdat_start: u64,
+    mdat_end: u64,
+    hevc: bool,
+    out: &mut impl std::io::Write,
+) -> std::io::Result<u64> {
+    let mut window = FileWindow { file: std::fs::File::open(path)?, start: 0, buf: Vec::new() };
+    let unit_at = |window: &mut FileWindow, pos: u64| -> Option<u64> {
+        let head = window.bytes(pos, 5)?;
+        let len = u32::from_be_bytes([head[0], head[1], head[2], head[3]]) as u64;
+        let fits = (2..=MAX_SALVAGE_NAL_BYTES).contains(&len) && pos + 4 + len <= mdat_end;
+        (fits && plausible_nal_header(head[4], hevc)).then_some(len)
     };
-    let output_path_str = output_path.to_string_lossy().to_string();
-    
-    let fps = options.fps.unwrap_or(15);
-    let scale = options.width.unwrap_or(480);
-    let speed = options.speed.unwrap_or(1.0);
 
-    let mut crop_filter = String::new();
-    if let Some(crop_val) = &options.crop {
-        let x = crop_val.get("x").and_then(|v| v.as_i64()).unwrap_or(0);
-        let y = crop_val.get("y").and_then(|v| v.as_i64()).unwrap_or(0);
-        let w = crop_val.get("w").and_then(|v| v.as_i64()).unwrap_or(0);
-        let h = crop_val.get("h").and_then(|v| v.as_i64()).unwrap_or(0);
-        if w > 0 && h > 0 {
-            crop_filter = format!(",crop={}:{}:{}:{}", w, h, x, y);
+    let mut pos = mdat_start;
+    let mut in_sync = true;
+    let mut written = 0u64;
+    while pos + 5 <= mdat_end {
+        let accepted = match unit_at(&mut window, pos) {
+            Some(len) if in_sync => Some(len),
+            Some(len) => {
+                let next = pos + 4 + len;
+                (next + 5 > mdat_end || unit_at(&mut window, next).is_some()).then_some(len)
+            }
+            None => None,
+        };
+        match accepted {
+            Some(len) => {
+                let Some(unit) = window.bytes(pos + 4, len as usize) else {
+                    break;
+                };
+                out.write_all(&[0, 0, 0, 1])?;
+                out.write_all(unit)?;
+                written += 1;
+                pos += 4 + len;
+                in_sync = true;
+            }
+            None => {
+                pos += 1;
+                in_sync = false;
+            }
         }
     }
+    Ok(written)
+}
 
-    let mut speed_filter = String::new();
-    if speed.is_finite() && (speed - 1.0).abs() > 0.00001 {
-        // speed up: setpts=PTS/<speed>
-        speed_filter = format!(",setpts=PTS/{}", speed);
+// ============================================================================
+// Disc Folders (VIDEO_TS / BDMV)
+// ============================================================================
+
+// Accepts the disc folder itself or its VIDEO_TS/BDMV subfolder
+fn find_disc_root(path: &std::path::Path) -> Option<(&'static str, PathBuf)> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_uppercase()).unwrap_or_default();
+    if name == "VIDEO_TS" {
+        return Some(("dvd", path.to_path_buf()));
     }
-    
-    // FFmpeg command to create a GIF
-    // Using a palettegen/paletteuse filter chain for better quality GIFs
-    // Order: crop -> speed -> fps -> scale -> palette
-    let filters = format!(
-        "[0:v]{}{}fps={},scale={}:-1:flags=lanczos[v];[v]split[v1][v2];[v1]palettegen=stats_mode=diff[p];[v2][p]paletteuse=dither=sierra2_4a[out]",
-        crop_filter,
-        speed_filter,
-        fps,
-        scale
-    );
-    
-    let mut args = vec!["-y".to_string()];
+    if name == "BDMV" {
+        return Some(("bluray", path.to_path_buf()));
+    }
+    for (kind, dir) in [("dvd", "VIDEO_TS"), ("bluray", "BDMV")] {
+        let candidate = path.join(dir);
+        if candidate.is_dir() {
+            return Some((kind, candidate));
+        }
+    }
+    None
+}
+
+// Looks a file up ignoring case, discs burned on Windows are not consistent about it
+fn find_case_insensitive(dir: &std::path::Path, name: &str) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| p.file_name().map(|n| n.to_string_lossy().eq_ignore_ascii_case(name)).unwrap_or(false))
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// DVD playback times are BCD hh:mm:ss:ff, the top two bits of ff give the frame rate
+fn dvd_time_seconds(bytes: &[u8]) -> f64 {
+    let bcd = |b: u8| ((b >> 4) * 10 + (b & 0x0f)) as f64;
+    let fps = if bytes[3] >> 6 == 1 { 25.0 } else { 29.97 };
+    bcd(bytes[0]) * 3600.0 + bcd(bytes[1]) * 60.0 + bcd(bytes[2]) + bcd(bytes[3] & 0x3f) / fps
+}
 
-    // Trim: use input-side -ss to reduce work (fast seek), and -t for length.
-    if let Some(start) = options.start_seconds {
-        if start.is_finite() && start > 0.0 {
-            args.push("-ss".to_string());
-            args.push(format!("{:.3}", start));
+// Chapter start times of the longest program chain in a VTS_xx_0.IFO
+fn parse_dvd_chapters(ifo: &[u8]) -> Option<(f64, Vec<f64>)> {
+    let pgcit = be_u32(ifo, 0xCC)? as usize * 2048;
+    let pgc_count = be_u16(ifo, pgcit)? as usize;
+    let mut best: Option<(f64, Vec<f64>)> = None;
+
+    for i in 0..pgc_count {
+        let pgc = pgcit + be_u32(ifo, pgcit + 8 + i * 8 + 4)? as usize;
+        let programs = *ifo.get(pgc + 2)? as usize;
+        let cells = *ifo.get(pgc + 3)? as usize;
+        let program_map = pgc + be_u16(ifo, pgc + 0xE6)? as usize;
+        let cell_table = pgc + be_u16(ifo, pgc + 0xE8)? as usize;
+
+        let mut cell_starts = Vec::with_capacity(cells);
+        let mut total = 0.0;
+        for c in 0..cells {
+            cell_starts.push(total);
+            total += dvd_time_seconds(ifo.get(cell_table + c * 24 + 4..cell_table + c * 24 + 8)?);
+        }
+        let chapters: Vec<f64> = (0..programs)
+            .filter_map(|p| ifo.get(program_map + p).and_then(|&entry| cell_starts.get((entry as usize).saturating_sub(1)).copied()))
+            .collect();
+        if best.as_ref().map(|(d, _)| total > *d).unwrap_or(true) {
+            best = Some((total, chapters));
         }
     }
+    best
+}
 
-    args.push("-i".to_string());
-    args.push(options.input.clone());
+// Clip name, in and out time of a play item, in 45 kHz ticks
+type PlayItem = (String, u32, u32);
 
-    if let (Some(start), Some(end)) = (options.start_seconds, options.end_seconds) {
-        if start.is_finite() && end.is_finite() && end > start {
-            args.push("-t".to_string());
-            args.push(format!("{:.3}", end - start));
-        }
+// Play items and chapter start times (seconds) of a Blu-ray .mpls playlist
+fn parse_mpls(data: &[u8]) -> Option<(Vec<PlayItem>, Vec<f64>)> {
+    if data.get(0..4)? != b"MPLS" {
+        return None;
     }
-
-    args.push("-filter_complex".to_string());
-    args.push(filters);
-    args.push("-map".to_string());
-    args.push("[out]".to_string());
-    args.push(output_path_str.clone());
-    
-    // Spawn FFmpeg
-    let mut child = new_command(&ffmpeg_path)
-        .args(&args)
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
-    
-    // Store process reference
-    let child_pid = child.id();
-    {
-        let mut pid = app_state.current_pid.lock().await;
-        *pid = child_pid;
+    let playlist = be_u32(data, 8)? as usize;
+    let marks = be_u32(data, 12)? as usize;
+
+    let item_count = be_u16(data, playlist + 6)? as usize;
+    let mut items = Vec::with_capacity(item_count);
+    let mut at = playlist + 10;
+    for _ in 0..item_count {
+        let length = be_u16(data, at)? as usize;
+        let clip = String::from_utf8_lossy(data.get(at + 2..at + 7)?).to_string();
+        items.push((clip, be_u32(data, at + 14)?, be_u32(data, at + 18)?));
+        at += 2 + length;
     }
-    {
-        let mut output_path = app_state.current_output_path.lock().await;
-        *output_path = Some(output_path_str.clone());
+
+    let mut chapters = Vec::new();
+    let mark_count = be_u16(data, marks + 4)? as usize;
+    for m in 0..mark_count {
+        let mark = marks + 6 + m * 14;
+        // Type 1 is an entry mark, i.e. a chapter
+        if *data.get(mark + 1)? != 1 {
+            continue;
+        }
+        let item = be_u16(data, mark + 2)? as usize;
+        let time = be_u32(data, mark + 4)?;
+        let Some(&(_, item_in, _)) = items.get(item) else { continue };
+        let before: u64 = items[..item].iter().map(|(_, i, o)| o.saturating_sub(*i) as u64).sum();
+        chapters.push((before + time.saturating_sub(item_in) as u64) as f64 / 45000.0);
     }
-    
-    // Track priority using system tools
-    #[cfg(target_os = "windows")]
-    if let Some(priority) = &options.work_priority {
-        if let Some(pid_val) = child_pid {
-            use std::os::windows::process::CommandExt;
-            match priority.as_str() {
-                "low" | "idle" => {
-                    let _ = std::process::Command::new("powershell")
-                        .args(&["-Command", &format!("(Get-Process -Id {}).PriorityClass = 'Idle'", pid_val)])
-                        .creation_flags(0x08000000)
-                        .spawn();
-                }
-                "high" => {
-                    let _ = std::process::Command::new("powershell")
-                        .args(&["-Command", &format!("(Get-Process -Id {}).PriorityClass = 'High'", pid_val)])
-                        .creation_flags(0x08000000)
-                        .spawn();
-                }
-                _ => {} // Normal priority is default
+    Some((items, chapters))
+}
+
+fn dvd_titles(video_ts: &std::path::Path) -> Vec<DiscTitle> {
+    let mut sets: std::collections::BTreeMap<u32, Vec<PathBuf>> = std::collections::BTreeMap::new();
+    for entry in std::fs::read_dir(video_ts).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_uppercase();
+        // VTS_01_0.VOB is the menu, the numbered parts after it hold the title itself
+        let Some(rest) = name.strip_prefix("VTS_").and_then(|r| r.strip_suffix(".VOB")) else { continue };
+        let Some((set, part)) = rest.split_once('_') else { continue };
+        if let (Ok(set), Ok(part)) = (set.parse::<u32>(), part.parse::<u32>()) {
+            if part > 0 {
+                sets.entry(set).or_default().push(entry.path());
             }
         }
     }
-    
-    // Read stderr for progress
-    if let Some(stderr) = child.stderr.take() {
-        let app_handle = app.clone();
-        let app_state_clone = app_state.clone();
-        
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr);
-            let mut buf = Vec::new();
-            
-            // Pre-compile regex pattern
-            let time_re = regex::Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").ok();
-            
-            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
-                if n == 0 { break; }
-                let line = String::from_utf8_lossy(&buf).to_string();
-                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n').to_string();
-                buf.clear();
-                
-                // Check for cancellation during the stderr reading loop
-                if app_state_clone.is_cancelling.lock().await.clone() {
-                    break;
-                }
-                
-                // Extract time using pre-compiled regex
-                if let Some(ref re) = time_re {
-                    if let Some(cap) = re.captures(&line) {
-                        let h: f64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let m: f64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let s: f64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0);
-                        let current_secs = h * 3600.0 + m * 60.0 + s;
-                        
-                        // Round to integer for cleaner display, cap at 99%
-                        let percent = if effective_duration_secs > 0.0 {
-                            ((current_secs / effective_duration_secs * 100.0).min(99.0)).round() as u32
-                        } else {
-                            0
-                        };
-                        
-                        let _ = app_handle.emit("encode-progress", serde_json::json!({
-                            "percent": percent,
-                            "time": format!("{:02}:{:02}:{:02}", h as u32, m as u32, s as u32),
-                            "speed": "N/A"
-                        }));
-                    }
-                }
+
+    sets.into_iter()
+        .map(|(set, mut files)| {
+            files.sort();
+            let ifo = find_case_insensitive(video_ts, &format!("VTS_{:02}_0.IFO", set)).and_then(|p| std::fs::read(p).ok());
+            let (duration, chapters) = ifo.as_deref().and_then(parse_dvd_chapters).unwrap_or((0.0, Vec::new()));
+            DiscTitle {
+                id: format!("{:02}", set),
+                name: format!("Title set {}", set),
+                duration_seconds: duration,
+                chapters,
+                size_bytes: files.iter().filter_map(|f| std::fs::metadata(f).ok()).map(|m| m.len()).sum(),
+                files: files.iter().map(|f| f.to_string_lossy().to_string()).collect(),
             }
+        })
+        .collect()
+}
+
+fn bluray_titles(bdmv: &std::path::Path) -> Vec<DiscTitle> {
+    let playlist_dir = find_case_insensitive(bdmv, "PLAYLIST");
+    let stream_dir = find_case_insensitive(bdmv, "STREAM");
+    let (Some(playlist_dir), Some(stream_dir)) = (playlist_dir, stream_dir) else {
+        return Vec::new();
+    };
+
+    let mut titles: Vec<DiscTitle> = Vec::new();
+    let mut playlists: Vec<PathBuf> = std::fs::read_dir(&playlist_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|e| e.eq_ignore_ascii_case("mpls")).unwrap_or(false))
+        .collect();
+    playlists.sort();
+
+    for playlist in playlists {
+        let Some((items, chapters)) = std::fs::read(&playlist).ok().as_deref().and_then(parse_mpls) else { continue };
+        let files: Vec<String> = items
+            .iter()
+            .filter_map(|(clip, _, _)| find_case_insensitive(&stream_dir, &format!("{}.m2ts", clip)))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        if files.is_empty() || files.len() != items.len() {
+            continue;
+        }
+        let duration = items.iter().map(|(_, i, o)| o.saturating_sub(*i) as f64).sum::<f64>() / 45000.0;
+        // Menus, logos and duplicate playlists pointing at the same clips clutter the list
+        if duration < 30.0 || titles.iter().any(|t| t.files == files) {
+            continue;
+        }
+        let id = playlist.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        titles.push(DiscTitle {
+            name: format!("Playlist {}", id.trim_end_matches(".mpls").trim_end_matches(".MPLS")),
+            id,
+            duration_seconds: duration,
+            chapters,
+            size_bytes: files.iter().filter_map(|f| std::fs::metadata(f).ok()).map(|m| m.len()).sum(),
+            files,
         });
     }
-    
-    let app_handle_wait = app.clone();
-    
-    let status = child.wait().await.map_err(|e| format!("Failed to wait for child: {}", e))?;
-    
-    // Clean up after process completes
-    let mut pid_guard = app_state.current_pid.lock().await;
-    *pid_guard = None;
-    
-    let mut path_guard = app_state.current_output_path.lock().await;
-    *path_guard = None;
-    
-    let is_cancelled = *app_state.is_cancelling.lock().await;
-    if is_cancelled {
-        info!("Video to GIF creation cancelled");
-        let _ = app_handle_wait.emit("encode-cancelled", ());
-        
-        // Try to clean up partial output
-        if std::path::Path::new(&output_path_str).exists() {
-            let _ = std::fs::remove_file(&output_path_str);
-        }
-        
-        return Ok(());
+    titles.sort_by(|a, b| b.duration_seconds.partial_cmp(&a.duration_seconds).unwrap_or(std::cmp::Ordering::Equal));
+    titles
+}
+
+#[tauri::command]
+async fn scan_disc_folder(app: tauri::AppHandle, path: String) -> Result<DiscInfo, String> {
+    scoped_path(&app, &path)?;
+    read_disc_folder(path).await
+}
+
+async fn read_disc_folder(path: String) -> Result<DiscInfo, String> {
+    info!("scan_disc_folder called for: {}", path);
+
+    let (kind, root) = find_disc_root(std::path::Path::new(&path)).ok_or("Not a DVD (VIDEO_TS) or Blu-ray (BDMV) folder")?;
+    let scan_root = root.clone();
+    let mut titles = tokio::task::spawn_blocking(move || if kind == "dvd" { dvd_titles(&scan_root) } else { bluray_titles(&scan_root) })
+        .await
+        .map_err(|e| format!("Disc scan failed: {}", e))?;
+    if titles.is_empty() {
+        return Err("No playable titles found on this disc".to_string());
     }
-    
-    if status.success() {
-        info!("Video to GIF creation completed successfully");
-        let _ = app_handle_wait.emit("encode-complete", serde_json::json!({ "outputPath": output_path_str }));
-        Ok(())
-    } else {
-        error!("Video to GIF creation failed with status: {}", status);
-        let _ = app_handle_wait.emit("encode-error", serde_json::json!({ "message": format!("Process exited with status: {}", status) }));
-        Err(format!("Process exited with status: {}", status))
+
+    // Titles without IFO timing get their duration from ffprobe over the joined VOBs
+    for title in titles.iter_mut().filter(|t| t.duration_seconds <= 0.0) {
+        let joined = format!("concat:{}", title.files.join("|"));
+        if let Ok(summary) = probe_media_summary(&joined).await {
+            title.duration_seconds = summary.duration.unwrap_or(0.0);
+        }
     }
+
+    Ok(DiscInfo { kind: kind.to_string(), root: root.to_string_lossy().to_string(), titles })
 }
 
-// ============================================================================
-// Media Processing Commands
-// ============================================================================
+// FFMETADATA list of (start, end, title) chapters for -map_chapters; titles are escaped as
+// FFMETADATA requires
+fn named_chapter_metadata(chapters: &[(f64, f64, String)]) -> String {
+    let mut text = String::from(";FFMETADATA1\n");
+    for (start, end, title) in chapters {
+        if end <= start {
+            continue;
+        }
+        let title: String = title
+            .chars()
+            .flat_map(|c| match c {
+                '=' | ';' | '#' | '\\' | '\n' => vec!['\\', c],
+                c => vec![c],
+            })
+            .collect();
+        text.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            (start * 1000.0).round() as u64,
+            (end * 1000.0).round() as u64,
+            title
+        ));
+    }
+    text
+}
 
+// Joins every VOB/M2TS file of a title set into one MKV without re-encoding. This copies
+// the files whole: a DVD title set can hold several program chains and a Blu-ray playlist
+// may only play part of each clip, so the result can run longer than the listed duration
+// and carries no chapters, as those are positions within one chain or playlist.
 #[tauri::command]
-async fn get_audio_waveform(file_path: String, mode: Option<String>, width: Option<u32>, height: Option<u32>, palette: Option<String>, palette_color: Option<String>) -> Result<String, String> {
-    info!("get_audio_waveform called for: {}", file_path);
-    
-    let ffmpeg_path = get_ffmpeg_path();
-    let has_audio = has_audio_stream(&file_path).await?;
-    if !has_audio {
-        return Err("No audio stream found in file".to_string());
-    }
-    let w = width.unwrap_or(800);
-    let h = height.unwrap_or(120);
-    let p = palette.unwrap_or_else(|| "heatmap".to_string());
-    let pc = palette_color.unwrap_or_else(|| "63f1af".to_string());
-    let m = mode.unwrap_or_else(|| "waveform".to_string());
-    
-    let filter = if m == "spectrogram" {
-        format!("[0:a]showspectrumpic=s={}x{}:legend=0:color=rainbow:scale=log", w, h)
-    } else if p == "heatmap" {
-        format!("[0:a]aformat=channel_layouts=mono,showwavespic=s={}x{}:colors=white:scale=log,format=gray,r='if(lte(val,128),0,2*(val-128))':g='if(lte(val,128),2*val,255-2*(val-128))':b='if(lte(val,128),255-2*val,0)'", w, h)
-    } else if p == "accent" {
-        format!("[0:a]aformat=channel_layouts=mono,showwavespic=s={}x{}:colors=0x{}:scale=log", w, h, pc.replace("#", ""))
-    } else {
-        format!("[0:a]aformat=channel_layouts=mono,showwavespic=s={}x{}:colors=white:scale=log", w, h)
+async fn rip_disc_title_set(app: tauri::AppHandle, window: tauri::WebviewWindow, path: String, title_set_id: String, output_path: Option<String>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("rip_disc_title_set called for: {} title set {}", path, title_set_id);
+
+    check_scope(&app, [&path].into_iter().chain(&output_path))?;
+    let info = read_disc_folder(path).await?;
+    let title_set = info.titles.iter().find(|t| t.id == title_set_id).ok_or_else(|| format!("Title set {} not found on this disc", title_set_id))?;
+
+    let disc_dir = PathBuf::from(&info.root);
+    let disc_name = disc_dir
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| sanitize_file_name(&n.to_string_lossy()))
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "disc".to_string());
+    let output_path_str = match output_path.filter(|p| !p.is_empty()) {
+        Some(p) => p,
+        None => disc_dir
+            .parent()
+            .and_then(|p| p.parent())
+            .unwrap_or(&disc_dir)
+            .join(format!("{} - {}.mkv", disc_name, sanitize_file_name(&title_set.name)))
+            .to_string_lossy()
+            .to_string(),
     };
-    
-    let args = vec![
+
+    // VOB/M2TS parts are byte-continuous, so the concat protocol joins them as one stream
+    let args: Vec<String> = vec![
         "-y".to_string(),
+        "-fflags".to_string(),
+        "+genpts".to_string(),
         "-i".to_string(),
-        file_path,
-        "-filter_complex".to_string(),
-        filter,
-        "-frames:v".to_string(),
-        "1".to_string(),
-        "-f".to_string(),
-        "image2".to_string(),
-        "pipe:1".to_string(),
+        format!("concat:{}", title_set.files.join("|")),
+        "-map".to_string(),
+        "0:v:0".to_string(),
+        "-map".to_string(),
+        "0:a?".to_string(),
+        "-map".to_string(),
+        "0:s?".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+        output_path_str.clone(),
     ];
-    
-    let output = new_command(&ffmpeg_path)
-        .args(&args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        error!("FFmpeg waveform failed: stderr={}, stdout={}", stderr, stdout);
-        return Err(format!("FFmpeg failed to generate waveform: {}", stderr));
-    }
-    
-    if output.stdout.is_empty() {
-        error!("FFmpeg waveform generated empty output");
-        return Err("FFmpeg generated empty waveform output".to_string());
+
+    let run = run_ffmpeg_job(&app, "disc_rip", None, &args, &output_path_str, Some(title_set.duration_seconds)).await?;
+    finish_ffmpeg_job(&app, "disc_rip", &run, &output_path_str);
+    if run.success {
+        Ok(output_path_str)
+    } else if run.cancelled {
+        Err("Disc rip cancelled".to_string())
+    } else {
+        Err(format!("Failed to rip title set: {}", run.stderr_tail))
     }
-    
-    Ok(base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &output.stdout))
+}
+
+// ============================================================================
+// Audio Recording
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioDevice {
+    pub id: String,
+    pub name: String,
+    // "input" for microphones and line-in, "loopback" for what an output device plays
+    pub kind: String,
+    pub is_default: bool,
+}
+
+// Payload of "record-level" events, sent about 20 times a second for a VU meter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordLevelEvent {
+    pub schema_version: u32,
+    pub peak_db: f32,
+    pub rms_db: f32,
+    pub channel_peaks_db: Vec<f32>,
+    pub elapsed_seconds: f64,
+}
+
+// Dropping `stop` ends the capture thread, which closes ffmpeg's stdin so it can finish
+// the file; `finished` reports how that went
+struct ActiveRecording {
+    stop: std::sync::mpsc::Sender<()>,
+    output_path: String,
+    finished: tokio::sync::oneshot::Receiver<Result<(), String>>,
+}
+
+const RECORD_LEVEL_FLOOR_DB: f32 = -60.0;
+
+fn level_db(value: f32) -> f32 {
+    (20.0 * value.max(1e-6).log10()).max(RECORD_LEVEL_FLOOR_DB)
 }
 
 #[tauri::command]
-async fn get_video_thumbnails(file_path: String, duration: f64, count: Option<u32>) -> Result<ThumbnailResult, String> {
-    info!("get_video_thumbnails called for: {}", file_path);
-    
-    let _ffmpeg_path = "ffmpeg".to_string();
-    
-    // Get file size
-    let file_size_mb = std::fs::metadata(&file_path)
-        .map(|m| m.len() as f64 / (1024.0 * 1024.0))
-        .unwrap_or(0.0);
-    
-    // Adjust parameters based on file size
-    let (target_height, quality, max_count) = if file_size_mb > 600.0 {
-        (160, 6, 80)
-    } else if file_size_mb > 300.0 {
-        (180, 5, 100)
-    } else if file_size_mb > 120.0 {
-        (200, 4, 140)
-    } else if file_size_mb > 40.0 {
-        (220, 3, 180)
-    } else {
-        (240, 2, 300)
+async fn list_audio_devices() -> Result<Vec<AudioDevice>, String> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    let host = cpal::default_host();
+    let mut devices = Vec::new();
+
+    let default_input = host.default_input_device().and_then(|d| d.name().ok());
+    for device in host.input_devices().map_err(|e| format!("Failed to list audio devices: {}", e))? {
+        if let Ok(name) = device.name() {
+            devices.push(AudioDevice {
+                id: format!("input:{}", name),
+                is_default: default_input.as_deref() == Some(name.as_str()),
+                name,
+                kind: "input".to_string(),
+            });
+        }
+    }
+
+    // WASAPI can open any output device as a capture stream of what it plays
+    if cfg!(windows) {
+        let default_output = host.default_output_device().and_then(|d| d.name().ok());
+        for device in host.output_devices().map_err(|e| format!("Failed to list audio devices: {}", e))? {
+            if let Ok(name) = device.name() {
+                devices.push(AudioDevice {
+                    id: format!("loopback:{}", name),
+                    is_default: default_output.as_deref() == Some(name.as_str()),
+                    name,
+                    kind: "loopback".to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(devices)
+}
+
+// "default", "input:<name>", "loopback" or "loopback:<name>"
+fn find_record_device(id: Option<&str>) -> Result<(cpal::Device, cpal::SupportedStreamConfig), String> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    let id = id.filter(|i| !i.is_empty()).unwrap_or("default");
+    let (loopback, name) = match id.strip_prefix("loopback") {
+        Some(rest) => (true, rest.trim_start_matches(':')),
+        None => (false, id.strip_prefix("input:").unwrap_or(if id == "default" { "" } else { id })),
     };
-    
-    let desired_count = count.unwrap_or(50).min(max_count).min(300);
-    let cols = 10;
-    let rows = (desired_count as f64 / cols as f64).ceil() as u32;
-    // Recalculate count to be exactly what the tile layout expects, to avoid FFmpeg getting stuck
-    let actual_count = cols * rows;
-    
-    let fps = (actual_count as f64) / duration;
-    
-    let tile_layout = format!("{}x{}", cols, rows);
-    let vf = format!("fps={},scale=-1:{},tile={}", fps, target_height, tile_layout);
-    
-    let args = vec![
+    if loopback && !cfg!(windows) {
+        return Err("System audio capture uses WASAPI loopback, which is only available on Windows".to_string());
+    }
+
+    let host = cpal::default_host();
+    let device = match (loopback, name.is_empty()) {
+        (true, true) => host.default_output_device(),
+        (false, true) => host.default_input_device(),
+        (true, false) => host.output_devices().ok().and_then(|mut d| d.find(|d| d.name().ok().as_deref() == Some(name))),
+        (false, false) => host.input_devices().ok().and_then(|mut d| d.find(|d| d.name().ok().as_deref() == Some(name))),
+    }
+    .ok_or_else(|| format!("Audio device not found: {}", id))?;
+
+    let config = if loopback { device.default_output_config() } else { device.default_input_config() }
+        .map_err(|e| format!("Failed to read the audio device format: {}", e))?;
+    Ok((device, config))
+}
+
+// Builds the capture stream, converting whatever the device delivers to f32
+fn build_record_stream<T>(device: &cpal::Device, config: &cpal::StreamConfig, tx: tokio::sync::mpsc::UnboundedSender<Vec<f32>>) -> Result<cpal::Stream, String>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    use cpal::traits::DeviceTrait;
+
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let _ = tx.send(data.iter().map(|s| s.to_sample::<f32>()).collect());
+            },
+            |e| error!("Audio capture error: {}", e),
+            None,
+        )
+        .map_err(|e| format!("Failed to open the audio device: {}", e))
+}
+
+// Records a microphone, or on Windows whatever an output device is playing, to MP3, FLAC
+// or WAV. Samples are piped into ffmpeg as raw f32 and measured on the way for "record-level".
+#[tauri::command]
+async fn start_audio_record(app: tauri::AppHandle, window: tauri::WebviewWindow, device: Option<String>, format: String, output_folder: Option<String>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("start_audio_record called: {:?} -> {}", device, format);
+
+    check_scope(&app, &output_folder)?;
+    let state = app.state::<Arc<AppState>>().inner().clone();
+    let format = format.to_lowercase();
+    let codec_args: Vec<String> = match format.as_str() {
+        "mp3" => vec!["-c:a".to_string(), "libmp3lame".to_string(), "-b:a".to_string(), "192k".to_string()],
+        "flac" => vec!["-c:a".to_string(), "flac".to_string()],
+        "wav" => vec!["-c:a".to_string(), "pcm_s16le".to_string()],
+        other => return Err(format!("Unsupported recording format: {}", other)),
+    };
+
+    let (device, supported) = find_record_device(device.as_deref())?;
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    let channels = config.channels.max(1) as usize;
+    let sample_rate = config.sample_rate.0;
+
+    let folder = match output_folder.filter(|f| !f.is_empty()) {
+        Some(folder) => PathBuf::from(folder),
+        None => dirs::audio_dir().or_else(dirs::home_dir).unwrap_or_else(|| PathBuf::from(".")),
+    };
+    std::fs::create_dir_all(&folder).map_err(|e| format!("Failed to create output folder: {}", e))?;
+    let output_path = folder
+        .join(format!("Recording {}.{}", chrono::Local::now().format("%Y-%m-%d %H-%M-%S"), format))
+        .to_string_lossy()
+        .to_string();
+
+    // Claimed before anything starts, so two starts racing each other can't both get through
+    let (stop_tx, stop_rx) = std::sync::mpsc::channel::<()>();
+    let (finished_tx, finished_rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    {
+        let mut recording = state.recording.lock().map_err(|_| "Recording state is unavailable".to_string())?;
+        if recording.is_some() {
+            return Err("A recording is already running".to_string());
+        }
+        *recording = Some(ActiveRecording {
+            stop: stop_tx,
+            output_path: output_path.clone(),
+            finished: finished_rx,
+        });
+    }
+    // Gives the claim back when starting fails, unless a stop has already taken it
+    let release = |state: &AppState| {
+        if let Ok(mut recording) = state.recording.lock() {
+            if recording.as_ref().map(|r| r.output_path == output_path).unwrap_or(false) {
+                *recording = None;
+            }
+        }
+    };
+
+    // Only errors go to stderr; ffmpeg's running stats would otherwise fill the pipe
+    let mut args: Vec<String> = vec![
         "-y".to_string(),
-        "-i".to_string(),
-        file_path,
-        "-vf".to_string(),
-        vf,
-        "-frames:v".to_string(),
-        "1".to_string(),
-        "-q:v".to_string(),
-        quality.to_string(),
+        "-hide_banner".to_string(),
+        "-nostats".to_string(),
+        "-loglevel".to_string(),
+        "error".to_string(),
         "-f".to_string(),
-        "image2".to_string(),
-        "pipe:1".to_string(),
+        "f32le".to_string(),
+        "-ar".to_string(),
+        sample_rate.to_string(),
+        "-ac".to_string(),
+        channels.to_string(),
+        "-i".to_string(),
+        "pipe:0".to_string(),
     ];
-    
-    let ffmpeg_path = get_ffmpeg_path();
-    let output = new_command(&ffmpeg_path)
-        .args(&args)
-        .stdout(Stdio::piped())
+    args.extend(codec_args);
+    args.push(output_path.clone());
+
+    let mut child = match ffmpeg_command(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
         .stderr(Stdio::piped())
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        error!("FFmpeg thumbnail generation failed: {}", stderr);
-        return Err(format!("FFmpeg failed to generate thumbnails: {}", stderr));
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            release(&state);
+            return Err(format!("Failed to spawn ffmpeg: {}", e));
+        }
+    };
+    // No output path: a recording cut short by quitting is still worth keeping
+    let pid = child.id();
+    register_child(pid, None);
+    let Some(mut stdin) = child.stdin.take() else {
+        release(&state);
+        return Err("Failed to open ffmpeg input".to_string());
+    };
+    let stderr_task = child.stderr.take().map(|mut stderr| {
+        tokio::spawn(async move {
+            let mut text = String::new();
+            let _ = tokio::io::AsyncReadExt::read_to_string(&mut stderr, &mut text).await;
+            text
+        })
+    });
+
+    // cpal streams aren't Send, so the capture lives on its own thread until told to stop
+    let (sample_tx, mut sample_rx) = tokio::sync::mpsc::unbounded_channel::<Vec<f32>>();
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    std::thread::spawn(move || {
+        use cpal::traits::StreamTrait;
+
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => build_record_stream::<f32>(&device, &config, sample_tx),
+            cpal::SampleFormat::I16 => build_record_stream::<i16>(&device, &config, sample_tx),
+            cpal::SampleFormat::U16 => build_record_stream::<u16>(&device, &config, sample_tx),
+            cpal::SampleFormat::I32 => build_record_stream::<i32>(&device, &config, sample_tx),
+            other => Err(format!("Unsupported audio sample format: {:?}", other)),
+        };
+        let stream = match stream.and_then(|s| s.play().map(|_| s).map_err(|e| format!("Failed to start capture: {}", e))) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        let _ = ready_tx.send(Ok(()));
+        // Returns on stop or when the sender is dropped
+        let _ = stop_rx.recv();
+        drop(stream);
+    });
+
+    let writer_app = app.clone();
+    let writer_output = output_path.clone();
+    spawn_job_task(async move {
+        use tokio::io::AsyncWriteExt;
+
+        let window = (sample_rate as usize / 20).max(1) * channels;
+        let mut peaks = vec![0f32; channels];
+        let mut sum_squares = 0f64;
+        let mut pending = 0usize;
+        let mut total_frames = 0u64;
+        let mut write_error = None;
+
+        while let Some(chunk) = sample_rx.recv().await {
+            for (i, sample) in chunk.iter().enumerate() {
+                let channel = i % channels;
+                peaks[channel] = peaks[channel].max(sample.abs());
+                sum_squares += (*sample as f64).powi(2);
+            }
+            pending += chunk.len();
+            total_frames += (chunk.len() / channels) as u64;
+            if pending >= window {
+                let peak = peaks.iter().cloned().fold(0.0, f32::max);
+                let _ = writer_app.emit("record-level", RecordLevelEvent {
+                    schema_version: JOB_EVENT_SCHEMA_VERSION,
+                    peak_db: level_db(peak),
+                    rms_db: level_db((sum_squares / pending as f64).sqrt() as f32),
+                    channel_peaks_db: peaks.iter().map(|p| level_db(*p)).collect(),
+                    elapsed_seconds: total_frames as f64 / sample_rate as f64,
+                });
+                peaks.iter_mut().for_each(|p| *p = 0.0);
+                sum_squares = 0.0;
+                pending = 0;
+            }
+
+            let bytes: Vec<u8> = chunk.iter().flat_map(|s| s.to_le_bytes()).collect();
+            if let Err(e) = stdin.write_all(&bytes).await {
+                write_error = Some(format!("Failed to write audio to ffmpeg: {}", e));
+                break;
+            }
+        }
+        drop(stdin);
+
+        let status = child.wait().await;
+        let stderr_text = match stderr_task {
+            Some(task) => task.await.unwrap_or_default(),
+            None => String::new(),
+        };
+        unregister_child(pid);
+
+        let result = match (write_error, status) {
+            (Some(e), _) => Err(e),
+            (None, Ok(status)) if status.success() => Ok(()),
+            (None, Ok(_)) => Err(format!("Failed to save the recording: {}", stderr_text.lines().last().unwrap_or(""))),
+            (None, Err(e)) => Err(format!("FFmpeg process error: {}", e)),
+        };
+        match &result {
+            Ok(()) => emit_job_complete(&writer_app, "record", &writer_output),
+            Err(e) => emit_job_error(&writer_app, "record", e.clone()),
+        }
+        let _ = finished_tx.send(result);
+    });
+
+    match ready_rx.await {
+        Ok(Ok(())) => Ok(output_path),
+        Ok(Err(e)) => {
+            release(&state);
+            Err(e)
+        }
+        Err(_) => {
+            release(&state);
+            Err("The capture thread stopped unexpectedly".to_string())
+        }
     }
-    
-    if output.stdout.is_empty() {
-        return Err("FFmpeg produced no output".to_string());
+}
+
+// Stops the capture and waits for ffmpeg to finish writing, returning the file path
+#[tauri::command]
+async fn stop_audio_record(app: tauri::AppHandle) -> Result<String, String> {
+    info!("stop_audio_record called");
+
+    let state = app.state::<Arc<AppState>>();
+    let recording = state
+        .recording
+        .lock()
+        .ok()
+        .and_then(|mut r| r.take())
+        .ok_or("No recording is running")?;
+
+    let _ = recording.stop.send(());
+    match recording.finished.await {
+        Ok(Ok(())) => Ok(recording.output_path),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err("The recording stopped unexpectedly".to_string()),
     }
-    
-    Ok(ThumbnailResult {
-        data: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &output.stdout),
-        count: desired_count,
-        cols,
-        rows,
-        interval: duration / desired_count as f64,
-    })
 }
 
 // ============================================================================
 // Download Commands (yt-dlp)
 // ============================================================================
 
+// Failures retrying won't fix, as (kind, message key, lowercase stderr patterns). Checked
+// in order, so private videos asking to sign in are reported as private.
+const YTDLP_ERROR_PATTERNS: &[(&str, &str, &[&str])] = &[
+    ("drm", "error.ytdlp_drm", &["drm protected", "drm-protected", "this video is drm", "widevine", "playready", "fairplay"]),
+    ("private", "error.ytdlp_private", &["private video", "this video is private", "video is private"]),
+    ("age_restricted", "error.ytdlp_age_restricted", &["confirm your age", "age-restricted", "age restricted", "inappropriate for some users"]),
+    ("login_required", "error.ytdlp_login_required", &[
+        "sign in to confirm",
+        "login required",
+        "requires authentication",
+        "use --cookies",
+        "--cookies-from-browser",
+        "only available for registered users",
+        "you need to log in",
+        "members-only",
+        "join this channel",
+        "only available to subscribers",
+    ]),
+    ("geo_blocked", "error.ytdlp_geo_blocked", &["not available in your country", "geo restriction", "geo-restricted", "from your location"]),
+    ("unavailable", "error.ytdlp_unavailable", &["video unavailable", "has been removed", "no longer available", "http error 404"]),
+    ("rate_limited", "error.ytdlp_rate_limited", &["http error 429", "too many requests"]),
+    ("unsupported_url", "error.ytdlp_unsupported_url", &["unsupported url"]),
+];
+
+// Maps yt-dlp stderr to a known failure kind and a friendly, localized message
+fn classify_ytdlp_error(stderr: &str) -> Option<(&'static str, String)> {
+    let lower = stderr.to_lowercase();
+    YTDLP_ERROR_PATTERNS
+        .iter()
+        .find(|(_, _, patterns)| patterns.iter().any(|p| lower.contains(p)))
+        .map(|(kind, key, _)| (*kind, msg(key)))
+}
+
 #[tauri::command]
 async fn get_video_info(url: String, disable_flat_playlist: Option<bool>) -> Result<VideoInfoResult, String> {
     info!("get_video_info called for: {}", url);
@@ -1931,7 +13195,8 @@ async fn get_video_info(url: String, disable_flat_playlist: Option<bool>) -> Res
             url: Some(url),
             count: None,
             entries: None,
-            error: Some("Invalid URL format".to_string()),
+            error: Some(msg("error.invalid_url")),
+            error_kind: None,
         });
     }
     
@@ -1964,16 +13229,16 @@ async fn get_video_info(url: String, disable_flat_playlist: Option<bool>) -> Res
         args.push("--flat-playlist".to_string());
     }
     
-    let output = new_command(&ytdlp_path)
+    let output = child_output(new_command(&ytdlp_path)
         .args(&args)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
+        .stderr(Stdio::piped()), None)
         .await
         .map_err(|e| format!("Failed to run yt-dlp: {}", e))?;
     
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        let failure = classify_ytdlp_error(&stderr);
         return Ok(VideoInfoResult {
             is_playlist: false,
             title: None,
@@ -1985,7 +13250,8 @@ async fn get_video_info(url: String, disable_flat_playlist: Option<bool>) -> Res
             url: Some(url),
             count: None,
             entries: None,
-            error: Some(stderr.to_string()),
+            error: Some(failure.as_ref().map(|f| f.1.clone()).unwrap_or_else(|| stderr.to_string())),
+            error_kind: failure.map(|f| f.0.to_string()),
         });
     }
     
@@ -2008,6 +13274,7 @@ async fn get_video_info(url: String, disable_flat_playlist: Option<bool>) -> Res
             count: None,
             entries: None,
             error: Some("No JSON output from yt-dlp".to_string()),
+            error_kind: None,
         });
     }
 
@@ -2031,6 +13298,7 @@ async fn get_video_info(url: String, disable_flat_playlist: Option<bool>) -> Res
                         count: info.get("entries").and_then(|e| e.as_array()).map(|a| a.len() as u32),
                         entries: info.get("entries").and_then(|e| e.as_array()).map(|a| a.clone()),
                         error: None,
+                        error_kind: None,
                     });
                 } else if video_info.is_none() {
                     let duration_secs = info.get("duration").and_then(|v| v.as_i64());
@@ -2053,6 +13321,7 @@ async fn get_video_info(url: String, disable_flat_playlist: Option<bool>) -> Res
                         count: None,
                         entries: None,
                         error: None,
+                        error_kind: None,
                     });
                 }
             }
@@ -2081,15 +13350,24 @@ async fn get_video_info(url: String, disable_flat_playlist: Option<bool>) -> Res
         count: None,
         entries: None,
         error: Some(last_err),
+        error_kind: None,
     })
 }
 
 #[tauri::command]
-async fn download_video(app: tauri::AppHandle, url: String, options: DownloadOptions) -> Result<(), String> {
+async fn download_video(app: tauri::AppHandle, window: tauri::WebviewWindow, url: String, mut options: DownloadOptions) -> Result<(), String> {
+    let _window = JobWindow::enter(window.label());
     info!("download_video called for: {}", url);
     
     if !validate_url(&url) {
-        return Err("Invalid URL format".to_string());
+        return Err(msg("error.invalid_url"));
+    }
+    check_scope(&app, &options.output_path)?;
+
+    if options.mode.as_deref() == Some("audio") {
+        apply_output_format_default(&app, "download_audio", &mut options.audio_format)?;
+    } else {
+        apply_output_format_default(&app, "download_video", &mut options.format)?;
     }
     
     let state = app.state::<Arc<AppState>>();
@@ -2244,6 +13522,7 @@ async fn download_video(app: tauri::AppHandle, url: String, options: DownloadOpt
     {
         let mut pid = state.current_pid.lock().await;
         *pid = child_pid;
+        register_child(child_pid, None);
     }
     
     let app_handle = app.clone();
@@ -2263,7 +13542,7 @@ async fn download_video(app: tauri::AppHandle, url: String, options: DownloadOpt
     
     // Read stdout for progress and capture final path
     if let Some(stdout) = child.stdout.take() {
-        tokio::spawn(async move {
+        spawn_job_task(async move {
             let mut reader = BufReader::new(stdout);
             let mut buf = Vec::new();
             let mut pending = Vec::<u8>::new();
@@ -2303,6 +13582,8 @@ async fn download_video(app: tauri::AppHandle, url: String, options: DownloadOpt
                     }
                 
                 let mut progress_data = DownloadProgress {
+                    schema_version: JOB_EVENT_SCHEMA_VERSION,
+                    job_type: "download".to_string(),
                     percent: None,
                     size: None,
                     speed: None,
@@ -2344,7 +13625,9 @@ async fn download_video(app: tauri::AppHandle, url: String, options: DownloadOpt
                         *fp = candidate.to_string();
                         let mut ef = expected_filename_clone.lock().await;
                         *ef = Some(candidate.to_string());
-                        progress_data.status = Some("Creating output file...".to_string());
+                        // Quitting mid-download removes this file and its .part pieces
+                        set_child_output(child_pid, candidate);
+                        progress_data.status = Some(msg("status.creating_output"));
                     }
                 } else if str.contains("Merging formats into") {
                     if let Some(start) = str.find("Merging formats into") {
@@ -2355,7 +13638,8 @@ async fn download_video(app: tauri::AppHandle, url: String, options: DownloadOpt
                             *fp = candidate.to_string();
                             let mut ef = expected_filename_clone.lock().await;
                             *ef = Some(candidate.to_string());
-                            progress_data.status = Some("Merging audio and video...".to_string());
+                            set_child_output(child_pid, candidate);
+                            progress_data.status = Some(msg("status.merging"));
                         }
                     }
                 }
@@ -2377,24 +13661,24 @@ async fn download_video(app: tauri::AppHandle, url: String, options: DownloadOpt
                     if let Some(cap) = re.captures(str) {
                         let tag = cap.get(1).map(|m| m.as_str()).unwrap_or("");
                         if tag == "Merger" {
-                            progress_data.status = Some("Merging audio and video...".to_string());
+                            progress_data.status = Some(msg("status.merging"));
                         } else if tag == "ExtractAudio" {
-                            progress_data.status = Some("Extracting audio...".to_string());
+                            progress_data.status = Some(msg("status.extracting_audio"));
                         } else if tag == "info" {
                             if str.contains("Downloading webpage") {
-                                progress_data.status = Some("Fetching metadata...".to_string());
+                                progress_data.status = Some(msg("status.fetching_metadata"));
                             } else if str.contains("Downloading m3u8") {
-                                progress_data.status = Some("Preparing stream...".to_string());
+                                progress_data.status = Some(msg("status.preparing_stream"));
                             } else if str.contains("Extracting") {
-                                progress_data.status = Some("Extracting metadata...".to_string());
+                                progress_data.status = Some(msg("status.extracting_metadata"));
                             } else {
-                                progress_data.status = Some("Extracting metadata...".to_string());
+                                progress_data.status = Some(msg("status.extracting_metadata"));
                             }
                         } else if tag == "download" && progress_data.percent.is_none() {
                             if str.contains("Destination:") {
-                                progress_data.status = Some("Creating file...".to_string());
+                                progress_data.status = Some(msg("status.creating_file"));
                             } else if str.contains("Downloading") {
-                                progress_data.status = Some("Starting download...".to_string());
+                                progress_data.status = Some(msg("status.starting_download"));
                             }
                         }
                     }
@@ -2402,14 +13686,14 @@ async fn download_video(app: tauri::AppHandle, url: String, options: DownloadOpt
 
                 if let Some(p) = progress_data.percent {
                     if p >= 99.9 {
-                        progress_data.status.get_or_insert_with(|| "Finalizing download...".to_string());
+                        progress_data.status.get_or_insert_with(|| msg("status.finalizing_download"));
                     } else {
-                        progress_data.status.get_or_insert_with(|| "Downloading...".to_string());
+                        progress_data.status.get_or_insert_with(|| msg("status.downloading"));
                     }
                 }
 
                 if progress_data.percent.is_some() || progress_data.status.is_some() {
-                    let _ = app_handle.emit("download-progress", &progress_data);
+                    emit_progress_throttled(&app_handle, "download", "download-progress", &progress_data);
                 }
             }
         }
@@ -2425,7 +13709,7 @@ if let Some(stderr) = child.stderr.take() {
     let final_path_clone = final_path.clone();
     let expected_filename_clone = expected_filename.clone();
 
-    tokio::spawn(async move {
+    spawn_job_task(async move {
         let mut reader = BufReader::new(stderr);
         let mut buf = vec![0u8; 4096];
         let mut pending = Vec::<u8>::new();
@@ -2464,6 +13748,8 @@ if let Some(stderr) = child.stderr.take() {
                 }
 
                 let mut progress_data = DownloadProgress {
+                    schema_version: JOB_EVENT_SCHEMA_VERSION,
+                    job_type: "download".to_string(),
                     percent: None,
                     size: None,
                     speed: None,
@@ -2485,7 +13771,9 @@ if let Some(stderr) = child.stderr.take() {
                         *fp = candidate.to_string();
                         let mut ef = expected_filename_clone.lock().await;
                         *ef = Some(candidate.to_string());
-                        progress_data.status = Some("Creating output file...".to_string());
+                        // Quitting mid-download removes this file and its .part pieces
+                        set_child_output(child_pid, candidate);
+                        progress_data.status = Some(msg("status.creating_output"));
                     }
                 }
 
@@ -2498,25 +13786,26 @@ if let Some(stderr) = child.stderr.take() {
                             *fp = candidate.to_string();
                             let mut ef = expected_filename_clone.lock().await;
                             *ef = Some(candidate.to_string());
+                            set_child_output(child_pid, candidate);
                         }
                     }
-                    progress_data.status = Some("Merging audio and video...".to_string());
+                    progress_data.status = Some(msg("status.merging"));
                 } else if err_str.contains("Deleting original file") || err_str.contains("Deleting original files") {
-                    progress_data.status = Some("Cleaning up temporary files...".to_string());
+                    progress_data.status = Some(msg("status.cleaning_up"));
                 } else if err_str.contains("Fixing video timestamp") {
-                    progress_data.status = Some("Finalizing media timestamps...".to_string());
+                    progress_data.status = Some(msg("status.fixing_timestamps"));
                 }
 
                 if let Some(ref re) = tag_re {
                     if let Some(cap) = re.captures(&err_str) {
                         let tag = cap.get(1).map(|m| m.as_str()).unwrap_or("");
                         if tag == "Merger" {
-                            progress_data.status = Some("Merging audio and video...".to_string());
+                            progress_data.status = Some(msg("status.merging"));
                         } else if tag == "ExtractAudio" {
-                            progress_data.status = Some("Extracting audio...".to_string());
+                            progress_data.status = Some(msg("status.extracting_audio"));
                         } else if tag == "download" && progress_data.percent.is_none() {
                             if err_str.contains("Destination:") {
-                                progress_data.status = Some("Creating output file...".to_string());
+                                progress_data.status = Some(msg("status.creating_output"));
                             }
                         }
                     }
@@ -2524,103 +13813,391 @@ if let Some(stderr) = child.stderr.take() {
 
                 if let Some(p) = progress_data.percent {
                     if p >= 99.9 {
-                        progress_data.status.get_or_insert_with(|| "Finalizing download...".to_string());
+                        progress_data.status.get_or_insert_with(|| msg("status.finalizing_download"));
                     } else {
-                        progress_data.status.get_or_insert_with(|| "Downloading...".to_string());
+                        progress_data.status.get_or_insert_with(|| msg("status.downloading"));
                     }
                 }
 
                 if err_str.contains("ERROR:") {
-                    let _ = app_handle.emit("download-progress", DownloadProgress {
+                    emit_job_event(&app_handle, "download", "download-progress", DownloadProgress {
+                        schema_version: JOB_EVENT_SCHEMA_VERSION,
+                        job_type: "download".to_string(),
                         percent: None,
                         size: None,
                         speed: None,
                         eta: None,
-                        status: Some(format!(
-                            "Error: {}",
-                            err_str
+                        status: Some(msg_with(
+                            "status.error",
+                            &[("message", err_str
                                 .split("ERROR:")
                                 .nth(1)
                                 .map(|s| s.trim())
-                                .unwrap_or(&err_str)
+                                .unwrap_or(&err_str))],
                         )),
                     });
                 } else if progress_data.percent.is_some() || progress_data.status.is_some() {
-                    let _ = app_handle.emit("download-progress", &progress_data);
+                    emit_progress_throttled(&app_handle, "download", "download-progress", &progress_data);
+                }
+            }
+        }
+    });
+}
+
+    let status = child.wait().await.map_err(|e| format!("yt-dlp process error: {}", e))?;
+    
+    // Clear process reference
+    {
+        let mut pid = state.current_pid.lock().await;
+        unregister_child(pid.take());
+    }
+    
+    let is_cancelling = {
+        let cancel = state.is_cancelling.lock().await;
+        *cancel
+    };
+    
+    if is_cancelling {
+        let mut cancel = state.is_cancelling.lock().await;
+        *cancel = false;
+        emit_job_cancelled(&app, "download");
+        return Ok(());
+    }
+    
+    if status.success() {
+        let mut final_path_str = final_path.lock().await.clone();
+        
+        // If the path is just a folder (doesn't have a file extension), construct the file path
+        let path_buf = std::path::Path::new(&final_path_str);
+        if path_buf.is_dir() || !path_buf.extension().map(|e| !e.is_empty()).unwrap_or(false) {
+            // Try to construct the file path from what we know
+            let filename_base = file_name_for_path.as_ref()
+                .map(|f| f.replace(".", "_"))
+                .unwrap_or_else(|| "downloaded_file".to_string());
+            
+            let constructed_path = format!("{}/{}.{}", output_folder_for_path, filename_base, expected_ext);
+            
+            // Check if this file exists (it might with a different extension if yt-dlp chose differently)
+            if std::path::Path::new(&constructed_path).exists() {
+                final_path_str = constructed_path;
+            } else {
+                // Try to find any file in the output folder that matches our expected filename base
+                let folder_path = std::path::Path::new(&output_folder_for_path);
+                if let Ok(entries) = std::fs::read_dir(folder_path) {
+                    for entry in entries.flatten() {
+                        if let Ok(metadata) = entry.metadata() {
+                            if metadata.is_file() {
+                                if let Some(name) = entry.path().file_stem().and_then(|s| s.to_str()) {
+                                    if name == filename_base {
+                                        final_path_str = entry.path().to_string_lossy().to_string();
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        
+        emit_job_complete(&app, "download", &final_path_str);
+    } else {
+        let stderr_text = stderr_log.lock().await.clone();
+        if let Some((kind, message)) = classify_ytdlp_error(&stderr_text) {
+            error!("Download failed ({}): {}", kind, stderr_text.trim());
+            emit_job_error_with_kind(&app, "download", message, Some(kind));
+            return Ok(());
+        }
+        let message = if stderr_text.trim().is_empty() {
+            msg_with("error.download_failed", &[("code", &format!("{:?}", status.code()))])
+        } else {
+            msg_with("error.download_failed_details", &[("code", &format!("{:?}", status.code())), ("details", stderr_text.trim())])
+        };
+        emit_job_error(&app, "download", message);
+    }
+    
+    Ok(())
+}
+
+// ffmpeg flags for converting the piped download, and whether the output is audio-only
+fn piped_convert_args(options: &PipedConvertOptions, format: &str) -> Result<(Vec<String>, bool), String> {
+    let audio_bitrate = options
+        .audio_bitrate
+        .clone()
+        .filter(|b| regex::Regex::new(r"^\d+[kK]$").map(|re| re.is_match(b)).unwrap_or(false))
+        .unwrap_or_else(|| "192k".to_string());
+    let audio = |codec: &str, bitrate: bool| {
+        let mut args = vec!["-vn".to_string(), "-c:a".to_string(), codec.to_string()];
+        if bitrate {
+            args.extend(["-b:a".to_string(), audio_bitrate.clone()]);
+        }
+        args
+    };
+    let args = match format {
+        "mp3" => audio("libmp3lame", true),
+        "m4a" => audio("aac", true),
+        "opus" => audio("libopus", true),
+        "flac" => audio("flac", false),
+        "wav" => audio("pcm_s16le", false),
+        "mp4" | "mkv" | "webm" => {
+            let default_codec = if format == "webm" { "vp9" } else { "h264" };
+            let encoder = match options.video_codec.as_deref().unwrap_or(default_codec) {
+                "h264" => "libx264",
+                "h265" => "libx265",
+                "vp9" => "libvpx-vp9",
+                "copy" => "copy",
+                other => return Err(format!("Unsupported video codec: {}", other)),
+            };
+            let mut args = vec!["-map".to_string(), "0:v:0".to_string(), "-map".to_string(), "0:a:0?".to_string()];
+            args.extend(["-c:v".to_string(), encoder.to_string()]);
+            if encoder != "copy" {
+                let crf = options.crf.unwrap_or(if encoder == "libvpx-vp9" { 32 } else { 23 }).min(63);
+                args.extend(["-crf".to_string(), crf.to_string()]);
+                if encoder == "libvpx-vp9" {
+                    args.extend(["-b:v".to_string(), "0".to_string()]);
+                } else {
+                    args.extend(encoder_preset_args(encoder, "medium"));
+                }
+            }
+            let audio_codec = if format == "webm" { "libopus" } else { "aac" };
+            args.extend(["-c:a".to_string(), audio_codec.to_string(), "-b:a".to_string(), audio_bitrate]);
+            if format == "mp4" {
+                args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+            }
+            return Ok((args, false));
+        }
+        other => return Err(format!("Unsupported output format: {}", other)),
+    };
+    Ok((args, true))
+}
+
+// Downloads with yt-dlp and converts with ffmpeg in one go: yt-dlp writes the media to
+// stdout and it is fed straight into ffmpeg's stdin, so no intermediate file ever hits
+// the disk and the conversion runs while the download is still in progress.
+#[tauri::command]
+async fn download_and_convert(app: tauri::AppHandle, window: tauri::WebviewWindow, url: String, options: Option<PipedConvertOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
+    info!("download_and_convert called for: {}", url);
+
+    if !validate_url(&url) {
+        return Err(msg("error.invalid_url"));
+    }
+    let options = options.unwrap_or_default();
+    check_scope(&app, &options.output_path)?;
+    let format = options.output_format.clone().unwrap_or_else(|| "mp3".to_string()).to_lowercase();
+    let (convert_args, audio_only) = piped_convert_args(&options, &format)?;
+
+    let state = app.state::<Arc<AppState>>();
+    let ytdlp_path = get_ytdlp_path();
+    let ffmpeg_path = get_ffmpeg_path();
+    let user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36";
+
+    // The title has to be known before ffmpeg starts, as it names the output file
+    let base_name = match options.file_name.as_deref().map(sanitize_file_name).filter(|n| !n.is_empty()) {
+        Some(name) => name,
+        None => {
+            let output = child_output(new_command(&ytdlp_path)
+                .args(["--no-playlist", "--skip-download", "--no-warnings", "--print", "title", "--user-agent", user_agent, &url])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()), None)
+                .await
+                .map_err(|e| format!("Failed to run yt-dlp: {}", e))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(match classify_ytdlp_error(&stderr) {
+                    Some((_, message)) => message,
+                    None => format!("Failed to read video info: {}", stderr.trim()),
+                });
+            }
+            let title = sanitize_file_name(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or(""));
+            if title.is_empty() { "download".to_string() } else { title }
+        }
+    };
+    let output_folder = match options.output_path.as_ref().filter(|p| !p.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => dirs::download_dir().unwrap_or_else(|| PathBuf::from(".")),
+    };
+    std::fs::create_dir_all(&output_folder).map_err(|e| format!("Failed to create output folder: {}", e))?;
+    let output_path_str = output_folder.join(format!("{}.{}", base_name, format)).to_string_lossy().to_string();
+
+    let selector = match options.format_selector.clone().filter(|s| !s.trim().is_empty()) {
+        Some(selector) => selector,
+        None if audio_only => "bestaudio/best".to_string(),
+        // Split video and audio streams are merged by yt-dlp (through ffmpeg) on the way out
+        None => match options.quality.as_deref().and_then(|q| q.parse::<u32>().ok()) {
+            Some(h) => format!("bv*[height<={h}]+ba/b[height<={h}]/best", h = h),
+            None => "bv*+ba/best".to_string(),
+        },
+    };
+    let mut ytdlp_args = vec![
+        "-o".to_string(),
+        "-".to_string(),
+        "-f".to_string(),
+        selector,
+        "--no-playlist".to_string(),
+        "--newline".to_string(),
+        "--no-cache-dir".to_string(),
+        "--force-ipv4".to_string(),
+        "--user-agent".to_string(),
+        user_agent.to_string(),
+    ];
+    if PathBuf::from(&ffmpeg_path).exists() {
+        ytdlp_args.extend(["--ffmpeg-location".to_string(), ffmpeg_path.clone()]);
+    }
+    ytdlp_args.push(url.clone());
+
+    let mut ffmpeg_args = vec!["-y".to_string(), "-hide_banner".to_string(), "-i".to_string(), "pipe:0".to_string()];
+    ffmpeg_args.extend(convert_args);
+    ffmpeg_args.push(output_path_str.clone());
+    info!("Piping yt-dlp {:?} into ffmpeg {:?}", ytdlp_args, ffmpeg_args);
+
+    let mut downloader = new_command(&ytdlp_path)
+        .args(&ytdlp_args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn yt-dlp: {}", e))?;
+    let mut converter = match ffmpeg_command(&ffmpeg_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            if let Some(pid) = downloader.id() {
+                kill_process_tree(pid).await;
+            }
+            return Err(format!("Failed to spawn ffmpeg: {}", e));
+        }
+    };
+
+    // Cancelling stops ffmpeg like any encode, yt-dlp is stopped once the pipe closes
+    let downloader_pid = downloader.id();
+    let converter_pid = converter.id();
+    register_child(downloader_pid, None);
+    {
+        let mut pid = state.current_pid.lock().await;
+        *pid = converter_pid;
+        register_child(converter_pid, Some(&output_path_str));
+    }
+    {
+        let mut current_output = state.current_output_path.lock().await;
+        *current_output = Some(output_path_str.clone());
+    }
+
+    let pipe = match (downloader.stdout.take(), converter.stdin.take()) {
+        (Some(mut from), Some(mut to)) => Some(tokio::spawn(async move {
+            let copied = tokio::io::copy(&mut from, &mut to).await;
+            // Dropping stdin is what tells ffmpeg the input has ended
+            drop(to);
+            copied
+        })),
+        _ => None,
+    };
+
+    // yt-dlp reports download progress on stderr when the media goes to stdout
+    let ytdlp_log = Arc::new(Mutex::new(String::new()));
+    if let Some(stderr) = downloader.stderr.take() {
+        let app_handle = app.clone();
+        let log = ytdlp_log.clone();
+        spawn_job_task(async move {
+            let progress_re = regex::Regex::new(r"\[download\]\s+(\d+\.?\d*)%").ok();
+            let speed_re = regex::Regex::new(r"at\s+(\d+\.?\d*[KMG]iB/s)").ok();
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                {
+                    let mut acc = log.lock().await;
+                    if acc.len() < 16_384 {
+                        acc.push_str(&line);
+                        acc.push('\n');
+                    }
+                }
+                let percent = progress_re.as_ref().and_then(|re| re.captures(&line)).and_then(|c| c.get(1)).and_then(|m| m.as_str().parse::<f64>().ok());
+                if let Some(percent) = percent {
+                    let speed = speed_re.as_ref().and_then(|re| re.captures(&line)).and_then(|c| c.get(1)).map(|m| m.as_str().to_string());
+                    emit_job_progress(&app_handle, "download_convert", None, percent.min(99.0), None, speed);
+                }
+            }
+        });
+    }
+    let ffmpeg_log = Arc::new(Mutex::new(String::new()));
+    if let Some(stderr) = converter.stderr.take() {
+        let log = ffmpeg_log.clone();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            while let Ok(n) = reader.read_until(b'\r', &mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                let mut acc = log.lock().await;
+                acc.push_str(&String::from_utf8_lossy(&buf));
+                // Only the end matters for error messages
+                if acc.len() > 8192 {
+                    let cut = acc.len() - 4096;
+                    let cut = (cut..acc.len()).find(|i| acc.is_char_boundary(*i)).unwrap_or(acc.len());
+                    acc.drain(..cut);
                 }
+                buf.clear();
             }
+        });
+    }
+
+    let converter_status = converter.wait().await.map_err(|e| format!("FFmpeg process error: {}", e))?;
+    if !converter_status.success() {
+        // ffmpeg gave up, nothing is reading the download any more
+        if let Some(pid) = downloader_pid {
+            kill_process_tree(pid).await;
         }
-    });
-}
+    }
+    let downloader_status = downloader.wait().await.map_err(|e| format!("yt-dlp process error: {}", e))?;
+    if let Some(pipe) = pipe {
+        let _ = pipe.await;
+    }
 
-    let status = child.wait().await.map_err(|e| format!("yt-dlp process error: {}", e))?;
-    
-    // Clear process reference
     {
         let mut pid = state.current_pid.lock().await;
-        *pid = None;
+        unregister_child(pid.take());
     }
-    
-    let is_cancelling = {
-        let cancel = state.is_cancelling.lock().await;
-        *cancel
-    };
-    
-    if is_cancelling {
+    unregister_child(downloader_pid);
+    {
+        let mut current_output = state.current_output_path.lock().await;
+        *current_output = None;
+    }
+
+    let cancelled = {
         let mut cancel = state.is_cancelling.lock().await;
-        *cancel = false;
-        let _ = app.emit("download-cancelled", ());
-        return Ok(());
+        std::mem::replace(&mut *cancel, false)
+    };
+    if cancelled {
+        let _ = std::fs::remove_file(&output_path_str);
+        emit_job_cancelled(&app, "download_convert");
+        return Err("Download cancelled".to_string());
     }
-    
-    if status.success() {
-        let mut final_path_str = final_path.lock().await.clone();
-        
-        // If the path is just a folder (doesn't have a file extension), construct the file path
-        let path_buf = std::path::Path::new(&final_path_str);
-        if path_buf.is_dir() || !path_buf.extension().map(|e| !e.is_empty()).unwrap_or(false) {
-            // Try to construct the file path from what we know
-            let filename_base = file_name_for_path.as_ref()
-                .map(|f| f.replace(".", "_"))
-                .unwrap_or_else(|| "downloaded_file".to_string());
-            
-            let constructed_path = format!("{}/{}.{}", output_folder_for_path, filename_base, expected_ext);
-            
-            // Check if this file exists (it might with a different extension if yt-dlp chose differently)
-            if std::path::Path::new(&constructed_path).exists() {
-                final_path_str = constructed_path;
-            } else {
-                // Try to find any file in the output folder that matches our expected filename base
-                let folder_path = std::path::Path::new(&output_folder_for_path);
-                if let Ok(entries) = std::fs::read_dir(folder_path) {
-                    for entry in entries.flatten() {
-                        if let Ok(metadata) = entry.metadata() {
-                            if metadata.is_file() {
-                                if let Some(name) = entry.path().file_stem().and_then(|s| s.to_str()) {
-                                    if name == filename_base {
-                                        final_path_str = entry.path().to_string_lossy().to_string();
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+
+    if converter_status.success() && downloader_status.success() {
+        emit_job_complete(&app, "download_convert", &output_path_str);
+        return Ok(output_path_str);
+    }
+
+    // A failed download still lets ffmpeg finish cleanly on what it got, so check both
+    let _ = std::fs::remove_file(&output_path_str);
+    let ytdlp_text = ytdlp_log.lock().await.clone();
+    if !downloader_status.success() {
+        if let Some((kind, message)) = classify_ytdlp_error(&ytdlp_text) {
+            error!("Piped download failed ({}): {}", kind, ytdlp_text.trim());
+            emit_job_error_with_kind(&app, "download_convert", message.clone(), Some(kind));
+            return Err(message);
         }
-        
-        let _ = app.emit("download-complete", serde_json::json!({ "outputPath": final_path_str }));
-    } else {
-        let stderr_text = stderr_log.lock().await.clone();
-        let message = if stderr_text.trim().is_empty() {
-            format!("Download failed with code {:?}", status.code())
-        } else {
-            format!("Download failed with code {:?}: {}", status.code(), stderr_text.trim())
-        };
-        let _ = app.emit("download-error", serde_json::json!({ "message": message }));
+        let message = msg_with("error.download_failed_details", &[("code", &format!("{:?}", downloader_status.code())), ("details", ytdlp_text.trim())]);
+        emit_job_error(&app, "download_convert", message.clone());
+        return Err(message);
     }
-    
-    Ok(())
+    let ffmpeg_text = ffmpeg_log.lock().await.clone();
+    error!("Piped conversion failed: {}", ffmpeg_text.trim());
+    emit_job_error(&app, "download_convert", msg_with("error.process_exited", &[("code", &format!("{:?}", converter_status.code()))]));
+    Err(format!("Failed to convert download: {}", ffmpeg_text.trim()))
 }
 
 #[tauri::command]
@@ -2636,24 +14213,10 @@ async fn cancel_download(app: tauri::AppHandle) -> Result<(), String> {
     
     let mut pid = state.current_pid.lock().await;
     if let Some(child_pid) = *pid {
-        #[cfg(windows)]
-        {
-            // Use taskkill for Windows to kill process tree
-            let _ = new_command("taskkill")
-                .args(&["/F", "/T", "/PID", &child_pid.to_string()])
-                .output()
-                .await;
-        }
-        #[cfg(not(windows))]
-        {
-            let _ = Command::new("kill")
-                .arg(&child_pid.to_string())
-                .output()
-                .await;
-        }
+        kill_process_tree(child_pid).await;
     }
     
-    *pid = None;
+    unregister_child(pid.take());
     
     Ok(())
 }
@@ -2663,10 +14226,10 @@ async fn cancel_download(app: tauri::AppHandle) -> Result<(), String> {
 // ============================================================================
 
 #[tauri::command]
-async fn open_file(file_path: String) -> Result<(), String> {
+async fn open_file(app: tauri::AppHandle, file_path: String) -> Result<(), String> {
     info!("open_file called for: {}", file_path);
     
-    let path = std::path::Path::new(&file_path);
+    let path = scoped_path(&app, &file_path)?;
     if !path.exists() {
         return Err(format!("File does not exist: {}", file_path));
     }
@@ -2700,10 +14263,10 @@ async fn open_file(file_path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn open_folder(folder_path: String) -> Result<(), String> {
+async fn open_folder(app: tauri::AppHandle, folder_path: String) -> Result<(), String> {
     info!("open_folder called for: {}", folder_path);
     
-    let path = std::path::Path::new(&folder_path);
+    let path = scoped_path(&app, &folder_path)?;
     
     // Check if path exists
     if !path.exists() {
@@ -2810,98 +14373,499 @@ async fn open_external(url: String) -> Result<(), String> {
     Ok(())
 }
 
+// Copies a generated image to the clipboard so it can be pasted straight into a chat or
+// document. Stills go on as a bitmap (PNG on Linux, DIB on Windows, NSImage on macOS).
+// GIFs go on as a file reference instead, since a bitmap would only keep the first frame
+// and chat apps upload a pasted file as is. Returns "image" or "file".
+#[tauri::command]
+async fn copy_to_clipboard(app: tauri::AppHandle, path: String, as_file: Option<bool>) -> Result<String, String> {
+    info!("copy_to_clipboard called for: {}", path);
+
+    let validated = validate_path(&path).ok_or_else(|| msg("error.invalid_path"))?;
+    scoped_path(&app, &path)?;
+    if !validated.is_file() {
+        return Err(format!("Not a file: {}", path));
+    }
+    let is_gif = validated.extension().map(|e| e.eq_ignore_ascii_case("gif")).unwrap_or(false);
+    let as_file = as_file.unwrap_or(is_gif);
+
+    let state = app.state::<Arc<AppState>>().inner().clone();
+    tokio::task::spawn_blocking(move || {
+        // Decode before taking the clipboard lock, large contact sheets take a moment
+        let image = if as_file {
+            None
+        } else {
+            let img = ImageReader::open(&validated)
+                .and_then(|r| r.with_guessed_format())
+                .map_err(|e| format!("Failed to open image: {}", e))?
+                .decode()
+                .map_err(|e| format!("Failed to decode image: {}", e))?
+                .to_rgba8();
+            Some(img)
+        };
+
+        let mut guard = state.clipboard.lock().map_err(|_| "Clipboard is unavailable".to_string())?;
+        if guard.is_none() {
+            *guard = Some(arboard::Clipboard::new().map_err(|e| format!("Failed to open the clipboard: {}", e))?);
+        }
+        let clipboard = guard.as_mut().ok_or("Clipboard is unavailable")?;
+        match image {
+            Some(img) => {
+                let (width, height) = img.dimensions();
+                clipboard
+                    .set_image(arboard::ImageData {
+                        width: width as usize,
+                        height: height as usize,
+                        bytes: std::borrow::Cow::Owned(img.into_raw()),
+                    })
+                    .map_err(|e| format!("Failed to copy image: {}", e))?;
+                Ok("image".to_string())
+            }
+            None => {
+                clipboard
+                    .set()
+                    .file_list(&[&validated])
+                    .map_err(|e| format!("Failed to copy file: {}", e))?;
+                Ok("file".to_string())
+            }
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to copy to clipboard: {}", e))?
+}
+
 // ============================================================================
 // PDF Commands
 // ============================================================================
 
 #[tauri::command]
-async fn convert_images_to_pdf(image_paths: Vec<String>, output_path: String, quality: Option<u32>, upscale: Option<bool>) -> Result<String, String> {
+async fn convert_images_to_pdf(app: tauri::AppHandle, window: tauri::WebviewWindow, image_paths: Vec<String>, output_path: String, quality: Option<u32>, upscale: Option<bool>, options: Option<PdfExportOptions>) -> Result<String, String> {
+    let _window = JobWindow::enter(window.label());
     info!("convert_images_to_pdf called with {} images", image_paths.len());
     
     if image_paths.is_empty() {
-        return Err("No images provided".to_string());
+        return Err(msg("error.no_images"));
     }
-    
-    use printpdf::*;
-    
-    // Use 300 DPI for better quality PDF (standard print resolution)
-    // 1 inch = 25.4 mm = 300 pixels at 300 DPI
-    let dpi = 300.0_f32;
-    let mm_per_px = 25.4_f32 / dpi;
+    check_scope(&app, image_paths.iter().chain([&output_path]))?;
+
+    let state = app.state::<Arc<AppState>>().inner().clone();
+    let worker_app = app.clone();
+    let worker_output = output_path.clone();
+    let options = options.unwrap_or_default();
+    let image_options = PdfImageOptions {
+        quality,
+        deskew: options.deskew.unwrap_or(false),
+        auto_orient: options.auto_orient.unwrap_or(false),
+        sampling: jpeg_sampling_factor(options.chroma_subsampling.as_deref()),
+        progressive: options.progressive.unwrap_or(false),
+        compression: match options.compression.as_deref() {
+            Some("auto") => PdfCompression::Auto,
+            Some("lossless") => PdfCompression::Lossless,
+            _ => PdfCompression::Jpeg,
+        },
+    };
+    let owner = job_window();
+    let result = tokio::task::spawn_blocking(move || {
+        let _window = owner.as_deref().map(JobWindow::enter);
+        build_pdf(&worker_app, &state, &image_paths, &worker_output, image_options, upscale.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| format!("PDF worker failed: {}", e))?;
 
-    // Get max dimensions if upscaling
-    let mut max_width = 0.0_f32;
-    let mut max_height = 0.0_f32;
-    
-    if upscale.unwrap_or(false) {
-        for img_path in &image_paths {
-            if let Ok(reader) = ImageReader::open(img_path) {
-                if let Ok(format) = reader.with_guessed_format() {
-                    if let Ok(img) = format.decode() {
-                        let (w, h) = img.dimensions();
-                        if w as f32 > max_width { max_width = w as f32; }
-                        if h as f32 > max_height { max_height = h as f32; }
-                    }
+    match result {
+        Ok(true) => {
+            emit_job_complete(&app, "pdf", &output_path);
+            Ok(output_path)
+        }
+        Ok(false) => {
+            emit_job_cancelled(&app, "pdf");
+            Err("PDF creation cancelled".to_string())
+        }
+        Err(e) => {
+            emit_job_error(&app, "pdf", e.clone());
+            Err(e)
+        }
+    }
+}
+
+// Per-image clean-up applied before a page is embedded
+#[derive(Debug, Clone, Copy)]
+struct PdfImageOptions {
+    quality: Option<u32>,
+    deskew: bool,
+    auto_orient: bool,
+    sampling: jpeg_encoder::SamplingFactor,
+    progressive: bool,
+    compression: PdfCompression,
+}
+
+// Jpeg re-encodes every page at the chosen quality, Lossless keeps the pixels (Flate
+// compressed when the document is saved) and Auto picks per page from its content
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PdfCompression {
+    Jpeg,
+    Auto,
+    Lossless,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PageContent {
+    Text,
+    Mixed,
+    Photo,
+}
+
+// A decoded page image, ready to embed: JPEG bytes, or raw pixels when lossless
+struct PdfPageImage {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    lossless: bool,
+    greyscale: bool,
+}
+
+// Cheap content guess from a thumbnail. Text, line art and screenshots are mostly paper
+// and ink in a handful of colours; photos spread smooth tones over the whole range.
+// Also reports whether the page has no real colour at all.
+fn classify_page(img: &DynamicImage) -> (PageContent, bool) {
+    let small = img.thumbnail(512, 512).to_rgb8();
+    let total = (small.width() * small.height()).max(1) as usize;
+
+    let mut extremes = 0usize;
+    let mut coloured = 0usize;
+    let mut palette = vec![0u32; 4096];
+    for p in small.pixels() {
+        let [r, g, b] = p.0;
+        let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+        if luma >= 200 || luma <= 60 {
+            extremes += 1;
+        }
+        if r.max(g).max(b) - r.min(g).min(b) > 24 {
+            coloured += 1;
+        }
+        palette[((r as usize >> 4) << 8) | ((g as usize >> 4) << 4) | (b as usize >> 4)] += 1;
+    }
+    // Colours covering a meaningful share of the page, so antialiasing and sensor noise don't count
+    let main_colours = palette.iter().filter(|c| **c as usize * 2000 > total).count();
+    let extreme_share = extremes as f64 / total as f64;
+    let greyscale = coloured * 100 < total;
+
+    let content = if extreme_share >= 0.85 && main_colours <= 48 {
+        PageContent::Text
+    } else if extreme_share >= 0.6 {
+        PageContent::Mixed
+    } else {
+        PageContent::Photo
+    };
+    (content, greyscale)
+}
+
+// Every pixel grey at full resolution, so storing luma alone loses nothing. classify_page
+// only samples a thumbnail and tolerates a little colour.
+fn is_exact_greyscale(img: &DynamicImage) -> bool {
+    match img {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageLumaA8(_) | DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) => true,
+        _ => img.to_rgb8().pixels().all(|p| p[0] == p[1] && p[1] == p[2]),
+    }
+}
+
+// Opens, decodes and encodes one image for embedding; runs on the rayon pool
+fn encode_pdf_image(img_path: &str, options: PdfImageOptions) -> Result<PdfPageImage, String> {
+    use image::ImageDecoder;
+
+    let mut reader = ImageReader::open(img_path).map_err(|e| format!("Failed to open image: {}", e))?;
+    reader.set_format(ImageFormat::from_path(img_path).unwrap_or(ImageFormat::Jpeg));
+    let mut decoder = reader.into_decoder().map_err(|e| format!("Failed to decode image: {}", e))?;
+    let orientation = decoder.orientation().unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).map_err(|e| format!("Failed to decode image: {}", e))?;
+
+    if options.auto_orient {
+        // Phone cameras store the rotation in EXIF rather than in the pixels
+        img.apply_orientation(orientation);
+        img = orient_text_upright(img);
+    }
+    if options.deskew {
+        img = deskew_scan(img);
+    }
+
+    let (w, h) = img.dimensions();
+    let (content, greyscale) = match options.compression {
+        PdfCompression::Jpeg => (PageContent::Photo, false),
+        PdfCompression::Lossless => (PageContent::Text, is_exact_greyscale(&img)),
+        PdfCompression::Auto => classify_page(&img),
+    };
+
+    if content == PageContent::Text {
+        let data = if greyscale { img.into_luma8().into_raw() } else { img.into_rgb8().into_raw() };
+        return Ok(PdfPageImage { data, width: w, height: h, lossless: true, greyscale });
+    }
+
+    if w > u16::MAX as u32 || h > u16::MAX as u32 {
+        return Err(format!("Image is too large for JPEG: {}x{}", w, h));
+    }
+    // Pages mixing text with pictures keep enough detail for the text to stay sharp
+    let (jpeg_quality, sampling) = match content {
+        PageContent::Mixed => (options.quality.unwrap_or(80).clamp(90, 100) as u8, jpeg_encoder::SamplingFactor::R_4_4_4),
+        _ => (options.quality.unwrap_or(80).clamp(1, 100) as u8, options.sampling),
+    };
+
+    let mut jpeg_bytes = Vec::new();
+    let mut encoder = jpeg_encoder::Encoder::new(&mut jpeg_bytes, jpeg_quality);
+    encoder.set_sampling_factor(sampling);
+    encoder.set_progressive(options.progressive);
+    let rgb_img = img.into_rgb8();
+    encoder
+        .encode(
+            rgb_img.as_raw(),
+            w as u16,
+            h as u16,
+            jpeg_encoder::ColorType::Rgb,
+        )
+        .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+
+    Ok(PdfPageImage { data: jpeg_bytes, width: w, height: h, lossless: false, greyscale: false })
+}
+
+// Dark-pixel mask of a page, downscaled so the analysis stays cheap on 600 DPI scans
+fn scan_ink_mask(img: &DynamicImage) -> (Vec<bool>, u32, u32) {
+    let small = img.thumbnail(1000, 1000).to_luma8();
+    let (w, h) = small.dimensions();
+    let mean = small.pixels().map(|p| p[0] as u64).sum::<u64>() / (w as u64 * h as u64).max(1);
+    let threshold = (mean as f32 * 0.75) as u8;
+    let mask = small.pixels().map(|p| p[0] < threshold).collect();
+    (mask, w, h)
+}
+
+// Blank or nearly blank pages give no usable signal
+fn has_enough_ink(mask: &[bool]) -> bool {
+    mask.iter().filter(|m| **m).count() * 1000 >= mask.len()
+}
+
+fn row_profile(mask: &[bool], w: u32, h: u32) -> Vec<u32> {
+    (0..h as usize)
+        .map(|y| mask[y * w as usize..(y + 1) * w as usize].iter().filter(|m| **m).count() as u32)
+        .collect()
+}
+
+fn column_profile(mask: &[bool], w: u32, h: u32) -> Vec<u32> {
+    let mut columns = vec![0u32; w as usize];
+    for y in 0..h as usize {
+        for (x, column) in columns.iter_mut().enumerate() {
+            if mask[y * w as usize + x] {
+                *column += 1;
+            }
+        }
+    }
+    columns
+}
+
+// How strongly the profile alternates between ink and gaps, 1.0 for a flat profile
+fn profile_peakiness(profile: &[u32]) -> f64 {
+    let total: f64 = profile.iter().map(|v| *v as f64).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    profile.iter().map(|v| (*v as f64).powi(2)).sum::<f64>() * profile.len() as f64 / (total * total)
+}
+
+// Row profile of the mask as if it were rotated by the given angle; text lines make
+// it sharpest when the angle matches their slope
+fn skewed_profile_score(mask: &[bool], w: u32, h: u32, degrees: f32) -> f64 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let mut bins = vec![0u32; (w + h) as usize * 2];
+    for y in 0..h {
+        for x in 0..w {
+            if mask[(y * w + x) as usize] {
+                let row = (y as f32 * cos - x as f32 * sin + w as f32) as usize;
+                if let Some(bin) = bins.get_mut(row) {
+                    *bin += 1;
                 }
             }
         }
-        // Default to A4 size at 300 DPI if no images found
-        if max_width == 0.0 { max_width = 2480.0; } // ~210mm at 300 DPI
-        if max_height == 0.0 { max_height = 3508.0; } // ~297mm at 300 DPI
     }
-    
-    // Helper to process image with quality - returns raw RGB data for printpdf
-    let process_image = |img: DynamicImage, q: Option<u32>| -> Result<(Vec<u8>, (u32, u32), Option<printpdf::ImageFilter>), String> {
-        let (w, h) = img.dimensions();
-        let jpeg_quality = q.unwrap_or(80).clamp(1, 100) as u8;
+    bins.iter().map(|b| (*b as f64).powi(2)).sum()
+}
 
-        let mut jpeg_bytes = Vec::new();
-        let mut encoder = JpegEncoder::new_with_quality(&mut jpeg_bytes, jpeg_quality);
-        let rgb_img = img.into_rgb8();
-        encoder
-            .encode(
-                rgb_img.as_raw(),
-                w,
-                h,
-                ExtendedColorType::Rgb8,
-            )
-            .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+// Latin ascenders and capitals outnumber descenders, so an upright line has more
+// ink above its x-height band than below it
+fn text_looks_upside_down(mask: &[bool], w: u32, h: u32) -> bool {
+    let rows = row_profile(mask, w, h);
+    let mean = rows.iter().map(|r| *r as f64).sum::<f64>() / rows.len().max(1) as f64;
+    let gap = (mean * 0.1).max(1.0) as u32;
+
+    let (mut above, mut below) = (0u64, 0u64);
+    let mut y = 0;
+    while y < rows.len() {
+        if rows[y] < gap {
+            y += 1;
+            continue;
+        }
+        let start = y;
+        while y < rows.len() && rows[y] >= gap {
+            y += 1;
+        }
+        let band = &rows[start..y];
+        if band.len() < 4 {
+            continue;
+        }
+        let peak = *band.iter().max().unwrap_or(&0);
+        let core_start = band.iter().position(|r| *r * 2 >= peak).unwrap_or(0);
+        let core_end = band.iter().rposition(|r| *r * 2 >= peak).unwrap_or(band.len() - 1);
+        above += band[..core_start].iter().map(|r| *r as u64).sum::<u64>();
+        below += band[core_end + 1..].iter().map(|r| *r as u64).sum::<u64>();
+    }
+    below > above
+}
 
-        Ok((jpeg_bytes, (w, h), Some(printpdf::ImageFilter::DCT)))
+// Turns a page whose text runs sideways or upside down the right way up. Lines of
+// text give a much sharper row profile than column profile when horizontal.
+fn orient_text_upright(img: DynamicImage) -> DynamicImage {
+    let (mask, w, h) = scan_ink_mask(&img);
+    if !has_enough_ink(&mask) {
+        return img;
+    }
+    let (img, mask, w, h) = if profile_peakiness(&column_profile(&mask, w, h)) > profile_peakiness(&row_profile(&mask, w, h)) * 1.3 {
+        let turned = img.rotate90();
+        let (mask, w, h) = scan_ink_mask(&turned);
+        (turned, mask, w, h)
+    } else {
+        (img, mask, w, h)
     };
-
-    let first_img_path = &image_paths[0];
-    let mut reader = ImageReader::open(first_img_path).map_err(|e| format!("Failed to open first image: {}", e))?;
-    reader.set_format(ImageFormat::from_path(first_img_path).unwrap_or(ImageFormat::Jpeg));
-    let first_img = reader.decode().map_err(|e| format!("Failed to decode first image: {}", e))?;
-    let (_first_img_w, _first_img_h) = first_img.dimensions();
-    let (first_data, (f_w, f_h), first_filter) = process_image(first_img, quality)?;
-    
-    // Calculate page dimensions
-    let (page_w_px, page_h_px) = if upscale.unwrap_or(false) {
-        (max_width, max_height)
+    if text_looks_upside_down(&mask, w, h) {
+        img.rotate180()
     } else {
-        (f_w as f32, f_h as f32)
+        img
+    }
+}
+
+// Straightens a crooked scan by up to 10 degrees, filling the exposed corners with white
+fn deskew_scan(img: DynamicImage) -> DynamicImage {
+    let (mask, w, h) = scan_ink_mask(&img);
+    if !has_enough_ink(&mask) {
+        return img;
+    }
+    let best_in = |angles: Vec<f32>| -> f32 {
+        angles
+            .into_iter()
+            .map(|a| (a, skewed_profile_score(&mask, w, h, a)))
+            .fold((0.0, f64::MIN), |best, cur| if cur.1 > best.1 { cur } else { best })
+            .0
     };
+    let coarse = best_in((-20..=20).map(|i| i as f32 * 0.5).collect());
+    let angle = best_in((-5..=5).map(|i| coarse + i as f32 * 0.1).collect());
+    if angle.abs() < 0.1 {
+        return img;
+    }
+    rotate_scan(&img, angle)
+}
 
-    let (doc, page1, layer1) = PdfDocument::new(
-        "output",
-        Mm(page_w_px * mm_per_px),
-        Mm(page_h_px * mm_per_px),
-        "Layer 1",
-    );
-    
-    let current_layer = doc.get_page(page1).get_layer(layer1);
-    
-    let add_img_to_page = |layer: PdfLayerReference, data: Vec<u8>, filter: Option<printpdf::ImageFilter>, img_w: u32, img_h: u32, p_w: f32, p_h: f32| {
+// Rotates about the centre so a line sloping by `degrees` comes out level, keeping the
+// original canvas size
+fn rotate_scan(img: &DynamicImage, degrees: f32) -> DynamicImage {
+    let src = img.to_rgb8();
+    let (w, h) = src.dimensions();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+    let out = image::RgbImage::from_fn(w, h, |x, y| {
+        let dx = x as f32 + 0.5 - cx;
+        let dy = y as f32 + 0.5 - cy;
+        let sx = cx + dx * cos - dy * sin - 0.5;
+        let sy = cy + dx * sin + dy * cos - 0.5;
+        if sx < 0.0 || sy < 0.0 || sx > (w - 1) as f32 || sy > (h - 1) as f32 {
+            return image::Rgb([255, 255, 255]);
+        }
+        let (x0, y0) = (sx as u32, sy as u32);
+        let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+        let (fx, fy) = (sx - x0 as f32, sy - y0 as f32);
+        let mut pixel = [0u8; 3];
+        for (c, value) in pixel.iter_mut().enumerate() {
+            let top = src.get_pixel(x0, y0)[c] as f32 * (1.0 - fx) + src.get_pixel(x1, y0)[c] as f32 * fx;
+            let bottom = src.get_pixel(x0, y1)[c] as f32 * (1.0 - fx) + src.get_pixel(x1, y1)[c] as f32 * fx;
+            *value = (top * (1.0 - fy) + bottom * fy).round() as u8;
+        }
+        image::Rgb(pixel)
+    });
+    DynamicImage::ImageRgb8(out)
+}
+
+// Decodes every image in parallel, then lays the pages out in order. Returns
+// Ok(false) when the job was cancelled part way through.
+fn build_pdf(app: &tauri::AppHandle, state: &AppState, image_paths: &[String], output_path: &str, image_options: PdfImageOptions, upscale: bool) -> Result<bool, String> {
+    use printpdf::*;
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    let count = image_paths.len();
+    let done = AtomicUsize::new(0);
+    let cancelled = AtomicBool::new(false);
+
+    emit_job_progress(app, "pdf", Some(JobItem { index: 0, count }), 0.0, None, None);
+
+    // The rayon workers below aren't part of the job's task, so they report to its window by name
+    let owner = job_window();
+    let images: Vec<Option<Result<PdfPageImage, String>>> = image_paths
+        .par_iter()
+        .map(|img_path| {
+            if cancelled.load(Ordering::Relaxed) || *state.is_cancelling.blocking_lock() {
+                cancelled.store(true, Ordering::Relaxed);
+                return None;
+            }
+            let image = encode_pdf_image(img_path, image_options);
+            let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+            emit_job_progress_to(
+                app,
+                owner.clone(),
+                "pdf",
+                Some(JobItem { index: finished, count }),
+                finished as f64 / count as f64 * 100.0,
+                None,
+                None,
+            );
+            Some(image)
+        })
+        .collect();
+
+    if cancelled.load(Ordering::Relaxed) {
+        *state.is_cancelling.blocking_lock() = false;
+        return Ok(false);
+    }
+
+    // The first image has to decode; later ones that fail are skipped like before
+    let mut pages = Vec::with_capacity(count);
+    for (i, image) in images.into_iter().enumerate() {
+        match image {
+            Some(Ok(image)) => pages.push(image),
+            Some(Err(e)) if i == 0 => return Err(format!("First image: {}", e)),
+            _ => {}
+        }
+    }
+
+    // Use 300 DPI for better quality PDF (standard print resolution)
+    // 1 inch = 25.4 mm = 300 pixels at 300 DPI
+    let dpi = 300.0_f32;
+    let mm_per_px = 25.4_f32 / dpi;
+
+    // Every page shares the largest dimensions when upscaling
+    let max_width = pages.iter().map(|p| p.width).max().unwrap_or(0) as f32;
+    let max_height = pages.iter().map(|p| p.height).max().unwrap_or(0) as f32;
+    let page_size = |image: &PdfPageImage| -> (f32, f32) {
+        if upscale {
+            (max_width, max_height)
+        } else {
+            (image.width as f32, image.height as f32)
+        }
+    };
+
+    let add_img_to_page = |layer: PdfLayerReference, page_image: PdfPageImage, p_w: f32, p_h: f32| {
+        let (img_w, img_h) = (page_image.width, page_image.height);
         let x_object = printpdf::ImageXObject {
             width: printpdf::Px(img_w as usize),
             height: printpdf::Px(img_h as usize),
-            color_space: printpdf::ColorSpace::Rgb,
+            color_space: if page_image.greyscale { printpdf::ColorSpace::Greyscale } else { printpdf::ColorSpace::Rgb },
             bits_per_component: printpdf::ColorBits::Bit8,
-            interpolate: true,
-            image_data: data,
-            image_filter: filter,
+            interpolate: !page_image.lossless,
+            image_data: page_image.data,
+            image_filter: if page_image.lossless { None } else { Some(printpdf::ImageFilter::DCT) },
             clipping_bbox: None,
             smask: None,
         };
@@ -2935,31 +14899,167 @@ async fn convert_images_to_pdf(image_paths: Vec<String>, output_path: String, qu
         image.add_to_layer(layer, transform);
     };
 
-    add_img_to_page(current_layer, first_data, first_filter, f_w, f_h, page_w_px, page_h_px);
-    
-    for i in 1..image_paths.len() {
-        let img_path = &image_paths[i];
-        if let Ok(mut r) = ImageReader::open(img_path) {
-            r.set_format(ImageFormat::from_path(img_path).unwrap_or(ImageFormat::Jpeg));
-            if let Ok(img) = r.decode() {
-                let (_img_w, _img_h) = img.dimensions();
-                let (data, (w, h), filter) = process_image(img, quality)?;
-                let (p_w, p_h) = if upscale.unwrap_or(false) {
-                    (max_width, max_height)
-                } else {
-                    (w as f32, h as f32)
-                };
-                
-                let (p, l) = doc.add_page(Mm(p_w * mm_per_px), Mm(p_h * mm_per_px), format!("Layer {}", i + 1));
-                add_img_to_page(doc.get_page(p).get_layer(l), data, filter, w, h, p_w, p_h);
-            }
-        }
+    let mut pages = pages.into_iter();
+    let first = pages.next().ok_or_else(|| msg("error.no_images"))?;
+    let (page_w_px, page_h_px) = page_size(&first);
+    let (doc, page1, layer1) = PdfDocument::new(
+        "output",
+        Mm(page_w_px * mm_per_px),
+        Mm(page_h_px * mm_per_px),
+        "Layer 1",
+    );
+    add_img_to_page(doc.get_page(page1).get_layer(layer1), first, page_w_px, page_h_px);
+
+    for (i, image) in pages.enumerate() {
+        let (p_w, p_h) = page_size(&image);
+        let (p, l) = doc.add_page(Mm(p_w * mm_per_px), Mm(p_h * mm_per_px), format!("Layer {}", i + 2));
+        add_img_to_page(doc.get_page(p).get_layer(l), image, p_w, p_h);
     }
     
-    let file = std::fs::File::create(&output_path).map_err(|e| format!("Failed to create PDF: {}", e))?;
+    let file = std::fs::File::create(output_path).map_err(|e| format!("Failed to create PDF: {}", e))?;
     doc.save(&mut std::io::BufWriter::new(file)).map_err(|e| format!("Failed to save PDF: {}", e))?;
     
-    Ok(output_path)
+    Ok(true)
+}
+
+// ============================================================================
+// Media Protocol
+// ============================================================================
+
+// Largest body sent for one range request; the player asks for the next part as it plays
+const MEDIA_CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+
+fn media_mime_type(path: &std::path::Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    Some(match ext.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        "avi" => "video/x-msvideo",
+        "ts" | "m2ts" => "video/mp2t",
+        "ogv" => "video/ogg",
+        "mp3" => "audio/mpeg",
+        "m4a" | "aac" => "audio/mp4",
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "ogg" | "opus" => "audio/ogg",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        _ => return None,
+    })
+}
+
+// Path segments are percent-encoded byte by byte; '+' stays literal
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(value) = u8::from_str_radix(&text[i + 1..i + 3], 16) {
+                out.push(value);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// URL the webview loads a local file from (Windows webviews only route custom schemes over http://<scheme>.localhost)
+#[tauri::command]
+fn get_media_url(path: String) -> String {
+    let encoded: String = path
+        .bytes()
+        .map(|b| if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') { (b as char).to_string() } else { format!("%{:02X}", b) })
+        .collect();
+    if cfg!(windows) {
+        format!("http://media.localhost/{}", encoded)
+    } else {
+        format!("media://localhost/{}", encoded)
+    }
+}
+
+// "bytes=start-end" -> inclusive range clamped to the file, None for anything unsatisfiable
+fn parse_byte_range(header: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = header.trim().strip_prefix("bytes=")?.split(',').next()?.trim();
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = if start.is_empty() {
+        // Suffix range: the last N bytes
+        let suffix: u64 = end.parse().ok()?;
+        (len.saturating_sub(suffix), len.checked_sub(1)?)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = if end.is_empty() { start.saturating_add(MEDIA_CHUNK_BYTES - 1) } else { end.parse().ok()? };
+        (start, end.min(len.checked_sub(1)?))
+    };
+    if start > end || start >= len {
+        return None;
+    }
+    Some((start, end.min(start + MEDIA_CHUNK_BYTES - 1)))
+}
+
+// Streams local media to the player with Range support, so previews play and seek
+// straight from disk instead of being copied or base64-encoded first
+fn serve_media_request(app: &tauri::AppHandle, request: &tauri::http::Request<Vec<u8>>) -> tauri::http::Response<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+    use tauri::http::{header, Response, StatusCode};
+
+    let error = |status: StatusCode| Response::builder().status(status).body(Vec::new()).unwrap_or_default();
+    let path = percent_decode(request.uri().path().trim_start_matches('/'));
+    let path = if cfg!(windows) { path } else { format!("/{}", path.trim_start_matches('/')) };
+    let Some(file_path) = validate_path(&path).filter(|p| p.is_file()) else {
+        return error(StatusCode::NOT_FOUND);
+    };
+    if scoped_path(app, &path).is_err() {
+        return error(StatusCode::FORBIDDEN);
+    }
+    // Only media types are served, this is not a general file server
+    let Some(mime) = media_mime_type(&file_path) else {
+        return error(StatusCode::FORBIDDEN);
+    };
+
+    let Ok(mut file) = std::fs::File::open(&file_path) else {
+        return error(StatusCode::NOT_FOUND);
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let range_header = request.headers().get(header::RANGE).and_then(|v| v.to_str().ok());
+
+    let (status, start, end) = match range_header {
+        Some(range) => match parse_byte_range(range, len) {
+            Some((start, end)) => (StatusCode::PARTIAL_CONTENT, start, end),
+            None => {
+                return Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                    .body(Vec::new())
+                    .unwrap_or_default();
+            }
+        },
+        // Players that don't ask for a range still only get one chunk at a time
+        None if len > MEDIA_CHUNK_BYTES => (StatusCode::PARTIAL_CONTENT, 0, MEDIA_CHUNK_BYTES - 1),
+        None => (StatusCode::OK, 0, len.saturating_sub(1)),
+    };
+
+    let mut body = vec![0u8; if len == 0 { 0 } else { (end - start + 1) as usize }];
+    if file.seek(SeekFrom::Start(start)).and_then(|_| file.read_exact(&mut body)).is_err() {
+        return error(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_LENGTH, body.len().to_string());
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len));
+    }
+    response.body(body).unwrap_or_default()
 }
 
 // ============================================================================
@@ -2979,8 +15079,29 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_os::init())
-        .setup(|_app| {
+        .register_asynchronous_uri_scheme_protocol("media", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || responder.respond(serve_media_request(&app, &request)));
+        })
+        .setup(|app| {
             info!("Video Toolbox starting up...");
+            let settings = load_settings(app.handle());
+            SAFE_PROBE.store(settings.safe_probe, std::sync::atomic::Ordering::Relaxed);
+            PROGRESS_EVENT_RATE.store(
+                settings.progress_events_per_second.map(|r| r.min(60)).unwrap_or(DEFAULT_PROGRESS_EVENTS_PER_SECOND),
+                std::sync::atomic::Ordering::Relaxed,
+            );
+            if let Ok(mut current) = OUTPUT_ORGANIZATION.write() {
+                *current = settings.output_organization;
+            }
+            if let Ok(mut scope) = app.state::<Arc<AppState>>().path_scope.lock() {
+                *scope = load_path_scope(app.handle());
+            }
+            if let Some(system_locale) = tauri_plugin_os::locale() {
+                if let Ok(mut current) = CURRENT_LOCALE.write() {
+                    *current = normalize_locale(&system_locale).to_string();
+                }
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -2991,36 +15112,146 @@ pub fn run() {
             select_folder,
             // Utility commands
             list_files,
+            list_approved_folders,
+            revoke_approved_folder,
             get_app_version,
+            set_locale,
+            get_locale,
+            get_settings,
+            set_default_output_format,
+            set_safe_probe_mode,
+            set_output_organization,
+            set_progress_event_rate,
+            set_job_event_broadcast,
+            set_webhooks,
+            test_webhook,
+            // Preset commands
+            save_preset,
+            list_presets,
+            load_preset,
+            delete_preset,
+            export_presets,
+            import_presets,
+            // Command template commands
+            save_command_template,
+            list_command_templates,
+            delete_command_template,
+            validate_command_template,
+            run_command_template,
+            // Session restore commands
+            load_session,
+            set_active_tool,
+            save_tool_state,
+            load_tool_state,
+            save_pending_jobs,
+            clear_session,
+            // File notes and tags
+            set_file_annotation,
+            get_file_annotations,
+            find_files_by_tag,
+            list_file_tags,
+            summarize_folder,
+            // Report commands
+            export_job_report,
             // FFmpeg commands
             get_encoders,
             get_metadata,
+            get_media_url,
+            detect_image_sequence,
             get_metadata_full,
             get_image_info,
             save_metadata,
+            extract_stream,
             // Encoding commands
             start_encode,
+            list_loudness_presets,
+            normalize_audio,
+            change_audio_speed,
+            convert_audio,
+            scan_replaygain,
+            get_audio_stats,
             extract_audio,
+            extract_vocal_track,
+            extract_audio_batch,
+            list_audio_streams,
             trim_video,
             video_to_gif,
+            optimize_gif,
+            export_sticker,
+            gif_to_video,
+            create_timelapse,
+            encode_renditions,
+            fix_av_sync,
+            detect_av_desync,
+            replace_audio,
+            remove_audio,
+            mix_background_music,
+            mux_accessibility_tracks,
             image_to_gif,
+            concat_videos,
+            concat_audio,
+            render_audio_visualizer,
+            add_intro_outro,
+            split_by_chapters,
+            split_audio_by_chapters,
+            split_video,
+            split_on_silence,
+            split_by_cue,
+            detect_scenes,
+            detect_telecine,
+            scan_disc_folder,
+            rip_disc_title_set,
             cancel_encode,
+            build_command_preview,
             // Media processing
             get_audio_waveform,
+            get_waveform_peaks,
+            make_audio_preview,
             get_video_thumbnails,
+            capture_screenshot_burst,
+            extract_frames,
+            video_to_frames,
+            suggest_thumbnails,
+            compare_quality,
+            create_comparison_video,
+            export_wipe_comparison,
+            stack_videos,
+            // Repair commands
+            repair_video,
+            recover_recording,
             // Download commands
             get_video_info,
             download_video,
+            download_and_convert,
             cancel_download,
+            // Audio recording
+            list_audio_devices,
+            start_audio_record,
+            stop_audio_record,
             // Shell commands
             open_file,
             open_folder,
             open_external,
+            copy_to_clipboard,
             // PDF commands
             convert_images_to_pdf,
             pdf_to_images,
             frontend_log,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            match event {
+                // Closing mid-job must not leave ffmpeg/yt-dlp running or half-written files behind
+                tauri::RunEvent::Exit => shutdown_children(),
+                // Dropping files on the window approves their folders like a file dialog does
+                tauri::RunEvent::WindowEvent { event: tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }), .. } => {
+                    for path in &paths {
+                        approve_path(app, path);
+                    }
+                }
+                tauri::RunEvent::WindowEvent { label, event: tauri::WindowEvent::Destroyed, .. } => forget_job_window(app, &label),
+                _ => {}
+            }
+        });
 }
@@ -0,0 +1,219 @@
+// Scripted stand-ins for ffmpeg, ffprobe and yt-dlp, so the code that builds their
+// arguments, parses their progress and cancels them can run in tests without the
+// real tools. Each fake is a shell script (a batch file on Windows) that records its
+// arguments and answers with the first response whose needle appears in them.
+
+use super::*;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::test::MockRuntime;
+use tauri::Listener;
+
+// The fakes are selected through process-wide environment variables, so tests that
+// install them take turns
+static FAKE_TOOLS_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+// Line written after each invocation's arguments in the call log
+const CALL_END: &str = "--end-of-call--";
+
+// What a fake prints and how it exits
+#[derive(Debug, Clone, Default)]
+pub struct FakeResponse {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+    hang_secs: u32,
+}
+
+impl FakeResponse {
+    pub fn success() -> Self {
+        Self::default()
+    }
+
+    pub fn failure(exit_code: i32) -> Self {
+        Self { exit_code, ..Self::default() }
+    }
+
+    pub fn stdout(mut self, text: &str) -> Self {
+        self.stdout.push_str(text);
+        self
+    }
+
+    pub fn stderr(mut self, line: &str) -> Self {
+        self.stderr.push_str(line);
+        self.stderr.push('\n');
+        self
+    }
+
+    // An ffmpeg status line, which ffmpeg ends with \r rather than a newline
+    pub fn progress(mut self, seconds: f64, speed: &str) -> Self {
+        let total = seconds.max(0.0);
+        let (h, m, s) = ((total / 3600.0) as u32, ((total % 3600.0) / 60.0) as u32, total % 60.0);
+        self.stderr.push_str(&format!(
+            "frame=  120 fps=60 q=28.0 size=    256kB time={:02}:{:02}:{:05.2} bitrate= 512.0kbits/s speed={}\r",
+            h, m, s, speed
+        ));
+        self
+    }
+
+    // Keeps running this long after printing, to have something to cancel
+    pub fn hang(mut self, secs: u32) -> Self {
+        self.hang_secs = secs;
+        self
+    }
+}
+
+// Responses checked in order; a call that matches none exits 0 without output
+#[derive(Debug, Clone, Default)]
+pub struct FakeTool {
+    responses: Vec<(Option<String>, FakeResponse)>,
+}
+
+impl FakeTool {
+    pub fn when(mut self, needle: &str, response: FakeResponse) -> Self {
+        assert!(!needle.contains(['\'', '"', '%']), "needles are matched literally and can't contain quotes or %");
+        self.responses.push((Some(needle.to_string()), response));
+        self
+    }
+
+    pub fn otherwise(mut self, response: FakeResponse) -> Self {
+        self.responses.push((None, response));
+        self
+    }
+}
+
+// Installed fakes with a scratch folder for inputs and outputs. Dropping it restores
+// the real tools and deletes the folder.
+pub struct FakeTools {
+    dir: PathBuf,
+    _lock: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl FakeTools {
+    // tool is "ffmpeg", "ffprobe" or "yt-dlp"
+    pub async fn install(tools: Vec<(&str, FakeTool)>) -> Self {
+        let lock = FAKE_TOOLS_LOCK.lock().await;
+        let dir = std::env::temp_dir().join(format!("video-toolbox-fakes-{}-{}", std::process::id(), NEXT_DIR.fetch_add(1, Ordering::Relaxed)));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create fake tool folder");
+        for (tool, fake) in tools {
+            let script = write_fake(&dir, tool, &fake);
+            std::env::set_var(override_var(tool), &script);
+        }
+        // Every progress event reaches the listeners instead of being coalesced
+        PROGRESS_EVENT_RATE.store(0, std::sync::atomic::Ordering::Relaxed);
+        Self { dir, _lock: lock }
+    }
+
+    pub fn scratch(&self, file_name: &str) -> PathBuf {
+        self.dir.join(file_name)
+    }
+
+    // Arguments of each time the tool ran. The batch file can only log its raw command
+    // line, so on Windows the arguments are split at spaces.
+    pub fn calls(&self, tool: &str) -> Vec<Vec<String>> {
+        let log = std::fs::read_to_string(self.dir.join(format!("{}.calls", tool))).unwrap_or_default();
+        let mut calls = Vec::new();
+        let mut current = Vec::new();
+        for line in log.lines() {
+            if line.trim() == CALL_END {
+                calls.push(std::mem::take(&mut current));
+            } else if cfg!(windows) {
+                current.extend(line.split_whitespace().map(|a| a.trim_matches('"').to_string()));
+            } else {
+                current.push(line.to_string());
+            }
+        }
+        calls
+    }
+}
+
+impl Drop for FakeTools {
+    fn drop(&mut self) {
+        for tool in ["ffmpeg", "ffprobe", "yt-dlp"] {
+            std::env::remove_var(override_var(tool));
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn override_var(tool: &str) -> String {
+    format!("VIDEO_TOOLBOX_{}", tool.to_uppercase().replace('-', "_"))
+}
+
+fn write_fake(dir: &Path, tool: &str, fake: &FakeTool) -> PathBuf {
+    for (i, (_, response)) in fake.responses.iter().enumerate() {
+        std::fs::write(dir.join(format!("{}.{}.out", tool, i)), &response.stdout).expect("write fake stdout");
+        std::fs::write(dir.join(format!("{}.{}.err", tool, i)), &response.stderr).expect("write fake stderr");
+    }
+    let file = |suffix: String| dir.join(format!("{}{}", tool, suffix)).to_string_lossy().to_string();
+
+    #[cfg(unix)]
+    let (script_path, script) = {
+        let mut script = format!(
+            "#!/bin/sh\nfor arg in \"$@\"; do printf '%s\\n' \"$arg\" >> '{calls}'; done\nprintf '%s\\n' '{end}' >> '{calls}'\ncase \" $* \" in\n",
+            calls = file(".calls".to_string()),
+            end = CALL_END
+        );
+        for (i, (needle, response)) in fake.responses.iter().enumerate() {
+            let pattern = needle.as_ref().map(|n| format!("*'{}'*", n)).unwrap_or_else(|| "*".to_string());
+            script.push_str(&format!("  {}) cat '{}'; cat '{}' >&2;", pattern, file(format!(".{}.out", i)), file(format!(".{}.err", i))));
+            if response.hang_secs > 0 {
+                script.push_str(&format!(" sleep {};", response.hang_secs));
+            }
+            script.push_str(&format!(" exit {} ;;\n", response.exit_code));
+        }
+        script.push_str("esac\nexit 0\n");
+        (dir.join(tool), script)
+    };
+
+    #[cfg(windows)]
+    let (script_path, script) = {
+        let calls = file(".calls".to_string());
+        let mut script = format!("@echo off\r\necho(%*>>\"{calls}\"\r\necho({end}>>\"{calls}\"\r\n", calls = calls, end = CALL_END);
+        for (i, (needle, _)) in fake.responses.iter().enumerate() {
+            match needle {
+                Some(needle) => script.push_str(&format!("echo(%* | findstr /L /C:\"{}\" >nul && goto response{}\r\n", needle, i)),
+                None => script.push_str(&format!("goto response{}\r\n", i)),
+            }
+        }
+        script.push_str("exit /b 0\r\n");
+        for (i, (_, response)) in fake.responses.iter().enumerate() {
+            script.push_str(&format!(":response{}\r\ntype \"{}\"\r\ntype \"{}\" 1>&2\r\n", i, file(format!(".{}.out", i)), file(format!(".{}.err", i))));
+            if response.hang_secs > 0 {
+                script.push_str(&format!("ping -n {} 127.0.0.1 >nul\r\n", response.hang_secs + 1));
+            }
+            script.push_str(&format!("exit /b {}\r\n", response.exit_code));
+        }
+        (dir.join(format!("{}.cmd", tool)), script)
+    };
+
+    std::fs::write(&script_path, script).expect("write fake tool");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755)).expect("make fake tool executable");
+    }
+    script_path
+}
+
+// App handle on Tauri's mock runtime with the state the commands expect
+pub fn mock_app() -> tauri::App<MockRuntime> {
+    tauri::test::mock_builder()
+        .manage(Arc::new(AppState::default()))
+        .build(tauri::test::mock_context(tauri::test::noop_assets()))
+        .expect("build mock app")
+}
+
+// Collects the payloads of an event as they are emitted
+pub fn capture_events(app: &tauri::AppHandle<MockRuntime>, event: &str) -> Arc<std::sync::Mutex<Vec<serde_json::Value>>> {
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = events.clone();
+    app.listen_any(event.to_string(), move |e| {
+        if let Ok(value) = serde_json::from_str(e.payload()) {
+            sink.lock().unwrap().push(value);
+        }
+    });
+    events
+}
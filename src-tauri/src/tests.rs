@@ -0,0 +1,353 @@
+use super::*;
+use crate::test_support::{capture_events, mock_app, FakeResponse, FakeTool, FakeTools};
+
+fn arg_after<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).map(|a| a.as_str())
+}
+
+#[test]
+fn atempo_chain_splits_factors_outside_its_range() {
+    assert!(atempo_chain(1.0).is_empty());
+    assert_eq!(atempo_chain(1.5), vec!["atempo=1.500000"]);
+    assert_eq!(atempo_chain(3.0), vec!["atempo=2.0", "atempo=1.500000"]);
+    assert_eq!(atempo_chain(0.3), vec!["atempo=0.5", "atempo=0.600000"]);
+}
+
+#[test]
+fn ytdlp_errors_are_classified() {
+    assert_eq!(classify_ytdlp_error("ERROR: [youtube] abc: This video is private").map(|f| f.0), Some("private"));
+    assert_eq!(classify_ytdlp_error("ERROR: unable to download webpage: timed out").map(|f| f.0), None);
+}
+
+#[test]
+fn preview_arguments_are_quoted_for_the_shell() {
+    assert_eq!(quote_command_arg("-c:v"), "-c:v");
+    assert_eq!(quote_command_arg("/videos/clip.mp4"), "/videos/clip.mp4");
+    assert_eq!(quote_command_arg("$(rm -rf ~)"), "'$(rm -rf ~)'");
+    assert_eq!(quote_command_arg("drawtext=text='Hi'"), r"'drawtext=text='\''Hi'\'''");
+    assert_eq!(quote_command_arg(""), "''");
+}
+
+#[tokio::test]
+async fn extract_audio_args_seek_on_the_input() {
+    // The builder may probe the source; a fake keeps that off whatever ffprobe is installed
+    let summary = r#"{"format":{"duration":"60.0"},"streams":[{"codec_type":"audio","codec_name":"aac","sample_rate":"48000","channels":2}]}"#;
+    let fakes = FakeTools::install(vec![("ffprobe", FakeTool::default().when("-print_format", FakeResponse::success().stdout(summary)))]).await;
+    let input = fakes.scratch("clip.mp4");
+    std::fs::write(&input, b"").unwrap();
+    let app = mock_app();
+
+    let plan = build_extract_audio_args(app.handle(), ExtractAudioOptions {
+        input: input.to_string_lossy().to_string(),
+        format: "mp3".to_string(),
+        start_seconds: Some(10.0),
+        end_seconds: Some(25.0),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let input_at = plan.args.iter().position(|a| a == "-i").unwrap();
+    let seek_at = plan.args.iter().position(|a| a == "-ss").unwrap();
+    assert!(seek_at < input_at, "-ss must come before -i: {:?}", plan.args);
+    assert_eq!(arg_after(&plan.args, "-t"), Some("15"));
+    assert_eq!(arg_after(&plan.args, "-c:a"), Some("libmp3lame"));
+    assert_eq!(plan.duration_secs, Some(15.0));
+    assert!(plan.output_path.ends_with("clip_audio_10-25s.mp3"), "{}", plan.output_path);
+}
+
+#[tokio::test]
+async fn extract_audio_downmix_uses_probed_layout() {
+    let streams = r#"{"streams":[{"index":1,"codec_name":"ac3","channels":6,"channel_layout":"5.1(side)","sample_rate":"48000"}]}"#;
+    let fakes = FakeTools::install(vec![(
+        "ffprobe",
+        FakeTool::default()
+            .when("-select_streams", FakeResponse::success().stdout(streams))
+            .otherwise(FakeResponse::failure(1).stderr("unexpected probe")),
+    )])
+    .await;
+    let input = fakes.scratch("movie.mkv");
+    std::fs::write(&input, b"").unwrap();
+    let app = mock_app();
+
+    let plan = build_extract_audio_args(app.handle(), ExtractAudioOptions {
+        input: input.to_string_lossy().to_string(),
+        format: "flac".to_string(),
+        channel_mode: Some("stereo".to_string()),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(arg_after(&plan.args, "-af"), Some("pan=stereo|FL<FL+0.707*FC+0.707*SL|FR<FR+0.707*FC+0.707*SR"));
+    assert!(plan.output_path.ends_with("movie_audio_stereo.flac"), "{}", plan.output_path);
+    assert_eq!(fakes.calls("ffprobe").len(), 1);
+}
+
+#[tokio::test]
+async fn safe_probe_accepts_lossless_audio_containers() {
+    let summary = r#"{"format":{"duration":"241.5"},"streams":[{"codec_type":"audio","codec_name":"flac","sample_rate":"44100","channels":2}]}"#;
+    let fakes = FakeTools::install(vec![("ffprobe", FakeTool::default().otherwise(FakeResponse::success().stdout(summary)))]).await;
+    let ape = fakes.scratch("album.ape");
+    let wv = fakes.scratch("album.wv");
+    std::fs::write(&ape, b"").unwrap();
+    std::fs::write(&wv, b"").unwrap();
+
+    SAFE_PROBE.store(true, std::sync::atomic::Ordering::Relaxed);
+    let probed = [probe_media_summary(&ape.to_string_lossy()).await, probe_media_summary(&wv.to_string_lossy()).await];
+    SAFE_PROBE.store(false, std::sync::atomic::Ordering::Relaxed);
+
+    for summary in probed {
+        assert_eq!(summary.unwrap().duration, Some(241.5));
+    }
+    for call in fakes.calls("ffprobe") {
+        let formats: Vec<&str> = arg_after(&call, "-format_whitelist").unwrap().split(',').collect();
+        assert!(formats.contains(&"ape") && formats.contains(&"wv"), "{:?}", formats);
+    }
+    assert_eq!(fakes.calls("ffprobe").len(), 2);
+}
+
+#[tokio::test]
+async fn ffmpeg_progress_is_parsed_into_events() {
+    let fakes = FakeTools::install(vec![(
+        "ffmpeg",
+        FakeTool::default().otherwise(
+            FakeResponse::success()
+                .stderr("Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mp4':")
+                .stderr("  Duration: 00:00:10.00, start: 0.000000, bitrate: 1024 kb/s")
+                .progress(2.5, "1.25x")
+                .progress(5.0, "2.00x"),
+        ),
+    )])
+    .await;
+    let app = mock_app();
+    let events = capture_events(app.handle(), "encode-progress");
+    let output = fakes.scratch("out.mp4").to_string_lossy().to_string();
+
+    let args = vec!["-i".to_string(), "in.mp4".to_string(), output.clone()];
+    let run = run_ffmpeg_job(app.handle(), "encode", Some(JobItem { index: 1, count: 2 }), &args, &output, None).await.unwrap();
+
+    assert!(run.success && !run.cancelled);
+    let events = events.lock().unwrap();
+    let last = events.last().expect("progress events");
+    assert_eq!(last["percent"], 50.0);
+    assert_eq!(last["speed"], "2.00x");
+    assert_eq!(last["itemIndex"], 1);
+    assert_eq!(last["overallPercent"], 75.0);
+    assert_eq!(events.len(), 2);
+    let calls = fakes.calls("ffmpeg");
+    assert_eq!(calls.len(), 1);
+    assert!(calls[0].iter().any(|a| a == "in.mp4"), "{:?}", calls);
+}
+
+#[tokio::test]
+async fn ffmpeg_failure_keeps_the_error_output() {
+    let _fakes = FakeTools::install(vec![(
+        "ffmpeg",
+        FakeTool::default().otherwise(FakeResponse::failure(1).stderr("Unknown encoder 'libfoo'")),
+    )])
+    .await;
+    let app = mock_app();
+
+    let run = run_ffmpeg_job(app.handle(), "encode", None, &["-c:v".to_string(), "libfoo".to_string()], "", Some(1.0)).await.unwrap();
+
+    assert!(!run.success && !run.cancelled);
+    assert_eq!(run.code, Some(1));
+    assert!(run.stderr_tail.contains("Unknown encoder 'libfoo'"), "{}", run.stderr_tail);
+}
+
+#[tokio::test]
+async fn cancelling_stops_ffmpeg_and_removes_the_partial_output() {
+    let fakes = FakeTools::install(vec![("ffmpeg", FakeTool::default().otherwise(FakeResponse::success().progress(1.0, "1.00x").hang(30)))]).await;
+    let app = mock_app();
+    let output = fakes.scratch("partial.mkv");
+    std::fs::write(&output, b"half a file").unwrap();
+
+    let handle = app.handle().clone();
+    let output_str = output.to_string_lossy().to_string();
+    let job = tokio::spawn(async move { run_ffmpeg_job(&handle, "encode", None, &[output_str.clone()], &output_str, Some(60.0)).await });
+
+    let started = std::time::Instant::now();
+    while app.state::<Arc<AppState>>().current_pid.lock().await.is_none() {
+        assert!(started.elapsed() < std::time::Duration::from_secs(10), "fake ffmpeg did not start");
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    cancel_encode(app.handle().clone()).await.unwrap();
+
+    let run = tokio::time::timeout(std::time::Duration::from_secs(10), job).await.expect("job ended").unwrap().unwrap();
+    assert!(run.cancelled);
+    assert!(!output.exists());
+    assert!(app.state::<Arc<AppState>>().current_pid.lock().await.is_none());
+}
+
+#[tokio::test]
+async fn video_info_reports_ytdlp_failures_by_kind() {
+    let _fakes = FakeTools::install(vec![(
+        "yt-dlp",
+        FakeTool::default().otherwise(FakeResponse::failure(1).stderr("ERROR: [youtube] abc: This video is private")),
+    )])
+    .await;
+
+    let info = get_video_info("https://www.youtube.com/watch?v=abc".to_string(), None).await.unwrap();
+
+    assert_eq!(info.error_kind.as_deref(), Some("private"));
+    assert!(info.title.is_none());
+}
+
+#[tokio::test]
+async fn video_info_parses_the_ytdlp_json() {
+    let fakes = FakeTools::install(vec![(
+        "yt-dlp",
+        FakeTool::default().when(
+            "--dump-single-json",
+            FakeResponse::success().stdout(r#"{"title":"Launch day","duration":3725,"uploader":"Example","vcodec":"avc1"}"#),
+        ),
+    )])
+    .await;
+
+    let info = get_video_info("https://example.com/videos/launch".to_string(), None).await.unwrap();
+
+    assert_eq!(info.title.as_deref(), Some("Launch day"));
+    assert_eq!(info.duration.as_deref(), Some("1:02:05"));
+    assert_eq!(info.channel.as_deref(), Some("Example"));
+    assert_eq!(info.is_video, Some(true));
+    let calls = fakes.calls("yt-dlp");
+    assert!(calls[0].iter().any(|a| a == "--flat-playlist"));
+}
+
+#[test]
+fn cue_sheets_are_parsed_with_index_01_starts() {
+    let sheet = parse_cue_sheet(
+        "\u{feff}REM GENRE Jazz\nREM DATE 1959\nPERFORMER \"Miles Davis\"\nTITLE \"Kind of Blue\"\nFILE \"Kind of Blue.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"So What\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Freddie Freeloader\"\n    ISRC USSM15900113\n    INDEX 00 09:20:30\n    INDEX 01 09:22:45\n",
+    )
+    .unwrap();
+
+    assert_eq!(sheet.performer.as_deref(), Some("Miles Davis"));
+    assert_eq!(sheet.title.as_deref(), Some("Kind of Blue"));
+    assert_eq!(sheet.date.as_deref(), Some("1959"));
+    assert_eq!(sheet.genre.as_deref(), Some("Jazz"));
+    assert_eq!(sheet.files, vec!["Kind of Blue.flac"]);
+    assert_eq!(sheet.tracks.len(), 2);
+    assert_eq!(sheet.tracks[1].number, 2);
+    assert_eq!(sheet.tracks[1].title.as_deref(), Some("Freddie Freeloader"));
+    assert_eq!(sheet.tracks[1].isrc.as_deref(), Some("USSM15900113"));
+    assert!((sheet.tracks[1].start - (9.0 * 60.0 + 22.0 + 45.0 / 75.0)).abs() < 1e-9);
+    assert!(parse_cue_sheet("TRACK 01 AUDIO\n  TITLE \"No index\"\n").is_err());
+}
+
+#[test]
+fn only_image_file_names_are_read_as_sequences() {
+    let dir = std::env::temp_dir().join("video_toolbox_sequence_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    for frame in ["shot_0007.png", "shot_0008.png"] {
+        std::fs::write(dir.join(frame), b"").unwrap();
+    }
+    let pattern = dir.join("shot_%04d.png").to_string_lossy().to_string();
+    let args = image_sequence_input_args(&pattern, Some("30"));
+    let literal = dir.join("100%d.png");
+    std::fs::write(&literal, b"").unwrap();
+    let literal_args = image_sequence_input_args(&literal.to_string_lossy(), None);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert_eq!(args, ["-framerate", "30", "-start_number", "7"]);
+    assert!(literal_args.is_empty());
+    assert!(image_sequence_input_args("/videos/100%d/clip.mp4", None).is_empty());
+    assert!(image_sequence_input_args("/videos/clip_%05d.mp4", None).is_empty());
+    assert_eq!(input_output_stem("/frames/shot_%04d.png"), "shot");
+    assert_eq!(input_output_stem("/videos/clip.mp4"), "clip");
+}
+
+#[test]
+fn salvaged_nal_units_skip_interleaved_audio() {
+    let mut mdat = Vec::new();
+    mdat.extend_from_slice(&[0, 0, 0, 5, 0x65, 0x88, 0x84, 0x00, 0x10]);
+    mdat.extend_from_slice(&[0, 0, 0, 3, 0x41, 0x9a, 0x02]);
+    mdat.extend_from_slice(&[0xff, 0xf1, 0x50, 0x80, 0x02, 0x1f, 0xfc]);
+    mdat.extend_from_slice(&[0, 0, 0, 4, 0x41, 0x9a, 0x04, 0x08]);
+    let path = std::env::temp_dir().join("video_toolbox_salvage_test.bin");
+    std::fs::write(&path, &mdat).unwrap();
+
+    let mut out = Vec::new();
+    let count = salvage_nal_units(&path, 0, mdat.len() as u64, false, &mut out).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(count, 3);
+    assert_eq!(
+        out,
+        [
+            &[0, 0, 0, 1, 0x65, 0x88, 0x84, 0x00, 0x10][..],
+            &[0, 0, 0, 1, 0x41, 0x9a, 0x02],
+            &[0, 0, 0, 1, 0x41, 0x9a, 0x04, 0x08],
+        ]
+        .concat()
+    );
+    assert_eq!(
+        annexb_parameter_sets(&[0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x68, 0xce, 0, 0, 0, 1, 0x65, 0x88], false),
+        vec![0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x68, 0xce]
+    );
+}
+
+#[test]
+fn command_templates_only_open_their_input_and_output() {
+    assert!(parse_command_template("-i {input} -c:v libx264 -crf 20 -an -movflags +faststart {output}").is_ok());
+    assert!(parse_command_template("-i {input} -metadata title=\"My movie\" {output}").is_ok());
+    assert!(parse_command_template("-i {input} -c copy {output} /tmp/copy.mp4").is_err());
+    assert!(parse_command_template("-i {input} -an /tmp/copy.mp4 {output}").is_err());
+    assert!(parse_command_template("-i {input} -vf \"movie=/etc/passwd[m];[0][m]overlay\" {output}").is_err());
+    assert!(parse_command_template("-i {input} -filter_complex amovie=secret.wav {output}").is_err());
+    assert!(parse_command_template("-i {input} -filter_complex_script graph.txt {output}").is_err());
+}
+
+#[test]
+fn command_templates_only_use_allowed_options_and_filters() {
+    assert!(parse_command_template("-i {input} -vf \"scale=1280:-2,drawtext=text='Hi, there':x=10:y=10\" {output}").is_ok());
+    assert!(parse_command_template("-i {input} -filter_complex \"[0:v]split[a][b];[a][b]hstack[v]\" -map \"[v]\" {output}").is_ok());
+    assert!(parse_command_template("-i {input} -vf metadata=mode=print:file=/home/u/.bashrc {output}").is_err());
+    assert!(parse_command_template("-i {input} -vf drawtext=textfile=/etc/passwd {output}").is_err());
+    assert!(parse_command_template("-i {input} -vf subtitles=/any/file {output}").is_err());
+    assert!(parse_command_template("-i {input} -vf sendcmd=f=commands.txt {output}").is_err());
+    assert!(parse_command_template("-i {input} -af azmq {output}").is_err());
+    assert!(parse_command_template("-i {input} -vf curves=psfile=preset.acv {output}").is_err());
+    assert!(parse_command_template("-i {input} -f segment -segment_list list.txt {output}").is_err());
+    assert!(parse_command_template("-i {input} -hls_segment_filename seg%d.ts {output}").is_err());
+    assert!(parse_command_template("-i {input} -stats_file stats.log {output}").is_err());
+}
+
+#[tokio::test]
+async fn job_window_follows_the_job_and_ends_with_it() {
+    // Commands run as spawned tasks; the test body itself isn't one
+    tokio::spawn(async {
+        assert_eq!(job_window(), None);
+        {
+            let _outer = JobWindow::enter("main");
+            {
+                let _inner = JobWindow::enter("second");
+                assert_eq!(job_window().as_deref(), Some("second"));
+            }
+            assert_eq!(job_window().as_deref(), Some("main"));
+            let spawned = spawn_job_task(async { job_window() }).await.unwrap();
+            assert_eq!(spawned.as_deref(), Some("main"));
+            let unrelated = tokio::spawn(async { job_window() }).await.unwrap();
+            assert_eq!(unrelated, None);
+        }
+        assert_eq!(job_window(), None);
+    })
+    .await
+    .unwrap();
+}
+
+#[test]
+fn waveform_peaks_stay_aligned_and_capped() {
+    let mut reducer = PeakReducer::new(2, 1_000);
+    for sample in [1, 2, 3, 4, 5] {
+        reducer.add(sample);
+    }
+    assert_eq!(reducer.finish(), (vec![(1, 2), (3, 4), (5, 5)], 2));
+
+    // Past the cap neighbouring pairs merge and each pixel covers twice the samples
+    let mut reducer = PeakReducer::new(1, 4);
+    for sample in 0..10 {
+        reducer.add(sample);
+    }
+    assert_eq!(reducer.finish(), (vec![(0, 3), (4, 7), (8, 9)], 4));
+}